@@ -0,0 +1,14285 @@
+//! A high-performance resource monitor TUI application using ratatui, sysinfo, and crossterm.
+//! Features real-time CPU usage, RAM gauge, thermal sensors, network sparklines,
+//! disk I/O monitoring, and a sortable process list with configurable scan rate and logging.
+//!
+//! The binary is a thin driver over this library. To put a monitor pane inside
+//! another ratatui application, see [`embed::MonitorPane`]; the individual widgets
+//! in [`widgets`] take plain data from [`collector::Collector::collect`].
+
+pub mod errors {
+        use std::fmt;
+        use std::io;
+
+        #[derive(Debug)]
+        pub enum SysmonError {
+            Io(io::Error),
+            Config(String),
+            /// Bad input or command-line usage for `sysmon-tui convert`.
+            Convert(String),
+        }
+
+        impl fmt::Display for SysmonError {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self {
+                    SysmonError::Io(e) => write!(f, "I/O error: {}", e),
+                    SysmonError::Config(e) => write!(f, "config error: {}", e),
+                    SysmonError::Convert(e) => write!(f, "convert: {}", e),
+                }
+            }
+        }
+
+        impl std::error::Error for SysmonError {}
+
+        impl From<io::Error> for SysmonError {
+            fn from(e: io::Error) -> Self {
+                SysmonError::Io(e)
+            }
+        }
+}
+
+pub mod config {
+        use std::collections::HashMap;
+        use std::path::{Path, PathBuf};
+
+        use serde::Deserialize;
+
+        use crate::errors::SysmonError;
+
+        /// User configuration, read from `$SYSMON_CONFIG` or
+        /// `$XDG_CONFIG_HOME/sysmon-tui/config.toml` (falling back to `~/.config`).
+        /// A missing file means defaults; every field is optional.
+        #[derive(Deserialize, Default, Clone)]
+        #[serde(default)]
+        pub struct Config {
+            pub layout: LayoutConfig,
+            pub process: ProcessConfig,
+            pub sensors: SensorsConfig,
+            #[serde(rename = "panel")]
+            pub panels: Vec<PanelConfig>,
+            #[serde(rename = "column")]
+            pub columns: Vec<ColumnConfig>,
+            #[serde(rename = "alert")]
+            pub alerts: Vec<AlertConfig>,
+            pub latency: LatencyConfig,
+            pub network: NetworkConfig,
+            #[serde(rename = "disk_alert")]
+            pub disk_alerts: Vec<DiskAlertConfig>,
+            #[serde(rename = "temp_alert")]
+            pub temp_alerts: Vec<TempAlertConfig>,
+            pub notify: NotifyConfig,
+            pub ups: Option<UpsConfig>,
+            pub journal: Option<JournalConfig>,
+            pub mqtt: Option<MqttConfig>,
+            pub statsd: Option<StatsdConfig>,
+            pub graphite: Option<GraphiteConfig>,
+            pub log: LogConfig,
+            pub recorder: RecorderConfig,
+            #[serde(rename = "watch")]
+            pub watches: Vec<WatchConfig>,
+            pub offenders: OffenderConfig,
+            pub summary: SummaryConfig,
+            pub compare: CompareConfig,
+            pub state: StateConfig,
+            pub format: FormatConfig,
+            pub nvme: NvmeConfig,
+            pub clock: ClockConfig,
+            pub idle: IdleConfig,
+            /// `"UTC"` or an IANA name like `"Europe/Berlin"` for every
+            /// timestamp written or shown; the system zone when unset.
+            pub timezone: Option<String>,
+            /// Names of the `[profile.<name>]` tables, in file order.
+            #[serde(skip)]
+            pub profiles: Vec<String>,
+            /// The profile merged over the base config, if any.
+            #[serde(skip)]
+            pub profile: Option<String>,
+        }
+
+        #[derive(Deserialize, Clone)]
+        #[serde(default)]
+        pub struct LayoutConfig {
+            /// Height in rows of the custom panel strip above the status bar.
+            pub panel_height: u16,
+            /// Show logged-in users (from utmp) in the panel strip.
+            pub sessions: bool,
+            /// Show per-device block I/O latency histograms in the panel strip
+            /// (`ebpf` feature).
+            pub biolatency: bool,
+            /// Window for the status bar's CPU and temperature percentiles; 0 hides them.
+            pub percentile_minutes: u64,
+            /// Pane proportions at startup; F1–F4 switch at runtime.
+            pub preset: LayoutPreset,
+            /// How the CPU panel shows each core at startup; `B` toggles it.
+            pub cpu_view: CpuView,
+            /// Capture the mouse so the wheel scrolls the table. Off by default,
+            /// since capture takes over the terminal's own text selection.
+            /// Read at startup only.
+            pub mouse: bool,
+            /// Palette at startup; `:theme` switches at runtime.
+            pub theme: Theme,
+        }
+
+        impl Default for LayoutConfig {
+            fn default() -> Self {
+                Self {
+                    panel_height: 10,
+                    sessions: false,
+                    biolatency: false,
+                    percentile_minutes: 5,
+                    preset: LayoutPreset::Overview,
+                    cpu_view: CpuView::Text,
+                    mouse: false,
+                    theme: Theme::Dark,
+                }
+            }
+        }
+
+        /// Per-core readings as a line of text figures or as a grid of bars.
+        #[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+        #[serde(rename_all = "lowercase")]
+        pub enum CpuView {
+            #[default]
+            Text,
+            Bars,
+        }
+
+        impl CpuView {
+            pub fn label(self) -> &'static str {
+                match self {
+                    CpuView::Text => "text",
+                    CpuView::Bars => "bars",
+                }
+            }
+
+            pub fn from_label(label: &str) -> Option<CpuView> {
+                [CpuView::Text, CpuView::Bars].into_iter().find(|v| v.label() == label)
+            }
+
+            pub fn toggled(self) -> Self {
+                match self {
+                    CpuView::Text => CpuView::Bars,
+                    CpuView::Bars => CpuView::Text,
+                }
+            }
+        }
+
+        /// Colour palette for the terminal's background.
+        #[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+        #[serde(rename_all = "lowercase")]
+        pub enum Theme {
+            #[default]
+            Dark,
+            /// Darker foreground colours that stay readable on a light background.
+            Light,
+        }
+
+        impl Theme {
+            pub fn label(self) -> &'static str {
+                match self {
+                    Theme::Dark => "dark",
+                    Theme::Light => "light",
+                }
+            }
+
+            pub fn from_label(label: &str) -> Option<Theme> {
+                [Theme::Dark, Theme::Light].into_iter().find(|t| t.label() == label)
+            }
+        }
+
+        /// Pane proportions tuned for one kind of investigation.
+        #[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+        #[serde(rename_all = "lowercase")]
+        pub enum LayoutPreset {
+            #[default]
+            Overview,
+            Processes,
+            Network,
+            Thermals,
+        }
+
+        impl LayoutPreset {
+            /// In F1–F4 order.
+            pub const ALL: [LayoutPreset; 4] =
+                [LayoutPreset::Overview, LayoutPreset::Processes, LayoutPreset::Network, LayoutPreset::Thermals];
+
+            pub fn label(self) -> &'static str {
+                match self {
+                    LayoutPreset::Overview => "overview",
+                    LayoutPreset::Processes => "processes",
+                    LayoutPreset::Network => "network",
+                    LayoutPreset::Thermals => "thermals",
+                }
+            }
+
+            pub fn from_label(label: &str) -> Option<LayoutPreset> {
+                Self::ALL.into_iter().find(|p| p.label() == label)
+            }
+        }
+
+        /// Precision of human-readable byte counts and rates.
+        #[derive(Deserialize, Clone)]
+        #[serde(default)]
+        pub struct FormatConfig {
+            /// Decimal places for KiB and MiB values.
+            pub decimals: usize,
+            /// Decimal places for GiB and larger.
+            pub gib_decimals: usize,
+            /// A value moves up to the next unit once it reaches this many of
+            /// the current one: 1000 shows `0.98 GiB` instead of `1000.0 MiB`.
+            pub unit_threshold: f64,
+            /// Take the separators from a locale: `"system"` (`LC_ALL`,
+            /// `LC_NUMERIC`, `LANG`) or a name like `"de_DE.UTF-8"`.
+            pub locale: Option<String>,
+            /// Override the locale's separators.
+            pub thousands_separator: Option<String>,
+            pub decimal_separator: Option<String>,
+            /// Use the separators in text reports, summaries and offender
+            /// reports too. CSV recordings always use plain numbers.
+            pub locale_exports: bool,
+        }
+
+        impl Default for FormatConfig {
+            fn default() -> Self {
+                Self {
+                    decimals: 1,
+                    gib_decimals: 1,
+                    unit_threshold: 1024.0,
+                    locale: None,
+                    thousands_separator: None,
+                    decimal_separator: None,
+                    locale_exports: false,
+                }
+            }
+        }
+
+        #[derive(Deserialize, Default, Clone)]
+        #[serde(default)]
+        pub struct ProcessConfig {
+            /// Drop sysmon-tui's own process from the process list.
+            pub hide_self: bool,
+            /// Where names too long for the Name column are cut.
+            pub truncate: Truncate,
+            /// Hold the row order while a process is selected, so rows don't
+            /// move under the cursor between refreshes.
+            pub freeze_on_select: bool,
+            /// Optional columns shown after MEM, in this order.
+            pub columns: Vec<ProcessColumn>,
+            pub highlight: HighlightConfig,
+            /// Initial process scan interval, rounded to the nearest of the
+            /// `[`/`]` steps. Unset keeps the current rate.
+            pub scan_ms: Option<u32>,
+        }
+
+        /// Yellow/red bands for the process table's CPU% and MEM cells, as the
+        /// CPU panel colors its cores.
+        #[derive(Deserialize, Clone)]
+        #[serde(default)]
+        pub struct HighlightConfig {
+            pub enabled: bool,
+            /// Color the whole row instead of just the cell over its threshold.
+            pub rows: bool,
+            pub cpu_warn: f32,
+            pub cpu_critical: f32,
+            /// Memory thresholds as a share of total RAM.
+            pub mem_warn_percent: f64,
+            pub mem_critical_percent: f64,
+        }
+
+        impl Default for HighlightConfig {
+            fn default() -> Self {
+                Self {
+                    enabled: true,
+                    rows: false,
+                    cpu_warn: 40.0,
+                    cpu_critical: 80.0,
+                    mem_warn_percent: 10.0,
+                    mem_critical_percent: 25.0,
+                }
+            }
+        }
+
+        #[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+        #[serde(rename_all = "lowercase")]
+        pub enum ProcessColumn {
+            User,
+            Threads,
+            Io,
+            Cmd,
+            /// Nice value, or real-time priority, with the scheduling class.
+            Nice,
+            /// Owning container or systemd unit.
+            Unit,
+            /// Scheduler wakeups per second.
+            Wakeups,
+            /// Socket bytes received and sent per second (`ebpf` feature).
+            Net,
+        }
+
+        impl ProcessColumn {
+            pub fn header(self) -> &'static str {
+                match self {
+                    ProcessColumn::User => "User",
+                    ProcessColumn::Threads => "Thr",
+                    ProcessColumn::Io => "I/O R/W/s",
+                    ProcessColumn::Cmd => "Command",
+                    ProcessColumn::Nice => "Nice",
+                    ProcessColumn::Unit => "Unit",
+                    ProcessColumn::Wakeups => "Wake/s",
+                    ProcessColumn::Net => "Net R/T/s",
+                }
+            }
+
+            pub fn width(self) -> u16 {
+                match self {
+                    ProcessColumn::User => 10,
+                    ProcessColumn::Threads => 5,
+                    ProcessColumn::Io => 15,
+                    ProcessColumn::Cmd => 40,
+                    ProcessColumn::Nice => 7,
+                    ProcessColumn::Unit => 20,
+                    ProcessColumn::Wakeups => 8,
+                    ProcessColumn::Net => 15,
+                }
+            }
+        }
+
+        #[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+        #[serde(rename_all = "lowercase")]
+        pub enum Truncate {
+            #[default]
+            End,
+            Middle,
+        }
+
+        /// Friendly names and hidden entries for the Thermals panel. Keys match a
+        /// sensor's raw label (`k10temp Tctl`) or sysfs zone (`thermal_zone3`).
+        #[derive(Deserialize, Default, Clone)]
+        #[serde(default)]
+        pub struct SensorsConfig {
+            pub rename: HashMap<String, String>,
+            pub hide: Vec<String>,
+        }
+
+        #[derive(Deserialize, Default, Clone)]
+        #[serde(default)]
+        pub struct LogConfig {
+            /// Encoder for continuous logs (Alt+L / `:log`).
+            pub compression: LogCompression,
+            /// Start snapshots, logs and flight recorder dumps with `# key: value`
+            /// lines describing the machine. Off by default, since plain CSV
+            /// readers take them for rows.
+            pub metadata: bool,
+        }
+
+        #[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+        #[serde(rename_all = "lowercase")]
+        pub enum LogCompression {
+            #[default]
+            None,
+            Gzip,
+            Zstd,
+        }
+
+        impl LogCompression {
+            /// File extension for a log written with this encoder.
+            pub fn extension(self) -> &'static str {
+                match self {
+                    LogCompression::None => "csv",
+                    LogCompression::Gzip => "csv.gz",
+                    LogCompression::Zstd => "csv.zst",
+                }
+            }
+        }
+
+        #[derive(Deserialize, Clone)]
+        #[serde(default)]
+        pub struct RecorderConfig {
+            /// Minutes of full refreshes kept in memory for `d` / `:dump`; 0 disables.
+            pub minutes: u64,
+        }
+
+        impl Default for RecorderConfig {
+            fn default() -> Self {
+                Self { minutes: 5 }
+            }
+        }
+
+        /// A panel whose text comes from periodically running a shell command.
+        #[derive(Deserialize, Clone)]
+        pub struct PanelConfig {
+            pub title: String,
+            pub command: String,
+            #[serde(default = "default_interval_secs")]
+            pub interval_secs: u64,
+        }
+
+        fn default_interval_secs() -> u64 {
+            5
+        }
+
+        #[derive(Deserialize, Clone)]
+        #[serde(default)]
+        pub struct NetworkConfig {
+            /// Transfer allowance in GiB (RX + TX, all interfaces); `None` disables tracking.
+            pub quota_gib: Option<f64>,
+            /// Period the quota applies to.
+            pub quota_period: QuotaPeriod,
+            /// Raise an alert once this share of the quota is used.
+            pub quota_warn_percent: f64,
+            /// Probe the default gateway and show whether it answers. Off by
+            /// default, since it runs `ping` every `check_interval_secs`.
+            pub check_gateway: bool,
+            /// Outside target probed alongside the gateway: `host` for ICMP,
+            /// `host:port` for a TCP connect.
+            pub check_target: Option<String>,
+            pub check_interval_secs: u64,
+            /// Names shown instead of the kernel's for some interfaces, such
+            /// as `enp5s0 = "LAN"`.
+            pub aliases: HashMap<String, String>,
+            /// Interfaces left out of the totals, sparklines and Links list;
+            /// a trailing `*` matches a prefix (`veth*`).
+            pub exclude: Vec<String>,
+        }
+
+        impl Default for NetworkConfig {
+            fn default() -> Self {
+                Self {
+                    quota_gib: None,
+                    quota_period: QuotaPeriod::Month,
+                    quota_warn_percent: 80.0,
+                    check_gateway: false,
+                    check_target: None,
+                    check_interval_secs: 5,
+                    aliases: HashMap::new(),
+                    exclude: Vec::new(),
+                }
+            }
+        }
+
+        impl NetworkConfig {
+            /// The alias configured for `iface`, or its name.
+            pub fn alias<'a>(&'a self, iface: &'a str) -> &'a str {
+                self.aliases.get(iface).map_or(iface, String::as_str)
+            }
+
+            /// `alias (name)` for an aliased interface, for text that needs
+            /// both; the bare name otherwise.
+            pub fn alias_and_name(&self, iface: &str) -> String {
+                match self.aliases.get(iface) {
+                    Some(alias) => format!("{} ({})", alias, iface),
+                    None => iface.to_string(),
+                }
+            }
+        }
+
+        #[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+        #[serde(rename_all = "lowercase")]
+        pub enum QuotaPeriod {
+            Day,
+            Month,
+        }
+
+        /// Directory for data kept across runs: `$XDG_STATE_HOME/sysmon-tui`
+        /// or `~/.local/state/sysmon-tui`.
+        pub fn state_dir() -> Option<PathBuf> {
+            let base = std::env::var("XDG_STATE_HOME")
+                .map(PathBuf::from)
+                .or_else(|_| std::env::var("HOME").map(|h| PathBuf::from(h).join(".local/state")))
+                .ok()?;
+            Some(base.join("sysmon-tui"))
+        }
+
+        /// Targets for the latency panel; the panel is hidden when empty.
+        /// `host` is pinged with ICMP, `host:port` is timed as a TCP connect.
+        #[derive(Deserialize, Clone)]
+        #[serde(default)]
+        pub struct LatencyConfig {
+            pub targets: Vec<String>,
+            pub interval_secs: u64,
+        }
+
+        impl Default for LatencyConfig {
+            fn default() -> Self {
+                Self { targets: Vec::new(), interval_secs: 1 }
+            }
+        }
+
+        /// A UPS served by a NUT daemon, named `upsname[@host[:port]]` as in
+        /// `upsc`.
+        #[derive(Deserialize, Clone)]
+        pub struct UpsConfig {
+            pub name: String,
+            #[serde(default = "default_interval_secs")]
+            pub interval_secs: u64,
+        }
+
+        /// Tail of the systemd journal shown as a panel.
+        #[derive(Deserialize, Clone)]
+        #[serde(default)]
+        pub struct JournalConfig {
+            /// Only these units (`journalctl -u`); empty for the whole journal.
+            pub units: Vec<String>,
+            /// Most verbose syslog priority shown: 0 emerg … 3 err … 7 debug.
+            pub priority: u8,
+            /// Lines kept in memory.
+            pub lines: usize,
+        }
+
+        impl Default for JournalConfig {
+            fn default() -> Self {
+                Self { units: Vec::new(), priority: 3, lines: 200 }
+            }
+        }
+
+        /// Publishes metrics to an MQTT broker (QoS 0).
+        #[derive(Deserialize, Clone)]
+        pub struct MqttConfig {
+            /// `host:port`; the port defaults to 1883.
+            pub broker: String,
+            /// Topic prefix; defaults to `sysmon/<hostname>`.
+            #[serde(default)]
+            pub topic_prefix: Option<String>,
+            /// Metric names to publish (`temp.*` matches a prefix); empty for all.
+            #[serde(default)]
+            pub metrics: Vec<String>,
+            #[serde(default = "default_sink_interval_secs")]
+            pub interval_secs: u64,
+            #[serde(default)]
+            pub username: Option<String>,
+            #[serde(default)]
+            pub password: Option<String>,
+            #[serde(default)]
+            pub retain: bool,
+        }
+
+        /// Sends metrics as StatsD gauges over UDP.
+        #[derive(Deserialize, Clone)]
+        pub struct StatsdConfig {
+            /// `host:port`; the port defaults to 8125.
+            pub address: String,
+            /// Prepended to every metric name; defaults to `sysmon.<hostname>`.
+            #[serde(default)]
+            pub prefix: Option<String>,
+            #[serde(default)]
+            pub metrics: Vec<String>,
+            #[serde(default = "default_sink_interval_secs")]
+            pub interval_secs: u64,
+        }
+
+        /// Pushes metrics in Graphite's plaintext protocol over TCP.
+        #[derive(Deserialize, Clone)]
+        pub struct GraphiteConfig {
+            /// `host:port`; the port defaults to 2003.
+            pub address: String,
+            /// Path prefix; `{hostname}` is replaced. Defaults to `sysmon.{hostname}`.
+            #[serde(default)]
+            pub prefix: Option<String>,
+            #[serde(default)]
+            pub metrics: Vec<String>,
+            #[serde(default = "default_sink_interval_secs")]
+            pub interval_secs: u64,
+        }
+
+        fn default_sink_interval_secs() -> u64 {
+            10
+        }
+
+        /// A computed process-table column defined by a rhai expression.
+        #[derive(Deserialize, Clone)]
+        pub struct ColumnConfig {
+            pub name: String,
+            pub expr: String,
+        }
+
+        /// An alert raised while a rhai predicate evaluates to true.
+        #[derive(Deserialize, Clone)]
+        pub struct AlertConfig {
+            pub name: String,
+            pub when: String,
+            /// Evaluate once per process (with process variables in scope)
+            /// instead of once per sample.
+            #[serde(default)]
+            pub per_process: bool,
+            /// Save a process snapshot when the alert fires.
+            #[serde(default)]
+            pub snapshot: bool,
+            /// Dump the flight recorder when the alert fires.
+            #[serde(default)]
+            pub dump: bool,
+            /// Start continuous logging for this many minutes when the alert fires.
+            #[serde(default)]
+            pub record_minutes: Option<u64>,
+        }
+
+        /// What counts as a sustained offender in the `o` report.
+        #[derive(Deserialize, Clone)]
+        #[serde(default)]
+        pub struct OffenderConfig {
+            /// CPU%, where 100 is one full core.
+            pub cpu_percent: f32,
+            /// Resident memory as a share of RAM.
+            pub mem_percent: f64,
+            /// Time over a threshold before a process is listed.
+            pub min_secs: u64,
+        }
+
+        impl Default for OffenderConfig {
+            fn default() -> Self {
+                Self { cpu_percent: 80.0, mem_percent: 20.0, min_secs: 10 }
+            }
+        }
+
+        /// Session summary written with `S` / `:summary`.
+        #[derive(Deserialize, Clone, Default)]
+        #[serde(default)]
+        pub struct SummaryConfig {
+            /// Also write one when sysmon-tui exits.
+            pub on_exit: bool,
+        }
+
+        /// History kept for the compare view and its `:overlay`.
+        #[derive(Deserialize, Clone)]
+        #[serde(default)]
+        pub struct CompareConfig {
+            /// How far back an overlay can reach, plus the 5 minutes shown.
+            pub keep_minutes: u64,
+        }
+
+        impl Default for CompareConfig {
+            fn default() -> Self {
+                Self { keep_minutes: 120 }
+            }
+        }
+
+        /// Saving the UI state on exit and restoring it at startup.
+        #[derive(Deserialize, Clone)]
+        #[serde(default)]
+        pub struct StateConfig {
+            pub restore: bool,
+        }
+
+        impl Default for StateConfig {
+            fn default() -> Self {
+                Self { restore: true }
+            }
+        }
+
+        /// A process expected to stay up, matched by exact name.
+        #[derive(Deserialize, Clone)]
+        pub struct WatchConfig {
+            pub name: String,
+            /// Shell command run when the process disappears or restarts.
+            #[serde(default)]
+            pub hook: Option<String>,
+        }
+
+        /// Filesystem fullness thresholds for one mount, or every mount when
+        /// `mount` is omitted.
+        #[derive(Deserialize, Clone)]
+        pub struct DiskAlertConfig {
+            #[serde(default)]
+            pub mount: Option<String>,
+            #[serde(default = "default_disk_warn")]
+            pub warn_percent: f64,
+            #[serde(default = "default_disk_critical")]
+            pub critical_percent: f64,
+        }
+
+        fn default_disk_warn() -> f64 {
+            85.0
+        }
+
+        fn default_disk_critical() -> f64 {
+            95.0
+        }
+
+        /// Alert and coloring thresholds for NVMe health on the Storage tab.
+        #[derive(Deserialize, Clone)]
+        #[serde(default)]
+        pub struct NvmeConfig {
+            /// Drive-reported endurance used (`percentage_used`); may pass 100.
+            pub wear_warn_percent: u8,
+            pub wear_critical_percent: u8,
+            /// Composite temperature, in °C.
+            pub temp_warn: f32,
+        }
+
+        impl Default for NvmeConfig {
+            fn default() -> Self {
+                Self { wear_warn_percent: 80, wear_critical_percent: 95, temp_warn: 70.0 }
+            }
+        }
+
+        /// When the system clock raises an alert.
+        #[derive(Deserialize, Clone)]
+        #[serde(default)]
+        pub struct ClockConfig {
+            /// Alert while no time daemon is steering the clock.
+            pub warn_unsynchronized: bool,
+            /// Alert when the offset being corrected exceeds this, in ms.
+            pub offset_warn_ms: f64,
+        }
+
+        impl Default for ClockConfig {
+            fn default() -> Self {
+                Self { warn_unsynchronized: true, offset_warn_ms: 100.0 }
+            }
+        }
+
+        /// What the screen does after a stretch without input.
+        #[derive(Deserialize, Clone)]
+        #[serde(default)]
+        pub struct IdleConfig {
+            /// Minutes without a key press before going idle; 0 never does.
+            pub after_minutes: u64,
+            pub mode: IdleMode,
+        }
+
+        impl Default for IdleConfig {
+            fn default() -> Self {
+                Self { after_minutes: 0, mode: IdleMode::Dim }
+            }
+        }
+
+        #[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+        #[serde(rename_all = "lowercase")]
+        pub enum IdleMode {
+            /// Everything drawn in dark grey.
+            #[default]
+            Dim,
+            /// Only a small clock and load readout that moves every minute.
+            Blank,
+        }
+
+        /// Temperature limit for sensors whose raw or renamed label contains
+        /// `sensor` (case-insensitive).
+        #[derive(Deserialize, Clone)]
+        pub struct TempAlertConfig {
+            pub sensor: String,
+            pub above: f32,
+        }
+
+        impl TempAlertConfig {
+            /// First rule matching any of `labels`; earlier rules take precedence.
+            pub fn find<'a>(rules: &'a [TempAlertConfig], labels: &[&str]) -> Option<&'a TempAlertConfig> {
+                let labels: Vec<String> = labels.iter().map(|l| l.to_lowercase()).collect();
+                rules.iter().find(|r| {
+                    let sensor = r.sensor.to_lowercase();
+                    labels.iter().any(|l| l.contains(&sensor))
+                })
+            }
+
+            /// `(hot, warm)` colour levels for a sensor: its rule's `above` and
+            /// ten degrees under it, or 85/65 without a rule.
+            pub fn levels(rules: &[TempAlertConfig], labels: &[&str]) -> (f32, f32) {
+                match Self::find(rules, labels) {
+                    Some(rule) => (rule.above, rule.above - 10.0),
+                    None => (85.0, 65.0),
+                }
+            }
+        }
+
+        /// Where newly fired alerts are sent, in addition to the status bar.
+        #[derive(Deserialize, Clone, Default)]
+        #[serde(default)]
+        pub struct NotifyConfig {
+            /// Run through `sh -c` with `SYSMON_ALERT` and `SYSMON_DETAIL` set.
+            pub command: Option<String>,
+            /// URL that receives a JSON POST (via `curl`).
+            pub webhook: Option<String>,
+        }
+
+        impl Config {
+            pub fn path() -> Option<PathBuf> {
+                if let Ok(p) = std::env::var("SYSMON_CONFIG") {
+                    return Some(PathBuf::from(p));
+                }
+                let base = std::env::var("XDG_CONFIG_HOME")
+                    .map(PathBuf::from)
+                    .or_else(|_| std::env::var("HOME").map(|h| PathBuf::from(h).join(".config")))
+                    .ok()?;
+                Some(base.join("sysmon-tui").join("config.toml"))
+            }
+
+            /// Loads the config with `[profile.<name>]` merged over it when a
+            /// profile is given.
+            pub fn load(profile: Option<&str>) -> Result<Self, SysmonError> {
+                let path = Self::path();
+                let text = match path.as_ref().map(std::fs::read_to_string) {
+                    Some(Ok(text)) => text,
+                    Some(Err(e)) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                    _ => String::new(),
+                };
+                let invalid = |e: String| match &path {
+                    Some(path) => SysmonError::Config(format!("{}: {}", path.display(), e)),
+                    None => SysmonError::Config(e),
+                };
+                let mut table: toml::Table = toml::from_str(&text).map_err(|e| invalid(e.to_string()))?;
+                let profiles = match table.remove("profile") {
+                    Some(toml::Value::Table(profiles)) => profiles,
+                    Some(_) => return Err(invalid("`profile` must be a table of [profile.<name>] tables".to_string())),
+                    None => toml::Table::new(),
+                };
+                if let Some(name) = profile {
+                    match profiles.get(name) {
+                        Some(toml::Value::Table(overlay)) => merge(&mut table, overlay.clone()),
+                        _ => return Err(invalid(format!("no profile named {:?}", name))),
+                    }
+                }
+                let mut config: Self = toml::Value::Table(table).try_into().map_err(|e: toml::de::Error| invalid(e.to_string()))?;
+                config.profiles = profiles.keys().cloned().collect();
+                config.profile = profile.map(str::to_string);
+                Ok(config)
+            }
+        }
+
+        /// Merges `overlay` into `base`: tables key by key, anything else
+        /// (arrays such as `[[panel]]` included) replaced whole.
+        fn merge(base: &mut toml::Table, overlay: toml::Table) {
+            for (key, value) in overlay {
+                match (base.get_mut(&key), value) {
+                    (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => merge(base, overlay),
+                    (_, value) => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+
+        /// Points `chrono::Local` at another zone by setting `TZ`, so it must
+        /// run before any other thread starts.
+        pub fn apply_timezone(name: &str) -> Result<(), String> {
+            let known = name == "UTC" || (!name.contains("..") && Path::new("/usr/share/zoneinfo").join(name).is_file());
+            if !known {
+                return Err(format!("unknown timezone {:?}", name));
+            }
+            std::env::set_var("TZ", name);
+            Ok(())
+        }
+}
+
+pub mod logfile {
+        use std::fs::File;
+        use std::io::{self, BufRead, BufReader, BufWriter, Write};
+        use std::path::Path;
+
+        use flate2::write::GzEncoder;
+
+        use crate::config::LogCompression;
+
+        /// A continuous log file, optionally compressed. Compressed streams
+        /// need their trailer written, so stop logging through `finish`.
+        pub enum LogWriter {
+            Plain(BufWriter<File>),
+            Gzip(GzEncoder<BufWriter<File>>),
+            Zstd(zstd::Encoder<'static, BufWriter<File>>),
+        }
+
+        impl LogWriter {
+            pub fn create(path: &Path, compression: LogCompression) -> io::Result<Self> {
+                let file = BufWriter::new(File::create(path)?);
+                Ok(match compression {
+                    LogCompression::None => LogWriter::Plain(file),
+                    LogCompression::Gzip => LogWriter::Gzip(GzEncoder::new(file, flate2::Compression::default())),
+                    LogCompression::Zstd => LogWriter::Zstd(zstd::Encoder::new(file, 0)?),
+                })
+            }
+
+            /// Ends the compressed stream and flushes the file.
+            pub fn finish(self) -> io::Result<()> {
+                match self {
+                    LogWriter::Plain(mut w) => w.flush(),
+                    LogWriter::Gzip(w) => w.finish()?.flush(),
+                    LogWriter::Zstd(w) => w.finish()?.flush(),
+                }
+            }
+        }
+
+        impl Write for LogWriter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                match self {
+                    LogWriter::Plain(w) => w.write(buf),
+                    LogWriter::Gzip(w) => w.write(buf),
+                    LogWriter::Zstd(w) => w.write(buf),
+                }
+            }
+
+            /// Compressed streams are sync-flushed, so everything written up to
+            /// the flush can be decoded even if sysmon-tui dies before `finish`.
+            fn flush(&mut self) -> io::Result<()> {
+                match self {
+                    LogWriter::Plain(w) => w.flush(),
+                    LogWriter::Gzip(w) => w.flush(),
+                    LogWriter::Zstd(w) => w.flush(),
+                }
+            }
+        }
+
+        /// The machine and settings a recording was made on, written ahead of
+        /// the CSV header as `# key: value` lines.
+        pub struct RecordingInfo {
+            pub cores: usize,
+            pub ram_bytes: u64,
+            pub tick_ms: u32,
+            /// Ticks per process scan.
+            pub process_every: u32,
+        }
+
+        impl RecordingInfo {
+            pub fn write(&self, w: &mut impl Write) -> io::Result<()> {
+                let unknown = || "unknown".to_string();
+                writeln!(w, "# sysmon_version: {}", env!("CARGO_PKG_VERSION"))?;
+                writeln!(w, "# hostname: {}", sysinfo::System::host_name().unwrap_or_else(unknown))?;
+                writeln!(w, "# kernel: {}", sysinfo::System::kernel_version().unwrap_or_else(unknown))?;
+                writeln!(w, "# os: {}", sysinfo::System::long_os_version().unwrap_or_else(unknown))?;
+                writeln!(w, "# timezone: {}", timezone_name())?;
+                writeln!(w, "# cores: {}", self.cores)?;
+                writeln!(w, "# ram_bytes: {}", self.ram_bytes)?;
+                writeln!(w, "# tick_ms: {}", self.tick_ms)?;
+                writeln!(w, "# scan_ms: {}", self.tick_ms.saturating_mul(self.process_every))
+            }
+        }
+
+        /// `TZ` if set, else the zone `/etc/localtime` links to.
+        pub fn timezone_name() -> String {
+            std::env::var("TZ")
+                .ok()
+                .or_else(|| {
+                    let target = std::fs::read_link("/etc/localtime").ok()?;
+                    Some(target.to_str()?.split_once("zoneinfo/")?.1.to_string())
+                })
+                .unwrap_or_else(|| "local".to_string())
+        }
+
+        /// Splits a `# key: value` metadata line; `None` for data lines.
+        pub fn metadata(line: &str) -> Option<(&str, &str)> {
+            let (key, value) = line.strip_prefix("# ")?.split_once(": ")?;
+            Some((key, value.trim()))
+        }
+
+        /// Opens a log or snapshot for reading, decompressing `.gz` and `.zst` files.
+        pub fn open(path: &Path) -> io::Result<Box<dyn BufRead>> {
+            let file = File::open(path)?;
+            Ok(match path.extension().and_then(|e| e.to_str()) {
+                Some("gz") => Box::new(BufReader::new(flate2::read::MultiGzDecoder::new(file))),
+                Some("zst") => Box::new(BufReader::new(zstd::Decoder::new(file)?)),
+                _ => Box::new(BufReader::new(file)),
+            })
+        }
+}
+
+pub mod convert {
+        use std::io::BufRead;
+        use std::path::{Path, PathBuf};
+        use std::sync::Arc;
+
+        use chrono::{Local, NaiveDateTime, TimeZone};
+        use parquet::basic::Compression;
+        use parquet::data_type::{ByteArray, ByteArrayType, FloatType, Int32Type, Int64Type};
+        use parquet::file::metadata::KeyValue;
+        use parquet::file::properties::WriterProperties;
+        use parquet::file::writer::SerializedFileWriter;
+        use parquet::schema::parser::parse_message_type;
+
+        use crate::errors::SysmonError;
+
+        /// Header shared by snapshots and continuous logs.
+        const PROCESS_HEADER: &str = "timestamp,pid,name,cpu_percent,mem_bytes";
+
+        const PROCESS_SCHEMA: &str = "
+            message process_sample {
+                REQUIRED INT64 timestamp (TIMESTAMP(MILLIS, true));
+                REQUIRED INT32 pid;
+                REQUIRED BYTE_ARRAY name (UTF8);
+                REQUIRED FLOAT cpu_percent;
+                REQUIRED INT64 mem_bytes;
+            }
+        ";
+
+        /// Rows buffered per Parquet row group.
+        const ROW_GROUP: usize = 100_000;
+
+        /// Columns of one row group.
+        #[derive(Default)]
+        struct Columns {
+            timestamp: Vec<i64>,
+            pid: Vec<i32>,
+            name: Vec<ByteArray>,
+            cpu_percent: Vec<f32>,
+            mem_bytes: Vec<i64>,
+        }
+
+        impl Columns {
+            fn len(&self) -> usize {
+                self.timestamp.len()
+            }
+
+            /// Parses `timestamp,pid,name,cpu,mem`. The name is unquoted and
+            /// may contain commas, so it is whatever lies between the second
+            /// field and the last two.
+            fn push(&mut self, line: &str) -> Option<()> {
+                let (ts, rest) = line.split_once(',')?;
+                let (pid, rest) = rest.split_once(',')?;
+                let (rest, mem) = rest.rsplit_once(',')?;
+                let (name, cpu) = rest.rsplit_once(',')?;
+                let ts = NaiveDateTime::parse_from_str(ts, "%Y-%m-%dT%H:%M:%S%.f").ok()?;
+                let ts = Local.from_local_datetime(&ts).earliest()?.timestamp_millis();
+                let (pid, cpu, mem) = (pid.parse().ok()?, cpu.parse().ok()?, mem.parse().ok()?);
+                self.timestamp.push(ts);
+                self.pid.push(pid);
+                self.name.push(ByteArray::from(name));
+                self.cpu_percent.push(cpu);
+                self.mem_bytes.push(mem);
+                Some(())
+            }
+
+            fn write(&mut self, writer: &mut SerializedFileWriter<std::fs::File>) -> parquet::errors::Result<()> {
+                let mut group = writer.next_row_group()?;
+                let mut index = 0;
+                while let Some(mut column) = group.next_column()? {
+                    match index {
+                        0 => column.typed::<Int64Type>().write_batch(&self.timestamp, None, None)?,
+                        1 => column.typed::<Int32Type>().write_batch(&self.pid, None, None)?,
+                        2 => column.typed::<ByteArrayType>().write_batch(&self.name, None, None)?,
+                        3 => column.typed::<FloatType>().write_batch(&self.cpu_percent, None, None)?,
+                        _ => column.typed::<Int64Type>().write_batch(&self.mem_bytes, None, None)?,
+                    };
+                    column.close()?;
+                    index += 1;
+                }
+                group.close()?;
+                *self = Columns::default();
+                Ok(())
+            }
+        }
+
+        /// `sysmon-tui convert <input.csv> [output.parquet]`
+        pub fn run(args: &[String]) -> Result<(), SysmonError> {
+            let usage = || SysmonError::Convert("usage: sysmon-tui convert <input.csv> [output.parquet]".to_string());
+            let input = PathBuf::from(args.first().ok_or_else(usage)?);
+            // `x.csv.zst` -> `x.csv`, for the format and the default output.
+            let plain = match input.extension().and_then(|e| e.to_str()) {
+                Some("gz" | "zst") => input.with_extension(""),
+                _ => input.clone(),
+            };
+            // sysmon-tui only records CSV; JSON lines from other tools are out of scope.
+            if let Some(ext @ ("json" | "jsonl" | "ndjson")) = plain.extension().and_then(|e| e.to_str()) {
+                return Err(SysmonError::Convert(format!(
+                    "{}: .{} input is not supported, only sysmon-tui's CSV snapshots and logs",
+                    input.display(),
+                    ext
+                )));
+            }
+            let output = match args.get(1) {
+                Some(o) => PathBuf::from(o),
+                None => plain.with_extension("parquet"),
+            };
+            let rows = to_parquet(&input, &output)?;
+            println!("{} rows -> {}", rows, output.display());
+            Ok(())
+        }
+
+        /// Converts a process CSV (snapshot or log, optionally gzip/zstd
+        /// compressed) and returns the row count.
+        pub fn to_parquet(input: &Path, output: &Path) -> Result<usize, SysmonError> {
+            let parquet_err = |e: parquet::errors::ParquetError| SysmonError::Convert(e.to_string());
+            let reader = crate::logfile::open(input)?;
+            let mut lines = reader.lines();
+            // Metadata lines become Parquet key-value metadata.
+            let mut metadata = Vec::new();
+            let header = loop {
+                let line = lines.next().transpose()?.unwrap_or_default();
+                match crate::logfile::metadata(&line) {
+                    Some((key, value)) => metadata.push(KeyValue::new(key.to_string(), value.to_string())),
+                    None => break line,
+                }
+            };
+            // Timestamps are wall-clock time in the zone they were recorded in.
+            if let Some(zone) = metadata.iter().find(|kv| kv.key == "timezone").and_then(|kv| kv.value.as_deref()) {
+                if zone != "local" {
+                    if let Err(e) = crate::config::apply_timezone(zone) {
+                        eprintln!("{}: {}, reading timestamps as local time", input.display(), e);
+                    }
+                }
+            }
+            if header.trim() != PROCESS_HEADER {
+                return Err(SysmonError::Convert(format!("{}: not a sysmon-tui process CSV", input.display())));
+            }
+            let first_row = metadata.len() + 2;
+
+            let schema = Arc::new(parse_message_type(PROCESS_SCHEMA).map_err(parquet_err)?);
+            let props = WriterProperties::builder()
+                .set_compression(Compression::SNAPPY)
+                .set_key_value_metadata((!metadata.is_empty()).then_some(metadata))
+                .build();
+            let props = Arc::new(props);
+            let mut writer = SerializedFileWriter::new(std::fs::File::create(output)?, schema, props).map_err(parquet_err)?;
+
+            let mut columns = Columns::default();
+            let mut rows = 0;
+            for (n, line) in lines.enumerate() {
+                let line = line?;
+                if line.is_empty() {
+                    continue;
+                }
+                columns
+                    .push(&line)
+                    .ok_or_else(|| SysmonError::Convert(format!("{}:{}: malformed row", input.display(), n + first_row)))?;
+                rows += 1;
+                if columns.len() >= ROW_GROUP {
+                    columns.write(&mut writer).map_err(parquet_err)?;
+                }
+            }
+            if columns.len() > 0 {
+                columns.write(&mut writer).map_err(parquet_err)?;
+            }
+            writer.close().map_err(parquet_err)?;
+            Ok(rows)
+        }
+}
+
+pub mod event {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::mpsc::Sender;
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        use crossterm::event::{self, Event as CEvent, KeyEvent, MouseEvent};
+
+        pub enum AppEvent {
+            Tick,
+            Input(KeyEvent),
+            /// New terminal size in columns and rows.
+            Resize(u16, u16),
+            /// Only arrives while mouse capture is enabled (`[layout] mouse`).
+            Mouse(MouseEvent),
+            /// The terminal window gained or lost focus; needs focus reporting.
+            FocusGained,
+            FocusLost,
+            /// Pasted text in one piece; needs bracketed paste, otherwise a
+            /// paste arrives as one key event per character.
+            Paste(String),
+        }
+
+        pub struct EventHandler {
+            pub tick_rate: Duration,
+            /// Events sent but not yet received; the receiver calls `received`.
+            pending: Arc<AtomicUsize>,
+        }
+
+        impl EventHandler {
+            pub fn new(tick_rate: Duration) -> Self {
+                EventHandler { tick_rate, pending: Arc::new(AtomicUsize::new(0)) }
+            }
+
+            pub fn run(&self, tx: Sender<AppEvent>) {
+                let tick_rate = self.tick_rate;
+                let pending = Arc::clone(&self.pending);
+                let send = move |ev: AppEvent| {
+                    pending.fetch_add(1, Ordering::Relaxed);
+                    let _ = tx.send(ev);
+                };
+                std::thread::spawn(move || {
+                    loop {
+                        if event::poll(tick_rate).unwrap_or(false) {
+                            match event::read() {
+                                Ok(CEvent::Key(key)) => send(AppEvent::Input(key)),
+                                Ok(CEvent::Resize(cols, rows)) => send(AppEvent::Resize(cols, rows)),
+                                Ok(CEvent::Mouse(mouse)) => send(AppEvent::Mouse(mouse)),
+                                Ok(CEvent::FocusGained) => send(AppEvent::FocusGained),
+                                Ok(CEvent::FocusLost) => send(AppEvent::FocusLost),
+                                Ok(CEvent::Paste(text)) => send(AppEvent::Paste(text)),
+                                Err(_) => {}
+                            }
+                        } else {
+                            send(AppEvent::Tick);
+                        }
+                    }
+                });
+            }
+
+            /// Marks one event as taken off the channel.
+            pub fn received(&self) {
+                self.pending.fetch_sub(1, Ordering::Relaxed);
+            }
+
+            /// Number of events still queued in the channel.
+            pub fn backlog(&self) -> usize {
+                self.pending.load(Ordering::Relaxed)
+            }
+        }
+}
+
+pub mod diag {
+        use std::alloc::{GlobalAlloc, Layout, System};
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::time::Duration;
+
+        static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+        static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+        /// System allocator wrapper that counts allocations for the F12 overlay.
+        pub struct CountingAllocator;
+
+        unsafe impl GlobalAlloc for CountingAllocator {
+            unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+                ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+                ALLOCATED_BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+                System.alloc(layout)
+            }
+
+            unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+                System.dealloc(ptr, layout)
+            }
+
+            unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+                ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+                ALLOCATED_BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+                System.alloc_zeroed(layout)
+            }
+
+            unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+                ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+                ALLOCATED_BYTES.fetch_add(new_size as u64, Ordering::Relaxed);
+                System.realloc(ptr, layout, new_size)
+            }
+        }
+
+        /// `(count, bytes)` allocated since startup.
+        pub fn allocations() -> (u64, u64) {
+            (ALLOCATIONS.load(Ordering::Relaxed), ALLOCATED_BYTES.load(Ordering::Relaxed))
+        }
+
+        /// Timing and allocation figures for the monitor's own work.
+        #[derive(Default, Clone)]
+        pub struct Diagnostics {
+            pub collect: Duration,
+            pub collect_max: Duration,
+            /// Whether the last collect was a full (process) refresh.
+            pub collect_full: bool,
+            pub render: Duration,
+            pub render_max: Duration,
+            pub backlog: usize,
+            pub collect_allocs: (u64, u64),
+            pub render_allocs: (u64, u64),
+        }
+
+        impl Diagnostics {
+            pub fn record_collect(&mut self, took: Duration, full: bool, allocs: (u64, u64)) {
+                self.collect = took;
+                self.collect_max = self.collect_max.max(took);
+                self.collect_full = full;
+                self.collect_allocs = allocs;
+            }
+
+            pub fn record_render(&mut self, took: Duration, allocs: (u64, u64), backlog: usize) {
+                self.render = took;
+                self.render_max = self.render_max.max(took);
+                self.render_allocs = allocs;
+                self.backlog = backlog;
+            }
+        }
+
+        /// Runs `f`, returning its result, elapsed time and `(count, bytes)` allocated.
+        pub fn measure<T>(f: impl FnOnce() -> T) -> (T, Duration, (u64, u64)) {
+            let (c0, b0) = allocations();
+            let start = std::time::Instant::now();
+            let out = f();
+            let took = start.elapsed();
+            let (c1, b1) = allocations();
+            (out, took, (c1 - c0, b1 - b0))
+        }
+}
+
+pub mod types {
+        use std::collections::{HashMap, HashSet};
+
+        #[derive(Clone)]
+        pub struct CpuCoreUsage {
+            pub core_id: usize,
+            pub usage_percent: f32,
+            /// Threads in state R that last ran on this core.
+            pub runnable: u32,
+            /// From coretemp (per physical core) or k10temp (per CCD), shared
+            /// by the hyperthreads and cores behind the same sensor.
+            pub temp_celsius: Option<f32>,
+        }
+
+        /// Share of wall time all CPUs together spent in one idle state.
+        #[derive(Clone)]
+        pub struct CStateResidency {
+            pub name: String,
+            pub percent: f32,
+        }
+
+        /// Why CPU frequency is held below its maximum.
+        #[derive(Clone, Copy, PartialEq)]
+        pub enum FreqLimit {
+            /// `thermal_throttle` core or package events since the previous
+            /// full refresh.
+            Thermal { package: bool },
+            /// Power-limit notifications since the previous full refresh.
+            PowerLimit,
+            /// intel_pstate `no_turbo` is set.
+            TurboOff,
+            /// intel_pstate `max_perf_pct` below 100.
+            MaxPerf(u32),
+            /// The lowest `scaling_max_freq` below its `cpuinfo_max_freq`.
+            PolicyCap { max_khz: u64, hw_max_khz: u64 },
+        }
+
+        impl FreqLimit {
+            pub fn label(&self) -> String {
+                match self {
+                    FreqLimit::Thermal { package: false } => "thermal (core)".to_string(),
+                    FreqLimit::Thermal { package: true } => "thermal (package)".to_string(),
+                    FreqLimit::PowerLimit => "power limit".to_string(),
+                    FreqLimit::TurboOff => "turbo off".to_string(),
+                    FreqLimit::MaxPerf(pct) => format!("max_perf {}%", pct),
+                    FreqLimit::PolicyCap { max_khz, hw_max_khz } => format!(
+                        "policy cap {:.2}/{:.2} GHz",
+                        *max_khz as f64 / 1e6,
+                        *hw_max_khz as f64 / 1e6
+                    ),
+                }
+            }
+
+            /// Hardware throttling happening now, as opposed to a setting.
+            pub fn is_event(&self) -> bool {
+                matches!(self, FreqLimit::Thermal { .. } | FreqLimit::PowerLimit)
+            }
+        }
+
+        /// CPUs sharing one cpufreq policy (a big.LITTLE cluster on ARM SoCs).
+        #[derive(Clone)]
+        pub struct CpuCluster {
+            pub cpus: Vec<usize>,
+            pub cur_khz: u64,
+            /// Current governor ceiling (`scaling_max_freq`), lowered when throttled.
+            pub max_khz: u64,
+            /// Hardware maximum (`cpuinfo_max_freq`).
+            pub hw_max_khz: u64,
+        }
+
+        impl CpuCluster {
+            /// Compact CPU list: `0-3`, or `0,2,5` when not contiguous.
+            pub fn cpu_list(&self) -> String {
+                match (self.cpus.first(), self.cpus.last()) {
+                    (Some(first), Some(last)) if last - first + 1 == self.cpus.len() && first != last => {
+                        format!("{}-{}", first, last)
+                    }
+                    _ => self.cpus.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(","),
+                }
+            }
+        }
+
+        #[derive(Clone)]
+        pub struct RamSwapUsage {
+            pub used: u64,
+            pub total: u64,
+        }
+
+        /// Kernel clock discipline state from `adjtimex`, as steered by
+        /// ntpd, chrony or systemd-timesyncd.
+        #[derive(Clone, Copy)]
+        pub struct ClockSync {
+            /// False while `STA_UNSYNC` is set, i.e. nothing is steering the clock.
+            pub synchronized: bool,
+            /// Offset still being slewed out, in ms.
+            pub offset_ms: f64,
+            /// The kernel's bound on the clock error, in ms; grows while
+            /// unsynchronized.
+            pub max_error_ms: f64,
+            /// Frequency correction, in ppm.
+            pub freq_ppm: f64,
+        }
+
+        /// Page cache waiting to reach disk, from `/proc/meminfo`.
+        #[derive(Clone, Copy, Default)]
+        pub struct DirtyPages {
+            /// Modified and not yet queued for writeback.
+            pub dirty: u64,
+            /// Being written back now.
+            pub writeback: u64,
+        }
+
+        /// RAM the gauge counts as used without any process owning it.
+        #[derive(Clone, Default)]
+        pub struct MemoryBreakdown {
+            /// `Shmem` from `/proc/meminfo`: tmpfs files plus shared memory
+            /// segments.
+            pub shmem: u64,
+            /// Mounted tmpfs filesystems, sorted by mount point.
+            pub tmpfs: Vec<FilesystemUsage>,
+            /// `None` when KSM is stopped and has nothing merged.
+            pub ksm: Option<KsmStats>,
+            /// `None` when the kernel has no transparent hugepage support.
+            pub thp: Option<ThpStats>,
+        }
+
+        /// Transparent hugepage settings and usage.
+        #[derive(Clone)]
+        pub struct ThpStats {
+            /// The bracketed choice in `transparent_hugepage/enabled`:
+            /// always, madvise or never.
+            pub mode: String,
+            /// `AnonHugePages` from `/proc/meminfo`.
+            pub anon_bytes: u64,
+            /// khugepaged's collapse and full-scan counters since boot.
+            pub collapsed: u64,
+            pub full_scans: u64,
+        }
+
+        /// Kernel samepage merging counters from `/sys/kernel/mm/ksm`.
+        #[derive(Clone, Copy)]
+        pub struct KsmStats {
+            /// `run` is 1; merged pages stay merged after it is set back to 0.
+            pub running: bool,
+            /// Deduplicated pages in use.
+            pub pages_shared: u64,
+            /// Mappings pointing at those pages.
+            pub pages_sharing: u64,
+            pub page_size: u64,
+        }
+
+        impl KsmStats {
+            /// Every sharing mapping beyond the first would otherwise be its
+            /// own page, so `pages_sharing` is the number of pages saved.
+            pub fn saved_bytes(&self) -> u64 {
+                self.pages_sharing * self.page_size
+            }
+        }
+
+        #[derive(Clone)]
+        pub struct NetworkStats {
+            pub received_bytes: u64,
+            pub transmitted_bytes: u64,
+            /// Per-interface figures, sorted by name.
+            pub interfaces: Vec<InterfaceStats>,
+        }
+
+        #[derive(Clone)]
+        pub struct InterfaceStats {
+            pub name: String,
+            pub received_bytes: u64,
+            pub transmitted_bytes: u64,
+            pub link: LinkInfo,
+        }
+
+        /// Link state from `/sys/class/net/<iface>/{operstate,speed}`.
+        #[derive(Clone, Default)]
+        pub struct LinkInfo {
+            /// `up`, `down`, `dormant`, `unknown`, ... (empty if unreadable).
+            pub operstate: String,
+            /// Negotiated speed; `None` when down or not reported (virtual links).
+            pub speed_mbps: Option<u32>,
+        }
+
+        impl LinkInfo {
+            pub fn read(iface: &str) -> Self {
+                let base = std::path::Path::new("/sys/class/net").join(iface);
+                let operstate = std::fs::read_to_string(base.join("operstate"))
+                    .map(|s| s.trim().to_string())
+                    .unwrap_or_default();
+                let speed_mbps = std::fs::read_to_string(base.join("speed"))
+                    .ok()
+                    .and_then(|s| s.trim().parse::<i64>().ok())
+                    .filter(|&s| s > 0)
+                    .map(|s| s as u32);
+                Self { operstate, speed_mbps }
+            }
+        }
+
+        #[derive(Clone)]
+        pub struct DiskIOStats {
+            pub read_bytes: u64,
+            pub write_bytes: u64,
+        }
+
+        /// Bytes one whole block device moved since the previous tick.
+        #[derive(Clone)]
+        pub struct DeviceTraffic {
+            pub name: String,
+            pub read_bytes: u64,
+            pub write_bytes: u64,
+        }
+
+        #[derive(Clone)]
+        pub struct ProcessInfo {
+            pub pid: i32,
+            pub ppid: Option<i32>,
+            pub name: String,
+            /// Full command line, space-joined.
+            pub cmd: String,
+            pub cpu_percent: f32,
+            pub mem_bytes: u64,
+            /// A thread listed alongside its process; its usage is already
+            /// counted in the process's own figures.
+            pub thread: bool,
+            /// Owner's login name, or the numeric UID without a passwd entry.
+            pub user: String,
+            /// Thread count; 0 for thread entries.
+            pub threads: u32,
+            /// Disk read and write rates in bytes/s over the last process refresh.
+            pub read_rate: u64,
+            pub write_rate: u64,
+            /// Only read while the nice column is shown.
+            pub priority: Option<Priority>,
+            /// Container or systemd unit from the cgroup path; only read while
+            /// the unit column is shown.
+            pub unit: String,
+            /// Times per second the process's threads were scheduled in; only
+            /// read while sorting by or showing wakeups.
+            pub wakeups: f64,
+            /// Socket receive and send rates in bytes/s; only counted while
+            /// the net column is shown, in builds with the `ebpf` feature.
+            pub net_rx_rate: u64,
+            pub net_tx_rate: u64,
+        }
+
+        /// Names the container or systemd unit a process belongs to from the
+        /// contents of `/proc/<pid>/cgroup`: `docker:3f2a9c1b7d4e`,
+        /// `podman:…`, `k8s:…`, `lxc:web`, `nginx.service`, `session-2.scope`.
+        /// Empty for the root cgroup.
+        pub fn cgroup_owner(cgroup: &str) -> String {
+            // cgroup v2's unified line, else v1's systemd hierarchy, else any
+            // controller placed below the root.
+            let paths: Vec<(&str, &str)> = cgroup
+                .lines()
+                .filter_map(|l| {
+                    let mut parts = l.splitn(3, ':');
+                    let (_, controllers, path) = (parts.next()?, parts.next()?, parts.next()?);
+                    Some((controllers, path))
+                })
+                .filter(|(_, path)| *path != "/")
+                .collect();
+            let path = paths
+                .iter()
+                .find(|(c, _)| c.is_empty())
+                .or_else(|| paths.iter().find(|(c, _)| *c == "name=systemd"))
+                .or_else(|| paths.first())
+                .map_or("", |(_, path)| path);
+
+            let short = |id: &str| id.chars().take(12).collect::<String>();
+            let is_id = |s: &str| s.len() >= 32 && s.chars().all(|c| c.is_ascii_hexdigit());
+            let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+            let kube = segments.iter().any(|s| s.starts_with("kubepods"));
+            for (i, seg) in segments.iter().enumerate().rev() {
+                let scope = seg.strip_suffix(".scope").unwrap_or(seg);
+                if let Some(id) = scope.strip_prefix("docker-") {
+                    return format!("{}:{}", if kube { "k8s" } else { "docker" }, short(id));
+                }
+                if let Some(id) = scope.strip_prefix("libpod-") {
+                    return format!("podman:{}", short(id));
+                }
+                if let Some(id) = scope.strip_prefix("cri-containerd-").or_else(|| scope.strip_prefix("crio-")) {
+                    return format!("k8s:{}", short(id));
+                }
+                if let Some(name) = seg.strip_prefix("lxc.payload.") {
+                    return format!("lxc:{}", name);
+                }
+                if is_id(seg) {
+                    let runtime = match i.checked_sub(1).map(|p| segments[p]) {
+                        _ if kube => "k8s",
+                        Some("docker") => "docker",
+                        Some(parent) if parent.starts_with("lxc") => "lxc",
+                        _ => "container",
+                    };
+                    return format!("{}:{}", runtime, short(seg));
+                }
+            }
+            // Not containerized (a unit inside a container would name less
+            // than the container does): the innermost systemd unit.
+            segments
+                .iter()
+                .rev()
+                .find(|s| s.ends_with(".service") || s.ends_with(".scope"))
+                .or(segments.last())
+                .map_or(String::new(), |s| s.to_string())
+        }
+
+        /// Scheduling settings from `/proc/<pid>/stat`.
+        #[derive(Clone, Copy)]
+        pub struct Priority {
+            pub nice: i32,
+            /// Real-time priority (1-99) under FIFO/RR, else 0.
+            pub rt_priority: u32,
+            /// `SCHED_*` policy number.
+            pub policy: u32,
+        }
+
+        impl Priority {
+            /// Scheduling class as `ps -o cls` abbreviates it.
+            pub fn class(self) -> &'static str {
+                match self.policy {
+                    0 => "TS",
+                    1 => "FF",
+                    2 => "RR",
+                    3 => "B",
+                    5 => "IDL",
+                    6 => "DLN",
+                    _ => "?",
+                }
+            }
+
+            pub fn is_realtime(self) -> bool {
+                matches!(self.policy, 1 | 2 | 6)
+            }
+        }
+
+        #[derive(Clone)]
+        pub struct ThermalInfo {
+            /// Stable source name: the sysfs zone directory or the raw hwmon label.
+            pub id: String,
+            pub label: String,
+            /// `label` as read, before any `[sensors] rename`.
+            pub raw_label: String,
+            pub temp_celsius: f32,
+            pub critical_celsius: Option<f32>,
+        }
+
+        /// Space usage of one mounted filesystem.
+        #[derive(Clone)]
+        pub struct FilesystemUsage {
+            pub mount: String,
+            pub device: String,
+            pub fs_type: String,
+            pub total: u64,
+            pub available: u64,
+            pub read_only: bool,
+        }
+
+        impl FilesystemUsage {
+            pub fn used(&self) -> u64 {
+                self.total - self.available.min(self.total)
+            }
+
+            pub fn used_percent(&self) -> f64 {
+                if self.total == 0 {
+                    return 0.0;
+                }
+                self.used() as f64 / self.total as f64 * 100.0
+            }
+        }
+
+        /// A filesystem that appeared or went away since the previous refresh.
+        pub enum MountChange {
+            Mounted(String),
+            Unmounted(String),
+        }
+
+        /// A block device or network interface plugged in or removed at runtime.
+        pub struct DeviceChange {
+            pub kind: &'static str,
+            pub name: String,
+            pub added: bool,
+        }
+
+        /// Raspberry Pi firmware throttling bits as reported by `get_throttled`.
+        /// The low bits are the current state, bits 16+ latch "has occurred
+        /// since boot".
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        pub struct PiThrottle(pub u32);
+
+        impl PiThrottle {
+            const FLAGS: [(u32, &'static str); 4] = [
+                (0, "under-voltage"),
+                (1, "freq capped"),
+                (2, "throttled"),
+                (3, "soft temp limit"),
+            ];
+
+            /// Conditions active right now.
+            pub fn current(self) -> Vec<&'static str> {
+                Self::FLAGS.iter().filter(|(bit, _)| self.0 & (1 << bit) != 0).map(|(_, n)| *n).collect()
+            }
+
+            /// Conditions seen since boot that are no longer active.
+            pub fn past(self) -> Vec<&'static str> {
+                Self::FLAGS
+                    .iter()
+                    .filter(|(bit, _)| self.0 & (1 << (bit + 16)) != 0 && self.0 & (1 << bit) == 0)
+                    .map(|(_, n)| *n)
+                    .collect()
+            }
+        }
+
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        pub enum SortOrder {
+            Cpu,
+            Mem,
+            /// Scheduler wakeups per second, for finding power hogs.
+            Wakeups,
+        }
+
+        impl SortOrder {
+            pub fn label(self) -> &'static str {
+                match self {
+                    SortOrder::Cpu => "CPU",
+                    SortOrder::Mem => "memory",
+                    SortOrder::Wakeups => "wakeups",
+                }
+            }
+        }
+
+        /// What the large right-hand pane shows.
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        pub enum Tab {
+            Processes,
+            Connections,
+            Storage,
+            Interrupts,
+            /// Two metric histories on one time axis.
+            Compare,
+        }
+
+        impl Tab {
+            pub const ALL: [Tab; 5] = [Tab::Processes, Tab::Connections, Tab::Storage, Tab::Interrupts, Tab::Compare];
+
+            pub fn label(self) -> &'static str {
+                match self {
+                    Tab::Processes => "processes",
+                    Tab::Connections => "connections",
+                    Tab::Storage => "storage",
+                    Tab::Interrupts => "interrupts",
+                    Tab::Compare => "compare",
+                }
+            }
+
+            pub fn next(self) -> Tab {
+                let i = Self::ALL.iter().position(|t| *t == self).unwrap_or(0);
+                Self::ALL[(i + 1) % Self::ALL.len()]
+            }
+
+            pub fn from_label(label: &str) -> Option<Tab> {
+                Self::ALL.into_iter().find(|t| t.label() == label)
+            }
+        }
+
+        /// Process filter set with `:filter`: a case-insensitive substring of
+        /// the name, `/regex/` matched against the name or command line, or
+        /// `~fuzzy` for the name containing those characters in order.
+        #[derive(Clone)]
+        pub struct ProcessFilter {
+            /// As typed, for display.
+            pub text: String,
+            kind: FilterKind,
+        }
+
+        #[derive(Clone)]
+        enum FilterKind {
+            Substring(String),
+            Regex(regex::Regex),
+            Fuzzy(Vec<char>),
+        }
+
+        impl ProcessFilter {
+            pub fn parse(text: &str) -> Result<Self, String> {
+                let kind = if let Some(pattern) = text.strip_prefix('/') {
+                    let pattern = pattern.strip_suffix('/').unwrap_or(pattern);
+                    let re = regex::Regex::new(pattern).map_err(|e| {
+                        // Syntax errors repeat the pattern with a caret; keep the reason.
+                        let msg = e.to_string();
+                        msg.lines().find_map(|l| l.strip_prefix("error: ")).unwrap_or(&msg).to_string()
+                    })?;
+                    FilterKind::Regex(re)
+                } else if let Some(query) = text.strip_prefix('~') {
+                    FilterKind::Fuzzy(query.to_lowercase().chars().collect())
+                } else {
+                    FilterKind::Substring(text.to_lowercase())
+                };
+                Ok(Self { text: text.to_string(), kind })
+            }
+
+            pub fn matches(&self, p: &ProcessInfo) -> bool {
+                match self.kind {
+                    FilterKind::Substring(ref needle) => p.name.to_lowercase().contains(needle.as_str()),
+                    FilterKind::Regex(ref re) => re.is_match(&p.name) || re.is_match(&p.cmd),
+                    FilterKind::Fuzzy(ref query) => {
+                        let mut rest = query.iter().peekable();
+                        for c in p.name.chars().flat_map(char::to_lowercase) {
+                            if rest.peek() == Some(&&c) {
+                                rest.next();
+                            }
+                        }
+                        rest.peek().is_none()
+                    }
+                }
+            }
+        }
+
+        /// Processes in display order: filtered and sorted descending by the
+        /// chosen key.
+        pub fn visible_processes<'a>(
+            data: &'a [ProcessInfo],
+            sort_order: SortOrder,
+            filter: Option<&ProcessFilter>,
+        ) -> Vec<&'a ProcessInfo> {
+            let mut sorted: Vec<&ProcessInfo> = data.iter().filter(|p| filter.is_none_or(|f| f.matches(p))).collect();
+            match sort_order {
+                SortOrder::Cpu => sorted.sort_by(|a, b| {
+                    b.cpu_percent
+                        .partial_cmp(&a.cpu_percent)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                }),
+                SortOrder::Mem => sorted.sort_by_key(|p| std::cmp::Reverse(p.mem_bytes)),
+                SortOrder::Wakeups => sorted.sort_by(|a, b| b.wakeups.partial_cmp(&a.wakeups).unwrap_or(std::cmp::Ordering::Equal)),
+            }
+            sorted
+        }
+
+        /// Reorders `list` to follow `order` (PIDs), for a table held still
+        /// while a row is selected. Processes started since go last.
+        pub fn keep_order(list: &mut [&ProcessInfo], order: &[i32]) {
+            let rank: HashMap<i32, usize> = order.iter().enumerate().map(|(i, pid)| (*pid, i)).collect();
+            list.sort_by_key(|p| rank.get(&p.pid).copied().unwrap_or(usize::MAX));
+        }
+
+        /// One line of the process tree.
+        pub struct TreeRow<'a> {
+            pub process: &'a ProcessInfo,
+            pub depth: usize,
+            pub has_children: bool,
+            /// Descendants folded away under this row; 0 unless it is collapsed.
+            pub hidden: usize,
+            /// CPU% of this process plus all its descendants (threads excluded).
+            pub total_cpu: f32,
+            pub total_mem: u64,
+        }
+
+        /// Processes nested under their parents, siblings sorted by the chosen
+        /// key summed over their subtree, skipping the children of `collapsed`
+        /// PIDs. With a filter, the matches are shown together with their
+        /// ancestors; totals always cover every descendant.
+        pub fn process_tree<'a>(
+            data: &'a [ProcessInfo],
+            sort_order: SortOrder,
+            filter: Option<&ProcessFilter>,
+            collapsed: &HashSet<i32>,
+        ) -> Vec<TreeRow<'a>> {
+            let mut shown: HashSet<i32> = visible_processes(data, sort_order, filter).iter().map(|p| p.pid).collect();
+            if filter.is_some() {
+                let parents: HashMap<i32, i32> = data.iter().filter_map(|p| Some((p.pid, p.ppid?))).collect();
+                for pid in shown.clone() {
+                    let mut cur = pid;
+                    while let Some(&parent) = parents.get(&cur) {
+                        if !shown.insert(parent) {
+                            break;
+                        }
+                        cur = parent;
+                    }
+                }
+            }
+
+            let totals = subtree_totals(data);
+            let total = |p: &ProcessInfo| totals.get(&p.pid).copied().unwrap_or((p.cpu_percent, p.mem_bytes));
+            let mut roots = Vec::new();
+            let mut children: HashMap<i32, Vec<&ProcessInfo>> = HashMap::new();
+            let present: HashSet<i32> = data.iter().map(|p| p.pid).collect();
+            for p in data.iter().filter(|p| shown.contains(&p.pid)) {
+                match p.ppid.filter(|pp| present.contains(pp) && shown.contains(pp)) {
+                    Some(parent) => children.entry(parent).or_default().push(p),
+                    None => roots.push(p),
+                }
+            }
+            let sort = |list: &mut Vec<&ProcessInfo>| match sort_order {
+                SortOrder::Cpu => list.sort_by(|a, b| total(b).0.partial_cmp(&total(a).0).unwrap_or(std::cmp::Ordering::Equal)),
+                SortOrder::Mem => list.sort_by_key(|p| std::cmp::Reverse(total(p).1)),
+                SortOrder::Wakeups => list.sort_by(|a, b| b.wakeups.partial_cmp(&a.wakeups).unwrap_or(std::cmp::Ordering::Equal)),
+            };
+            sort(&mut roots);
+            children.values_mut().for_each(sort);
+
+            let mut rows = Vec::new();
+            let mut stack: Vec<(&ProcessInfo, usize)> = roots.into_iter().rev().map(|p| (p, 0)).collect();
+            while let Some((p, depth)) = stack.pop() {
+                let kids = children.get(&p.pid).map(Vec::as_slice).unwrap_or_default();
+                let folded = !kids.is_empty() && collapsed.contains(&p.pid);
+                let hidden = if folded { count_descendants(p.pid, &children) } else { 0 };
+                let (total_cpu, total_mem) = total(p);
+                rows.push(TreeRow { process: p, depth, has_children: !kids.is_empty(), hidden, total_cpu, total_mem });
+                if !folded {
+                    stack.extend(kids.iter().rev().map(|k| (*k, depth + 1)));
+                }
+            }
+            rows
+        }
+
+        /// Own plus descendant usage per PID. Threads are left out of every
+        /// sum so they are not counted twice.
+        fn subtree_totals(data: &[ProcessInfo]) -> HashMap<i32, (f32, u64)> {
+            let procs: HashMap<i32, &ProcessInfo> = data.iter().filter(|p| !p.thread).map(|p| (p.pid, p)).collect();
+            let mut children: HashMap<i32, Vec<i32>> = HashMap::new();
+            let mut pending = Vec::new();
+            for p in procs.values() {
+                match p.ppid.filter(|pp| procs.contains_key(pp)) {
+                    Some(parent) => children.entry(parent).or_default().push(p.pid),
+                    None => pending.push(p.pid),
+                }
+            }
+            // Pre-order walk; visiting it backwards sees every child before its parent.
+            let mut order = Vec::with_capacity(procs.len());
+            while let Some(pid) = pending.pop() {
+                order.push(pid);
+                pending.extend(children.get(&pid).into_iter().flatten());
+            }
+            let mut totals: HashMap<i32, (f32, u64)> = procs.values().map(|p| (p.pid, (p.cpu_percent, p.mem_bytes))).collect();
+            for pid in order.into_iter().rev() {
+                if let Some(parent) = procs[&pid].ppid.filter(|pp| procs.contains_key(pp)) {
+                    let (cpu, mem) = totals[&pid];
+                    let t = totals.get_mut(&parent).expect("parent is a process");
+                    t.0 += cpu;
+                    t.1 += mem;
+                }
+            }
+            totals
+        }
+
+        fn count_descendants(pid: i32, children: &HashMap<i32, Vec<&ProcessInfo>>) -> usize {
+            let mut count = 0;
+            let mut pending = vec![pid];
+            while let Some(pid) = pending.pop() {
+                if let Some(kids) = children.get(&pid) {
+                    count += kids.len();
+                    pending.extend(kids.iter().map(|k| k.pid));
+                }
+            }
+            count
+        }
+
+        #[derive(Clone)]
+        pub struct SystemMetrics {
+            pub cpu: Vec<CpuCoreUsage>,
+            pub ram: RamSwapUsage,
+            pub swap: RamSwapUsage,
+            /// Refreshed with the filesystems.
+            pub memory: MemoryBreakdown,
+            pub dirty: DirtyPages,
+            pub network: NetworkStats,
+            pub disk_io: DiskIOStats,
+            /// Per-device traffic from `/proc/diskstats`, sorted by name.
+            pub block_io: Vec<DeviceTraffic>,
+            pub processes: Vec<ProcessInfo>,
+            pub thermals: Vec<ThermalInfo>,
+            pub filesystems: Vec<FilesystemUsage>,
+            /// `None` when not running on a Raspberry Pi.
+            pub pi_throttle: Option<PiThrottle>,
+            /// Frequency clusters; empty unless several CPUs share each policy.
+            pub clusters: Vec<CpuCluster>,
+            /// Reasons the CPU is running below its maximum frequency.
+            pub freq_limits: Vec<FreqLimit>,
+            /// Idle-state residency since the previous full refresh; empty
+            /// without cpuidle.
+            pub cstates: Vec<CStateResidency>,
+            /// Runnable and total scheduling entities from `/proc/loadavg`.
+            pub run_queue: Option<(u32, u32)>,
+            /// Mean runqueue wait per timeslice across all CPUs, in ms.
+            pub sched_wait_ms: Option<f64>,
+            pub batteries: Vec<crate::battery::BatteryInfo>,
+            /// `None` where `adjtimex` is unavailable.
+            pub clock: Option<ClockSync>,
+        }
+
+        /// Rolling history for sparkline widgets.
+        pub struct SparklineHistory {
+            pub net_rx: std::collections::VecDeque<u64>,
+            pub net_tx: std::collections::VecDeque<u64>,
+            pub disk_read: std::collections::VecDeque<u64>,
+            pub disk_write: std::collections::VecDeque<u64>,
+            /// Dirty plus writeback bytes; a level, not a per-tick delta.
+            pub dirty: std::collections::VecDeque<u64>,
+            /// RX/TX history per interface name.
+            pub ifaces: std::collections::BTreeMap<String, (std::collections::VecDeque<u64>, std::collections::VecDeque<u64>)>,
+            /// Read/write history per block device name.
+            pub disks: std::collections::BTreeMap<String, (std::collections::VecDeque<u64>, std::collections::VecDeque<u64>)>,
+            capacity: usize,
+        }
+
+        impl SparklineHistory {
+            pub fn new(capacity: usize) -> Self {
+                Self {
+                    net_rx: std::collections::VecDeque::with_capacity(capacity),
+                    net_tx: std::collections::VecDeque::with_capacity(capacity),
+                    disk_read: std::collections::VecDeque::with_capacity(capacity),
+                    disk_write: std::collections::VecDeque::with_capacity(capacity),
+                    dirty: std::collections::VecDeque::with_capacity(capacity),
+                    ifaces: std::collections::BTreeMap::new(),
+                    disks: std::collections::BTreeMap::new(),
+                    capacity,
+                }
+            }
+
+            pub fn push(&mut self, net: &NetworkStats, disk: &DiskIOStats, devices: &[DeviceTraffic], dirty: DirtyPages) {
+                if self.net_rx.len() >= self.capacity {
+                    self.net_rx.pop_front();
+                    self.net_tx.pop_front();
+                    self.disk_read.pop_front();
+                    self.disk_write.pop_front();
+                    self.dirty.pop_front();
+                }
+                self.net_rx.push_back(net.received_bytes);
+                self.net_tx.push_back(net.transmitted_bytes);
+                self.disk_read.push_back(disk.read_bytes);
+                self.disk_write.push_back(disk.write_bytes);
+                self.dirty.push_back(dirty.dirty + dirty.writeback);
+
+                self.ifaces.retain(|name, _| net.interfaces.iter().any(|i| &i.name == name));
+                for iface in &net.interfaces {
+                    let (rx, tx) = self.ifaces.entry(iface.name.clone()).or_default();
+                    if rx.len() >= self.capacity {
+                        rx.pop_front();
+                        tx.pop_front();
+                    }
+                    rx.push_back(iface.received_bytes);
+                    tx.push_back(iface.transmitted_bytes);
+                }
+
+                self.disks.retain(|name, _| devices.iter().any(|d| &d.name == name));
+                for device in devices {
+                    let (read, write) = self.disks.entry(device.name.clone()).or_default();
+                    if read.len() >= self.capacity {
+                        read.pop_front();
+                        write.pop_front();
+                    }
+                    read.push_back(device.read_bytes);
+                    write.push_back(device.write_bytes);
+                }
+            }
+
+            /// RX and TX series for one interface, or the aggregate for `None`.
+            pub fn net_series(
+                &self,
+                iface: Option<&str>,
+            ) -> (&std::collections::VecDeque<u64>, &std::collections::VecDeque<u64>) {
+                match iface.and_then(|name| self.ifaces.get(name)) {
+                    Some((rx, tx)) => (rx, tx),
+                    None => (&self.net_rx, &self.net_tx),
+                }
+            }
+
+            /// Read and write series for one block device, or the aggregate for
+            /// `None`. A device without samples gets empty series rather than
+            /// the aggregate under its name.
+            pub fn disk_series(
+                &self,
+                device: Option<&str>,
+            ) -> (&std::collections::VecDeque<u64>, &std::collections::VecDeque<u64>) {
+                static EMPTY: std::collections::VecDeque<u64> = std::collections::VecDeque::new();
+                match device.map(|name| self.disks.get(name)) {
+                    Some(Some((read, write))) => (read, write),
+                    Some(None) => (&EMPTY, &EMPTY),
+                    None => (&self.disk_read, &self.disk_write),
+                }
+            }
+        }
+
+        /// `(p50, p95)` of average CPU% and of the hottest sensor.
+        #[derive(Clone, Copy)]
+        pub struct Percentiles {
+            pub cpu: (f32, f32),
+            /// `None` without sensors.
+            pub temp: Option<(f32, f32)>,
+        }
+
+        /// Average CPU% and the hottest sensor per full refresh over a time
+        /// window, for percentiles.
+        pub struct RollingStats {
+            window: std::time::Duration,
+            samples: std::collections::VecDeque<(std::time::Instant, f32, Option<f32>)>,
+        }
+
+        impl RollingStats {
+            pub fn new(window: std::time::Duration) -> Self {
+                Self { window, samples: std::collections::VecDeque::new() }
+            }
+
+            pub fn push(&mut self, cpu: &[CpuCoreUsage], thermals: &[ThermalInfo]) {
+                let now = std::time::Instant::now();
+                while self.samples.front().is_some_and(|(t, _, _)| now.duration_since(*t) > self.window) {
+                    self.samples.pop_front();
+                }
+                let avg = cpu.iter().map(|c| c.usage_percent).sum::<f32>() / cpu.len().max(1) as f32;
+                let hottest = thermals.iter().map(|t| t.temp_celsius).reduce(f32::max);
+                self.samples.push_back((now, avg, hottest));
+            }
+
+            pub fn percentiles(&self) -> Option<Percentiles> {
+                // Nearest rank on a sorted copy
+                let pair = |mut values: Vec<f32>| {
+                    if values.is_empty() {
+                        return None;
+                    }
+                    values.sort_by(f32::total_cmp);
+                    let rank = |p: f32| values[((p * values.len() as f32).ceil() as usize).clamp(1, values.len()) - 1];
+                    Some((rank(0.50), rank(0.95)))
+                };
+                Some(Percentiles {
+                    cpu: pair(self.samples.iter().map(|s| s.1).collect())?,
+                    temp: pair(self.samples.iter().filter_map(|s| s.2).collect()),
+                })
+            }
+        }
+}
+
+pub mod command {
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+        use crate::config::{LayoutPreset, Theme};
+        use crate::types::{SortOrder, Tab};
+
+        /// Commands accepted by the `:` prompt.
+        pub enum Command {
+            Sort(SortOrder),
+            /// `None` clears the active filter.
+            Filter(Option<String>),
+            Log(LogAction),
+            Snapshot,
+            /// Toggles the process tree.
+            Tree,
+            Dump,
+            Export,
+            /// Writes the Markdown session summary.
+            Summary,
+            /// Writes the sustained-offender report.
+            Offenders,
+            /// Switches to a config profile; `None` is the base config.
+            Profile(Option<String>),
+            Layout(LayoutPreset),
+            Theme(Theme),
+            /// Plots two metrics in the compare view.
+            Compare(String, String),
+            /// Ghosts the compare view's window from this long ago; `None` turns it off.
+            Overlay(Option<std::time::Duration>),
+            View(Tab),
+            ScanFaster,
+            ScanSlower,
+            /// `true` locks the session read-only, `false` unlocks it.
+            Lock(bool),
+            Quit,
+        }
+
+        impl Command {
+            /// Writes files or swaps the config, so refused while locked.
+            pub fn needs_unlock(&self) -> bool {
+                matches!(
+                    self,
+                    Command::Log(_)
+                        | Command::Snapshot
+                        | Command::Dump
+                        | Command::Export
+                        | Command::Summary
+                        | Command::Offenders
+                        | Command::Profile(_)
+                )
+            }
+
+            /// The command a single-key shortcut stands for, if any, so keys
+            /// go through the same `needs_unlock` check. `P` cycles profiles,
+            /// which is a profile switch.
+            pub fn for_key(key: &KeyEvent) -> Option<Command> {
+                match key.code {
+                    KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::ALT) => Some(Command::Log(LogAction::Toggle)),
+                    KeyCode::Char('l' | 'L') => Some(Command::Snapshot),
+                    KeyCode::Char('e') => Some(Command::Export),
+                    KeyCode::Char('o') => Some(Command::Offenders),
+                    KeyCode::Char('P') => Some(Command::Profile(None)),
+                    KeyCode::Char('S') => Some(Command::Summary),
+                    KeyCode::Char('d') => Some(Command::Dump),
+                    _ => None,
+                }
+            }
+        }
+
+        pub enum LogAction {
+            Start,
+            Stop,
+            Toggle,
+        }
+
+        /// Parses a prompt line (without the leading `:`) into a command.
+        pub fn parse(input: &str) -> Result<Command, String> {
+            let input = input.trim();
+            let (name, arg) = match input.split_once(char::is_whitespace) {
+                Some((n, a)) => (n, a.trim()),
+                None => (input, ""),
+            };
+            match name {
+                "sort" => match arg {
+                    "cpu" => Ok(Command::Sort(SortOrder::Cpu)),
+                    "mem" => Ok(Command::Sort(SortOrder::Mem)),
+                    "wakeups" => Ok(Command::Sort(SortOrder::Wakeups)),
+                    _ => Err(format!("sort: expected cpu|mem|wakeups, got '{}'", arg)),
+                },
+                "filter" => Ok(Command::Filter(if arg.is_empty() {
+                    None
+                } else {
+                    Some(arg.to_string())
+                })),
+                "log" => match arg {
+                    "start" => Ok(Command::Log(LogAction::Start)),
+                    "stop" => Ok(Command::Log(LogAction::Stop)),
+                    "" => Ok(Command::Log(LogAction::Toggle)),
+                    _ => Err(format!("log: expected start|stop, got '{}'", arg)),
+                },
+                "snap" | "snapshot" => Ok(Command::Snapshot),
+                "dump" => Ok(Command::Dump),
+                "tree" => Ok(Command::Tree),
+                "export" => Ok(Command::Export),
+                "offenders" => Ok(Command::Offenders),
+                "summary" => Ok(Command::Summary),
+                "profile" => Ok(Command::Profile((!arg.is_empty()).then(|| arg.to_string()))),
+                "layout" => LayoutPreset::from_label(arg)
+                    .map(Command::Layout)
+                    .ok_or_else(|| format!("layout: expected overview|processes|network|thermals, got '{}'", arg)),
+                "theme" => Theme::from_label(arg)
+                    .map(Command::Theme)
+                    .ok_or_else(|| format!("theme: expected dark|light, got '{}'", arg)),
+                "compare" => match arg.split_whitespace().collect::<Vec<_>>()[..] {
+                    [top, bottom] => Ok(Command::Compare(top.to_string(), bottom.to_string())),
+                    _ => Err("compare: expected two metric names, e.g. 'compare cpu.avg temp.composite'".to_string()),
+                },
+                "overlay" => match arg {
+                    "off" => Ok(Command::Overlay(None)),
+                    _ => crate::compare::parse_offset(arg)
+                        .map(|offset| Command::Overlay(Some(offset)))
+                        .ok_or_else(|| format!("overlay: expected a time like 30m or 1h, or off, got '{}'", arg)),
+                },
+                "view" => Tab::from_label(arg)
+                    .map(Command::View)
+                    .ok_or_else(|| format!("view: expected processes|connections|storage|interrupts|compare, got '{}'", arg)),
+                "scan" => match arg {
+                    "faster" => Ok(Command::ScanFaster),
+                    "slower" => Ok(Command::ScanSlower),
+                    _ => Err(format!("scan: expected faster|slower, got '{}'", arg)),
+                },
+                "lock" => Ok(Command::Lock(true)),
+                "unlock" => Ok(Command::Lock(false)),
+                "q" | "quit" => Ok(Command::Quit),
+                "" => Err("empty command".to_string()),
+                _ => Err(format!("unknown command: {}", name)),
+            }
+        }
+}
+
+pub mod latency {
+        use std::collections::VecDeque;
+        use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, ToSocketAddrs};
+        use std::process::Command;
+        use std::sync::{Arc, Mutex};
+        use std::time::{Duration, Instant};
+
+        use crate::config::{LatencyConfig, NetworkConfig};
+
+        const HISTORY: usize = 120;
+        const TIMEOUT: Duration = Duration::from_secs(1);
+
+        /// Rolling probe results for one target.
+        #[derive(Clone)]
+        pub struct TargetStats {
+            pub target: String,
+            /// Round-trip time per probe; `None` for a lost probe.
+            pub samples: VecDeque<Option<Duration>>,
+            /// Why the last probe failed, if it did.
+            pub error: Option<String>,
+        }
+
+        impl TargetStats {
+            pub fn last_rtt(&self) -> Option<Duration> {
+                self.samples.back().copied().flatten()
+            }
+
+            pub fn loss_percent(&self) -> f32 {
+                if self.samples.is_empty() {
+                    return 0.0;
+                }
+                let lost = self.samples.iter().filter(|s| s.is_none()).count();
+                lost as f32 / self.samples.len() as f32 * 100.0
+            }
+        }
+
+        /// Probes each configured target on its own background thread.
+        pub struct LatencyMonitor {
+            targets: Vec<Arc<Mutex<TargetStats>>>,
+        }
+
+        impl LatencyMonitor {
+            pub fn spawn(cfg: &LatencyConfig) -> Self {
+                let interval = Duration::from_secs(cfg.interval_secs.max(1));
+                let targets = cfg
+                    .targets
+                    .iter()
+                    .map(|target| {
+                        let stats = Arc::new(Mutex::new(TargetStats {
+                            target: target.clone(),
+                            samples: VecDeque::with_capacity(HISTORY),
+                            error: None,
+                        }));
+                        let shared = Arc::clone(&stats);
+                        let target = target.clone();
+                        // Stops once the monitor is dropped
+                        std::thread::spawn(move || {
+                            while Arc::strong_count(&shared) > 1 {
+                                let started = Instant::now();
+                                let result = probe(&target);
+                                if let Ok(mut s) = shared.lock() {
+                                    if s.samples.len() >= HISTORY {
+                                        s.samples.pop_front();
+                                    }
+                                    match result {
+                                        Ok(rtt) => {
+                                            s.samples.push_back(Some(rtt));
+                                            s.error = None;
+                                        }
+                                        Err(e) => {
+                                            s.samples.push_back(None);
+                                            s.error = Some(e);
+                                        }
+                                    }
+                                }
+                                std::thread::sleep(interval.saturating_sub(started.elapsed()));
+                            }
+                        });
+                        stats
+                    })
+                    .collect();
+                Self { targets }
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.targets.is_empty()
+            }
+
+            pub fn stats(&self) -> Vec<TargetStats> {
+                self.targets.iter().filter_map(|t| t.lock().ok().map(|s| s.clone())).collect()
+            }
+        }
+
+        /// Latest connectivity check; the `_up` fields are `None` until the
+        /// first probe finishes, when that check is off, or (for the gateway)
+        /// when there is no default route to probe.
+        #[derive(Clone, Default)]
+        pub struct Reachability {
+            /// Default gateway address, `None` when there is no default route.
+            pub gateway: Option<String>,
+            pub gateway_up: Option<bool>,
+            pub target: Option<String>,
+            pub target_up: Option<bool>,
+        }
+
+        /// Checks the default gateway and an optional outside target on a
+        /// background thread, so a dead network is told apart from a dead box.
+        pub struct ReachabilityMonitor {
+            status: Arc<Mutex<Reachability>>,
+        }
+
+        impl ReachabilityMonitor {
+            /// `None` when neither check is configured.
+            pub fn spawn(cfg: &NetworkConfig) -> Option<Self> {
+                if !cfg.check_gateway && cfg.check_target.is_none() {
+                    return None;
+                }
+                let status = Arc::new(Mutex::new(Reachability { target: cfg.check_target.clone(), ..Default::default() }));
+                let shared = Arc::clone(&status);
+                let (check_gateway, target) = (cfg.check_gateway, cfg.check_target.clone());
+                let interval = Duration::from_secs(cfg.check_interval_secs.max(1));
+                // Stops once the monitor is dropped
+                std::thread::spawn(move || {
+                    while Arc::strong_count(&shared) > 1 {
+                        let started = Instant::now();
+                        let gateway = check_gateway.then(default_gateway).flatten();
+                        // No default route (static setups, some VPNs) is unknown, not down
+                        let gateway_up = gateway.as_ref().map(gateway_answers);
+                        let target_up = target.as_deref().map(|t| probe(t).is_ok());
+                        if let Ok(mut s) = shared.lock() {
+                            s.gateway_up = gateway_up;
+                            s.gateway = gateway.map(|gw| gw.to_string());
+                            s.target_up = target_up;
+                        }
+                        std::thread::sleep(interval.saturating_sub(started.elapsed()));
+                    }
+                });
+                Some(Self { status })
+            }
+
+            pub fn status(&self) -> Reachability {
+                self.status.lock().map(|s| s.clone()).unwrap_or_default()
+            }
+        }
+
+        /// Next hop of a default route and the interface it leaves through.
+        struct Gateway {
+            addr: IpAddr,
+            dev: String,
+        }
+
+        impl std::fmt::Display for Gateway {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                match self.addr {
+                    // Link-local next hops need the interface as scope
+                    IpAddr::V6(v6) if v6.is_unicast_link_local() => write!(f, "{}%{}", v6, self.dev),
+                    addr => write!(f, "{}", addr),
+                }
+            }
+        }
+
+        /// Gateway of the lowest-metric IPv4 default route in `/proc/net/route`,
+        /// or the IPv6 one from `/proc/net/ipv6_route` on IPv6-only hosts.
+        fn default_gateway() -> Option<Gateway> {
+            default_gateway_v4().or_else(default_gateway_v6)
+        }
+
+        fn default_gateway_v4() -> Option<Gateway> {
+            let text = std::fs::read_to_string("/proc/net/route").ok()?;
+            text.lines()
+                .skip(1)
+                .filter_map(|line| {
+                    let fields: Vec<&str> = line.split_whitespace().collect();
+                    // Destination 0.0.0.0 with RTF_GATEWAY set
+                    let flags = u32::from_str_radix(fields.get(3)?, 16).ok()?;
+                    if *fields.get(1)? != "00000000" || flags & 0x2 == 0 {
+                        return None;
+                    }
+                    // Hex of the address in host (little-endian) byte order
+                    let gateway = u32::from_str_radix(fields.get(2)?, 16).ok()?;
+                    let metric: u32 = fields.get(6)?.parse().ok()?;
+                    let addr = IpAddr::V4(Ipv4Addr::from(gateway.to_le_bytes()));
+                    Some((metric, Gateway { addr, dev: fields.first()?.to_string() }))
+                })
+                .min_by_key(|(metric, _)| *metric)
+                .map(|(_, gateway)| gateway)
+        }
+
+        fn default_gateway_v6() -> Option<Gateway> {
+            let text = std::fs::read_to_string("/proc/net/ipv6_route").ok()?;
+            text.lines()
+                .filter_map(|line| {
+                    let fields: Vec<&str> = line.split_whitespace().collect();
+                    // Destination ::/0 with RTF_GATEWAY set
+                    let flags = u32::from_str_radix(fields.get(8)?, 16).ok()?;
+                    if u128::from_str_radix(fields.first()?, 16).ok()? != 0 || *fields.get(1)? != "00" || flags & 0x2 == 0 {
+                        return None;
+                    }
+                    // Hex of the address in network byte order
+                    let gateway = u128::from_str_radix(fields.get(4)?, 16).ok()?;
+                    let metric = u32::from_str_radix(fields.get(5)?, 16).ok()?;
+                    let addr = IpAddr::V6(Ipv6Addr::from(gateway));
+                    Some((metric, Gateway { addr, dev: fields.get(9)?.to_string() }))
+                })
+                .min_by_key(|(metric, _)| *metric)
+                .map(|(_, gateway)| gateway)
+        }
+
+        /// Pings the gateway. Some routers drop ICMP and `ping` may be missing
+        /// or lack the privilege, so on any failure a resolved neighbour
+        /// entry is taken as the gateway being there.
+        fn gateway_answers(gateway: &Gateway) -> bool {
+            icmp_probe(&gateway.to_string()).is_ok() || neighbour_resolved(gateway)
+        }
+
+        fn neighbour_resolved(gateway: &Gateway) -> bool {
+            match gateway.addr {
+                IpAddr::V4(ip) => {
+                    let ip = ip.to_string();
+                    std::fs::read_to_string("/proc/net/arp").unwrap_or_default().lines().skip(1).any(|line| {
+                        let fields: Vec<&str> = line.split_whitespace().collect();
+                        // ATF_COM: the hardware address is resolved
+                        fields.first() == Some(&ip.as_str())
+                            && fields.get(2).and_then(|f| u32::from_str_radix(f.trim_start_matches("0x"), 16).ok()).is_some_and(|f| f & 0x2 != 0)
+                    })
+                }
+                // The IPv6 neighbour table is only exposed over netlink
+                IpAddr::V6(ip) => Command::new("ip")
+                    .args(["-6", "neigh", "show", &ip.to_string(), "dev", &gateway.dev])
+                    .output()
+                    .is_ok_and(|out| {
+                        let text = String::from_utf8_lossy(&out.stdout);
+                        ["REACHABLE", "STALE", "DELAY", "PROBE", "PERMANENT"].iter().any(|s| text.contains(s))
+                    }),
+            }
+        }
+
+        /// Pings a bare address or host name; connects to `ip:port`,
+        /// `[ipv6]:port` or `host:port`.
+        fn probe(target: &str) -> Result<Duration, String> {
+            let bare = target.strip_prefix('[').and_then(|t| t.strip_suffix(']')).unwrap_or(target);
+            if bare.parse::<IpAddr>().is_ok() {
+                icmp_probe(bare)
+            } else if target.parse::<SocketAddr>().is_ok() || target.matches(':').count() == 1 {
+                tcp_probe(target)
+            } else {
+                icmp_probe(target)
+            }
+        }
+
+        fn tcp_probe(target: &str) -> Result<Duration, String> {
+            let addr = target
+                .to_socket_addrs()
+                .map_err(|e| e.to_string())?
+                .next()
+                .ok_or_else(|| "no address".to_string())?;
+            let start = Instant::now();
+            TcpStream::connect_timeout(&addr, TIMEOUT).map_err(|e| e.to_string())?;
+            Ok(start.elapsed())
+        }
+
+        /// Uses the system `ping`, which is setuid or has the capability for raw ICMP.
+        fn icmp_probe(target: &str) -> Result<Duration, String> {
+            let out = Command::new("ping")
+                .args(["-n", "-c", "1", "-W", &TIMEOUT.as_secs().to_string(), target])
+                .output()
+                .map_err(|e| format!("ping: {}", e))?;
+            let stdout = String::from_utf8_lossy(&out.stdout);
+            stdout
+                .split("time=")
+                .nth(1)
+                .and_then(|rest| rest.split_whitespace().next())
+                .and_then(|ms| ms.parse::<f64>().ok())
+                .map(|ms| Duration::from_secs_f64(ms / 1000.0))
+                .ok_or_else(|| "timeout".to_string())
+        }
+}
+
+pub mod ups {
+        use std::collections::HashMap;
+        use std::io::{BufRead, BufReader, Write};
+        use std::net::{TcpStream, ToSocketAddrs};
+        use std::sync::{Arc, Mutex};
+        use std::time::Duration;
+
+        use crate::config::UpsConfig;
+
+        const NUT_PORT: u16 = 3493;
+        const TIMEOUT: Duration = Duration::from_secs(2);
+
+        /// Variables from the last `LIST VAR` reply.
+        #[derive(Clone, Default)]
+        pub struct UpsStatus {
+            pub name: String,
+            pub vars: HashMap<String, String>,
+            pub error: Option<String>,
+        }
+
+        impl UpsStatus {
+            /// A numeric variable; `nan`, `inf` and other junk read as missing.
+            fn num(&self, key: &str) -> Option<f64> {
+                self.vars.get(key)?.parse().ok().filter(|v: &f64| v.is_finite())
+            }
+
+            pub fn charge_percent(&self) -> Option<f64> {
+                self.num("battery.charge")
+            }
+
+            pub fn load_percent(&self) -> Option<f64> {
+                self.num("ups.load")
+            }
+
+            pub fn runtime(&self) -> Option<Duration> {
+                self.num("battery.runtime").and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+            }
+
+            /// `ups.status` flags, e.g. `OL CHRG` or `OB LB`.
+            pub fn flags(&self) -> Vec<&str> {
+                self.vars.get("ups.status").map(|s| s.split_whitespace().collect()).unwrap_or_default()
+            }
+
+            pub fn on_battery(&self) -> bool {
+                self.flags().contains(&"OB")
+            }
+        }
+
+        /// Polls the NUT daemon on a background thread.
+        pub struct UpsMonitor {
+            status: Arc<Mutex<UpsStatus>>,
+        }
+
+        impl UpsMonitor {
+            pub fn spawn(cfg: &UpsConfig) -> Self {
+                let status = Arc::new(Mutex::new(UpsStatus { name: cfg.name.clone(), ..Default::default() }));
+                let shared = Arc::clone(&status);
+                let interval = Duration::from_secs(cfg.interval_secs.max(1));
+                let name = cfg.name.clone();
+                std::thread::spawn(move || loop {
+                    let result = query(&name);
+                    if let Ok(mut s) = shared.lock() {
+                        match result {
+                            Ok(vars) => {
+                                s.vars = vars;
+                                s.error = None;
+                            }
+                            Err(e) => {
+                                // Old readings would show a dead link as a healthy UPS
+                                s.vars.clear();
+                                s.error = Some(e);
+                            }
+                        }
+                    }
+                    std::thread::sleep(interval);
+                });
+                Self { status }
+            }
+
+            pub fn status(&self) -> UpsStatus {
+                self.status.lock().map(|s| s.clone()).unwrap_or_default()
+            }
+        }
+
+        fn query(name: &str) -> Result<HashMap<String, String>, String> {
+            let (ups, server) = name.split_once('@').unwrap_or((name, "localhost"));
+            let server = if server.contains(':') { server.to_string() } else { format!("{}:{}", server, NUT_PORT) };
+            let addr = server
+                .to_socket_addrs()
+                .map_err(|e| e.to_string())?
+                .next()
+                .ok_or_else(|| "no address".to_string())?;
+            let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT).map_err(|e| e.to_string())?;
+            stream.set_read_timeout(Some(TIMEOUT)).map_err(|e| e.to_string())?;
+            writeln!(stream, "LIST VAR {}", ups).map_err(|e| e.to_string())?;
+
+            let mut vars = HashMap::new();
+            for line in BufReader::new(&stream).lines() {
+                let line = line.map_err(|e| e.to_string())?;
+                if let Some(err) = line.strip_prefix("ERR ") {
+                    return Err(err.to_string());
+                }
+                if line.starts_with("END LIST VAR") {
+                    break;
+                }
+                // VAR <ups> <key> "<value>"
+                let Some(rest) = line.strip_prefix("VAR ") else {
+                    continue;
+                };
+                let mut parts = rest.splitn(3, ' ');
+                if let (Some(_), Some(key), Some(value)) = (parts.next(), parts.next(), parts.next()) {
+                    vars.insert(key.to_string(), value.trim_matches('"').replace("\\\"", "\""));
+                }
+            }
+            let _ = writeln!(stream, "LOGOUT");
+            Ok(vars)
+        }
+}
+
+pub mod connections {
+        use std::collections::HashMap;
+        use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+        use std::sync::mpsc::{channel, Sender};
+        use std::sync::{Arc, Mutex};
+
+        /// One socket from `/proc/net/{tcp,tcp6,udp,udp6}`.
+        #[derive(Clone)]
+        pub struct Connection {
+            pub proto: &'static str,
+            pub local: SocketAddr,
+            pub remote: SocketAddr,
+            pub state: &'static str,
+            pub inode: u64,
+            /// Owning process, when its fd table was readable.
+            pub pid: Option<i32>,
+        }
+
+        fn tcp_state(code: u8) -> &'static str {
+            match code {
+                0x01 => "ESTABLISHED",
+                0x02 => "SYN_SENT",
+                0x03 => "SYN_RECV",
+                0x04 => "FIN_WAIT1",
+                0x05 => "FIN_WAIT2",
+                0x06 => "TIME_WAIT",
+                0x07 => "CLOSE",
+                0x08 => "CLOSE_WAIT",
+                0x09 => "LAST_ACK",
+                0x0A => "LISTEN",
+                0x0B => "CLOSING",
+                _ => "UNKNOWN",
+            }
+        }
+
+        /// Parses `ADDR:PORT` where ADDR is the kernel's hex dump of the address
+        /// in host (little-endian) 32-bit words.
+        fn parse_addr(s: &str) -> Option<SocketAddr> {
+            let (addr, port) = s.split_once(':')?;
+            let port = u16::from_str_radix(port, 16).ok()?;
+            let ip = match addr.len() {
+                8 => IpAddr::V4(Ipv4Addr::from(u32::from_str_radix(addr, 16).ok()?.to_le_bytes())),
+                32 => {
+                    let mut bytes = [0u8; 16];
+                    for (i, word) in bytes.chunks_mut(4).enumerate() {
+                        let w = u32::from_str_radix(&addr[i * 8..i * 8 + 8], 16).ok()?;
+                        word.copy_from_slice(&w.to_le_bytes());
+                    }
+                    IpAddr::V6(Ipv6Addr::from(bytes))
+                }
+                _ => return None,
+            };
+            Some(SocketAddr::new(ip, port))
+        }
+
+        fn read_table(proto: &'static str, out: &mut Vec<Connection>) {
+            let Ok(text) = std::fs::read_to_string(format!("/proc/net/{}", proto)) else {
+                return;
+            };
+            let udp = proto.starts_with("udp");
+            for line in text.lines().skip(1) {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                if fields.len() < 10 {
+                    continue;
+                }
+                let (Some(local), Some(remote)) = (parse_addr(fields[1]), parse_addr(fields[2])) else {
+                    continue;
+                };
+                let code = u8::from_str_radix(fields[3], 16).unwrap_or(0);
+                let state = if udp {
+                    if code == 0x01 { "ESTABLISHED" } else { "" }
+                } else {
+                    tcp_state(code)
+                };
+                out.push(Connection {
+                    proto,
+                    local,
+                    remote,
+                    state,
+                    inode: fields[9].parse().unwrap_or(0),
+                    pid: None,
+                });
+            }
+        }
+
+        /// Maps socket inodes to PIDs by walking `/proc/<pid>/fd`. Processes we
+        /// may not inspect are silently skipped.
+        fn socket_owners() -> HashMap<u64, i32> {
+            let mut owners = HashMap::new();
+            let Ok(procs) = std::fs::read_dir("/proc") else {
+                return owners;
+            };
+            for entry in procs.flatten() {
+                let Some(pid) = entry.file_name().to_str().and_then(|n| n.parse::<i32>().ok()) else {
+                    continue;
+                };
+                let Ok(fds) = std::fs::read_dir(entry.path().join("fd")) else {
+                    continue;
+                };
+                for fd in fds.flatten() {
+                    if let Ok(target) = std::fs::read_link(fd.path()) {
+                        let target = target.to_string_lossy();
+                        if let Some(inode) = target
+                            .strip_prefix("socket:[")
+                            .and_then(|t| t.strip_suffix(']'))
+                            .and_then(|t| t.parse::<u64>().ok())
+                        {
+                            owners.insert(inode, pid);
+                        }
+                    }
+                }
+            }
+            owners
+        }
+
+        /// All TCP/UDP sockets in table order, without owning PIDs.
+        pub fn read_tables() -> Vec<Connection> {
+            let mut conns = Vec::new();
+            for proto in ["tcp", "tcp6", "udp", "udp6"] {
+                read_table(proto, &mut conns);
+            }
+            conns
+        }
+
+        /// All TCP/UDP sockets, listeners first, then by protocol and local port.
+        pub fn read_all() -> Vec<Connection> {
+            let mut conns = read_tables();
+            let owners = socket_owners();
+            for c in &mut conns {
+                c.pid = owners.get(&c.inode).copied();
+            }
+            conns.sort_by(|a, b| {
+                (b.state == "LISTEN")
+                    .cmp(&(a.state == "LISTEN"))
+                    .then(a.proto.cmp(b.proto))
+                    .then(a.local.port().cmp(&b.local.port()))
+            });
+            conns
+        }
+
+        /// Port-to-name table from `/etc/services`.
+        pub struct Services {
+            names: HashMap<(u16, &'static str), String>,
+        }
+
+        impl Services {
+            pub fn load() -> Self {
+                let mut names = HashMap::new();
+                if let Ok(text) = std::fs::read_to_string("/etc/services") {
+                    for line in text.lines() {
+                        let line = line.split('#').next().unwrap_or("");
+                        let mut parts = line.split_whitespace();
+                        let (Some(name), Some(spec)) = (parts.next(), parts.next()) else {
+                            continue;
+                        };
+                        let Some((port, proto)) = spec.split_once('/') else {
+                            continue;
+                        };
+                        let proto = match proto {
+                            "tcp" => "tcp",
+                            "udp" => "udp",
+                            _ => continue,
+                        };
+                        if let Ok(port) = port.parse::<u16>() {
+                            names.entry((port, proto)).or_insert_with(|| name.to_string());
+                        }
+                    }
+                }
+                Self { names }
+            }
+
+            /// Service name for a port, e.g. `(443, "tcp6")` -> `https`.
+            pub fn name(&self, port: u16, proto: &str) -> Option<&str> {
+                let base = if proto.starts_with("udp") { "udp" } else { "tcp" };
+                self.names.get(&(port, base)).map(String::as_str)
+            }
+        }
+
+        /// Reverse-DNS cache filled by a background thread, so lookups never
+        /// block rendering. Unresolved addresses show as raw IPs meanwhile.
+        pub struct Resolver {
+            /// Present = requested; `None` value = pending or no PTR record.
+            cache: Arc<Mutex<HashMap<IpAddr, Option<String>>>>,
+            tx: Sender<IpAddr>,
+        }
+
+        impl Default for Resolver {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl Resolver {
+            pub fn new() -> Self {
+                let cache: Arc<Mutex<HashMap<IpAddr, Option<String>>>> = Arc::default();
+                let (tx, rx) = channel::<IpAddr>();
+                let shared = Arc::clone(&cache);
+                std::thread::spawn(move || {
+                    for ip in rx {
+                        let name = dns_lookup::lookup_addr(&ip).ok().filter(|n| n != &ip.to_string());
+                        if let Ok(mut cache) = shared.lock() {
+                            cache.insert(ip, name);
+                        }
+                    }
+                });
+                Self { cache, tx }
+            }
+
+            /// Cached hostname for `ip`, queueing a lookup on first sight.
+            pub fn lookup(&self, ip: IpAddr) -> Option<String> {
+                if ip.is_unspecified() {
+                    return None;
+                }
+                let mut cache = self.cache.lock().ok()?;
+                match cache.get(&ip) {
+                    Some(name) => name.clone(),
+                    None => {
+                        cache.insert(ip, None);
+                        let _ = self.tx.send(ip);
+                        None
+                    }
+                }
+            }
+        }
+}
+
+pub mod interrupts {
+        use std::collections::HashMap;
+        use std::time::Instant;
+
+        /// Rate of one `/proc/interrupts` line since the previous sample.
+        #[derive(Clone)]
+        pub struct IrqRate {
+            /// IRQ number or architecture name (`LOC`, `NMI`, ...).
+            pub irq: String,
+            /// Everything after the counters: chip, hwirq and device names.
+            pub desc: String,
+            /// Interrupts per second on each CPU.
+            pub per_cpu: Vec<f64>,
+        }
+
+        impl IrqRate {
+            pub fn total(&self) -> f64 {
+                self.per_cpu.iter().sum()
+            }
+
+            /// Device names without the chip and trigger columns. For numbered
+            /// IRQs that is whatever follows the `edge`/`level`/`fasteoi` token;
+            /// architecture rows (`LOC`, `NMI`) keep their whole description.
+            pub fn device(&self) -> String {
+                if self.irq.parse::<u32>().is_err() {
+                    return self.desc.clone();
+                }
+                let tokens: Vec<&str> = self.desc.split_whitespace().collect();
+                let trigger = tokens.iter().rposition(|t| {
+                    let t = t.to_lowercase();
+                    t.ends_with("edge") || t.ends_with("level") || t.ends_with("fasteoi")
+                });
+                match trigger {
+                    Some(i) if i + 1 < tokens.len() => tokens[i + 1..].join(" "),
+                    _ => tokens.last().map(|t| t.to_string()).unwrap_or_default(),
+                }
+            }
+        }
+
+        /// Interrupt rates per CPU and per source.
+        #[derive(Clone, Default)]
+        pub struct InterruptStats {
+            pub cpus: Vec<String>,
+            /// Interrupts per second on each CPU, all sources combined.
+            pub per_cpu: Vec<f64>,
+            /// Sources with a non-zero rate, in `/proc/interrupts` order.
+            pub sources: Vec<IrqRate>,
+            /// Every softirq type (`NET_RX`, `TIMER`, ...) from `/proc/softirqs`.
+            pub softirqs: Vec<IrqRate>,
+        }
+
+        /// Counters of one `/proc/interrupts` line.
+        struct IrqCounters {
+            irq: String,
+            desc: String,
+            counts: Vec<u64>,
+        }
+
+        /// Parses the CPU header and one counter row per source of
+        /// `/proc/interrupts` or `/proc/softirqs`. Rows without per-CPU
+        /// counters (`ERR`, `MIS`) are skipped.
+        fn read_counters(path: &str) -> Option<(Vec<String>, Vec<IrqCounters>)> {
+            let text = std::fs::read_to_string(path).ok()?;
+            let mut lines = text.lines();
+            let cpus: Vec<String> = lines.next()?.split_whitespace().map(str::to_string).collect();
+            let rows = lines
+                .filter_map(|line| {
+                    let (irq, rest) = line.trim_start().split_once(':')?;
+                    let mut fields = rest.split_whitespace().peekable();
+                    let mut counts = Vec::with_capacity(cpus.len());
+                    while counts.len() < cpus.len() {
+                        match fields.peek().and_then(|f| f.parse::<u64>().ok()) {
+                            Some(n) => {
+                                counts.push(n);
+                                fields.next();
+                            }
+                            None => break,
+                        }
+                    }
+                    (counts.len() == cpus.len()).then(|| IrqCounters {
+                        irq: irq.to_string(),
+                        desc: fields.collect::<Vec<_>>().join(" "),
+                        counts,
+                    })
+                })
+                .collect();
+            Some((cpus, rows))
+        }
+
+        /// Per-CPU rates of the rows present in both samples.
+        fn rates(rows: &[IrqCounters], before: &Baseline, secs: f64) -> Vec<IrqRate> {
+            rows.iter()
+                .filter_map(|row| {
+                    let prev = before.get(&row.irq)?;
+                    let per_cpu = row
+                        .counts
+                        .iter()
+                        .zip(prev)
+                        .map(|(c, p)| c.saturating_sub(*p) as f64 / secs)
+                        .collect();
+                    Some(IrqRate { irq: row.irq.clone(), desc: row.desc.clone(), per_cpu })
+                })
+                .collect()
+        }
+
+        /// Last counters per row, keyed by IRQ name.
+        type Baseline = HashMap<String, Vec<u64>>;
+
+        fn baseline(rows: Vec<IrqCounters>) -> Baseline {
+            rows.into_iter().map(|r| (r.irq, r.counts)).collect()
+        }
+
+        /// Turns successive `/proc/interrupts` and `/proc/softirqs` reads into rates.
+        #[derive(Default)]
+        pub struct InterruptSampler {
+            previous: Option<(Instant, Baseline, Baseline)>,
+        }
+
+        impl InterruptSampler {
+            /// Reads the counters and returns rates since the previous call;
+            /// the first call only primes the baseline.
+            pub fn sample(&mut self) -> InterruptStats {
+                let Some((cpus, rows)) = read_counters("/proc/interrupts") else {
+                    return InterruptStats::default();
+                };
+                let soft_rows = read_counters("/proc/softirqs").map(|(_, rows)| rows).unwrap_or_default();
+                let now = Instant::now();
+                let mut stats = InterruptStats {
+                    per_cpu: vec![0.0; cpus.len()],
+                    cpus,
+                    sources: Vec::new(),
+                    softirqs: Vec::new(),
+                };
+                if let Some((then, ref before, ref soft_before)) = self.previous {
+                    let secs = now.duration_since(then).as_secs_f64().max(0.001);
+                    for source in rates(&rows, before, secs) {
+                        for (sum, r) in stats.per_cpu.iter_mut().zip(&source.per_cpu) {
+                            *sum += r;
+                        }
+                        if source.total() > 0.0 {
+                            stats.sources.push(source);
+                        }
+                    }
+                    stats.softirqs = rates(&soft_rows, soft_before, secs);
+                }
+                self.previous = Some((now, baseline(rows), baseline(soft_rows)));
+                stats
+            }
+        }
+}
+
+pub mod blockdev {
+        use std::collections::HashMap;
+        use std::time::Instant;
+
+        /// Queue state of one whole block device since the previous sample.
+        #[derive(Clone)]
+        pub struct DeviceIo {
+            pub name: String,
+            /// Requests issued to the driver and not yet completed, right now.
+            pub in_flight: u64,
+            /// `queue/nr_requests`: how many requests the block layer queues.
+            pub queue_size: Option<u64>,
+            /// Mean number of requests outstanding (iostat's `aqu-sz`).
+            pub avg_queue: f64,
+            /// Share of wall time with at least one request outstanding.
+            pub util_percent: f64,
+            /// Mean time per completed read or write, queueing included;
+            /// `None` when nothing completed.
+            pub await_ms: Option<f64>,
+            /// Completed reads and writes per second.
+            pub iops: f64,
+        }
+
+        /// Counters of one `/proc/diskstats` line.
+        #[derive(Clone, Copy)]
+        struct Counters {
+            ios: u64,
+            read_sectors: u64,
+            write_sectors: u64,
+            io_ms: u64,
+            in_flight: u64,
+            busy_ms: u64,
+            weighted_ms: u64,
+        }
+
+        /// Whole devices (those under `/sys/block`) from `/proc/diskstats`;
+        /// partitions and never-used devices are skipped.
+        fn read_counters() -> Vec<(String, Counters)> {
+            let text = std::fs::read_to_string("/proc/diskstats").unwrap_or_default();
+            text.lines()
+                .filter_map(|line| {
+                    let fields: Vec<&str> = line.split_whitespace().collect();
+                    let name = *fields.get(2)?;
+                    let n = |i: usize| fields.get(i).and_then(|v| v.parse::<u64>().ok());
+                    let counters = Counters {
+                        ios: n(3)? + n(7)?,
+                        read_sectors: n(5)?,
+                        write_sectors: n(9)?,
+                        io_ms: n(6)? + n(10)?,
+                        in_flight: n(11)?,
+                        busy_ms: n(12)?,
+                        weighted_ms: n(13)?,
+                    };
+                    if counters.ios == 0 && counters.in_flight == 0 {
+                        return None;
+                    }
+                    // `cciss/c0d0` in diskstats is `cciss!c0d0` in sysfs
+                    let whole = std::path::Path::new("/sys/block").join(name.replace('/', "!")).exists();
+                    whole.then(|| (name.to_string(), counters))
+                })
+                .collect()
+        }
+
+        /// Bytes read and written by each whole device since boot. The kernel
+        /// counts in 512-byte sectors whatever the device's sector size.
+        pub fn byte_counters() -> Vec<(String, u64, u64)> {
+            read_counters()
+                .into_iter()
+                .map(|(name, c)| (name, c.read_sectors * 512, c.write_sectors * 512))
+                .collect()
+        }
+
+        /// Loop and RAM disks: a snap-heavy desktop has dozens of mostly idle
+        /// loop devices that would crowd out the real drives.
+        pub fn pseudo_device(name: &str) -> bool {
+            name.starts_with("loop") || name.starts_with("ram")
+        }
+
+        fn queue_size(name: &str) -> Option<u64> {
+            std::fs::read_to_string(format!("/sys/block/{}/queue/nr_requests", name.replace('/', "!")))
+                .ok()?
+                .trim()
+                .parse()
+                .ok()
+        }
+
+        /// Turns successive `/proc/diskstats` reads into per-device queue
+        /// figures.
+        #[derive(Default)]
+        pub struct DeviceSampler {
+            previous: Option<(Instant, HashMap<String, Counters>)>,
+        }
+
+        impl DeviceSampler {
+            /// Reads the counters and returns figures since the previous call;
+            /// the first call only has the in-flight counts.
+            pub fn sample(&mut self) -> Vec<DeviceIo> {
+                let rows = read_counters();
+                let now = Instant::now();
+                let elapsed = self.previous.as_ref().map(|(then, _)| now.duration_since(*then).as_secs_f64().max(0.001));
+                let devices = rows
+                    .iter()
+                    .map(|(name, c)| {
+                        let before = self.previous.as_ref().and_then(|(_, b)| b.get(name));
+                        let mut device = DeviceIo {
+                            name: name.clone(),
+                            in_flight: c.in_flight,
+                            queue_size: queue_size(name),
+                            avg_queue: 0.0,
+                            util_percent: 0.0,
+                            await_ms: None,
+                            iops: 0.0,
+                        };
+                        if let (Some(b), Some(secs)) = (before, elapsed) {
+                            let ms = secs * 1000.0;
+                            let ios = c.ios.saturating_sub(b.ios);
+                            device.avg_queue = c.weighted_ms.saturating_sub(b.weighted_ms) as f64 / ms;
+                            device.util_percent = (c.busy_ms.saturating_sub(b.busy_ms) as f64 / ms * 100.0).min(100.0);
+                            device.await_ms = (ios > 0).then(|| c.io_ms.saturating_sub(b.io_ms) as f64 / ios as f64);
+                            device.iops = ios as f64 / secs;
+                        }
+                        device
+                    })
+                    .collect();
+                self.previous = Some((now, rows.into_iter().collect()));
+                devices
+            }
+        }
+}
+
+pub mod nvme {
+        use std::os::fd::AsRawFd;
+        use std::path::Path;
+        use std::time::Duration;
+
+        /// SMART data changes slowly; reading it more often only costs ioctls.
+        pub const REREAD: Duration = Duration::from_secs(30);
+
+        /// Health of one NVMe controller. Only the temperature is available
+        /// without root, from hwmon.
+        #[derive(Clone)]
+        pub struct NvmeHealth {
+            /// Controller name (`nvme0`).
+            pub name: String,
+            pub model: String,
+            pub temp_celsius: Option<f32>,
+            /// Endurance used as estimated by the drive; may exceed 100.
+            pub percent_used: Option<u8>,
+            pub available_spare: Option<u8>,
+            /// Spare level below which the drive raises a critical warning.
+            pub spare_threshold: Option<u8>,
+            /// SMART critical warning bits; 0 when fine or unknown.
+            pub critical_warning: u8,
+        }
+
+        impl NvmeHealth {
+            /// Names of the critical warning bits that are set.
+            pub fn warnings(&self) -> Vec<&'static str> {
+                const BITS: [&str; 6] = [
+                    "spare low",
+                    "temperature",
+                    "reliability degraded",
+                    "read-only",
+                    "volatile backup failed",
+                    "PMR read-only",
+                ];
+                BITS.iter()
+                    .enumerate()
+                    .filter(|(i, _)| self.critical_warning & (1 << i) != 0)
+                    .map(|(_, name)| *name)
+                    .collect()
+            }
+        }
+
+        /// `struct nvme_passthru_cmd` from `<linux/nvme_ioctl.h>`.
+        #[repr(C)]
+        #[derive(Default)]
+        struct AdminCommand {
+            opcode: u8,
+            flags: u8,
+            rsvd1: u16,
+            nsid: u32,
+            cdw2: u32,
+            cdw3: u32,
+            metadata: u64,
+            addr: u64,
+            metadata_len: u32,
+            data_len: u32,
+            cdw10: u32,
+            cdw11: u32,
+            cdw12: u32,
+            cdw13: u32,
+            cdw14: u32,
+            cdw15: u32,
+            timeout_ms: u32,
+            result: u32,
+        }
+
+        /// `_IOWR('N', 0x41, struct nvme_admin_cmd)`.
+        const NVME_IOCTL_ADMIN_CMD: u64 = 0xC048_4E41;
+        // The size is encoded in the ioctl number above
+        const _: () = assert!(std::mem::size_of::<AdminCommand>() == 72);
+
+        /// The 512-byte SMART / Health Information log page (log id 2), read
+        /// with a Get Log Page admin command. Needs CAP_SYS_ADMIN.
+        fn smart_log(name: &str) -> Option<[u8; 512]> {
+            let device = std::fs::File::open(Path::new("/dev").join(name)).ok()?;
+            let mut log = [0u8; 512];
+            let mut cmd = AdminCommand {
+                opcode: 0x02,
+                nsid: 0xFFFF_FFFF,
+                addr: log.as_mut_ptr() as u64,
+                data_len: log.len() as u32,
+                // Dword count minus one in the upper half, log id in the lower
+                cdw10: ((log.len() as u32 / 4 - 1) << 16) | 0x02,
+                ..Default::default()
+            };
+            // SAFETY: `cmd` describes `log`, which outlives the call, and the
+            // kernel writes at most `data_len` bytes into it.
+            let rc = unsafe { libc::ioctl(device.as_raw_fd(), NVME_IOCTL_ADMIN_CMD as _, &mut cmd) };
+            (rc == 0).then_some(log)
+        }
+
+        /// hwmon `temp1_input` of the controller (the composite temperature).
+        fn hwmon_temp(dir: &Path) -> Option<f32> {
+            std::fs::read_dir(dir)
+                .ok()?
+                .flatten()
+                .filter(|e| e.file_name().to_string_lossy().starts_with("hwmon"))
+                .find_map(|e| std::fs::read_to_string(e.path().join("temp1_input")).ok())
+                .and_then(|t| t.trim().parse::<f32>().ok())
+                .map(|milli| milli / 1000.0)
+        }
+
+        /// Every controller under `/sys/class/nvme`, sorted by name.
+        pub fn read_all() -> Vec<NvmeHealth> {
+            let Ok(entries) = std::fs::read_dir("/sys/class/nvme") else {
+                return Vec::new();
+            };
+            let mut drives: Vec<NvmeHealth> = entries
+                .flatten()
+                .map(|e| {
+                    let name = e.file_name().to_string_lossy().to_string();
+                    let dir = e.path();
+                    let mut health = NvmeHealth {
+                        model: std::fs::read_to_string(dir.join("model")).unwrap_or_default().trim().to_string(),
+                        temp_celsius: hwmon_temp(&dir),
+                        percent_used: None,
+                        available_spare: None,
+                        spare_threshold: None,
+                        critical_warning: 0,
+                        name,
+                    };
+                    if let Some(log) = smart_log(&health.name) {
+                        let kelvin = u16::from_le_bytes([log[1], log[2]]);
+                        if kelvin > 0 {
+                            health.temp_celsius = Some(kelvin as f32 - 273.15);
+                        }
+                        health.critical_warning = log[0];
+                        health.available_spare = Some(log[3]);
+                        health.spare_threshold = Some(log[4]);
+                        health.percent_used = Some(log[5]);
+                    }
+                    health
+                })
+                .collect();
+            drives.sort_by(|a, b| a.name.cmp(&b.name));
+            drives
+        }
+}
+
+pub mod procinfo {
+        use std::collections::HashMap;
+        use std::io;
+        use std::time::Instant;
+
+        /// Cumulative `/proc/<pid>/schedstat` counters at one point in time.
+        #[derive(Clone, Copy)]
+        struct SchedSample {
+            at: Instant,
+            wait_ns: u64,
+            slices: u64,
+        }
+
+        impl SchedSample {
+            fn read(pid: i32) -> Option<Self> {
+                let text = std::fs::read_to_string(format!("/proc/{}/schedstat", pid)).ok()?;
+                // on-CPU ns, runqueue wait ns, timeslices
+                let mut f = text.split_whitespace().skip(1).map(|v| v.parse::<u64>().ok());
+                Some(Self { at: Instant::now(), wait_ns: f.next()??, slices: f.next()?? })
+            }
+        }
+
+        /// Scheduler delay between two schedstat samples.
+        #[derive(Clone, Copy)]
+        pub struct SchedWait {
+            /// Share of wall time spent runnable but waiting for a CPU.
+            pub wait_percent: f64,
+            /// Mean wait before each timeslice, in milliseconds.
+            pub avg_wait_ms: f64,
+        }
+
+        /// Pages of the detail pane, switched with Left/Right.
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        pub enum DetailTab {
+            Overview,
+            Environment,
+            Files,
+            Memory,
+            Limits,
+        }
+
+        impl DetailTab {
+            pub const ALL: [DetailTab; 5] =
+                [DetailTab::Overview, DetailTab::Environment, DetailTab::Files, DetailTab::Memory, DetailTab::Limits];
+
+            pub fn label(self) -> &'static str {
+                match self {
+                    DetailTab::Overview => "overview",
+                    DetailTab::Environment => "env",
+                    DetailTab::Files => "files",
+                    DetailTab::Memory => "memory",
+                    DetailTab::Limits => "limits",
+                }
+            }
+
+            pub fn next(self) -> DetailTab {
+                let i = Self::ALL.iter().position(|t| *t == self).unwrap_or(0);
+                Self::ALL[(i + 1) % Self::ALL.len()]
+            }
+
+            pub fn prev(self) -> DetailTab {
+                let i = Self::ALL.iter().position(|t| *t == self).unwrap_or(0);
+                Self::ALL[(i + Self::ALL.len() - 1) % Self::ALL.len()]
+            }
+        }
+
+        /// One entry of `/proc/<pid>/fd`.
+        pub struct OpenFile {
+            pub fd: u32,
+            /// `file`, `dir`, `dev`, `socket`, `pipe` or `anon`.
+            pub kind: &'static str,
+            /// Path, socket endpoints, or the kernel's name for the object.
+            pub target: String,
+        }
+
+        /// Lists a process's descriptors, resolving TCP/UDP sockets to their
+        /// endpoints.
+        fn read_open_files(pid: i32) -> io::Result<Vec<OpenFile>> {
+            let dir = format!("/proc/{}/fd", pid);
+            let mut files = Vec::new();
+            for entry in std::fs::read_dir(&dir)?.flatten() {
+                let Some(fd) = entry.file_name().to_str().and_then(|n| n.parse().ok()) else {
+                    continue;
+                };
+                let Ok(link) = std::fs::read_link(entry.path()) else {
+                    continue;
+                };
+                let target = link.to_string_lossy().into_owned();
+                let kind = if target.starts_with("socket:[") {
+                    "socket"
+                } else if target.starts_with("pipe:[") {
+                    "pipe"
+                } else if let Some(name) = target.strip_prefix("anon_inode:") {
+                    files.push(OpenFile { fd, kind: "anon", target: name.trim_matches(['[', ']']).to_string() });
+                    continue;
+                } else if target.starts_with("/dev/") {
+                    "dev"
+                } else if std::fs::metadata(entry.path()).is_ok_and(|m| m.is_dir()) {
+                    "dir"
+                } else {
+                    "file"
+                };
+                files.push(OpenFile { fd, kind, target });
+            }
+            files.sort_by_key(|f| f.fd);
+
+            if files.iter().any(|f| f.kind == "socket") {
+                let sockets: HashMap<String, String> = crate::connections::read_tables()
+                    .into_iter()
+                    .map(|c| {
+                        let desc = if c.remote.port() == 0 {
+                            format!("{} {} {}", c.proto, c.local, c.state)
+                        } else {
+                            format!("{} {} -> {} {}", c.proto, c.local, c.remote, c.state)
+                        };
+                        (format!("socket:[{}]", c.inode), desc.trim_end().to_string())
+                    })
+                    .collect();
+                for f in &mut files {
+                    if let Some(desc) = sockets.get(&f.target) {
+                        f.target = desc.clone();
+                    }
+                }
+            }
+            Ok(files)
+        }
+
+        /// Totals from `/proc/<pid>/smaps_rollup`, in bytes.
+        #[derive(Clone, Copy, Default)]
+        pub struct MemoryRollup {
+            pub rss: u64,
+            /// Shared pages divided evenly among the processes mapping them.
+            pub pss: u64,
+            /// Private pages only (clean + dirty): what exiting would free.
+            pub uss: u64,
+            pub shared_clean: u64,
+            pub shared_dirty: u64,
+            pub private_clean: u64,
+            pub private_dirty: u64,
+            pub anonymous: u64,
+            pub pss_file: u64,
+            pub pss_shmem: u64,
+            pub swap: u64,
+            pub swap_pss: u64,
+        }
+
+        impl MemoryRollup {
+            fn read(pid: i32) -> io::Result<Self> {
+                let text = std::fs::read_to_string(format!("/proc/{}/smaps_rollup", pid))?;
+                let fields: HashMap<&str, u64> = text
+                    .lines()
+                    .filter_map(|line| {
+                        let (key, value) = line.split_once(':')?;
+                        Some((key, value.split_whitespace().next()?.parse::<u64>().ok()? * 1024))
+                    })
+                    .collect();
+                let get = |key: &str| fields.get(key).copied().unwrap_or(0);
+                Ok(Self {
+                    rss: get("Rss"),
+                    pss: get("Pss"),
+                    uss: get("Private_Clean") + get("Private_Dirty"),
+                    shared_clean: get("Shared_Clean"),
+                    shared_dirty: get("Shared_Dirty"),
+                    private_clean: get("Private_Clean"),
+                    private_dirty: get("Private_Dirty"),
+                    anonymous: get("Anonymous"),
+                    pss_file: get("Pss_File"),
+                    pss_shmem: get("Pss_Shmem"),
+                    swap: get("Swap"),
+                    swap_pss: get("SwapPss"),
+                })
+            }
+        }
+
+        /// One row of `/proc/<pid>/limits`, with current usage where it can be measured.
+        pub struct ResourceLimit {
+            /// Name without the `Max ` prefix, e.g. `open files`.
+            pub name: String,
+            /// `None` means unlimited.
+            pub soft: Option<u64>,
+            pub hard: Option<u64>,
+            pub units: String,
+            pub used: Option<u64>,
+        }
+
+        impl ResourceLimit {
+            /// Usage as a fraction of the soft limit.
+            pub fn usage(&self) -> Option<f64> {
+                match (self.used, self.soft) {
+                    (Some(used), Some(soft)) if soft > 0 => Some(used as f64 / soft as f64),
+                    _ => None,
+                }
+            }
+        }
+
+        /// Reads the limits and fills in usage: descriptors from `fd`, memory
+        /// from `status`, pending signals from `SigQ`, and processes as the
+        /// threads of every process owned by the same user (what `RLIMIT_NPROC`
+        /// counts).
+        fn read_limits(pid: i32) -> io::Result<Vec<ResourceLimit>> {
+            let text = std::fs::read_to_string(format!("/proc/{}/limits", pid))?;
+            let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).unwrap_or_default();
+            let field = |key: &str| {
+                status.lines().find_map(|l| l.strip_prefix(key)?.strip_prefix(':').map(|v| v.trim().to_string()))
+            };
+            let kib = |key: &str| field(key)?.split_whitespace().next()?.parse::<u64>().ok().map(|v| v * 1024);
+
+            let mut limits = Vec::new();
+            for line in text.lines().skip(1) {
+                let (name, rest) = line.split_at(line.len().min(26));
+                let mut values = rest.split_whitespace();
+                let parse = |v: Option<&str>| v.and_then(|v| v.parse::<u64>().ok());
+                let (soft, hard) = (parse(values.next()), parse(values.next()));
+                let name = name.trim().trim_start_matches("Max ").to_string();
+                let used = match name.as_str() {
+                    "open files" => std::fs::read_dir(format!("/proc/{}/fd", pid)).ok().map(|d| d.count() as u64),
+                    "address space" => kib("VmSize"),
+                    "data size" => kib("VmData"),
+                    "stack size" => kib("VmStk"),
+                    "resident set" => kib("VmRSS"),
+                    "locked memory" => kib("VmLck"),
+                    "pending signals" => field("SigQ").and_then(|q| q.split('/').next()?.parse().ok()),
+                    "processes" => user_threads(pid),
+                    _ => None,
+                };
+                limits.push(ResourceLimit { name, soft, hard, units: values.next().unwrap_or("").to_string(), used });
+            }
+            Ok(limits)
+        }
+
+        fn user_threads(pid: i32) -> Option<u64> {
+            use std::os::unix::fs::MetadataExt;
+            let uid = std::fs::metadata(format!("/proc/{}", pid)).ok()?.uid();
+            let mut threads = 0;
+            for entry in std::fs::read_dir("/proc").ok()?.flatten() {
+                let is_pid = entry.file_name().to_str().is_some_and(|n| n.bytes().all(|b| b.is_ascii_digit()));
+                if is_pid && entry.metadata().is_ok_and(|m| m.uid() == uid) {
+                    threads += std::fs::read_dir(entry.path().join("task")).map(|t| t.count() as u64).unwrap_or(0);
+                }
+            }
+            Some(threads)
+        }
+
+        /// Details of one process for the detail pane, refreshed while it is open.
+        pub struct ProcessDetail {
+            pub pid: i32,
+            pub tab: DetailTab,
+            /// First line shown; moved with PgUp/PgDn.
+            pub scroll: u16,
+            /// `State`, `PPid`, `Threads`, ... from `/proc/<pid>/status`.
+            pub status: Vec<(String, String)>,
+            pub sched: Option<SchedWait>,
+            /// Total time waited for a CPU since the process started.
+            pub total_wait_ms: Option<f64>,
+            pub exited: bool,
+            last_sched: Option<SchedSample>,
+            /// Working directory, or why it could not be read. Only read while
+            /// the environment tab is shown.
+            pub cwd: Result<String, String>,
+            /// `KEY=value` entries of the environment the process was started with.
+            pub environ: Result<Vec<String>, String>,
+            /// Only read while the files tab is shown.
+            pub files: Result<Vec<OpenFile>, String>,
+            /// Only read while the memory tab is shown.
+            pub memory: Result<MemoryRollup, String>,
+            /// Only read while the limits tab is shown.
+            pub limits: Result<Vec<ResourceLimit>, String>,
+        }
+
+        const STATUS_FIELDS: [&str; 6] = ["State", "PPid", "Uid", "Threads", "VmRSS", "VmSwap"];
+
+        impl ProcessDetail {
+            pub fn new(pid: i32) -> Self {
+                let mut detail = Self {
+                    pid,
+                    tab: DetailTab::Overview,
+                    scroll: 0,
+                    status: Vec::new(),
+                    sched: None,
+                    total_wait_ms: None,
+                    exited: false,
+                    last_sched: None,
+                    cwd: Ok(String::new()),
+                    environ: Ok(Vec::new()),
+                    files: Ok(Vec::new()),
+                    memory: Ok(MemoryRollup::default()),
+                    limits: Ok(Vec::new()),
+                };
+                detail.refresh();
+                detail
+            }
+
+            pub fn set_tab(&mut self, tab: DetailTab) {
+                self.tab = tab;
+                self.scroll = 0;
+                if !self.exited {
+                    self.refresh_tab();
+                }
+            }
+
+            pub fn scroll_by(&mut self, lines: isize) {
+                self.scroll = (self.scroll as isize + lines).clamp(0, u16::MAX as isize) as u16;
+            }
+
+            fn refresh_tab(&mut self) {
+                if self.tab == DetailTab::Files {
+                    self.files = read_open_files(self.pid).map_err(describe);
+                }
+                if self.tab == DetailTab::Memory {
+                    self.memory = MemoryRollup::read(self.pid).map_err(describe);
+                }
+                if self.tab == DetailTab::Limits {
+                    self.limits = read_limits(self.pid).map_err(describe);
+                }
+                if self.tab == DetailTab::Environment {
+                    self.cwd = std::fs::read_link(format!("/proc/{}/cwd", self.pid))
+                        .map(|p| p.display().to_string())
+                        .map_err(describe);
+                    self.environ = std::fs::read(format!("/proc/{}/environ", self.pid))
+                        .map(|raw| {
+                            raw.split(|b| *b == 0)
+                                .filter(|kv| !kv.is_empty())
+                                .map(|kv| String::from_utf8_lossy(kv).into_owned())
+                                .collect()
+                        })
+                        .map_err(describe);
+                }
+            }
+
+            pub fn refresh(&mut self) {
+                let Ok(status) = std::fs::read_to_string(format!("/proc/{}/status", self.pid)) else {
+                    self.exited = true;
+                    return;
+                };
+                self.status = status
+                    .lines()
+                    .filter_map(|line| {
+                        let (key, value) = line.split_once(':')?;
+                        STATUS_FIELDS
+                            .contains(&key)
+                            .then(|| (key.to_string(), value.split_whitespace().collect::<Vec<_>>().join(" ")))
+                    })
+                    .collect();
+
+                let sample = SchedSample::read(self.pid);
+                if let (Some(now), Some(before)) = (sample, self.last_sched) {
+                    let wall_ns = now.at.duration_since(before.at).as_nanos().max(1) as f64;
+                    let waited = now.wait_ns.saturating_sub(before.wait_ns) as f64;
+                    let slices = now.slices.saturating_sub(before.slices);
+                    self.sched = Some(SchedWait {
+                        wait_percent: waited / wall_ns * 100.0,
+                        avg_wait_ms: if slices == 0 { 0.0 } else { waited / slices as f64 / 1e6 },
+                    });
+                }
+                self.total_wait_ms = sample.map(|s| s.wait_ns as f64 / 1e6);
+                self.last_sched = sample.or(self.last_sched);
+                self.refresh_tab();
+            }
+        }
+
+        /// Short reason for a failed `/proc/<pid>` read.
+        fn describe(e: io::Error) -> String {
+            match e.kind() {
+                io::ErrorKind::PermissionDenied => "permission denied (process owned by another user)".to_string(),
+                io::ErrorKind::NotFound => "process exited".to_string(),
+                _ => e.to_string(),
+            }
+        }
+}
+
+pub mod battery {
+        use std::collections::VecDeque;
+        use std::path::Path;
+        use std::time::{Duration, Instant};
+
+        /// One `/sys/class/power_supply/BAT*` device, normalised to Wh and W.
+        #[derive(Clone)]
+        pub struct BatteryInfo {
+            pub name: String,
+            /// `Charging`, `Discharging`, `Full`, `Not charging`, ...
+            pub status: String,
+            pub capacity_percent: f64,
+            pub energy_now_wh: f64,
+            pub energy_full_wh: f64,
+            pub energy_design_wh: f64,
+            /// Absolute charge or discharge rate.
+            pub power_w: f64,
+        }
+
+        impl BatteryInfo {
+            /// Full-charge capacity as a share of the design capacity.
+            pub fn health_percent(&self) -> Option<f64> {
+                (self.energy_design_wh > 0.0).then(|| self.energy_full_wh / self.energy_design_wh * 100.0)
+            }
+
+            pub fn discharging(&self) -> bool {
+                self.status == "Discharging"
+            }
+        }
+
+        fn read_num(dir: &Path, name: &str) -> Option<f64> {
+            std::fs::read_to_string(dir.join(name)).ok()?.trim().parse().ok()
+        }
+
+        /// Reads one supply. Drivers report either energy (µWh, µW) or charge
+        /// (µAh, µA); charge figures are converted using the current voltage.
+        fn read_battery(dir: &Path) -> Option<BatteryInfo> {
+            let volts = read_num(dir, "voltage_now").map(|v| v / 1e6);
+            let wh = |energy: &str, charge: &str| {
+                read_num(dir, energy)
+                    .map(|e| e / 1e6)
+                    .or_else(|| Some(read_num(dir, charge)? / 1e6 * volts?))
+            };
+            let energy_now_wh = wh("energy_now", "charge_now")?;
+            let energy_full_wh = wh("energy_full", "charge_full")?;
+            let energy_design_wh = wh("energy_full_design", "charge_full_design").unwrap_or(0.0);
+            let power_w = read_num(dir, "power_now")
+                .map(|p| p / 1e6)
+                .or_else(|| Some(read_num(dir, "current_now")? / 1e6 * volts?))
+                .unwrap_or(0.0)
+                .abs();
+            let capacity_percent = read_num(dir, "capacity")
+                .unwrap_or_else(|| energy_now_wh / energy_full_wh.max(f64::MIN_POSITIVE) * 100.0);
+            Some(BatteryInfo {
+                name: dir.file_name()?.to_string_lossy().to_string(),
+                status: std::fs::read_to_string(dir.join("status")).unwrap_or_default().trim().to_string(),
+                capacity_percent,
+                energy_now_wh,
+                energy_full_wh,
+                energy_design_wh,
+                power_w,
+            })
+        }
+
+        pub fn read_all() -> Vec<BatteryInfo> {
+            let Ok(entries) = std::fs::read_dir("/sys/class/power_supply") else {
+                return Vec::new();
+            };
+            let mut batteries: Vec<BatteryInfo> = entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| std::fs::read_to_string(p.join("type")).is_ok_and(|t| t.trim() == "Battery"))
+                .filter_map(|p| read_battery(&p))
+                .collect();
+            batteries.sort_by(|a, b| a.name.cmp(&b.name));
+            batteries
+        }
+
+        /// How far back the power draw is averaged for the time estimate.
+        const WINDOW: Duration = Duration::from_secs(300);
+
+        /// Recent total power draw, so the time estimate doesn't jump with
+        /// every momentary spike.
+        #[derive(Default)]
+        pub struct PowerHistory {
+            samples: VecDeque<(Instant, f64)>,
+            discharging: bool,
+        }
+
+        impl PowerHistory {
+            pub fn push(&mut self, batteries: &[BatteryInfo]) {
+                let discharging = batteries.iter().any(BatteryInfo::discharging);
+                if discharging != self.discharging {
+                    // Charging and discharging rates don't mix
+                    self.samples.clear();
+                    self.discharging = discharging;
+                }
+                let now = Instant::now();
+                self.samples.push_back((now, batteries.iter().map(|b| b.power_w).sum()));
+                while self.samples.front().is_some_and(|(t, _)| now.duration_since(*t) > WINDOW) {
+                    self.samples.pop_front();
+                }
+            }
+
+            pub fn average_w(&self) -> Option<f64> {
+                let avg = self.samples.iter().map(|(_, w)| w).sum::<f64>() / self.samples.len().max(1) as f64;
+                (avg > 0.0).then_some(avg)
+            }
+
+            /// Time until empty when discharging, or until full when charging.
+            pub fn time_remaining(&self, batteries: &[BatteryInfo]) -> Option<Duration> {
+                let power = self.average_w()?;
+                let now: f64 = batteries.iter().map(|b| b.energy_now_wh).sum();
+                let full: f64 = batteries.iter().map(|b| b.energy_full_wh).sum();
+                let wh = if self.discharging {
+                    now
+                } else if batteries.iter().any(|b| b.status == "Charging") {
+                    (full - now).max(0.0)
+                } else {
+                    return None;
+                };
+                Some(Duration::from_secs_f64(wh / power * 3600.0))
+            }
+        }
+}
+
+pub mod rapl {
+        use std::collections::VecDeque;
+        use std::path::{Path, PathBuf};
+        use std::time::Instant;
+
+        const POWERCAP: &str = "/sys/class/powercap";
+        /// Samples kept per domain for the sparklines.
+        const HISTORY: usize = 120;
+
+        /// One RAPL domain (package, cores, uncore, DRAM, platform).
+        struct Domain {
+            label: String,
+            energy: PathBuf,
+            /// The counter wraps to 0 after this many µJ.
+            max_uj: u64,
+            last: Option<u64>,
+        }
+
+        /// Average power of one domain since the previous sample, with its
+        /// recent history in mW.
+        pub struct DomainPower {
+            pub label: String,
+            pub watts: f64,
+            pub history: Vec<u64>,
+        }
+
+        /// `package-0` becomes `pkg0`, its `core` child `pkg0 core`; `psys`
+        /// (the whole SoC on recent laptops) is `platform`.
+        fn label(name: &str, parent: Option<&str>) -> String {
+            let own = match name {
+                "psys" => "platform".to_string(),
+                n => match n.strip_prefix("package-") {
+                    Some(id) => format!("pkg{}", id),
+                    None => n.to_string(),
+                },
+            };
+            match parent {
+                Some(p) => format!("{} {}", p, own),
+                None => own,
+            }
+        }
+
+        fn read_u64(path: &Path) -> Option<u64> {
+            std::fs::read_to_string(path).ok()?.trim().parse().ok()
+        }
+
+        /// Turns successive RAPL energy counters into per-domain power. Intel
+        /// and AMD both expose them as `intel-rapl` powercap zones; reading
+        /// `energy_uj` needs root on current kernels.
+        #[derive(Default)]
+        pub struct RaplSampler {
+            domains: Vec<Domain>,
+            history: Vec<VecDeque<u64>>,
+            previous: Option<Instant>,
+        }
+
+        impl RaplSampler {
+            /// Finds the top-level zones (`intel-rapl:N`) and their subzones
+            /// (`intel-rapl:N:M`) whose counters are readable.
+            pub fn scan() -> Self {
+                let Ok(entries) = std::fs::read_dir(POWERCAP) else {
+                    return Self::default();
+                };
+                let mut zones: Vec<(String, PathBuf)> = entries
+                    .flatten()
+                    .filter_map(|e| {
+                        let name = e.file_name().to_str()?.to_string();
+                        // intel-rapl-mmio duplicates the package counters
+                        name.strip_prefix("intel-rapl:")?;
+                        Some((name, e.path()))
+                    })
+                    .collect();
+                // Parents (one colon) sort before their children
+                zones.sort();
+                let mut domains = Vec::new();
+                let mut parents: Vec<(String, String)> = Vec::new();
+                for (zone, path) in zones {
+                    let name = std::fs::read_to_string(path.join("name")).unwrap_or_default().trim().to_string();
+                    let parent = zone.rsplit_once(':').and_then(|(p, _)| {
+                        parents.iter().find(|(z, _)| *z == p).map(|(_, label)| label.clone())
+                    });
+                    let label = label(&name, parent.as_deref());
+                    if parent.is_none() {
+                        parents.push((zone.clone(), label.clone()));
+                    }
+                    let energy = path.join("energy_uj");
+                    if read_u64(&energy).is_none() {
+                        continue;
+                    }
+                    domains.push(Domain {
+                        label,
+                        max_uj: read_u64(&path.join("max_energy_range_uj")).unwrap_or(u64::MAX),
+                        energy,
+                        last: None,
+                    });
+                }
+                let history = domains.iter().map(|_| VecDeque::with_capacity(HISTORY)).collect();
+                Self { domains, history, previous: None }
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.domains.is_empty()
+            }
+
+            /// Reads every counter and returns power since the previous call;
+            /// the first call only primes the counters.
+            pub fn sample(&mut self) -> Vec<DomainPower> {
+                let now = Instant::now();
+                let secs = self.previous.replace(now).map(|then| now.duration_since(then).as_secs_f64().max(0.001));
+                let mut out = Vec::new();
+                for (domain, history) in self.domains.iter_mut().zip(&mut self.history) {
+                    let Some(uj) = read_u64(&domain.energy) else { continue };
+                    let before = domain.last.replace(uj);
+                    let (Some(before), Some(secs)) = (before, secs) else { continue };
+                    let delta = if uj >= before { uj - before } else { domain.max_uj.saturating_sub(before) + uj };
+                    let watts = delta as f64 / 1e6 / secs;
+                    if history.len() >= HISTORY {
+                        history.pop_front();
+                    }
+                    history.push_back((watts * 1000.0) as u64);
+                    out.push(DomainPower {
+                        label: domain.label.clone(),
+                        watts,
+                        history: history.iter().copied().collect(),
+                    });
+                }
+                out
+            }
+        }
+}
+
+pub mod hwhealth {
+        use std::collections::{HashMap, VecDeque};
+        use std::fs::File;
+        use std::io::Read;
+        use std::os::unix::fs::OpenOptionsExt;
+        use std::path::Path;
+        use std::time::{Duration, SystemTime};
+
+        const EDAC: &str = "/sys/devices/system/edac/mc";
+
+        /// ECC error counts of one DIMM (or one memory controller on kernels
+        /// without per-DIMM entries).
+        #[derive(Clone)]
+        pub struct Dimm {
+            /// `dimm_label` (the board silkscreen name) or `mc0/dimm3`.
+            pub label: String,
+            pub correctable: u64,
+            pub uncorrectable: u64,
+            /// Increase since sysmon-tui started.
+            pub new_correctable: u64,
+            pub new_uncorrectable: u64,
+        }
+
+        fn read_u64(path: &Path) -> Option<u64> {
+            std::fs::read_to_string(path).ok()?.trim().parse().ok()
+        }
+
+        fn sorted_dirs(dir: &Path, prefix: &str) -> Vec<std::path::PathBuf> {
+            let mut dirs: Vec<_> = std::fs::read_dir(dir)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .filter(|e| e.file_name().to_str().is_some_and(|n| n.starts_with(prefix)))
+                .map(|e| e.path())
+                .collect();
+            dirs.sort();
+            dirs
+        }
+
+        /// Kernel log lines kept in the panel.
+        const EVENTS_KEPT: usize = 20;
+
+        /// How bad a logged hardware error is.
+        #[derive(Clone, Copy, PartialEq, PartialOrd)]
+        pub enum Severity {
+            Corrected,
+            Error,
+            Fatal,
+        }
+
+        /// A hardware error reported in the kernel log.
+        #[derive(Clone)]
+        pub struct HwEvent {
+            pub time: SystemTime,
+            pub severity: Severity,
+            pub message: String,
+        }
+
+        /// Per-CPU counters from `/proc/interrupts`: machine check
+        /// exceptions, threshold interrupts (corrected errors past the bank
+        /// threshold) and deferred errors (AMD).
+        #[derive(Clone, Copy, Default)]
+        pub struct MceCounts {
+            pub exceptions: u64,
+            pub threshold: u64,
+            pub deferred: u64,
+        }
+
+        impl MceCounts {
+            fn read() -> Option<Self> {
+                let text = std::fs::read_to_string("/proc/interrupts").ok()?;
+                let mut counts = Self::default();
+                let mut found = false;
+                for line in text.lines() {
+                    let Some((name, rest)) = line.trim_start().split_once(':') else { continue };
+                    let slot = match name {
+                        "MCE" => &mut counts.exceptions,
+                        "THR" => &mut counts.threshold,
+                        "DFR" => &mut counts.deferred,
+                        _ => continue,
+                    };
+                    *slot = rest.split_whitespace().map_while(|v| v.parse::<u64>().ok()).sum();
+                    found = true;
+                }
+                found.then_some(counts)
+            }
+
+            pub fn total(&self) -> u64 {
+                self.exceptions + self.threshold + self.deferred
+            }
+        }
+
+        /// Whether a kernel log line reports a hardware error: x86 `mce:`,
+        /// APEI/GHES `[Hardware Error]`, EDAC and PCIe AER. Those sources also
+        /// log setup and thermal lines at boot (`mce: CPU supports 32 MCE
+        /// banks`, `AER: enabled with IRQ 28`), so a line must carry the
+        /// `[Hardware Error]` tag, say corrected/uncorrected, or be logged at
+        /// warning priority or above.
+        fn classify(priority: u8, message: &str) -> Option<Severity> {
+            let lower = message.to_lowercase();
+            let source = lower.contains("[hardware error]")
+                || lower.starts_with("mce:")
+                || lower.contains("machine check")
+                || lower.starts_with("edac")
+                || lower.contains("aer:");
+            let thermal = ["thermal", "temperature", "throttl"].iter().any(|w| lower.contains(w));
+            let tagged = lower.contains("[hardware error]") || lower.contains("corrected") || lower.contains("correctable");
+            if !source || thermal || !(tagged || priority <= 4) {
+                return None;
+            }
+            Some(if lower.contains("fatal") || lower.contains("uncorrect") || priority <= 2 {
+                Severity::Fatal
+            } else if lower.contains("corrected") {
+                Severity::Corrected
+            } else {
+                Severity::Error
+            })
+        }
+
+        /// Machine check counters and hardware errors from the kernel log.
+        /// `/dev/kmsg` is read without blocking; the first read covers the
+        /// log since boot, so errors from before sysmon-tui started show too.
+        pub struct MceMonitor {
+            kmsg: Option<File>,
+            baseline: Option<MceCounts>,
+            pub counts: Option<MceCounts>,
+            /// Most recent last.
+            pub events: VecDeque<HwEvent>,
+            /// Events logged after startup.
+            pub new_events: usize,
+            started: bool,
+        }
+
+        impl Default for MceMonitor {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl MceMonitor {
+            pub fn new() -> Self {
+                let kmsg = std::fs::OpenOptions::new()
+                    .read(true)
+                    .custom_flags(libc::O_NONBLOCK)
+                    .open("/dev/kmsg")
+                    .ok();
+                let baseline = MceCounts::read();
+                Self { kmsg, baseline, counts: baseline, events: VecDeque::new(), new_events: 0, started: false }
+            }
+
+            /// Machine checks counted since startup.
+            pub fn new_counts(&self) -> u64 {
+                match (self.counts, self.baseline) {
+                    (Some(now), Some(then)) => now.total().saturating_sub(then.total()),
+                    _ => 0,
+                }
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.events.is_empty() && self.counts.is_none_or(|c| c.total() == 0)
+            }
+
+            pub fn poll(&mut self) {
+                self.counts = MceCounts::read();
+                let Some(ref mut kmsg) = self.kmsg else { return };
+                let uptime = std::fs::read_to_string("/proc/uptime")
+                    .ok()
+                    .and_then(|t| t.split_whitespace().next()?.parse::<f64>().ok())
+                    .unwrap_or(0.0);
+                let boot = SystemTime::now() - Duration::from_secs_f64(uptime);
+                // One record per read; EAGAIN once caught up
+                let mut buf = vec![0u8; 8192];
+                loop {
+                    let n = match kmsg.read(&mut buf) {
+                        Ok(0) => break,
+                        Ok(n) => n,
+                        // Records overwritten before we read them
+                        Err(e) if e.raw_os_error() == Some(libc::EPIPE) => continue,
+                        Err(_) => break,
+                    };
+                    let record = String::from_utf8_lossy(&buf[..n]);
+                    // `priority,seq,microseconds,flags;message`
+                    let Some((header, message)) = record.split_once(';') else { continue };
+                    let mut fields = header.split(',');
+                    let priority = fields.next().and_then(|p| p.parse::<u32>().ok()).unwrap_or(6) as u8 & 7;
+                    let micros = fields.nth(1).and_then(|t| t.parse::<u64>().ok()).unwrap_or(0);
+                    let message = message.lines().next().unwrap_or_default().trim();
+                    let Some(severity) = classify(priority, message) else { continue };
+                    if self.events.len() >= EVENTS_KEPT {
+                        self.events.pop_front();
+                    }
+                    self.events.push_back(HwEvent {
+                        time: boot + Duration::from_micros(micros),
+                        severity,
+                        message: message.to_string(),
+                    });
+                    if self.started {
+                        self.new_events += 1;
+                    }
+                }
+                self.started = true;
+            }
+        }
+
+        /// EDAC counters per DIMM, and the counts first seen for each so that
+        /// only errors during this session count as new. Keyed by sysfs path,
+        /// since labels can be blank or repeated across controllers.
+        #[derive(Default)]
+        pub struct EdacMonitor {
+            baseline: HashMap<std::path::PathBuf, (u64, u64)>,
+        }
+
+        impl EdacMonitor {
+            /// Reads `mc*/dimm*/dimm_{ce,ue}_count`, falling back to the
+            /// controller totals when a controller has no DIMM entries.
+            pub fn read(&mut self) -> Vec<Dimm> {
+                let mut dimms = Vec::new();
+                for mc in sorted_dirs(Path::new(EDAC), "mc") {
+                    let mc_name = mc.file_name().unwrap_or_default().to_string_lossy().to_string();
+                    let entries = sorted_dirs(&mc, "dimm");
+                    if entries.is_empty() {
+                        if let (Some(ce), Some(ue)) = (read_u64(&mc.join("ce_count")), read_u64(&mc.join("ue_count"))) {
+                            dimms.push((mc.clone(), mc_name, ce, ue));
+                        }
+                        continue;
+                    }
+                    for dimm in entries {
+                        let (Some(ce), Some(ue)) = (read_u64(&dimm.join("dimm_ce_count")), read_u64(&dimm.join("dimm_ue_count"))) else {
+                            continue;
+                        };
+                        let label = std::fs::read_to_string(dimm.join("dimm_label")).unwrap_or_default().trim().to_string();
+                        let label = if label.is_empty() {
+                            format!("{}/{}", mc_name, dimm.file_name().unwrap_or_default().to_string_lossy())
+                        } else {
+                            label
+                        };
+                        dimms.push((dimm, label, ce, ue));
+                    }
+                }
+                dimms
+                    .into_iter()
+                    .map(|(path, label, ce, ue)| {
+                        let (ce0, ue0) = *self.baseline.entry(path).or_insert((ce, ue));
+                        Dimm {
+                            new_correctable: ce.saturating_sub(ce0),
+                            new_uncorrectable: ue.saturating_sub(ue0),
+                            label,
+                            correctable: ce,
+                            uncorrectable: ue,
+                        }
+                    })
+                    .collect()
+            }
+        }
+}
+
+pub mod guests {
+        use std::collections::HashMap;
+        use std::time::Instant;
+
+        use crate::types::ProcessInfo;
+
+        /// Clock ticks per second in `/proc/<pid>/task/<tid>/stat` (USER_HZ).
+        /// 100 on x86 and most ARM builds, but configurable elsewhere.
+        fn ticks_per_sec() -> f64 {
+            // SAFETY: sysconf has no preconditions.
+            let hz = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+            if hz > 0 { hz as f64 } else { 100.0 }
+        }
+
+        /// A running KVM guest, seen through its QEMU process.
+        #[derive(Clone)]
+        pub struct Guest {
+            pub name: String,
+            pub pid: i32,
+            pub vcpus: usize,
+            /// Time on the vCPU threads; 100% per fully busy vCPU.
+            pub vcpu_percent: f64,
+            /// Memory given with `-m`.
+            pub assigned: Option<u64>,
+            /// Host memory the guest occupies (QEMU's RSS), which stays below
+            /// `assigned` until the guest touches it or after ballooning.
+            pub resident: u64,
+        }
+
+        /// Turns QEMU processes into per-guest figures, keeping vCPU thread
+        /// times between samples for the usage rate.
+        #[derive(Default)]
+        pub struct GuestSampler {
+            last: HashMap<i32, (u64, Instant)>,
+        }
+
+        impl GuestSampler {
+            pub fn sample(&mut self, processes: &[ProcessInfo]) -> Vec<Guest> {
+                let now = Instant::now();
+                let hz = ticks_per_sec();
+                let mut seen = HashMap::new();
+                let mut guests: Vec<Guest> = processes
+                    .iter()
+                    .filter(|p| !p.thread && (p.name.starts_with("qemu-system") || p.name.starts_with("qemu-kvm")))
+                    .map(|p| {
+                        let (vcpus, ticks) = vcpu_ticks(p.pid);
+                        let vcpu_percent = self.last.get(&p.pid).map_or(0.0, |(before, at)| {
+                            let secs = now.duration_since(*at).as_secs_f64();
+                            if secs > 0.0 {
+                                ticks.saturating_sub(*before) as f64 / hz / secs * 100.0
+                            } else {
+                                0.0
+                            }
+                        });
+                        seen.insert(p.pid, (ticks, now));
+                        let args: Vec<&str> = p.cmd.split_whitespace().collect();
+                        let arg = |flag: &str| args.iter().position(|a| *a == flag).and_then(|i| args.get(i + 1)).copied();
+                        Guest {
+                            name: arg("-name").map_or_else(|| p.name.clone(), guest_name),
+                            pid: p.pid,
+                            vcpus,
+                            vcpu_percent,
+                            assigned: arg("-m").and_then(memory_size),
+                            resident: p.mem_bytes,
+                        }
+                    })
+                    .collect();
+                self.last = seen;
+                guests.sort_by(|a, b| a.name.cmp(&b.name));
+                guests
+            }
+        }
+
+        /// `-name guest=web01,debug-threads=on` (libvirt) or `-name web01`.
+        fn guest_name(value: &str) -> String {
+            value
+                .split(',')
+                .find_map(|part| part.strip_prefix("guest="))
+                .unwrap_or_else(|| value.split(',').next().unwrap_or(value))
+                .to_string()
+        }
+
+        /// `-m 4096`, `-m 4G` or `-m size=4194304k,slots=...`; a bare
+        /// number is MiB.
+        fn memory_size(value: &str) -> Option<u64> {
+            let size = value.split(',').find_map(|p| p.strip_prefix("size=")).unwrap_or_else(|| value.split(',').next().unwrap_or(value));
+            let (digits, shift) = match size.chars().last()? {
+                'k' | 'K' => (&size[..size.len() - 1], 10),
+                'm' | 'M' => (&size[..size.len() - 1], 20),
+                'g' | 'G' => (&size[..size.len() - 1], 30),
+                't' | 'T' => (&size[..size.len() - 1], 40),
+                _ => (size, 20),
+            };
+            digits.parse::<u64>().ok().map(|n| n << shift)
+        }
+
+        /// Number of vCPU threads (named `CPU <n>/KVM`) and their summed
+        /// utime + stime in ticks.
+        fn vcpu_ticks(pid: i32) -> (usize, u64) {
+            let mut count = 0;
+            let mut ticks = 0;
+            for task in std::fs::read_dir(format!("/proc/{}/task", pid)).into_iter().flatten().flatten() {
+                let comm = std::fs::read_to_string(task.path().join("comm")).unwrap_or_default();
+                if !(comm.starts_with("CPU ") && comm.trim_end().ends_with("/KVM")) {
+                    continue;
+                }
+                count += 1;
+                let Ok(stat) = std::fs::read_to_string(task.path().join("stat")) else {
+                    continue;
+                };
+                // Fields after the parenthesised comm start at field 3; utime
+                // and stime are fields 14 and 15.
+                if let Some((_, rest)) = stat.rsplit_once(')') {
+                    let f: Vec<u64> = rest.split_whitespace().skip(11).take(2).filter_map(|v| v.parse().ok()).collect();
+                    ticks += f.iter().sum::<u64>();
+                }
+            }
+            (count, ticks)
+        }
+}
+
+pub mod virt {
+        /// What sysmon-tui runs inside, detected once at startup the way
+        /// `systemd-detect-virt` does: container markers first, then WSL,
+        /// then DMI vendor strings and the CPU's hypervisor flag.
+        #[derive(Clone, Default)]
+        pub struct Virtualization {
+            /// Hypervisor name (`KVM`, `VMware`, `Hyper-V`, ...), or `VM`
+            /// when only the CPU flag gives it away.
+            pub vm: Option<String>,
+            /// Container runtime (`docker`, `podman`, `lxc`, ...).
+            pub container: Option<String>,
+            pub wsl: bool,
+        }
+
+        /// DMI vendor substrings and the hypervisor each one means.
+        const DMI_VENDORS: [(&str, &str); 14] = [
+            ("KVM", "KVM"),
+            ("OpenStack", "KVM"),
+            ("KubeVirt", "KVM"),
+            ("Amazon EC2", "Amazon"),
+            ("QEMU", "QEMU"),
+            ("VMware", "VMware"),
+            ("VMW", "VMware"),
+            ("innotek GmbH", "VirtualBox"),
+            ("VirtualBox", "VirtualBox"),
+            ("Xen", "Xen"),
+            ("Bochs", "Bochs"),
+            ("Parallels", "Parallels"),
+            ("BHYVE", "bhyve"),
+            ("Google Compute Engine", "Google"),
+        ];
+
+        /// Vendors that also sell bare-metal machines with the same DMI
+        /// strings (EC2 `*.metal`), so they only count with the hypervisor flag.
+        const BARE_METAL_VENDORS: [&str; 1] = ["Amazon"];
+
+        impl Virtualization {
+            pub fn detect() -> Option<Self> {
+                let read = |path: &str| std::fs::read_to_string(path).map(|s| s.trim().to_string()).unwrap_or_default();
+                let wsl = read("/proc/sys/kernel/osrelease").to_lowercase().contains("microsoft");
+                let v = Self { vm: if wsl { None } else { detect_vm(&read) }, container: detect_container(&read), wsl };
+                (v.vm.is_some() || v.container.is_some() || v.wsl).then_some(v)
+            }
+
+            /// Short form for panel titles: `docker`, `KVM guest`, `WSL`,
+            /// `docker on KVM`.
+            pub fn label(&self) -> String {
+                let host = if self.wsl { Some("WSL".to_string()) } else { self.vm.clone() };
+                match (&self.container, host) {
+                    (Some(c), Some(h)) => format!("{} on {}", c, h),
+                    (Some(c), None) => format!("{} container", c),
+                    (None, Some(h)) if self.wsl => h,
+                    (None, Some(h)) => format!("{} guest", h),
+                    (None, None) => String::new(),
+                }
+            }
+
+            /// Why some readings look unusual here.
+            pub fn note(&self) -> &'static str {
+                if self.container.is_some() {
+                    "Inside a container: CPU, memory, disk and network figures are host-wide, and sensors belong to the host if visible at all."
+                } else if self.wsl {
+                    "Under WSL: figures describe the WSL utility VM, and Windows doesn't pass hardware sensors through."
+                } else {
+                    "In a virtual machine: hardware sensors are rarely passed through, and CPU steal time is time the host gave to other guests."
+                }
+            }
+        }
+
+        fn detect_container(read: &dyn Fn(&str) -> String) -> Option<String> {
+            // systemd-nspawn, lxc and podman set $container for PID 1
+            let environ = std::fs::read("/proc/1/environ").unwrap_or_default();
+            if let Some(name) = environ
+                .split(|b| *b == 0)
+                .find_map(|v| v.strip_prefix(b"container="))
+                .map(|v| String::from_utf8_lossy(v).to_string())
+                .filter(|v| !v.is_empty())
+            {
+                return Some(if name == "oci" { "podman".to_string() } else { name });
+            }
+            if std::path::Path::new("/run/.containerenv").exists() {
+                return Some("podman".to_string());
+            }
+            if std::path::Path::new("/.dockerenv").exists() {
+                return Some("docker".to_string());
+            }
+            let cgroup = read("/proc/1/cgroup");
+            ["kubepods", "docker", "lxc"]
+                .into_iter()
+                .find(|k| cgroup.contains(k))
+                .map(|k| if k == "kubepods" { "kubernetes" } else { k }.to_string())
+        }
+
+        fn detect_vm(read: &dyn Fn(&str) -> String) -> Option<String> {
+            let hypervisor = hypervisor_flag(read);
+            for file in ["sys_vendor", "product_name", "board_vendor", "bios_vendor"] {
+                let value = read(&format!("/sys/class/dmi/id/{}", file));
+                if let Some((_, name)) = DMI_VENDORS.iter().find(|(vendor, _)| value.starts_with(vendor)) {
+                    if BARE_METAL_VENDORS.contains(name) && hypervisor == Some(false) {
+                        return None;
+                    }
+                    return Some(name.to_string());
+                }
+            }
+            if read("/sys/class/dmi/id/sys_vendor") == "Microsoft Corporation"
+                && read("/sys/class/dmi/id/product_name") == "Virtual Machine"
+            {
+                return Some("Hyper-V".to_string());
+            }
+            if read("/sys/hypervisor/type") == "xen" {
+                return Some("Xen".to_string());
+            }
+            hypervisor.unwrap_or(false).then(|| "VM".to_string())
+        }
+
+        /// The CPUID hypervisor bit (the `hypervisor` cpuinfo flag) or a
+        /// populated `/sys/hypervisor`. `None` off x86, where there is no such
+        /// flag and its absence says nothing.
+        fn hypervisor_flag(read: &dyn Fn(&str) -> String) -> Option<bool> {
+            if !read("/sys/hypervisor/type").is_empty() {
+                return Some(true);
+            }
+            cfg!(any(target_arch = "x86", target_arch = "x86_64")).then(|| {
+                read("/proc/cpuinfo")
+                    .lines()
+                    .any(|l| l.starts_with("flags") && l.split_whitespace().any(|f| f == "hypervisor"))
+            })
+        }
+}
+
+pub mod sysdetails {
+        use chrono::{DateTime, Local};
+
+        /// Static facts about the running system for the details overlay,
+        /// read when it is opened.
+        pub struct SystemDetails {
+            pub kernel: String,
+            pub boot_time: Option<DateTime<Local>>,
+            /// `/proc/cmdline`.
+            pub cmdline: String,
+            /// Distinct microcode revisions across CPUs (more than one means a
+            /// partial late load).
+            pub microcode: Vec<String>,
+            /// `(name, status)` from `/sys/devices/system/cpu/vulnerabilities`.
+            pub vulnerabilities: Vec<(String, String)>,
+        }
+
+        impl SystemDetails {
+            pub fn read() -> Self {
+                let read = |path: &str| std::fs::read_to_string(path).map(|s| s.trim().to_string()).unwrap_or_default();
+                let boot_time = read("/proc/stat")
+                    .lines()
+                    .find_map(|l| l.strip_prefix("btime ")?.trim().parse::<i64>().ok())
+                    .and_then(|secs| DateTime::from_timestamp(secs, 0))
+                    .map(|t| t.with_timezone(&Local));
+                let mut microcode: Vec<String> = Vec::new();
+                for line in read("/proc/cpuinfo").lines() {
+                    if let Some((key, value)) = line.split_once(':') {
+                        let value = value.trim().to_string();
+                        if key.trim() == "microcode" && !microcode.contains(&value) {
+                            microcode.push(value);
+                        }
+                    }
+                }
+                let mut vulnerabilities: Vec<(String, String)> = std::fs::read_dir("/sys/devices/system/cpu/vulnerabilities")
+                    .into_iter()
+                    .flatten()
+                    .flatten()
+                    .map(|e| {
+                        let status = std::fs::read_to_string(e.path()).unwrap_or_default().trim().to_string();
+                        (e.file_name().to_string_lossy().to_string(), status)
+                    })
+                    .collect();
+                vulnerabilities.sort();
+                Self {
+                    kernel: format!("{} {}", read("/proc/sys/kernel/ostype"), read("/proc/sys/kernel/osrelease")),
+                    boot_time,
+                    cmdline: read("/proc/cmdline"),
+                    microcode,
+                    vulnerabilities,
+                }
+            }
+        }
+}
+
+pub mod sessions {
+        use std::time::{Duration, SystemTime};
+
+        const UTMP: &str = "/run/utmp";
+        /// `struct utmp` on Linux (x86_64, aarch64 and other 64-bit ABIs use
+        /// 32-bit time fields here too).
+        const RECORD: usize = 384;
+        const USER_PROCESS: i16 = 7;
+
+        /// One login from utmp.
+        #[derive(Clone)]
+        pub struct Session {
+            pub user: String,
+            /// Terminal, e.g. `pts/0` or `tty1`.
+            pub line: String,
+            /// Remote host for SSH logins; empty for local ones.
+            pub host: String,
+            pub login: Option<SystemTime>,
+            /// Time since the terminal last saw input.
+            pub idle: Option<Duration>,
+        }
+
+        /// Current logins, oldest first. Empty when utmp is missing
+        /// (containers, minimal systems).
+        pub fn read() -> Vec<Session> {
+            let Ok(data) = std::fs::read(UTMP) else {
+                return Vec::new();
+            };
+            let text = |bytes: &[u8]| {
+                let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+                String::from_utf8_lossy(&bytes[..end]).to_string()
+            };
+            let now = SystemTime::now();
+            let mut sessions: Vec<Session> = data
+                .chunks_exact(RECORD)
+                .filter(|r| i16::from_ne_bytes([r[0], r[1]]) == USER_PROCESS)
+                .map(|r| {
+                    let line = text(&r[8..40]);
+                    let secs = i32::from_ne_bytes([r[340], r[341], r[342], r[343]]);
+                    let idle = std::fs::metadata(format!("/dev/{}", line))
+                        .and_then(|m| m.accessed())
+                        .ok()
+                        .and_then(|t| now.duration_since(t).ok());
+                    Session {
+                        user: text(&r[44..76]),
+                        line,
+                        host: text(&r[76..332]),
+                        login: (secs > 0).then(|| SystemTime::UNIX_EPOCH + Duration::from_secs(secs as u64)),
+                        idle,
+                    }
+                })
+                .collect();
+            sessions.sort_by_key(|s| s.login);
+            sessions
+        }
+}
+
+pub mod units {
+        use std::process::Command;
+        use std::sync::{Arc, Mutex};
+        use std::time::Duration;
+
+        const INTERVAL: Duration = Duration::from_secs(10);
+        /// Longest wait between retries after `systemctl` failed.
+        const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+        /// A systemd unit in the `failed` state.
+        #[derive(Clone)]
+        pub struct FailedUnit {
+            pub name: String,
+            /// `loaded`, or `not-found` for a unit file that has since gone.
+            pub load: String,
+            pub description: String,
+        }
+
+        /// Polls systemd for failed units on a background thread.
+        pub struct FailedUnits {
+            /// `None` until the first answer, and for good when `systemctl`
+            /// is not installed.
+            units: Arc<Mutex<Option<Vec<FailedUnit>>>>,
+        }
+
+        enum QueryError {
+            /// No `systemctl` binary; nothing will change that.
+            Missing,
+            /// Failed or exited non-zero (D-Bus timeout, systemd restarting).
+            Failed,
+        }
+
+        impl FailedUnits {
+            pub fn spawn() -> Self {
+                let units = Arc::new(Mutex::new(None));
+                let shared = Arc::clone(&units);
+                std::thread::spawn(move || {
+                    let mut wait = INTERVAL;
+                    loop {
+                        match query() {
+                            Ok(list) => {
+                                if let Ok(mut u) = shared.lock() {
+                                    *u = Some(list);
+                                }
+                                wait = INTERVAL;
+                            }
+                            // The last answer stays up while retrying
+                            Err(QueryError::Failed) => wait = (wait * 2).min(MAX_BACKOFF),
+                            Err(QueryError::Missing) => break,
+                        }
+                        std::thread::sleep(wait);
+                    }
+                    if let Ok(mut u) = shared.lock() {
+                        *u = None;
+                    }
+                });
+                Self { units }
+            }
+
+            pub fn get(&self) -> Option<Vec<FailedUnit>> {
+                self.units.lock().ok()?.clone()
+            }
+        }
+
+        fn query() -> Result<Vec<FailedUnit>, QueryError> {
+            let out = Command::new("systemctl")
+                .args(["list-units", "--state=failed", "--all", "--no-legend", "--plain", "--no-pager"])
+                .output()
+                .map_err(|e| if e.kind() == std::io::ErrorKind::NotFound { QueryError::Missing } else { QueryError::Failed })?;
+            if !out.status.success() {
+                return Err(QueryError::Failed);
+            }
+            // `unit load active sub description...`
+            let units = String::from_utf8_lossy(&out.stdout)
+                .lines()
+                .filter_map(|line| {
+                    let mut fields = line.split_whitespace();
+                    let name = fields.next()?.to_string();
+                    let load = fields.next()?.to_string();
+                    let description = fields.skip(2).collect::<Vec<_>>().join(" ");
+                    Some(FailedUnit { name, load, description })
+                })
+                .collect();
+            Ok(units)
+        }
+}
+
+#[cfg(feature = "perf")]
+pub mod perf {
+        use std::fs::File;
+        use std::io::{self, Read};
+        use std::os::fd::FromRawFd;
+        use std::time::Instant;
+
+        /// `struct perf_event_attr` up to `config1` (`PERF_ATTR_SIZE_VER0`);
+        /// the kernel accepts this shorter layout from older callers.
+        #[repr(C)]
+        #[derive(Default)]
+        struct EventAttr {
+            kind: u32,
+            size: u32,
+            config: u64,
+            sample_period: u64,
+            sample_type: u64,
+            read_format: u64,
+            flags: u64,
+            wakeup_events: u32,
+            bp_type: u32,
+            config1: u64,
+        }
+
+        const PERF_TYPE_HARDWARE: u32 = 0;
+        /// Generic hardware events: cycles, instructions, cache references,
+        /// cache misses.
+        const EVENTS: [u64; 4] = [0, 1, 2, 3];
+        /// `PERF_FORMAT_TOTAL_TIME_ENABLED | PERF_FORMAT_TOTAL_TIME_RUNNING`,
+        /// to scale counts when the PMU multiplexes events.
+        const READ_FORMAT: u64 = 1 | 2;
+        const PERF_FLAG_FD_CLOEXEC: libc::c_ulong = 8;
+
+        /// System-wide event rates per second since the previous sample.
+        /// The cache figures are `None` when the PMU doesn't count them.
+        #[derive(Clone, Copy)]
+        pub struct PerfRates {
+            pub cycles: f64,
+            pub instructions: f64,
+            pub cache_references: Option<f64>,
+            pub cache_misses: Option<f64>,
+        }
+
+        impl PerfRates {
+            /// Instructions per cycle.
+            pub fn ipc(&self) -> Option<f64> {
+                (self.cycles > 0.0).then(|| self.instructions / self.cycles)
+            }
+
+            /// Share of last-level cache references that missed.
+            pub fn cache_miss_percent(&self) -> Option<f64> {
+                let references = self.cache_references.filter(|r| *r > 0.0)?;
+                Some(self.cache_misses? / references * 100.0)
+            }
+        }
+
+        /// One counter on one CPU and its last `[value, enabled, running]`.
+        struct Counter {
+            event: usize,
+            file: File,
+            last: [u64; 3],
+        }
+
+        impl Counter {
+            fn open(event: usize, cpu: i32) -> io::Result<Self> {
+                let attr = EventAttr {
+                    kind: PERF_TYPE_HARDWARE,
+                    size: std::mem::size_of::<EventAttr>() as u32,
+                    config: EVENTS[event],
+                    read_format: READ_FORMAT,
+                    ..Default::default()
+                };
+                // SAFETY: `attr` outlives the call; pid -1 with a CPU counts
+                // every task on that CPU, and no group fd is passed.
+                let fd = unsafe {
+                    libc::syscall(
+                        libc::SYS_perf_event_open,
+                        &attr as *const EventAttr,
+                        -1 as libc::pid_t,
+                        cpu,
+                        -1 as libc::c_int,
+                        PERF_FLAG_FD_CLOEXEC,
+                    )
+                };
+                if fd < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                // SAFETY: the syscall returned a new descriptor nobody else owns.
+                let file = unsafe { File::from_raw_fd(fd as i32) };
+                Ok(Self { event, file, last: [0; 3] })
+            }
+
+            fn read(&mut self) -> Option<[u64; 3]> {
+                let mut buf = [0u8; 24];
+                (&self.file).read_exact(&mut buf).ok()?;
+                let field = |i: usize| u64::from_ne_bytes(buf[i * 8..i * 8 + 8].try_into().unwrap_or_default());
+                Some([field(0), field(1), field(2)])
+            }
+        }
+
+        /// Online CPUs from `/sys/devices/system/cpu/online` (`0-3,6`).
+        fn online_cpus() -> Vec<i32> {
+            let text = std::fs::read_to_string("/sys/devices/system/cpu/online").unwrap_or_default();
+            text.trim()
+                .split(',')
+                .filter_map(|range| match range.split_once('-') {
+                    Some((a, b)) => Some((a.parse().ok()?..=b.parse().ok()?).collect::<Vec<i32>>()),
+                    None => Some(vec![range.parse().ok()?]),
+                })
+                .flatten()
+                .collect()
+        }
+
+        /// Hardware counters on every online CPU, counting all tasks.
+        pub struct PerfCounters {
+            counters: Vec<Counter>,
+            previous: Option<Instant>,
+        }
+
+        impl PerfCounters {
+            /// Opens each event on every CPU. Events the PMU lacks are left
+            /// out; without cycles and instructions there is nothing to show,
+            /// and the error says why.
+            pub fn open() -> Result<Self, String> {
+                let cpus = online_cpus();
+                let mut counters = Vec::new();
+                for event in 0..EVENTS.len() {
+                    let opened: io::Result<Vec<Counter>> = cpus.iter().map(|cpu| Counter::open(event, *cpu)).collect();
+                    match opened {
+                        Ok(mut c) => counters.append(&mut c),
+                        Err(e) if event < 2 => {
+                            return Err(match e.raw_os_error() {
+                                Some(libc::ENOENT) | Some(libc::EOPNOTSUPP) => {
+                                    "no hardware counters (virtual machine?)".to_string()
+                                }
+                                Some(libc::EACCES) | Some(libc::EPERM) => {
+                                    "not permitted: needs CAP_PERFMON or kernel.perf_event_paranoid <= 0".to_string()
+                                }
+                                _ => format!("perf_event_open: {}", e),
+                            });
+                        }
+                        Err(_) => {}
+                    }
+                }
+                Ok(Self { counters, previous: None })
+            }
+
+            /// Rates since the previous call; the first call only primes
+            /// the counters.
+            pub fn sample(&mut self) -> Option<PerfRates> {
+                let now = Instant::now();
+                let mut totals = [None::<f64>; 4];
+                for counter in &mut self.counters {
+                    let Some(reading) = counter.read() else { continue };
+                    let [value, enabled, running] = [0, 1, 2].map(|i| reading[i].saturating_sub(counter.last[i]));
+                    counter.last = reading;
+                    // Scale up for the time the event wasn't on the PMU
+                    let scaled = if running > 0 { value as f64 * enabled as f64 / running as f64 } else { 0.0 };
+                    *totals[counter.event].get_or_insert(0.0) += scaled;
+                }
+                let secs = now.duration_since(self.previous.replace(now)?).as_secs_f64().max(0.001);
+                let rate = |i: usize| totals[i].map(|t| t / secs);
+                Some(PerfRates {
+                    cycles: rate(0)?,
+                    instructions: rate(1)?,
+                    cache_references: rate(2),
+                    cache_misses: rate(3),
+                })
+            }
+        }
+}
+
+#[cfg(feature = "ebpf")]
+pub mod ebpf {
+        use std::collections::{HashMap, HashSet, VecDeque};
+        use std::fs::File;
+        use std::io;
+        use std::os::fd::{AsRawFd, FromRawFd};
+        use std::os::unix::fs::MetadataExt;
+        use std::path::PathBuf;
+        use std::time::{Duration, Instant};
+
+        // bpf(2) commands, map types and the program type used here
+        const BPF_MAP_CREATE: libc::c_int = 0;
+        const BPF_MAP_LOOKUP_ELEM: libc::c_int = 1;
+        const BPF_MAP_DELETE_ELEM: libc::c_int = 3;
+        const BPF_MAP_GET_NEXT_KEY: libc::c_int = 4;
+        const BPF_PROG_LOAD: libc::c_int = 5;
+        const BPF_MAP_TYPE_HASH: u32 = 1;
+        const BPF_MAP_TYPE_LRU_HASH: u32 = 9;
+        const BPF_PROG_TYPE_TRACEPOINT: u32 = 5;
+
+        /// `union bpf_attr` for `BPF_MAP_CREATE`, up to `map_flags`.
+        #[repr(C)]
+        struct MapCreate {
+            map_type: u32,
+            key_size: u32,
+            value_size: u32,
+            max_entries: u32,
+            map_flags: u32,
+        }
+
+        /// `union bpf_attr` for the map element commands.
+        #[repr(C)]
+        struct MapElem {
+            map_fd: u32,
+            pad: u32,
+            key: u64,
+            /// The value, or the next key for `BPF_MAP_GET_NEXT_KEY`.
+            value: u64,
+            flags: u64,
+        }
+
+        /// `union bpf_attr` for `BPF_PROG_LOAD`, up to `prog_name`.
+        #[repr(C)]
+        struct ProgLoad {
+            prog_type: u32,
+            insn_cnt: u32,
+            insns: u64,
+            license: u64,
+            log_level: u32,
+            log_size: u32,
+            log_buf: u64,
+            kern_version: u32,
+            prog_flags: u32,
+            prog_name: [u8; 16],
+        }
+
+        /// `bpf(2)` with `attr` as the command's part of `union bpf_attr`.
+        ///
+        /// # Safety
+        ///
+        /// Every pointer in `attr` must be valid for the access `cmd` makes.
+        unsafe fn bpf<T>(cmd: libc::c_int, attr: &T) -> io::Result<libc::c_long> {
+            let ret = libc::syscall(libc::SYS_bpf, cmd, attr as *const T, std::mem::size_of::<T>() as libc::c_uint);
+            if ret < 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(ret)
+            }
+        }
+
+        /// `bpf` for the commands that return a new descriptor.
+        ///
+        /// # Safety
+        ///
+        /// As for `bpf`.
+        unsafe fn bpf_fd<T>(cmd: libc::c_int, attr: &T) -> io::Result<File> {
+            let fd = bpf(cmd, attr)?;
+            // The kernel returned a new descriptor nobody else owns.
+            Ok(File::from_raw_fd(fd as i32))
+        }
+
+        fn describe(e: &io::Error) -> String {
+            match e.raw_os_error() {
+                Some(libc::EPERM) | Some(libc::EACCES) => "not permitted: needs root, or CAP_BPF and CAP_PERFMON".to_string(),
+                _ => e.to_string(),
+            }
+        }
+
+        /// Kernels before 5.11 charge maps and programs to RLIMIT_MEMLOCK,
+        /// whose usual default is too small for them.
+        fn raise_memlock() {
+            let unlimited = libc::rlimit { rlim_cur: libc::RLIM_INFINITY, rlim_max: libc::RLIM_INFINITY };
+            // SAFETY: plain data in, nothing kept; on failure the limit stays as it was.
+            unsafe { libc::setrlimit(libc::RLIMIT_MEMLOCK, &unlimited) };
+        }
+
+        /// A BPF map with fixed-size keys and values.
+        struct Map {
+            fd: File,
+            key_size: usize,
+            value_size: usize,
+            max_entries: u32,
+        }
+
+        impl Map {
+            fn create(map_type: u32, key_size: usize, value_size: usize, max_entries: u32) -> Result<Self, String> {
+                let attr = MapCreate { map_type, key_size: key_size as u32, value_size: value_size as u32, max_entries, map_flags: 0 };
+                // SAFETY: map creation takes no pointers.
+                let fd = unsafe { bpf_fd(BPF_MAP_CREATE, &attr) }.map_err(|e| format!("map: {}", describe(&e)))?;
+                Ok(Self { fd, key_size, value_size, max_entries })
+            }
+
+            fn elem(&self, key: *const u8, value: *mut u8) -> MapElem {
+                MapElem { map_fd: self.fd.as_raw_fd() as u32, pad: 0, key: key as u64, value: value as u64, flags: 0 }
+            }
+
+            fn lookup(&self, key: &[u8]) -> Option<Vec<u8>> {
+                if key.len() != self.key_size {
+                    return None;
+                }
+                let mut value = vec![0u8; self.value_size];
+                // SAFETY: `key` and `value` are the sizes the map was created with.
+                unsafe { bpf(BPF_MAP_LOOKUP_ELEM, &self.elem(key.as_ptr(), value.as_mut_ptr())) }.ok()?;
+                Some(value)
+            }
+
+            fn delete(&self, key: &[u8]) {
+                if key.len() == self.key_size {
+                    // SAFETY: `key` is the map's key size; delete reads no value.
+                    let _ = unsafe { bpf(BPF_MAP_DELETE_ELEM, &self.elem(key.as_ptr(), std::ptr::null_mut())) };
+                }
+            }
+
+            /// Every entry. Ones added or deleted meanwhile may be missed.
+            fn entries(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+                let mut entries = Vec::new();
+                let mut key: Option<Vec<u8>> = None;
+                // Bounded, since a key deleted under the walk restarts it
+                for _ in 0..self.max_entries {
+                    let mut next = vec![0u8; self.key_size];
+                    let current = key.as_ref().map_or(std::ptr::null(), |k| k.as_ptr());
+                    // SAFETY: `current` is null (first key) or a key of the map's
+                    // size, and `next` has room for one.
+                    if unsafe { bpf(BPF_MAP_GET_NEXT_KEY, &self.elem(current, next.as_mut_ptr())) }.is_err() {
+                        break;
+                    }
+                    if let Some(value) = self.lookup(&next) {
+                        entries.push((next.clone(), value));
+                    }
+                    key = Some(next);
+                }
+                entries
+            }
+        }
+
+        /// `struct bpf_insn`.
+        #[repr(C)]
+        #[derive(Clone, Copy)]
+        struct Insn {
+            code: u8,
+            /// `dst_reg:4, src_reg:4`.
+            regs: u8,
+            off: i16,
+            imm: i32,
+        }
+
+        // Instruction classes, sizes, modes and operations
+        const LD: u8 = 0x00;
+        const LDX: u8 = 0x01;
+        const ST: u8 = 0x02;
+        const STX: u8 = 0x03;
+        const JMP: u8 = 0x05;
+        const ALU64: u8 = 0x07;
+        const W: u8 = 0x00;
+        const H: u8 = 0x08;
+        const B: u8 = 0x10;
+        const DW: u8 = 0x18;
+        const IMM: u8 = 0x00;
+        const MEM: u8 = 0x60;
+        const ATOMIC: u8 = 0xc0;
+        const K: u8 = 0x00;
+        const X: u8 = 0x08;
+        const ADD: u8 = 0x00;
+        const SUB: u8 = 0x10;
+        const DIV: u8 = 0x30;
+        const LSH: u8 = 0x60;
+        const RSH: u8 = 0x70;
+        const MOV: u8 = 0xb0;
+        const ARSH: u8 = 0xc0;
+        const JEQ: u8 = 0x10;
+        const JSET: u8 = 0x40;
+        const JNE: u8 = 0x50;
+        const JLE: u8 = 0xb0;
+        const JSLE: u8 = 0xd0;
+        const CALL: u8 = 0x80;
+        const EXIT: u8 = 0x90;
+        const BPF_PSEUDO_MAP_FD: u8 = 1;
+
+        // Helper function numbers
+        const MAP_LOOKUP_ELEM: i32 = 1;
+        const MAP_UPDATE_ELEM: i32 = 2;
+        const MAP_DELETE_ELEM: i32 = 3;
+        const KTIME_GET_NS: i32 = 5;
+        const GET_NS_CURRENT_PID_TGID: i32 = 120;
+        const BPF_ANY: i32 = 0;
+        const BPF_NOEXIST: i32 = 1;
+
+        // R0 returns, R1-R5 pass arguments and are clobbered by calls, R6-R9
+        // survive calls, R10 is the read-only frame pointer.
+        const R0: u8 = 0;
+        const R1: u8 = 1;
+        const R2: u8 = 2;
+        const R3: u8 = 3;
+        const R4: u8 = 4;
+        const R6: u8 = 6;
+        const R7: u8 = 7;
+        const R10: u8 = 10;
+
+        struct Label(usize);
+
+        /// Builds a program, resolving jumps to labels bound later.
+        #[derive(Default)]
+        struct Asm {
+            insns: Vec<Insn>,
+            labels: Vec<Option<usize>>,
+            /// Instruction index and the label it jumps to.
+            jumps: Vec<(usize, usize)>,
+        }
+
+        impl Asm {
+            fn emit(&mut self, code: u8, dst: u8, src: u8, off: i16, imm: i32) {
+                // A bitfield, so the nibble order follows the byte order
+                let regs = if cfg!(target_endian = "little") { dst | src << 4 } else { dst << 4 | src };
+                self.insns.push(Insn { code, regs, off, imm });
+            }
+
+            fn label(&mut self) -> Label {
+                self.labels.push(None);
+                Label(self.labels.len() - 1)
+            }
+
+            fn bind(&mut self, label: &Label) {
+                self.labels[label.0] = Some(self.insns.len());
+            }
+
+            fn alu(&mut self, op: u8, dst: u8, imm: i32) {
+                self.emit(ALU64 | op | K, dst, 0, 0, imm);
+            }
+
+            fn alu_reg(&mut self, op: u8, dst: u8, src: u8) {
+                self.emit(ALU64 | op | X, dst, src, 0, 0);
+            }
+
+            /// `dst = *(size *)(src + off)`
+            fn load(&mut self, size: u8, dst: u8, src: u8, off: i16) {
+                self.emit(LDX | MEM | size, dst, src, off, 0);
+            }
+
+            /// `*(size *)(dst + off) = src`
+            fn store(&mut self, size: u8, dst: u8, off: i16, src: u8) {
+                self.emit(STX | MEM | size, dst, src, off, 0);
+            }
+
+            fn store_imm(&mut self, size: u8, dst: u8, off: i16, imm: i32) {
+                self.emit(ST | MEM | size, dst, 0, off, imm);
+            }
+
+            /// `lock *(u64 *)(dst + off) += src`
+            fn atomic_add(&mut self, dst: u8, off: i16, src: u8) {
+                self.emit(STX | ATOMIC | DW, dst, src, off, ADD as i32);
+            }
+
+            fn load_u64(&mut self, dst: u8, value: u64) {
+                self.emit(LD | IMM | DW, dst, 0, 0, value as i32);
+                self.emit(0, 0, 0, 0, (value >> 32) as i32);
+            }
+
+            fn load_map(&mut self, dst: u8, map: &Map) {
+                self.emit(LD | IMM | DW, dst, BPF_PSEUDO_MAP_FD, 0, map.fd.as_raw_fd());
+                self.emit(0, 0, 0, 0, 0);
+            }
+
+            /// `dst = r10 + off`, a pointer into the stack.
+            fn stack(&mut self, dst: u8, off: i32) {
+                self.alu_reg(MOV, dst, R10);
+                self.alu(ADD, dst, off);
+            }
+
+            fn jump(&mut self, op: u8, dst: u8, imm: i32, target: &Label) {
+                self.jumps.push((self.insns.len(), target.0));
+                self.emit(JMP | op | K, dst, 0, 0, imm);
+            }
+
+            fn call(&mut self, helper: i32) {
+                self.emit(JMP | CALL, 0, 0, 0, helper);
+            }
+
+            /// `return 0`
+            fn exit(&mut self) {
+                self.alu(MOV, R0, 0);
+                self.emit(JMP | EXIT, 0, 0, 0, 0);
+            }
+
+            /// Finds the value for the key at `r10 + key` in `map`, inserting
+            /// `value_size` zero bytes at `r10 + zero` first if it is missing.
+            /// Leaves the value pointer in R0, or jumps to `fail`.
+            fn lookup_or_zero(&mut self, map: &Map, key: i32, zero: i32, fail: &Label) {
+                let found = self.label();
+                self.load_map(R1, map);
+                self.stack(R2, key);
+                self.call(MAP_LOOKUP_ELEM);
+                self.jump(JNE, R0, 0, &found);
+                for off in (0..map.value_size as i32).step_by(8) {
+                    self.store_imm(DW, R10, (zero + off) as i16, 0);
+                }
+                self.load_map(R1, map);
+                self.stack(R2, key);
+                self.stack(R3, zero);
+                self.alu(MOV, R4, BPF_NOEXIST);
+                self.call(MAP_UPDATE_ELEM);
+                // Another CPU may have inserted it first; either way it is there now
+                self.load_map(R1, map);
+                self.stack(R2, key);
+                self.call(MAP_LOOKUP_ELEM);
+                self.jump(JEQ, R0, 0, fail);
+                self.bind(&found);
+            }
+
+            fn finish(mut self) -> Vec<Insn> {
+                for (at, label) in self.jumps {
+                    let target = self.labels[label].expect("jump to a bound label");
+                    self.insns[at].off = (target as isize - at as isize - 1) as i16;
+                }
+                self.insns
+            }
+        }
+
+        /// Loads a tracepoint program; when the verifier rejects it, its
+        /// last complaint is in the error.
+        fn load(name: &str, insns: &[Insn]) -> Result<File, String> {
+            const LICENSE: &[u8] = b"GPL\0";
+            let mut prog_name = [0u8; 16];
+            let len = name.len().min(15);
+            prog_name[..len].copy_from_slice(&name.as_bytes()[..len]);
+            let mut attr = ProgLoad {
+                prog_type: BPF_PROG_TYPE_TRACEPOINT,
+                insn_cnt: insns.len() as u32,
+                insns: insns.as_ptr() as u64,
+                license: LICENSE.as_ptr() as u64,
+                log_level: 0,
+                log_size: 0,
+                log_buf: 0,
+                kern_version: 0,
+                prog_flags: 0,
+                prog_name,
+            };
+            // SAFETY: the instructions and license outlive the call.
+            let error = match unsafe { bpf_fd(BPF_PROG_LOAD, &attr) } {
+                Ok(fd) => return Ok(fd),
+                Err(e) => e,
+            };
+            // Load again with the verifier log for the reason
+            let mut log = vec![0u8; 64 * 1024];
+            attr.log_level = 1;
+            attr.log_size = log.len() as u32;
+            attr.log_buf = log.as_mut_ptr() as u64;
+            // SAFETY: as above, and the log buffer is `log_size` bytes.
+            let _ = unsafe { bpf_fd(BPF_PROG_LOAD, &attr) };
+            let log = String::from_utf8_lossy(&log[..log.iter().position(|b| *b == 0).unwrap_or(log.len())]).to_string();
+            match log.lines().rev().find(|l| !l.trim().is_empty() && !l.starts_with("processed")) {
+                Some(reason) => Err(format!("{}: rejected: {}", name, reason.trim())),
+                None => Err(format!("{}: {}", name, describe(&error))),
+            }
+        }
+
+        /// `struct perf_event_attr` up to `config1` (`PERF_ATTR_SIZE_VER0`).
+        #[repr(C)]
+        #[derive(Default)]
+        struct EventAttr {
+            kind: u32,
+            size: u32,
+            config: u64,
+            sample_period: u64,
+            sample_type: u64,
+            read_format: u64,
+            flags: u64,
+            wakeup_events: u32,
+            bp_type: u32,
+            config1: u64,
+        }
+
+        const PERF_TYPE_TRACEPOINT: u32 = 2;
+        const PERF_FLAG_FD_CLOEXEC: libc::c_ulong = 8;
+        /// `_IOW('$', 8, u32)` and `_IO('$', 0)`.
+        const PERF_EVENT_IOC_SET_BPF: libc::c_ulong = 0x4004_2408;
+        const PERF_EVENT_IOC_ENABLE: libc::c_ulong = 0x2400;
+
+        fn tracefs() -> Option<PathBuf> {
+            ["/sys/kernel/tracing", "/sys/kernel/debug/tracing"].into_iter().map(PathBuf::from).find(|p| p.join("events").is_dir())
+        }
+
+        /// Field names and `(offset, size)` from a tracepoint's `format` file.
+        /// Arrays go by their bare name: `saddr[28]` is `saddr`, 28 bytes.
+        fn parse_format(format: &str) -> HashMap<String, (i16, usize)> {
+            // `field:unsigned int nr_sector;	offset:24;	size:4;	signed:0;`
+            format
+                .lines()
+                .filter_map(|line| {
+                    let mut parts = line.split(';').map(str::trim);
+                    let decl = parts.next()?.strip_prefix("field:")?;
+                    let name = decl.rsplit(' ').next()?.split('[').next()?.to_string();
+                    let offset = parts.next()?.strip_prefix("offset:")?.parse().ok()?;
+                    let size = parts.next()?.strip_prefix("size:")?.parse().ok()?;
+                    Some((name, (offset, size)))
+                })
+                .collect()
+        }
+
+        /// A tracepoint's id and the `(offset, size)` of each field of its
+        /// record, from tracefs.
+        struct Tracepoint {
+            event: &'static str,
+            id: u64,
+            fields: HashMap<String, (i16, usize)>,
+        }
+
+        impl Tracepoint {
+            /// `event` is `category/name`, as under `events/` in tracefs.
+            fn read(event: &'static str) -> Result<Self, String> {
+                let dir = tracefs()
+                    .ok_or("tracefs is not mounted (mount -t tracefs none /sys/kernel/tracing)")?
+                    .join("events")
+                    .join(event);
+                let read = |file: &str| {
+                    std::fs::read_to_string(dir.join(file)).map_err(|e| match e.kind() {
+                        io::ErrorKind::NotFound => format!("no {} tracepoint in this kernel", event),
+                        _ => format!("{}: {}", event, describe(&e)),
+                    })
+                };
+                let id = read("id")?.trim().parse().map_err(|_| format!("{}: unreadable id", event))?;
+                Ok(Self { event, id, fields: parse_format(&read("format")?) })
+            }
+
+            /// A field's offset and load size.
+            fn field(&self, name: &str) -> Result<(i16, u8), String> {
+                match self.fields.get(name) {
+                    Some(&(offset, 1)) => Ok((offset, B)),
+                    Some(&(offset, 2)) => Ok((offset, H)),
+                    Some(&(offset, 4)) => Ok((offset, W)),
+                    Some(&(offset, 8)) => Ok((offset, DW)),
+                    _ => Err(format!("{}: no `{}` field", self.event, name)),
+                }
+            }
+
+            /// Runs `prog` on every hit, on all CPUs, until the returned
+            /// event is closed.
+            fn attach(&self, prog: &File) -> Result<File, String> {
+                let attr = EventAttr {
+                    kind: PERF_TYPE_TRACEPOINT,
+                    size: std::mem::size_of::<EventAttr>() as u32,
+                    config: self.id,
+                    sample_period: 1,
+                    wakeup_events: 1,
+                    ..Default::default()
+                };
+                let failed = |e: io::Error| format!("{}: {}", self.event, describe(&e));
+                // SAFETY: `attr` outlives the call. The program belongs to the
+                // tracepoint, so one event on CPU 0 runs it wherever it fires.
+                let fd = unsafe {
+                    libc::syscall(
+                        libc::SYS_perf_event_open,
+                        &attr as *const EventAttr,
+                        -1 as libc::pid_t,
+                        0 as libc::c_int,
+                        -1 as libc::c_int,
+                        PERF_FLAG_FD_CLOEXEC,
+                    )
+                };
+                if fd < 0 {
+                    return Err(failed(io::Error::last_os_error()));
+                }
+                // SAFETY: the syscall returned a new descriptor nobody else owns.
+                let event = unsafe { File::from_raw_fd(fd as i32) };
+                // SAFETY: both descriptors are open; SET_BPF takes the program
+                // descriptor and ENABLE nothing.
+                let attached = unsafe {
+                    libc::ioctl(event.as_raw_fd(), PERF_EVENT_IOC_SET_BPF as _, prog.as_raw_fd()) == 0
+                        && libc::ioctl(event.as_raw_fd(), PERF_EVENT_IOC_ENABLE as _, 0) == 0
+                };
+                if !attached {
+                    return Err(failed(io::Error::last_os_error()));
+                }
+                Ok(event)
+            }
+        }
+
+        /// Processes tracked at once; more are left out until some exit.
+        const MAX_PROCESSES: u32 = 16384;
+        const AF_INET: i32 = 2;
+        const AF_INET6: i32 = 10;
+        const MSG_PEEK: i32 = 2;
+
+        /// Adds the byte count of each successful send or receive on an
+        /// IPv4/IPv6 socket to `bytes[tgid]`: received at offset 0, sent at 8.
+        fn socket_program(tp: &Tracepoint, bytes: &Map, send: bool, (dev, ino): (u64, u64)) -> Result<Vec<Insn>, String> {
+            let (ret, ret_size) = tp.field("ret")?;
+            let (family, family_size) = tp.field("family")?;
+            let mut a = Asm::default();
+            let out = a.label();
+            let inet = a.label();
+            a.alu_reg(MOV, R6, R1);
+            // Sign-extend the return value; errors and EOF count nothing
+            a.load(ret_size, R7, R6, ret);
+            a.alu(LSH, R7, 32);
+            a.alu(ARSH, R7, 32);
+            a.jump(JSLE, R7, 0, &out);
+            if !send {
+                // A peek leaves the data to be read again
+                let (flags, flags_size) = tp.field("flags")?;
+                a.load(flags_size, R2, R6, flags);
+                a.jump(JSET, R2, MSG_PEEK, &out);
+            }
+            a.load(family_size, R2, R6, family);
+            a.jump(JEQ, R2, AF_INET, &inet);
+            a.jump(JNE, R2, AF_INET6, &out);
+            a.bind(&inet);
+            // PIDs as seen in sysmon-tui's own PID namespace; tasks outside it fail
+            a.load_u64(R1, dev);
+            a.load_u64(R2, ino);
+            a.stack(R3, -8);
+            a.alu(MOV, R4, 8);
+            a.call(GET_NS_CURRENT_PID_TGID);
+            a.jump(JNE, R0, 0, &out);
+            // `struct bpf_pidns_info { u32 pid; u32 tgid; }`
+            a.load(W, R2, R10, -4);
+            a.store(W, R10, -16, R2);
+            a.lookup_or_zero(bytes, -16, -32, &out);
+            a.atomic_add(R0, if send { 8 } else { 0 }, R7);
+            a.bind(&out);
+            a.exit();
+            Ok(a.finish())
+        }
+
+        /// Bytes each process sends and receives over IPv4/IPv6 sockets,
+        /// counted by eBPF programs on the `sock_send_length` and
+        /// `sock_recv_length` tracepoints (Linux 6.3+).
+        pub struct SocketBytes {
+            /// Cumulative `[received, sent]` per TGID.
+            bytes: Map,
+            /// Programs and the events they are attached through; closing
+            /// them detaches.
+            _attached: Vec<File>,
+            last: HashMap<i32, (u64, u64)>,
+            previous: Option<Instant>,
+        }
+
+        impl SocketBytes {
+            pub fn open() -> Result<Self, String> {
+                raise_memlock();
+                let ns = std::fs::metadata("/proc/self/ns/pid").map_err(|e| format!("PID namespace: {}", e))?;
+                let bytes = Map::create(BPF_MAP_TYPE_HASH, 4, 16, MAX_PROCESSES)?;
+                let mut attached = Vec::new();
+                for (event, send) in [("sock/sock_send_length", true), ("sock/sock_recv_length", false)] {
+                    let tp = Tracepoint::read(event)?;
+                    let prog = load(if send { "sysmon_send" } else { "sysmon_recv" }, &socket_program(&tp, &bytes, send, (ns.dev(), ns.ino()))?)?;
+                    attached.push(tp.attach(&prog)?);
+                    attached.push(prog);
+                }
+                Ok(Self { bytes, _attached: attached, last: HashMap::new(), previous: None })
+            }
+
+            /// Bytes per second received and sent by each process since the
+            /// previous call; the first call only primes. Counts for PIDs not
+            /// in `pids` are dropped, so exited processes free their entries.
+            pub fn sample(&mut self, pids: &HashSet<i32>) -> HashMap<i32, (u64, u64)> {
+                let now = Instant::now();
+                let secs = self.previous.replace(now).map(|t| now.duration_since(t).as_secs_f64().max(0.001));
+                let mut rates = HashMap::new();
+                let mut next = HashMap::new();
+                for (key, value) in self.bytes.entries() {
+                    let pid = u32::from_ne_bytes(key[..4].try_into().unwrap_or_default()) as i32;
+                    if !pids.contains(&pid) {
+                        self.bytes.delete(&key);
+                        continue;
+                    }
+                    let field = |i: usize| u64::from_ne_bytes(value[i * 8..i * 8 + 8].try_into().unwrap_or_default());
+                    let (rx, tx) = (field(0), field(1));
+                    if let Some(secs) = secs {
+                        // A process first seen now did all its traffic since the last call
+                        let (rx0, tx0) = self.last.get(&pid).copied().unwrap_or((0, 0));
+                        let rate = |now: u64, before: u64| (now.saturating_sub(before) as f64 / secs) as u64;
+                        rates.insert(pid, (rate(rx, rx0), rate(tx, tx0)));
+                    }
+                    next.insert(pid, (rx, tx));
+                }
+                self.last = next;
+                rates
+            }
+        }
+
+        /// Histogram buckets per device: under 1µs, then one per power of two
+        /// up to 2^26..2^27µs (~134s), which also takes anything slower.
+        pub const SLOTS: usize = 28;
+        /// Requests in flight tracked at once; the least recently issued are
+        /// dropped beyond it.
+        const MAX_IN_FLIGHT: u32 = 10240;
+        /// How far back the histograms reach.
+        const LATENCY_WINDOW: Duration = Duration::from_secs(10);
+
+        /// Stores the issue time of each request under `(dev, sector)`.
+        fn issue_program(tp: &Tracepoint, start: &Map) -> Result<Vec<Insn>, String> {
+            let (dev, dev_size) = tp.field("dev")?;
+            let (sector, sector_size) = tp.field("sector")?;
+            let (nr_sector, nr_sector_size) = tp.field("nr_sector")?;
+            let mut a = Asm::default();
+            let out = a.label();
+            a.alu_reg(MOV, R6, R1);
+            // Flushes carry no data and may share a sector with a write
+            a.load(nr_sector_size, R2, R6, nr_sector);
+            a.jump(JEQ, R2, 0, &out);
+            // `struct { u32 dev; u32 pad; u64 sector; }` at r10 - 16
+            a.load(dev_size, R2, R6, dev);
+            a.store(W, R10, -16, R2);
+            a.store_imm(W, R10, -12, 0);
+            a.load(sector_size, R2, R6, sector);
+            a.store(DW, R10, -8, R2);
+            a.call(KTIME_GET_NS);
+            a.store(DW, R10, -24, R0);
+            a.load_map(R1, start);
+            a.stack(R2, -16);
+            a.stack(R3, -24);
+            a.alu(MOV, R4, BPF_ANY);
+            a.call(MAP_UPDATE_ELEM);
+            a.bind(&out);
+            a.exit();
+            Ok(a.finish())
+        }
+
+        /// Takes a request's issue time back out of `start` and counts its
+        /// latency in `hist[(dev, slot)]`.
+        fn complete_program(tp: &Tracepoint, start: &Map, hist: &Map) -> Result<Vec<Insn>, String> {
+            let (dev, dev_size) = tp.field("dev")?;
+            let (sector, sector_size) = tp.field("sector")?;
+            let mut a = Asm::default();
+            let out = a.label();
+            a.alu_reg(MOV, R6, R1);
+            a.load(dev_size, R2, R6, dev);
+            a.store(W, R10, -16, R2);
+            a.store_imm(W, R10, -12, 0);
+            a.load(sector_size, R2, R6, sector);
+            a.store(DW, R10, -8, R2);
+            a.load_map(R1, start);
+            a.stack(R2, -16);
+            a.call(MAP_LOOKUP_ELEM);
+            // Issued before the programs were attached
+            a.jump(JEQ, R0, 0, &out);
+            a.load(DW, R7, R0, 0);
+            a.load_map(R1, start);
+            a.stack(R2, -16);
+            a.call(MAP_DELETE_ELEM);
+            a.call(KTIME_GET_NS);
+            a.alu_reg(SUB, R0, R7);
+            a.alu(DIV, R0, 1000);
+            // Slot 0 for under 1µs, else 1 + floor(log2(µs))
+            let slotted = a.label();
+            a.alu(MOV, R1, 0);
+            a.jump(JEQ, R0, 0, &slotted);
+            a.alu(MOV, R1, 1);
+            for shift in [32, 16, 8, 4, 2, 1] {
+                let smaller = a.label();
+                a.alu_reg(MOV, R2, R0);
+                a.alu(RSH, R2, shift);
+                a.jump(JEQ, R2, 0, &smaller);
+                a.alu_reg(MOV, R0, R2);
+                a.alu(ADD, R1, shift);
+                a.bind(&smaller);
+            }
+            a.jump(JLE, R1, SLOTS as i32 - 1, &slotted);
+            a.alu(MOV, R1, SLOTS as i32 - 1);
+            a.bind(&slotted);
+            // `struct { u32 dev; u32 slot; }` at r10 - 32
+            a.store(W, R10, -28, R1);
+            a.load(dev_size, R2, R6, dev);
+            a.store(W, R10, -32, R2);
+            a.lookup_or_zero(hist, -32, -40, &out);
+            a.alu(MOV, R1, 1);
+            a.atomic_add(R0, 0, R1);
+            a.bind(&out);
+            a.exit();
+            Ok(a.finish())
+        }
+
+        /// Whole disks by the kernel's internal `dev_t` (major << 20 | minor),
+        /// which the block tracepoints record.
+        fn block_devices() -> HashMap<u32, String> {
+            let Ok(entries) = std::fs::read_dir("/sys/block") else {
+                return HashMap::new();
+            };
+            entries
+                .flatten()
+                .filter_map(|entry| {
+                    let dev = std::fs::read_to_string(entry.path().join("dev")).ok()?;
+                    let (major, minor) = dev.trim().split_once(':')?;
+                    let dev = major.parse::<u32>().ok()? << 20 | minor.parse::<u32>().ok()?;
+                    Some((dev, entry.file_name().to_string_lossy().to_string()))
+                })
+                .collect()
+        }
+
+        /// One device's completed requests over the last window.
+        pub struct DeviceLatency {
+            pub name: String,
+            /// Request counts per `SLOTS` bucket.
+            pub buckets: [u64; SLOTS],
+            pub per_sec: f64,
+        }
+
+        impl DeviceLatency {
+            pub fn count(&self) -> u64 {
+                self.buckets.iter().sum()
+            }
+
+            /// Upper bound in µs of the bucket holding the `p`th percentile.
+            pub fn percentile(&self, p: f64) -> Option<u64> {
+                let target = (self.count() as f64 * p / 100.0).ceil().max(1.0) as u64;
+                let mut seen = 0;
+                self.buckets.iter().position(|&n| {
+                    seen += n;
+                    seen >= target
+                })
+                .map(bucket_limit)
+            }
+        }
+
+        /// Exclusive upper bound of a bucket in µs.
+        pub fn bucket_limit(slot: usize) -> u64 {
+            1 << slot
+        }
+
+        /// Per-device block I/O latency histograms from eBPF programs on the
+        /// `block_rq_issue` and `block_rq_complete` tracepoints.
+        pub struct BioLatency {
+            hist: Map,
+            _attached: Vec<File>,
+            /// Cumulative counts per device, oldest first, reaching back one
+            /// window.
+            history: VecDeque<(Instant, HashMap<u32, [u64; SLOTS]>)>,
+            names: HashMap<u32, String>,
+        }
+
+        impl BioLatency {
+            pub fn open() -> Result<Self, String> {
+                raise_memlock();
+                let start = Map::create(BPF_MAP_TYPE_LRU_HASH, 16, 8, MAX_IN_FLIGHT)?;
+                let hist = Map::create(BPF_MAP_TYPE_HASH, 8, 8, 64 * SLOTS as u32)?;
+                let issue = Tracepoint::read("block/block_rq_issue")?;
+                let complete = Tracepoint::read("block/block_rq_complete")?;
+                let issue_prog = load("sysmon_bio_issue", &issue_program(&issue, &start)?)?;
+                let complete_prog = load("sysmon_bio_done", &complete_program(&complete, &start, &hist)?)?;
+                // The programs hold their own references to the start map
+                let attached = vec![issue.attach(&issue_prog)?, complete.attach(&complete_prog)?, issue_prog, complete_prog];
+                Ok(Self { hist, _attached: attached, history: VecDeque::new(), names: block_devices() })
+            }
+
+            /// Whether `sample` has a window to compare against yet.
+            pub fn primed(&self) -> bool {
+                self.history.len() > 1
+            }
+
+            /// Devices that completed requests within the window, busiest
+            /// first. Empty until a second sample.
+            pub fn sample(&mut self) -> Vec<DeviceLatency> {
+                let now = Instant::now();
+                let mut counts: HashMap<u32, [u64; SLOTS]> = HashMap::new();
+                for (key, value) in self.hist.entries() {
+                    let field = |i: usize| u32::from_ne_bytes(key[i * 4..i * 4 + 4].try_into().unwrap_or_default());
+                    let (dev, slot) = (field(0), field(1) as usize);
+                    if slot < SLOTS {
+                        counts.entry(dev).or_insert([0; SLOTS])[slot] = u64::from_ne_bytes(value[..8].try_into().unwrap_or_default());
+                    }
+                }
+                if counts.keys().any(|dev| !self.names.contains_key(dev)) {
+                    // Hot-plugged since the last look
+                    self.names = block_devices();
+                }
+                self.history.push_back((now, counts));
+                while self.history.len() > 2 && now.duration_since(self.history[1].0) >= LATENCY_WINDOW {
+                    self.history.pop_front();
+                }
+                let (Some((then, before)), Some((_, counts))) = (self.history.front(), self.history.back()) else {
+                    return Vec::new();
+                };
+                let secs = now.duration_since(*then).as_secs_f64();
+                if secs <= 0.0 {
+                    return Vec::new();
+                }
+                let mut devices: Vec<DeviceLatency> = counts
+                    .iter()
+                    .map(|(dev, buckets)| {
+                        let earlier = before.get(dev).copied().unwrap_or([0; SLOTS]);
+                        let buckets: [u64; SLOTS] = std::array::from_fn(|i| buckets[i].saturating_sub(earlier[i]));
+                        let name = self.names.get(dev).cloned().unwrap_or_else(|| format!("{}:{}", dev >> 20, dev & 0xfffff));
+                        let per_sec = buckets.iter().sum::<u64>() as f64 / secs;
+                        DeviceLatency { name, buckets, per_sec }
+                    })
+                    .filter(|d| d.count() > 0)
+                    .collect();
+                devices.sort_by(|a, b| b.count().cmp(&a.count()).then_with(|| a.name.cmp(&b.name)));
+                devices
+            }
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+
+            /// `events/sock/sock_send_length/format` from Linux 6.18.
+            const SEND_FORMAT: &str = concat!(
+                "name: sock_send_length\n",
+                "ID: 2184\n",
+                "format:\n",
+                "\tfield:unsigned short common_type;\toffset:0;\tsize:2;\tsigned:0;\n",
+                "\tfield:unsigned char common_flags;\toffset:2;\tsize:1;\tsigned:0;\n",
+                "\tfield:unsigned char common_preempt_count;\toffset:3;\tsize:1;\tsigned:0;\n",
+                "\tfield:int common_pid;\toffset:4;\tsize:4;\tsigned:1;\n",
+                "\n",
+                "\tfield:void * sk;\toffset:8;\tsize:8;\tsigned:0;\n",
+                "\tfield:__u16 family;\toffset:16;\tsize:2;\tsigned:0;\n",
+                "\tfield:__u16 protocol;\toffset:18;\tsize:2;\tsigned:0;\n",
+                "\tfield:int ret;\toffset:20;\tsize:4;\tsigned:1;\n",
+                "\tfield:int flags;\toffset:24;\tsize:4;\tsigned:1;\n",
+                "\n",
+                "print fmt: \"sk address = %p, family = %s protocol = %s, length = %d, error = %d, flags = 0x%x\", REC->sk\n",
+            );
+
+            /// Array fields from `events/tcp/tcp_probe/format`.
+            const PROBE_FIELDS: &str = concat!(
+                "\tfield:__u8 saddr[28];\toffset:8;\tsize:28;\tsigned:0;\n",
+                "\tfield:__u8 daddr[28];\toffset:36;\tsize:28;\tsigned:0;\n",
+                "\tfield:__u16 sport;\toffset:64;\tsize:2;\tsigned:0;\n",
+            );
+
+            fn tracepoint(format: &str) -> Tracepoint {
+                Tracepoint { event: "test/event", id: 0, fields: parse_format(format) }
+            }
+
+            /// A map over a stand-in descriptor, for programs that are only
+            /// assembled.
+            fn map(key_size: usize, value_size: usize) -> Map {
+                Map { fd: File::open("/dev/null").unwrap(), key_size, value_size, max_entries: 1 }
+            }
+
+            /// Every jump lands inside the program, on an instruction rather
+            /// than the second half of a 64-bit load.
+            fn assert_jumps_in_range(insns: &[Insn]) {
+                let wide: Vec<bool> = insns.iter().map(|i| i.code == LD | IMM | DW).collect();
+                for (at, insn) in insns.iter().enumerate() {
+                    let call_or_exit = insn.code == JMP | CALL || insn.code == JMP | EXIT;
+                    if insn.code & 0x07 != JMP || call_or_exit {
+                        continue;
+                    }
+                    let target = at as isize + 1 + insn.off as isize;
+                    assert!((0..insns.len() as isize).contains(&target), "jump at {} to {}", at, target);
+                    assert!(target == 0 || !wide[target as usize - 1], "jump at {} into a wide load", at);
+                }
+            }
+
+            #[test]
+            fn emit_packs_dst_in_the_low_nibble() {
+                let mut a = Asm::default();
+                a.alu_reg(MOV, R1, R2);
+                let insns = a.finish();
+                // `mov r1, r2` is bf 21 00 00 00 00 00 00
+                assert_eq!(insns[0].code, 0xbf);
+                let expected = if cfg!(target_endian = "little") { 0x21 } else { 0x12 };
+                assert_eq!(insns[0].regs, expected);
+                assert_eq!(std::mem::size_of::<Insn>(), 8);
+            }
+
+            #[test]
+            fn load_u64_takes_two_slots() {
+                let mut a = Asm::default();
+                a.load_u64(R3, 0x1122_3344_5566_7788);
+                let insns = a.finish();
+                assert_eq!(insns.len(), 2);
+                assert_eq!(insns[0].code, 0x18);
+                assert_eq!(insns[0].regs & 0x0f, R3);
+                assert_eq!(insns[0].imm, 0x5566_7788);
+                assert_eq!((insns[1].code, insns[1].regs, insns[1].off), (0, 0, 0));
+                assert_eq!(insns[1].imm, 0x1122_3344);
+            }
+
+            #[test]
+            fn load_map_marks_the_map_descriptor() {
+                let map = map(4, 8);
+                let mut a = Asm::default();
+                a.load_map(R1, &map);
+                let insns = a.finish();
+                assert_eq!(insns.len(), 2);
+                assert_eq!(insns[0].code, 0x18);
+                assert_eq!(insns[0].regs & 0x0f, R1);
+                assert_eq!(insns[0].regs >> 4, BPF_PSEUDO_MAP_FD);
+                assert_eq!(insns[0].imm, map.fd.as_raw_fd());
+                assert_eq!((insns[1].code, insns[1].regs, insns[1].off, insns[1].imm), (0, 0, 0, 0));
+            }
+
+            #[test]
+            fn finish_patches_forward_and_backward_jumps() {
+                let mut a = Asm::default();
+                let top = a.label();
+                let end = a.label();
+                a.bind(&top);
+                a.alu(ADD, R1, 1); // 0
+                a.jump(JEQ, R1, 5, &end); // 1
+                a.jump(JNE, R1, 0, &top); // 2
+                a.load_u64(R2, 1); // 3, 4
+                a.bind(&end);
+                a.exit(); // 5, 6
+                let insns = a.finish();
+                assert_eq!(insns[1].off, 3);
+                assert_eq!(insns[2].off, -3);
+                assert_jumps_in_range(&insns);
+            }
+
+            #[test]
+            #[should_panic(expected = "bound label")]
+            fn finish_rejects_unbound_labels() {
+                let mut a = Asm::default();
+                let nowhere = a.label();
+                a.jump(JEQ, R0, 0, &nowhere);
+                a.finish();
+            }
+
+            #[test]
+            fn parse_format_reads_offsets_and_sizes() {
+                let fields = parse_format(SEND_FORMAT);
+                assert_eq!(fields.len(), 9);
+                assert_eq!(fields["sk"], (8, 8));
+                assert_eq!(fields["family"], (16, 2));
+                assert_eq!(fields["ret"], (20, 4));
+                assert_eq!(fields["flags"], (24, 4));
+                assert_eq!(fields["common_pid"], (4, 4));
+            }
+
+            #[test]
+            fn parse_format_names_arrays_without_the_length() {
+                let fields = parse_format(PROBE_FIELDS);
+                assert_eq!(fields["saddr"], (8, 28));
+                assert_eq!(fields["daddr"], (36, 28));
+                assert_eq!(fields["sport"], (64, 2));
+            }
+
+            #[test]
+            fn field_picks_the_load_size() {
+                let tp = tracepoint(SEND_FORMAT);
+                assert_eq!(tp.field("common_flags"), Ok((2, B)));
+                assert_eq!(tp.field("family"), Ok((16, H)));
+                assert_eq!(tp.field("ret"), Ok((20, W)));
+                assert_eq!(tp.field("sk"), Ok((8, DW)));
+            }
+
+            #[test]
+            fn field_rejects_missing_and_unloadable_fields() {
+                assert!(tracepoint(SEND_FORMAT).field("nr_sector").is_err());
+                assert!(tracepoint(PROBE_FIELDS).field("saddr").is_err());
+            }
+
+            /// `events/block/block_rq_complete/format` from Linux 6.18, fields only.
+            const COMPLETE_FIELDS: &str = concat!(
+                "\tfield:dev_t dev;\toffset:8;\tsize:4;\tsigned:0;\n",
+                "\tfield:sector_t sector;\toffset:16;\tsize:8;\tsigned:0;\n",
+                "\tfield:unsigned int nr_sector;\toffset:24;\tsize:4;\tsigned:0;\n",
+                "\tfield:int error;\toffset:28;\tsize:4;\tsigned:1;\n",
+                "\tfield:unsigned short ioprio;\toffset:32;\tsize:2;\tsigned:0;\n",
+                "\tfield:char rwbs[10];\toffset:34;\tsize:10;\tsigned:0;\n",
+                "\tfield:__data_loc char[] cmd;\toffset:44;\tsize:4;\tsigned:0;\n",
+            );
+
+            fn latency(buckets: &[(usize, u64)]) -> DeviceLatency {
+                let mut latency = DeviceLatency { name: "sda".to_string(), buckets: [0; SLOTS], per_sec: 0.0 };
+                for &(slot, n) in buckets {
+                    latency.buckets[slot] = n;
+                }
+                latency
+            }
+
+            /// Runs the part of `complete_program` that turns the latency in
+            /// µs (R0, after the divide) into a slot (R1, stored at r10 - 28).
+            fn slot_of(insns: &[Insn], us: u64) -> usize {
+                let mut pc = insns.iter().position(|i| i.code == ALU64 | DIV | K).expect("divide") + 1;
+                let mut regs = [0u64; 11];
+                regs[R0 as usize] = us;
+                loop {
+                    let insn = insns[pc];
+                    let (dst, src) = match cfg!(target_endian = "little") {
+                        true => ((insn.regs & 0x0f) as usize, (insn.regs >> 4) as usize),
+                        false => ((insn.regs >> 4) as usize, (insn.regs & 0x0f) as usize),
+                    };
+                    let operand = if insn.code & X != 0 { regs[src] } else { insn.imm as i64 as u64 };
+                    pc += 1;
+                    match insn.code {
+                        c if c == STX | MEM | W && dst == R10 as usize && insn.off == -28 => return regs[src] as usize,
+                        c if c & 0x07 == ALU64 => match c & 0xf0 {
+                            MOV => regs[dst] = operand,
+                            ADD => regs[dst] = regs[dst].wrapping_add(operand),
+                            RSH => regs[dst] >>= operand,
+                            op => panic!("unexpected ALU op {:#x}", op),
+                        },
+                        c if c & 0x07 == JMP => {
+                            let taken = match c & 0xf0 {
+                                JEQ => regs[dst] == operand,
+                                JLE => regs[dst] <= operand,
+                                op => panic!("unexpected jump {:#x}", op),
+                            };
+                            if taken {
+                                pc = (pc as isize + insn.off as isize) as usize;
+                            }
+                        }
+                        c => panic!("unexpected instruction {:#x}", c),
+                    }
+                }
+            }
+
+            #[test]
+            fn bucket_limit_doubles_from_one_microsecond() {
+                assert_eq!(bucket_limit(0), 1);
+                assert_eq!(bucket_limit(1), 2);
+                assert_eq!(bucket_limit(10), 1024);
+                assert_eq!(bucket_limit(SLOTS - 1), 1 << 27);
+            }
+
+            #[test]
+            fn percentile_of_nothing_is_none() {
+                assert_eq!(latency(&[]).percentile(50.0), None);
+                assert_eq!(latency(&[]).percentile(99.0), None);
+            }
+
+            #[test]
+            fn percentile_in_the_end_slots() {
+                let fast = latency(&[(0, 40)]);
+                assert_eq!(fast.percentile(50.0), Some(1));
+                assert_eq!(fast.percentile(99.0), Some(1));
+                let slow = latency(&[(SLOTS - 1, 3)]);
+                assert_eq!(slow.percentile(50.0), Some(bucket_limit(SLOTS - 1)));
+                assert_eq!(slow.percentile(100.0), Some(bucket_limit(SLOTS - 1)));
+            }
+
+            #[test]
+            fn percentile_boundaries() {
+                // 100 requests: 50 under 8µs, 49 under 32µs, one under 1024µs
+                let d = latency(&[(3, 50), (5, 49), (10, 1)]);
+                assert_eq!(d.count(), 100);
+                assert_eq!(d.percentile(50.0), Some(8));
+                assert_eq!(d.percentile(50.5), Some(32));
+                assert_eq!(d.percentile(99.0), Some(32));
+                assert_eq!(d.percentile(99.5), Some(1024));
+                // A second slow request reaches p99
+                let d = latency(&[(3, 50), (5, 48), (10, 2)]);
+                assert_eq!(d.percentile(99.0), Some(1024));
+            }
+
+            #[test]
+            fn complete_program_slots_match_bucket_limit() {
+                let tp = tracepoint(COMPLETE_FIELDS);
+                let insns = complete_program(&tp, &map(16, 8), &map(8, 8)).unwrap();
+                assert_jumps_in_range(&insns);
+                let samples = [0, 1, 2, 3, 4, 7, 8, 999, 1023, 1024, 1025, (1 << 26) - 1, 1 << 26, (1 << 27) - 1, 1 << 27, 1 << 40, u64::MAX / 1000];
+                for us in samples {
+                    let slot = slot_of(&insns, us);
+                    assert!(slot < SLOTS, "{}µs in slot {}", us, slot);
+                    if slot < SLOTS - 1 {
+                        assert!(us < bucket_limit(slot), "{}µs in slot {}", us, slot);
+                    }
+                    if slot > 0 {
+                        assert!(us >= bucket_limit(slot - 1), "{}µs in slot {}", us, slot);
+                    }
+                }
+                assert_eq!(slot_of(&insns, 0), 0);
+                assert_eq!(slot_of(&insns, 1), 1);
+                assert_eq!(slot_of(&insns, 1024), 11);
+                // Everything from 2^26µs up shares the last slot
+                assert_eq!(slot_of(&insns, 1 << 26), SLOTS - 1);
+                assert_eq!(slot_of(&insns, 1 << 40), SLOTS - 1);
+                assert_eq!(slot_of(&insns, u64::MAX / 1000), SLOTS - 1);
+            }
+
+            #[test]
+            fn issue_program_assembles() {
+                let insns = issue_program(&tracepoint(COMPLETE_FIELDS), &map(16, 8)).unwrap();
+                assert_jumps_in_range(&insns);
+                assert_eq!(insns.last().unwrap().code, JMP | EXIT);
+            }
+
+            #[test]
+            fn socket_programs_assemble() {
+                let tp = tracepoint(SEND_FORMAT);
+                let bytes = map(4, 16);
+                for send in [true, false] {
+                    let insns = socket_program(&tp, &bytes, send, (1, 2)).unwrap();
+                    assert_jumps_in_range(&insns);
+                    assert_eq!(insns.last().unwrap().code, JMP | EXIT);
+                }
+            }
+        }
+}
+
+pub mod bandwidth {
+        use std::collections::BTreeMap;
+        use std::io::{self, Write};
+        use std::path::{Path, PathBuf};
+
+        use chrono::{Datelike, Local, NaiveDate};
+
+        use crate::config::{NetworkConfig, QuotaPeriod};
+        use crate::types::NetworkStats;
+
+        /// Bytes transferred per interface since startup and per calendar day.
+        /// Daily totals persist to `bandwidth.tsv` in the state dir
+        /// (`date<TAB>iface<TAB>rx<TAB>tx` per line).
+        pub struct BandwidthTracker {
+            pub since_start: BTreeMap<String, (u64, u64)>,
+            days: BTreeMap<(NaiveDate, String), (u64, u64)>,
+            path: Option<PathBuf>,
+            dirty: bool,
+        }
+
+        impl BandwidthTracker {
+            pub fn load() -> Self {
+                let path = crate::config::state_dir().map(|d| d.join("bandwidth.tsv"));
+                let mut days = BTreeMap::new();
+                if let Some(text) = path.as_ref().and_then(|p| std::fs::read_to_string(p).ok()) {
+                    for line in text.lines() {
+                        let f: Vec<&str> = line.split('\t').collect();
+                        if let [date, iface, rx, tx] = f[..] {
+                            if let (Ok(date), Ok(rx), Ok(tx)) = (
+                                NaiveDate::parse_from_str(date, "%Y-%m-%d"),
+                                rx.parse::<u64>(),
+                                tx.parse::<u64>(),
+                            ) {
+                                days.insert((date, iface.to_string()), (rx, tx));
+                            }
+                        }
+                    }
+                }
+                Self { since_start: BTreeMap::new(), days, path, dirty: false }
+            }
+
+            /// Adds one tick's per-interface deltas.
+            pub fn record(&mut self, net: &NetworkStats) {
+                let today = Local::now().date_naive();
+                for iface in &net.interfaces {
+                    if iface.received_bytes == 0 && iface.transmitted_bytes == 0 {
+                        continue;
+                    }
+                    let total = self.since_start.entry(iface.name.clone()).or_default();
+                    total.0 += iface.received_bytes;
+                    total.1 += iface.transmitted_bytes;
+                    let day = self.days.entry((today, iface.name.clone())).or_default();
+                    day.0 += iface.received_bytes;
+                    day.1 += iface.transmitted_bytes;
+                    self.dirty = true;
+                }
+            }
+
+            /// RX + TX over all non-loopback interfaces in the current quota period.
+            pub fn period_total(&self, period: QuotaPeriod) -> u64 {
+                let today = Local::now().date_naive();
+                self.days
+                    .iter()
+                    .filter(|((_, iface), _)| iface != "lo")
+                    .filter(|((date, _), _)| match period {
+                        QuotaPeriod::Day => *date == today,
+                        QuotaPeriod::Month => date.year() == today.year() && date.month() == today.month(),
+                    })
+                    .map(|(_, (rx, tx))| rx + tx)
+                    .sum()
+            }
+
+            /// `(used, quota)` in bytes when a quota is configured.
+            pub fn quota_usage(&self, cfg: &NetworkConfig) -> Option<(u64, u64)> {
+                let quota = (cfg.quota_gib? * 1024.0 * 1024.0 * 1024.0) as u64;
+                Some((self.period_total(cfg.quota_period), quota))
+            }
+
+            /// Writes a temporary file and renames it over `bandwidth.tsv`, so
+            /// an interrupted save leaves the previous totals intact. Stays
+            /// dirty until a save succeeds.
+            pub fn save(&mut self) {
+                if !self.dirty {
+                    return;
+                }
+                let Some(ref path) = self.path else {
+                    return;
+                };
+                if let Some(dir) = path.parent() {
+                    let _ = std::fs::create_dir_all(dir);
+                }
+                let tmp = path.with_extension("tsv.tmp");
+                if self.write(&tmp).and_then(|()| std::fs::rename(&tmp, path)).is_ok() {
+                    self.dirty = false;
+                }
+            }
+
+            fn write(&self, path: &Path) -> io::Result<()> {
+                let mut w = io::BufWriter::new(std::fs::File::create(path)?);
+                for ((date, iface), (rx, tx)) in &self.days {
+                    writeln!(w, "{}\t{}\t{}\t{}", date.format("%Y-%m-%d"), iface, rx, tx)?;
+                }
+                w.into_inner().map_err(io::IntoInnerError::into_error)?.sync_all()
+            }
+        }
+}
+
+pub mod state {
+        use std::io::{self, Write};
+        use std::path::{Path, PathBuf};
+
+        use crate::config::{CpuView, LayoutPreset};
+        use crate::types::{SortOrder, Tab};
+
+        /// UI choices kept between runs in `ui-state.tsv` in the state dir
+        /// (`key<TAB>value` per line). `None` leaves the startup default.
+        #[derive(Default)]
+        pub struct UiState {
+            pub tab: Option<Tab>,
+            pub sort: Option<SortOrder>,
+            /// Ticks per process scan.
+            pub process_every: Option<u32>,
+            pub filter: Option<String>,
+            pub tree: bool,
+            pub net_iface: Option<String>,
+            pub disk_device: Option<String>,
+            pub raw_addresses: bool,
+            pub profile: Option<String>,
+            pub layout: Option<LayoutPreset>,
+            pub cpu_view: Option<CpuView>,
+        }
+
+        fn path() -> Option<PathBuf> {
+            crate::config::state_dir().map(|d| d.join("ui-state.tsv"))
+        }
+
+        fn sort_name(order: SortOrder) -> &'static str {
+            match order {
+                SortOrder::Cpu => "cpu",
+                SortOrder::Mem => "mem",
+                SortOrder::Wakeups => "wakeups",
+            }
+        }
+
+        impl UiState {
+            /// The saved state, or defaults when there is none. Unknown keys
+            /// and values are skipped.
+            pub fn load() -> Self {
+                let mut state = Self::default();
+                let Some(text) = path().and_then(|p| std::fs::read_to_string(p).ok()) else {
+                    return state;
+                };
+                for (key, value) in text.lines().filter_map(|l| l.split_once('\t')) {
+                    match key {
+                        "tab" => state.tab = Tab::from_label(value),
+                        "sort" => {
+                            state.sort = [SortOrder::Cpu, SortOrder::Mem, SortOrder::Wakeups]
+                                .into_iter()
+                                .find(|o| sort_name(*o) == value)
+                        }
+                        "process_every" => state.process_every = value.parse().ok(),
+                        "filter" => state.filter = Some(value.to_string()),
+                        "tree" => state.tree = value == "true",
+                        "net_iface" => state.net_iface = Some(value.to_string()),
+                        "disk_device" => state.disk_device = Some(value.to_string()),
+                        "raw_addresses" => state.raw_addresses = value == "true",
+                        "profile" => state.profile = Some(value.to_string()),
+                        "layout" => state.layout = LayoutPreset::from_label(value),
+                        "cpu_view" => state.cpu_view = CpuView::from_label(value),
+                        _ => {}
+                    }
+                }
+                state
+            }
+
+            /// Writes a temporary file and renames it over the old state, so a
+            /// crash or full disk mid-write leaves the previous state intact.
+            pub fn save(&self) -> io::Result<()> {
+                let Some(path) = path() else {
+                    return Ok(());
+                };
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                let tmp = path.with_extension("tsv.tmp");
+                self.write(&tmp)?;
+                std::fs::rename(&tmp, &path)
+            }
+
+            fn write(&self, path: &Path) -> io::Result<()> {
+                let mut w = io::BufWriter::new(std::fs::File::create(path)?);
+                if let Some(tab) = self.tab {
+                    writeln!(w, "tab\t{}", tab.label())?;
+                }
+                if let Some(sort) = self.sort {
+                    writeln!(w, "sort\t{}", sort_name(sort))?;
+                }
+                if let Some(every) = self.process_every {
+                    writeln!(w, "process_every\t{}", every)?;
+                }
+                // Filters and names are single-line, so tabs are the only risk
+                if let Some(ref filter) = self.filter {
+                    writeln!(w, "filter\t{}", filter.replace('\t', " "))?;
+                }
+                writeln!(w, "tree\t{}", self.tree)?;
+                if let Some(ref iface) = self.net_iface {
+                    writeln!(w, "net_iface\t{}", iface)?;
+                }
+                if let Some(ref device) = self.disk_device {
+                    writeln!(w, "disk_device\t{}", device)?;
+                }
+                writeln!(w, "raw_addresses\t{}", self.raw_addresses)?;
+                if let Some(ref profile) = self.profile {
+                    writeln!(w, "profile\t{}", profile)?;
+                }
+                if let Some(layout) = self.layout {
+                    writeln!(w, "layout\t{}", layout.label())?;
+                }
+                if let Some(view) = self.cpu_view {
+                    writeln!(w, "cpu_view\t{}", view.label())?;
+                }
+                w.into_inner().map_err(io::IntoInnerError::into_error)?.sync_all()
+            }
+        }
+}
+
+pub mod journal {
+        use std::collections::VecDeque;
+        use std::io::{BufRead, BufReader, Read};
+        use std::process::{Child, Command, Stdio};
+        use std::sync::{Arc, Mutex};
+
+        use crate::config::JournalConfig;
+
+        #[derive(Clone, Default)]
+        pub struct JournalOutput {
+            /// `short-iso` lines, oldest first.
+            pub lines: VecDeque<String>,
+            /// Why `journalctl` stopped, if it did.
+            pub error: Option<String>,
+        }
+
+        /// Follows `journalctl -f` in a child process, read on a background
+        /// thread. The child is killed when this is dropped.
+        pub struct JournalTail {
+            pub title: String,
+            child: Option<Child>,
+            output: Arc<Mutex<JournalOutput>>,
+        }
+
+        impl JournalTail {
+            pub fn spawn(cfg: &JournalConfig) -> Self {
+                let priority = cfg.priority.min(7);
+                let title = match cfg.units.len() {
+                    0 => format!("Journal (priority ≤ {})", priority),
+                    _ => format!("Journal {} (priority ≤ {})", cfg.units.join(", "), priority),
+                };
+                let output = Arc::new(Mutex::new(JournalOutput::default()));
+                let mut command = Command::new("journalctl");
+                command
+                    .args(["--follow", "--no-pager", "--output=short-iso", "--lines", &cfg.lines.to_string()])
+                    .arg(format!("--priority={}", priority));
+                for unit in &cfg.units {
+                    command.arg("--unit").arg(unit);
+                }
+                let child = command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn();
+                let mut child = match child {
+                    Ok(child) => child,
+                    Err(e) => {
+                        if let Ok(mut out) = output.lock() {
+                            out.error = Some(format!("journalctl: {}", e));
+                        }
+                        return Self { title, child: None, output };
+                    }
+                };
+                let (stdout, mut stderr) = (child.stdout.take(), child.stderr.take());
+                let shared = Arc::clone(&output);
+                let keep = cfg.lines.max(1);
+                std::thread::spawn(move || {
+                    for line in stdout.map(BufReader::new).into_iter().flat_map(|r| r.lines()) {
+                        let Ok(line) = line else { break };
+                        // Separator journalctl prints between boots
+                        if line.starts_with("-- ") {
+                            continue;
+                        }
+                        if let Ok(mut out) = shared.lock() {
+                            if out.lines.len() >= keep {
+                                out.lines.pop_front();
+                            }
+                            out.lines.push_back(line);
+                        }
+                    }
+                    let mut message = String::new();
+                    if let Some(ref mut stderr) = stderr {
+                        let _ = stderr.read_to_string(&mut message);
+                    }
+                    let message = message.lines().next().unwrap_or("exited").trim().to_string();
+                    if let Ok(mut out) = shared.lock() {
+                        out.error = Some(format!("journalctl: {}", message));
+                    }
+                });
+                Self { title, child: Some(child), output }
+            }
+
+            pub fn output(&self) -> JournalOutput {
+                self.output.lock().map(|o| o.clone()).unwrap_or_default()
+            }
+        }
+
+        impl Drop for JournalTail {
+            fn drop(&mut self) {
+                if let Some(ref mut child) = self.child {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                }
+            }
+        }
+}
+
+pub mod panels {
+        use std::process::Command;
+        use std::sync::{Arc, Mutex};
+        use std::time::Duration;
+
+        use crate::config::PanelConfig;
+
+        /// Latest output of a panel command.
+        #[derive(Clone, Default)]
+        pub struct PanelOutput {
+            pub text: String,
+            pub failed: bool,
+        }
+
+        /// A custom panel refreshed by a background thread running its command.
+        pub struct CommandPanel {
+            pub title: String,
+            output: Arc<Mutex<PanelOutput>>,
+        }
+
+        impl CommandPanel {
+            pub fn spawn(cfg: &PanelConfig) -> Self {
+                let output = Arc::new(Mutex::new(PanelOutput {
+                    text: "waiting for first run...".to_string(),
+                    failed: false,
+                }));
+                let shared = Arc::clone(&output);
+                let command = cfg.command.clone();
+                let interval = Duration::from_secs(cfg.interval_secs.max(1));
+                // Stops once the panel is dropped
+                std::thread::spawn(move || {
+                    while Arc::strong_count(&shared) > 1 {
+                        let result = run(&command);
+                        if let Ok(mut out) = shared.lock() {
+                            *out = result;
+                        }
+                        std::thread::sleep(interval);
+                    }
+                });
+                Self { title: cfg.title.clone(), output }
+            }
+
+            pub fn output(&self) -> PanelOutput {
+                self.output.lock().map(|o| o.clone()).unwrap_or_default()
+            }
+        }
+
+        fn run(command: &str) -> PanelOutput {
+            match Command::new("sh").arg("-c").arg(command).output() {
+                Ok(out) if out.status.success() => PanelOutput {
+                    text: String::from_utf8_lossy(&out.stdout).into_owned(),
+                    failed: false,
+                },
+                Ok(out) => PanelOutput {
+                    text: format!("{}\n{}", out.status, String::from_utf8_lossy(&out.stderr)),
+                    failed: true,
+                },
+                Err(e) => PanelOutput {
+                    text: format!("failed to run: {}", e),
+                    failed: true,
+                },
+            }
+        }
+}
+
+pub mod script {
+        use std::cell::RefCell;
+        use std::collections::HashMap;
+        use std::rc::Rc;
+
+        use regex::Regex;
+        use rhai::{Dynamic, Engine, Scope, AST};
+
+        use crate::config::Config;
+        use crate::errors::SysmonError;
+        use crate::types::{ProcessInfo, SystemMetrics};
+
+        /// Evaluates the user's computed columns and alert predicates.
+        ///
+        /// Scripts see `cpu_avg`, `ram_percent` and `swap_percent` always, plus
+        /// `pid`, `name`, `cpu` and `mem` (bytes) when evaluated per process.
+        /// `text matches "regex"` is a regex match.
+        pub struct ScriptEngine {
+            engine: Engine,
+            columns: Vec<(String, AST)>,
+            alerts: Vec<CompiledAlert>,
+        }
+
+        struct CompiledAlert {
+            name: String,
+            ast: AST,
+            per_process: bool,
+        }
+
+        impl ScriptEngine {
+            pub fn new(config: &Config) -> Result<Self, SysmonError> {
+                let mut engine = Engine::new();
+                engine
+                    .register_custom_operator("matches", 110)
+                    .map_err(|e| SysmonError::Config(e.to_string()))?;
+                let cache: Rc<RefCell<HashMap<String, Option<Regex>>>> = Rc::default();
+                engine.register_fn("matches", move |text: &str, pattern: &str| -> bool {
+                    cache
+                        .borrow_mut()
+                        .entry(pattern.to_string())
+                        .or_insert_with(|| Regex::new(pattern).ok())
+                        .as_ref()
+                        .is_some_and(|re| re.is_match(text))
+                });
+
+                let compile = |what: &str, name: &str, src: &str| {
+                    engine
+                        .compile_expression(src)
+                        .map_err(|e| SysmonError::Config(format!("{} '{}': {}", what, name, e)))
+                };
+                let columns = config
+                    .columns
+                    .iter()
+                    .map(|c| Ok((c.name.clone(), compile("column", &c.name, &c.expr)?)))
+                    .collect::<Result<Vec<_>, SysmonError>>()?;
+                let alerts = config
+                    .alerts
+                    .iter()
+                    .map(|a| {
+                        Ok(CompiledAlert {
+                            name: a.name.clone(),
+                            ast: compile("alert", &a.name, &a.when)?,
+                            per_process: a.per_process,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, SysmonError>>()?;
+
+                Ok(Self { engine, columns, alerts })
+            }
+
+            pub fn column_names(&self) -> Vec<String> {
+                self.columns.iter().map(|(name, _)| name.clone()).collect()
+            }
+
+            /// Formatted custom column values for each process, keyed by PID.
+            pub fn eval_columns(&self, metrics: &SystemMetrics) -> HashMap<i32, Vec<String>> {
+                if self.columns.is_empty() {
+                    return HashMap::new();
+                }
+                let base = system_scope(metrics);
+                metrics
+                    .processes
+                    .iter()
+                    .map(|p| {
+                        let mut scope = process_scope(&base, p);
+                        let values = self
+                            .columns
+                            .iter()
+                            .map(|(_, ast)| {
+                                match self.engine.eval_ast_with_scope::<Dynamic>(&mut scope, ast) {
+                                    Ok(v) if v.is_float() => format!("{:.1}", v.as_float().unwrap_or_default()),
+                                    Ok(v) => v.to_string(),
+                                    Err(_) => "err".to_string(),
+                                }
+                            })
+                            .collect();
+                        (p.pid, values)
+                    })
+                    .collect()
+            }
+
+            /// Alerts whose predicate currently holds, as `(name, detail)` pairs.
+            /// Evaluation errors count as not firing.
+            pub fn eval_alerts(&self, metrics: &SystemMetrics) -> Vec<(String, String)> {
+                let base = system_scope(metrics);
+                let mut firing = Vec::new();
+                for alert in &self.alerts {
+                    if alert.per_process {
+                        let hits: Vec<&ProcessInfo> = metrics
+                            .processes
+                            .iter()
+                            .filter(|p| self.check(&mut process_scope(&base, p), &alert.ast))
+                            .collect();
+                        if let Some(first) = hits.first() {
+                            let detail = if hits.len() > 1 {
+                                format!("{} ({}), {} more", first.name, first.pid, hits.len() - 1)
+                            } else {
+                                format!("{} ({})", first.name, first.pid)
+                            };
+                            firing.push((alert.name.clone(), detail));
+                        }
+                    } else if self.check(&mut base.clone(), &alert.ast) {
+                        firing.push((alert.name.clone(), String::new()));
+                    }
+                }
+                firing
+            }
+
+            fn check(&self, scope: &mut Scope, ast: &AST) -> bool {
+                self.engine.eval_ast_with_scope::<bool>(scope, ast).unwrap_or(false)
+            }
+        }
+
+        fn percent(used: u64, total: u64) -> f64 {
+            if total > 0 {
+                used as f64 / total as f64 * 100.0
+            } else {
+                0.0
+            }
+        }
+
+        fn system_scope(metrics: &SystemMetrics) -> Scope<'static> {
+            let cpu_avg = if metrics.cpu.is_empty() {
+                0.0
+            } else {
+                metrics.cpu.iter().map(|c| c.usage_percent as f64).sum::<f64>() / metrics.cpu.len() as f64
+            };
+            let mut scope = Scope::new();
+            scope.push_constant("cpu_avg", cpu_avg);
+            scope.push_constant("ram_percent", percent(metrics.ram.used, metrics.ram.total));
+            scope.push_constant("swap_percent", percent(metrics.swap.used, metrics.swap.total));
+            scope.push_constant("load1", sysinfo::System::load_average().one);
+            scope.push_constant("cores", metrics.cpu.len() as i64);
+            scope
+        }
+
+        fn process_scope(base: &Scope<'static>, p: &ProcessInfo) -> Scope<'static> {
+            let mut scope = base.clone();
+            scope.push_constant("pid", p.pid as i64);
+            scope.push_constant("name", p.name.clone());
+            scope.push_constant("cpu", p.cpu_percent as f64);
+            scope.push_constant("mem", p.mem_bytes as i64);
+            scope
+        }
+}
+
+pub mod alerts {
+        use chrono::{DateTime, Local};
+
+        pub struct ActiveAlert {
+            pub name: String,
+            pub detail: String,
+            pub since: DateTime<Local>,
+        }
+
+        /// Tracks which alerts are currently firing and counts how often each fired.
+        #[derive(Default)]
+        pub struct AlertTracker {
+            pub active: Vec<ActiveAlert>,
+            /// Every transition into the firing state, oldest first.
+            pub fired: Vec<(DateTime<Local>, String)>,
+        }
+
+        impl AlertTracker {
+            /// Replaces the firing set, keeping the original `since` for alerts that
+            /// were already active. Returns the names of newly fired alerts.
+            pub fn update(&mut self, firing: Vec<(String, String)>) -> Vec<String> {
+                let now = Local::now();
+                let mut newly = Vec::new();
+                let previous = std::mem::take(&mut self.active);
+                for (name, detail) in firing {
+                    let since = match previous.iter().find(|a| a.name == name) {
+                        Some(a) => a.since,
+                        None => {
+                            self.fired.push((now, name.clone()));
+                            newly.push(name.clone());
+                            now
+                        }
+                    };
+                    self.active.push(ActiveAlert { name, detail, since });
+                }
+                newly
+            }
+        }
+}
+
+pub mod sinks {
+        use std::io::{Read, Write};
+        use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+        use std::sync::{Arc, Mutex};
+        use std::time::Duration;
+
+        use crate::config::{GraphiteConfig, MqttConfig, StatsdConfig};
+        use crate::notify::json_escape;
+        use crate::types::SystemMetrics;
+
+        const TIMEOUT: Duration = Duration::from_secs(5);
+
+        /// Metrics and active alert names at one point in time.
+        #[derive(Clone, Default)]
+        pub struct Snapshot {
+            pub samples: Vec<(String, f64)>,
+            pub alerts: Vec<String>,
+        }
+
+        /// Flattens the core metrics into dotted names such as `cpu.avg` or
+        /// `temp.nvme`. `scan_secs` is the span the disk counters cover.
+        pub fn snapshot(metrics: &SystemMetrics, net_rate: (u64, u64), scan_secs: f64, alerts: Vec<String>) -> Snapshot {
+            let pct = |used: u64, total: u64| if total == 0 { 0.0 } else { used as f64 / total as f64 * 100.0 };
+            let cpu_avg = metrics.cpu.iter().map(|c| c.usage_percent as f64).sum::<f64>() / metrics.cpu.len().max(1) as f64;
+            let mut samples = vec![
+                ("cpu.avg".to_string(), cpu_avg),
+                ("ram.percent".to_string(), pct(metrics.ram.used, metrics.ram.total)),
+                ("ram.used_bytes".to_string(), metrics.ram.used as f64),
+                ("swap.percent".to_string(), pct(metrics.swap.used, metrics.swap.total)),
+                ("net.rx_bytes_per_sec".to_string(), net_rate.0 as f64),
+                ("net.tx_bytes_per_sec".to_string(), net_rate.1 as f64),
+                ("disk.read_bytes_per_sec".to_string(), metrics.disk_io.read_bytes as f64 / scan_secs),
+                ("disk.write_bytes_per_sec".to_string(), metrics.disk_io.write_bytes as f64 / scan_secs),
+            ];
+            for t in &metrics.thermals {
+                samples.push((format!("temp.{}", metric_name(&t.label)), t.temp_celsius as f64));
+            }
+            for fs in &metrics.filesystems {
+                samples.push((format!("disk_used.{}", metric_name(&fs.mount)), fs.used_percent()));
+            }
+            Snapshot { samples, alerts }
+        }
+
+        /// Lowercases and replaces anything but `[a-z0-9_]` so labels are safe
+        /// as topic levels and metric path components.
+        pub fn metric_name(label: &str) -> String {
+            let name: String = label
+                .trim_matches('/')
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+                .collect();
+            if name.is_empty() { "root".to_string() } else { name }
+        }
+
+        /// Whether `name` is selected; an empty list selects everything.
+        pub fn selected(patterns: &[String], name: &str) -> bool {
+            patterns.is_empty()
+                || patterns.iter().any(|p| match p.strip_suffix('*') {
+                    Some(prefix) => name.starts_with(prefix),
+                    None => p == name,
+                })
+        }
+
+        /// A metrics backend fed from a background thread.
+        pub trait Sink: Send {
+            fn send(&mut self, snapshot: &Snapshot) -> Result<(), String>;
+        }
+
+        /// Hands the latest snapshot to a sink on its own thread every interval,
+        /// so a slow or unreachable backend never stalls the UI.
+        pub struct SinkWorker {
+            latest: Arc<Mutex<Option<Snapshot>>>,
+            error: Arc<Mutex<Option<String>>>,
+        }
+
+        impl SinkWorker {
+            pub fn spawn(mut sink: Box<dyn Sink>, interval: Duration) -> Self {
+                let latest: Arc<Mutex<Option<Snapshot>>> = Arc::new(Mutex::new(None));
+                let error = Arc::new(Mutex::new(None));
+                let (shared, shared_error) = (Arc::clone(&latest), Arc::clone(&error));
+                std::thread::spawn(move || loop {
+                    std::thread::sleep(interval);
+                    let snapshot = shared.lock().ok().and_then(|mut s| s.take());
+                    if let Some(snapshot) = snapshot {
+                        let result = sink.send(&snapshot);
+                        if let Ok(mut e) = shared_error.lock() {
+                            *e = result.err();
+                        }
+                    }
+                });
+                Self { latest, error }
+            }
+
+            pub fn update(&self, snapshot: Snapshot) {
+                if let Ok(mut s) = self.latest.lock() {
+                    *s = Some(snapshot);
+                }
+            }
+
+            /// Error from the last delivery attempt, if it failed and hasn't
+            /// been reported yet.
+            pub fn take_error(&self) -> Option<String> {
+                self.error.lock().ok().and_then(|mut e| e.take())
+            }
+        }
+
+        /// Minimal MQTT 3.1.1 publisher: CONNECT then QoS 0 PUBLISHes,
+        /// reconnecting after any error.
+        pub struct MqttSink {
+            cfg: MqttConfig,
+            prefix: String,
+            stream: Option<TcpStream>,
+        }
+
+        impl MqttSink {
+            pub fn new(cfg: &MqttConfig) -> Self {
+                let prefix = cfg.topic_prefix.clone().unwrap_or_else(|| {
+                    format!("sysmon/{}", sysinfo::System::host_name().unwrap_or_else(|| "unknown".to_string()))
+                });
+                Self { cfg: cfg.clone(), prefix: prefix.trim_end_matches('/').to_string(), stream: None }
+            }
+
+            fn connect(&self) -> Result<TcpStream, String> {
+                let broker = if self.cfg.broker.contains(':') { self.cfg.broker.clone() } else { format!("{}:1883", self.cfg.broker) };
+                let addr = broker
+                    .to_socket_addrs()
+                    .map_err(|e| e.to_string())?
+                    .next()
+                    .ok_or_else(|| "no address".to_string())?;
+                let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT).map_err(|e| e.to_string())?;
+                stream.set_read_timeout(Some(TIMEOUT)).map_err(|e| e.to_string())?;
+                stream.set_write_timeout(Some(TIMEOUT)).map_err(|e| e.to_string())?;
+
+                // Keep-alive comfortably above the publish interval
+                let keep_alive = (self.cfg.interval_secs.saturating_mul(3).max(60)).min(u16::MAX as u64) as u16;
+                let mut flags = 0x02; // clean session
+                let mut payload = Vec::new();
+                put_str(&mut payload, &format!("sysmon-tui-{}", std::process::id())).map_err(|e| e.to_string())?;
+                if let Some(ref user) = self.cfg.username {
+                    flags |= 0x80;
+                    put_str(&mut payload, user).map_err(|e| format!("username: {}", e))?;
+                }
+                if let Some(ref pass) = self.cfg.password {
+                    flags |= 0x40;
+                    put_str(&mut payload, pass).map_err(|e| format!("password: {}", e))?;
+                }
+                let mut body = Vec::new();
+                put_str(&mut body, "MQTT").map_err(|e| e.to_string())?;
+                body.push(4); // protocol level 3.1.1
+                body.push(flags);
+                body.extend_from_slice(&keep_alive.to_be_bytes());
+                body.extend_from_slice(&payload);
+                stream.write_all(&packet(0x10, &body)).map_err(|e| e.to_string())?;
+
+                let mut connack = [0u8; 4];
+                stream.read_exact(&mut connack).map_err(|e| e.to_string())?;
+                if connack[0] != 0x20 || connack[3] != 0 {
+                    return Err(format!("broker refused connection (code {})", connack[3]));
+                }
+                Ok(stream)
+            }
+
+            fn publish(stream: &mut TcpStream, topic: &str, payload: &str, retain: bool) -> std::io::Result<()> {
+                let mut body = Vec::new();
+                put_str(&mut body, topic)?;
+                body.extend_from_slice(payload.as_bytes());
+                stream.write_all(&packet(0x30 | u8::from(retain), &body))
+            }
+        }
+
+        impl Sink for MqttSink {
+            fn send(&mut self, snapshot: &Snapshot) -> Result<(), String> {
+                if self.stream.is_none() {
+                    self.stream = Some(self.connect()?);
+                }
+                let Some(stream) = self.stream.as_mut() else {
+                    return Ok(());
+                };
+                let mut result = Ok(());
+                for (name, value) in snapshot.samples.iter().filter(|(n, _)| selected(&self.cfg.metrics, n)) {
+                    let topic = format!("{}/{}", self.prefix, name.replace('.', "/"));
+                    result = result.and(Self::publish(stream, &topic, &format!("{:.2}", value), self.cfg.retain));
+                }
+                if selected(&self.cfg.metrics, "alerts") {
+                    let alerts = snapshot.alerts.iter().map(|a| format!("\"{}\"", json_escape(a))).collect::<Vec<_>>();
+                    let topic = format!("{}/alerts", self.prefix);
+                    result = result.and(Self::publish(stream, &topic, &format!("[{}]", alerts.join(",")), self.cfg.retain));
+                }
+                result.map_err(|e| {
+                    self.stream = None;
+                    e.to_string()
+                })
+            }
+        }
+
+        /// Path prefix for the dotted-path backends: the configured one with
+        /// `{hostname}` filled in, or `sysmon.<hostname>`.
+        fn metric_prefix(configured: Option<&str>) -> String {
+            let host = metric_name(&sysinfo::System::host_name().unwrap_or_else(|| "unknown".to_string()));
+            configured.unwrap_or("sysmon.{hostname}").replace("{hostname}", &host).trim_end_matches('.').to_string()
+        }
+
+        /// StatsD gauges (`name:value|g`), several per datagram.
+        pub struct StatsdSink {
+            cfg: StatsdConfig,
+            prefix: String,
+            socket: Option<UdpSocket>,
+        }
+
+        /// Stays under the common 1500-byte MTU after IP/UDP headers.
+        const MAX_DATAGRAM: usize = 1432;
+
+        impl StatsdSink {
+            pub fn new(cfg: &StatsdConfig) -> Self {
+                Self { cfg: cfg.clone(), prefix: metric_prefix(cfg.prefix.as_deref()), socket: None }
+            }
+
+            /// A socket of the collector's address family, connected to it.
+            fn open(&self) -> Result<UdpSocket, String> {
+                let address = if self.cfg.address.contains(':') { self.cfg.address.clone() } else { format!("{}:8125", self.cfg.address) };
+                let addr = address
+                    .to_socket_addrs()
+                    .map_err(|e| e.to_string())?
+                    .next()
+                    .ok_or_else(|| "no address".to_string())?;
+                let local = if addr.is_ipv6() { "[::]:0" } else { "0.0.0.0:0" };
+                let socket = UdpSocket::bind(local).map_err(|e| e.to_string())?;
+                socket.connect(addr).map_err(|e| e.to_string())?;
+                Ok(socket)
+            }
+        }
+
+        impl Sink for StatsdSink {
+            fn send(&mut self, snapshot: &Snapshot) -> Result<(), String> {
+                if self.socket.is_none() {
+                    self.socket = Some(self.open()?);
+                }
+                let Some(ref socket) = self.socket else {
+                    return Ok(());
+                };
+                let mut lines: Vec<String> = snapshot
+                    .samples
+                    .iter()
+                    .filter(|(n, _)| selected(&self.cfg.metrics, n))
+                    .map(|(name, value)| format!("{}.{}:{:.2}|g", self.prefix, name, value))
+                    .collect();
+                if selected(&self.cfg.metrics, "alerts") {
+                    lines.push(format!("{}.alerts:{}|g", self.prefix, snapshot.alerts.len()));
+                }
+                let mut datagrams: Vec<String> = Vec::new();
+                for line in lines {
+                    match datagrams.last_mut() {
+                        Some(d) if d.len() + 1 + line.len() <= MAX_DATAGRAM => {
+                            d.push('\n');
+                            d.push_str(&line);
+                        }
+                        _ => datagrams.push(line),
+                    }
+                }
+                let result = datagrams.iter().try_for_each(|d| socket.send(d.as_bytes()).map(|_| ()));
+                result.map_err(|e| {
+                    // Re-resolve and rebind next time, in case the collector moved
+                    self.socket = None;
+                    e.to_string()
+                })
+            }
+        }
+
+        /// Graphite plaintext lines (`path value timestamp`) on a persistent TCP
+        /// connection, reopened after a failed write.
+        pub struct GraphiteSink {
+            cfg: GraphiteConfig,
+            prefix: String,
+            stream: Option<TcpStream>,
+        }
+
+        impl GraphiteSink {
+            pub fn new(cfg: &GraphiteConfig) -> Self {
+                Self { cfg: cfg.clone(), prefix: metric_prefix(cfg.prefix.as_deref()), stream: None }
+            }
+        }
+
+        impl Sink for GraphiteSink {
+            fn send(&mut self, snapshot: &Snapshot) -> Result<(), String> {
+                if self.stream.is_none() {
+                    let address = if self.cfg.address.contains(':') { self.cfg.address.clone() } else { format!("{}:2003", self.cfg.address) };
+                    let addr = address
+                        .to_socket_addrs()
+                        .map_err(|e| e.to_string())?
+                        .next()
+                        .ok_or_else(|| "no address".to_string())?;
+                    let stream = TcpStream::connect_timeout(&addr, TIMEOUT).map_err(|e| e.to_string())?;
+                    stream.set_write_timeout(Some(TIMEOUT)).map_err(|e| e.to_string())?;
+                    self.stream = Some(stream);
+                }
+                let now = chrono::Local::now().timestamp();
+                let mut out = String::new();
+                for (name, value) in snapshot.samples.iter().filter(|(n, _)| selected(&self.cfg.metrics, n)) {
+                    out.push_str(&format!("{}.{} {:.2} {}\n", self.prefix, name, value, now));
+                }
+                if selected(&self.cfg.metrics, "alerts") {
+                    out.push_str(&format!("{}.alerts {} {}\n", self.prefix, snapshot.alerts.len(), now));
+                }
+                let result = self.stream.as_mut().map_or(Ok(()), |s| s.write_all(out.as_bytes()));
+                result.map_err(|e| {
+                    self.stream = None;
+                    e.to_string()
+                })
+            }
+        }
+
+        /// Fixed header with the variable-length "remaining length" encoding.
+        fn packet(kind: u8, body: &[u8]) -> Vec<u8> {
+            let mut out = vec![kind];
+            let mut len = body.len();
+            loop {
+                let mut byte = (len % 128) as u8;
+                len /= 128;
+                if len > 0 {
+                    byte |= 0x80;
+                }
+                out.push(byte);
+                if len == 0 {
+                    break;
+                }
+            }
+            out.extend_from_slice(body);
+            out
+        }
+
+        /// A length-prefixed UTF-8 string; MQTT caps these at 65535 bytes.
+        fn put_str(buf: &mut Vec<u8>, s: &str) -> std::io::Result<()> {
+            let len = u16::try_from(s.len()).map_err(|_| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{} bytes is over the 64 KiB MQTT string limit", s.len()))
+            })?;
+            buf.extend_from_slice(&len.to_be_bytes());
+            buf.extend_from_slice(s.as_bytes());
+            Ok(())
+        }
+}
+
+pub mod notify {
+        use std::process::{Command, Stdio};
+
+        use crate::config::NotifyConfig;
+
+        /// Delivers fired alerts to the configured command and webhook. Each
+        /// delivery runs on its own thread so a slow endpoint never stalls a tick.
+        pub struct Notifier {
+            config: NotifyConfig,
+        }
+
+        impl Notifier {
+            pub fn new(config: &NotifyConfig) -> Self {
+                Self { config: config.clone() }
+            }
+
+            pub fn send(&self, name: &str, detail: &str) {
+                if let Some(ref cmd) = self.config.command {
+                    let mut child = Command::new("sh");
+                    child
+                        .args(["-c", cmd])
+                        .env("SYSMON_ALERT", name)
+                        .env("SYSMON_DETAIL", detail)
+                        .stdin(Stdio::null())
+                        .stdout(Stdio::null())
+                        .stderr(Stdio::null());
+                    std::thread::spawn(move || child.status());
+                }
+                if let Some(ref url) = self.config.webhook {
+                    let body = format!(
+                        "{{\"alert\":\"{}\",\"detail\":\"{}\",\"time\":\"{}\"}}",
+                        json_escape(name),
+                        json_escape(detail),
+                        chrono::Local::now().to_rfc3339(),
+                    );
+                    let mut child = Command::new("curl");
+                    child
+                        .args(["-fsS", "-m", "10", "-H", "Content-Type: application/json", "-d", &body, url])
+                        .stdin(Stdio::null())
+                        .stdout(Stdio::null())
+                        .stderr(Stdio::null());
+                    std::thread::spawn(move || child.status());
+                }
+            }
+        }
+
+        /// Escapes `s` for use inside a JSON string literal.
+        pub fn json_escape(s: &str) -> String {
+            let mut out = String::with_capacity(s.len());
+            for c in s.chars() {
+                match c {
+                    '"' => out.push_str("\\\""),
+                    '\\' => out.push_str("\\\\"),
+                    c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+                    c => out.push(c),
+                }
+            }
+            out
+        }
+}
+
+pub mod watchdog {
+        use std::collections::BTreeSet;
+        use std::process::{Command, Stdio};
+        use std::time::{Duration, Instant};
+
+        use crate::config::WatchConfig;
+        use crate::types::ProcessInfo;
+
+        /// How long a restart stays in the alert list after it is noticed.
+        const RESTART_HOLD: Duration = Duration::from_secs(60);
+
+        struct Watched {
+            config: WatchConfig,
+            /// PIDs seen on the last check; empty while the process is missing.
+            pids: BTreeSet<i32>,
+            /// Previous and new PID of the last restart, and when it was seen.
+            restart: Option<(i32, i32, Instant)>,
+        }
+
+        /// Liveness checker for the `[[watch]]` processes.
+        pub struct Watchdog {
+            watched: Vec<Watched>,
+            /// False until the first check, so processes already running at
+            /// startup are not reported as restarts.
+            primed: bool,
+        }
+
+        impl Watchdog {
+            pub fn new(config: &[WatchConfig]) -> Self {
+                let watched = config
+                    .iter()
+                    .map(|c| Watched { config: c.clone(), pids: BTreeSet::new(), restart: None })
+                    .collect();
+                Self { watched, primed: false }
+            }
+
+            /// Compares `processes` with the previous check, runs hooks for new
+            /// events and returns the alerts that are currently firing. A
+            /// restart means none of the previously seen PIDs remain.
+            pub fn check(&mut self, processes: &[ProcessInfo]) -> Vec<(String, String)> {
+                let mut firing = Vec::new();
+                for w in &mut self.watched {
+                    let pids: BTreeSet<i32> = processes.iter().filter(|p| p.name == w.config.name).map(|p| p.pid).collect();
+                    let first = pids.first().copied();
+                    if first.is_none() && (!w.pids.is_empty() || !self.primed) {
+                        run_hook(&w.config, "missing", None);
+                    } else if let (Some(old), Some(new)) = (w.pids.first().copied(), first) {
+                        if w.pids.is_disjoint(&pids) {
+                            w.restart = Some((old, new, Instant::now()));
+                            run_hook(&w.config, "restarted", Some(new));
+                        }
+                    }
+                    w.pids = pids;
+
+                    if w.pids.is_empty() {
+                        firing.push((format!("{} not running", w.config.name), String::new()));
+                    } else if let Some((old, new, at)) = w.restart {
+                        if at.elapsed() < RESTART_HOLD {
+                            firing.push((format!("{} restarted", w.config.name), format!("pid {} -> {}", old, new)));
+                        }
+                    }
+                }
+                self.primed = true;
+                firing
+            }
+        }
+
+        /// Runs the hook through `sh -c` with `SYSMON_WATCH`, `SYSMON_EVENT`
+        /// (`missing` or `restarted`) and, after a restart, `SYSMON_PID`.
+        fn run_hook(config: &WatchConfig, event: &str, pid: Option<i32>) {
+            let Some(ref cmd) = config.hook else {
+                return;
+            };
+            let mut child = Command::new("sh");
+            child
+                .args(["-c", cmd])
+                .env("SYSMON_WATCH", &config.name)
+                .env("SYSMON_EVENT", event)
+                .env("SYSMON_PID", pid.map(|p| p.to_string()).unwrap_or_default())
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null());
+            std::thread::spawn(move || child.status());
+        }
+}
+
+pub mod offenders {
+        use std::collections::HashMap;
+        use std::fmt::Write;
+        use std::time::Instant;
+
+        use chrono::{DateTime, Local};
+
+        use crate::config::OffenderConfig;
+        use crate::report::human_bytes;
+        use crate::types::ProcessInfo;
+
+        /// Rows per section of the report.
+        const TOP: usize = 20;
+        /// Command lines are cut to this many characters.
+        const CMD_WIDTH: usize = 120;
+
+        struct Record {
+            pid: i32,
+            name: String,
+            user: String,
+            cmd: String,
+            cpu_secs: f64,
+            /// CPU% × seconds while over the CPU threshold, for the average.
+            cpu_weighted: f64,
+            mem_secs: f64,
+            peak_cpu: f32,
+            peak_mem: u64,
+            first_over: DateTime<Local>,
+        }
+
+        /// Time each process spends over the CPU and memory thresholds during
+        /// the session.
+        pub struct OffenderTracker {
+            config: OffenderConfig,
+            /// Keyed by PID and name so a reused PID starts a new record.
+            records: HashMap<(i32, String), Record>,
+            last: Option<Instant>,
+            started: DateTime<Local>,
+        }
+
+        impl OffenderTracker {
+            pub fn new(config: &OffenderConfig) -> Self {
+                Self { config: config.clone(), records: HashMap::new(), last: None, started: Local::now() }
+            }
+
+            /// New thresholds apply from the next refresh; time already
+            /// recorded is kept.
+            pub fn set_config(&mut self, config: &OffenderConfig) {
+                self.config = config.clone();
+            }
+
+            /// Credits the time since the previous call to every process over a
+            /// threshold now.
+            pub fn record(&mut self, processes: &[ProcessInfo], ram_total: u64) {
+                let now = Instant::now();
+                let Some(secs) = self.last.replace(now).map(|t| now.duration_since(t).as_secs_f64()) else {
+                    return;
+                };
+                let mem_limit = (ram_total as f64 * self.config.mem_percent / 100.0) as u64;
+                for p in processes.iter().filter(|p| !p.thread) {
+                    let cpu_over = p.cpu_percent >= self.config.cpu_percent;
+                    let mem_over = p.mem_bytes >= mem_limit;
+                    if !cpu_over && !mem_over {
+                        continue;
+                    }
+                    let r = self.records.entry((p.pid, p.name.clone())).or_insert_with(|| Record {
+                        pid: p.pid,
+                        name: p.name.clone(),
+                        user: p.user.clone(),
+                        cmd: p.cmd.clone(),
+                        cpu_secs: 0.0,
+                        cpu_weighted: 0.0,
+                        mem_secs: 0.0,
+                        peak_cpu: 0.0,
+                        peak_mem: 0,
+                        first_over: Local::now(),
+                    });
+                    if cpu_over {
+                        r.cpu_secs += secs;
+                        r.cpu_weighted += p.cpu_percent as f64 * secs;
+                    }
+                    if mem_over {
+                        r.mem_secs += secs;
+                    }
+                    r.peak_cpu = r.peak_cpu.max(p.cpu_percent);
+                    r.peak_mem = r.peak_mem.max(p.mem_bytes);
+                }
+            }
+
+            /// Plain-text report of the processes longest over each threshold.
+            pub fn report(&self) -> String {
+                let mut out = String::new();
+                let now = Local::now();
+                let _ = writeln!(
+                    out,
+                    "sysmon-tui offender report - {} on {}",
+                    now.format("%Y-%m-%d %H:%M:%S"),
+                    sysinfo::System::host_name().unwrap_or_else(|| "unknown".to_string()),
+                );
+                let _ = writeln!(
+                    out,
+                    "session since {} ({}), listing processes over a threshold for at least {}",
+                    self.started.format("%Y-%m-%d %H:%M:%S"),
+                    duration((now - self.started).num_seconds().max(0) as f64),
+                    duration(self.config.min_secs as f64),
+                );
+                let min = self.config.min_secs as f64;
+
+                let _ = writeln!(out, "\n== CPU >= {}% ==", self.config.cpu_percent);
+                let mut cpu: Vec<&Record> = self.records.values().filter(|r| r.cpu_secs >= min).collect();
+                cpu.sort_by(|a, b| b.cpu_secs.total_cmp(&a.cpu_secs));
+                section(&mut out, &cpu, |r| {
+                    format!(
+                        "{:>9}  avg {:>5.0}%  peak {:>5.0}%",
+                        duration(r.cpu_secs),
+                        r.cpu_weighted / r.cpu_secs,
+                        r.peak_cpu
+                    )
+                });
+
+                let _ = writeln!(out, "\n== Memory >= {}% of RAM ==", self.config.mem_percent);
+                let mut mem: Vec<&Record> = self.records.values().filter(|r| r.mem_secs >= min).collect();
+                mem.sort_by(|a, b| b.mem_secs.total_cmp(&a.mem_secs));
+                section(&mut out, &mem, |r| format!("{:>9}  peak {:>10}", duration(r.mem_secs), human_bytes(r.peak_mem)));
+                out
+            }
+        }
+
+        fn section(out: &mut String, records: &[&Record], figures: impl Fn(&Record) -> String) {
+            if records.is_empty() {
+                let _ = writeln!(out, "none");
+                return;
+            }
+            for (rank, r) in records.iter().take(TOP).enumerate() {
+                let _ = writeln!(
+                    out,
+                    "{:>2}. {:>7} {:<16} {:<10} {}  since {}",
+                    rank + 1,
+                    r.pid,
+                    r.name,
+                    r.user,
+                    figures(r),
+                    r.first_over.format("%H:%M:%S"),
+                );
+                if !r.cmd.is_empty() {
+                    let cmd: String = r.cmd.split_whitespace().collect::<Vec<_>>().join(" ");
+                    let cut = cmd.char_indices().nth(CMD_WIDTH).map_or(cmd.len(), |(i, _)| i);
+                    let _ = writeln!(out, "    {}{}", &cmd[..cut], if cut < cmd.len() { "…" } else { "" });
+                }
+            }
+            if records.len() > TOP {
+                let _ = writeln!(out, "... and {} more", records.len() - TOP);
+            }
+        }
+
+        /// `45s`, `3m05s`, `2h07m`.
+        fn duration(secs: f64) -> String {
+            let secs = secs.round() as u64;
+            match secs {
+                0..60 => format!("{}s", secs),
+                60..3600 => format!("{}m{:02}s", secs / 60, secs % 60),
+                _ => format!("{}h{:02}m", secs / 3600, secs / 60 % 60),
+            }
+        }
+}
+
+pub mod summary {
+        use std::collections::{HashMap, HashSet};
+        use std::fmt::Write;
+        use std::time::Instant;
+
+        use chrono::{DateTime, Local};
+
+        use crate::report::{decimal, human_bytes};
+        use crate::sinks::Snapshot;
+        use crate::types::ProcessInfo;
+
+        /// Chart width; older points are merged pairwise to stay within it.
+        const CHART_POINTS: usize = 100;
+        /// Rows in each process table.
+        const TOP: usize = 10;
+        const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+        /// One metric over the session: peak, mean and a chart series whose
+        /// points each cover `per_point` refreshes (their maximum).
+        struct Series {
+            peak: f64,
+            peak_at: DateTime<Local>,
+            sum: f64,
+            count: u64,
+            points: Vec<f64>,
+            per_point: u32,
+            pending: Option<(f64, u32)>,
+        }
+
+        impl Series {
+            fn push(&mut self, value: f64, now: DateTime<Local>) {
+                if self.count == 0 || value > self.peak {
+                    self.peak = value;
+                    self.peak_at = now;
+                }
+                self.sum += value;
+                self.count += 1;
+                let (max, n) = self.pending.take().map_or((value, 1), |(m, n)| (m.max(value), n + 1));
+                if n < self.per_point {
+                    self.pending = Some((max, n));
+                    return;
+                }
+                self.points.push(max);
+                if self.points.len() >= CHART_POINTS {
+                    self.points = self.points.chunks(2).map(|c| c.iter().copied().fold(f64::MIN, f64::max)).collect();
+                    self.per_point *= 2;
+                }
+            }
+
+            /// Bars scaled to `top`, or to the series maximum when `None`.
+            fn chart(&self, top: Option<f64>) -> String {
+                let top = top.unwrap_or_else(|| self.points.iter().copied().fold(0.0, f64::max));
+                self.points
+                    .iter()
+                    .map(|v| if top <= 0.0 { BARS[0] } else { BARS[((v / top) * 7.0).round().clamp(0.0, 7.0) as usize] })
+                    .collect()
+            }
+        }
+
+        struct ProcessTotals {
+            name: String,
+            cpu_secs: f64,
+            /// Time the process was seen for, for its average CPU%.
+            seen_secs: f64,
+            peak_mem: u64,
+        }
+
+        /// Everything the session summary needs, gathered on each full refresh.
+        pub struct SessionSummary {
+            started: DateTime<Local>,
+            /// In first-seen order, which keeps the core metrics first.
+            series: Vec<(String, Series)>,
+            processes: HashMap<(i32, String), ProcessTotals>,
+            last: Option<Instant>,
+        }
+
+        impl Default for SessionSummary {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl SessionSummary {
+            pub fn new() -> Self {
+                Self { started: Local::now(), series: Vec::new(), processes: HashMap::new(), last: None }
+            }
+
+            pub fn record(&mut self, snapshot: &Snapshot, processes: &[ProcessInfo]) {
+                let now = Local::now();
+                for (name, value) in &snapshot.samples {
+                    // Filesystem fill levels and absolute RAM add little here
+                    if name.starts_with("disk_used.") || name == "ram.used_bytes" {
+                        continue;
+                    }
+                    let i = match self.series.iter().position(|(n, _)| n == name) {
+                        Some(i) => i,
+                        None => {
+                            let series = Series {
+                                peak: 0.0,
+                                peak_at: now,
+                                sum: 0.0,
+                                count: 0,
+                                points: Vec::new(),
+                                per_point: 1,
+                                pending: None,
+                            };
+                            self.series.push((name.clone(), series));
+                            self.series.len() - 1
+                        }
+                    };
+                    self.series[i].1.push(*value, now);
+                }
+
+                let instant = Instant::now();
+                let Some(secs) = self.last.replace(instant).map(|t| instant.duration_since(t).as_secs_f64()) else {
+                    return;
+                };
+                for p in processes.iter().filter(|p| !p.thread) {
+                    let totals = self.processes.entry((p.pid, p.name.clone())).or_insert_with(|| ProcessTotals {
+                        name: p.name.clone(),
+                        cpu_secs: 0.0,
+                        seen_secs: 0.0,
+                        peak_mem: 0,
+                    });
+                    totals.cpu_secs += p.cpu_percent as f64 / 100.0 * secs;
+                    totals.seen_secs += secs;
+                    totals.peak_mem = totals.peak_mem.max(p.mem_bytes);
+                }
+                self.prune(processes);
+            }
+
+            /// Drops exited processes that can no longer make either table:
+            /// their totals are final, so any exited process outranked by `TOP`
+            /// other exited ones on both CPU time and peak memory stays out.
+            /// Without this, a build or a fork-heavy service grows the map
+            /// by every short-lived PID.
+            fn prune(&mut self, processes: &[ProcessInfo]) {
+                let live: HashSet<(i32, &str)> = processes.iter().map(|p| (p.pid, p.name.as_str())).collect();
+                let is_live = |key: &(i32, String)| live.contains(&(key.0, key.1.as_str()));
+                let exited: Vec<&ProcessTotals> = self.processes.iter().filter(|(k, _)| !is_live(k)).map(|(_, t)| t).collect();
+                if exited.len() <= 2 * TOP {
+                    return;
+                }
+                let mut cpu: Vec<f64> = exited.iter().map(|t| t.cpu_secs).collect();
+                let mut mem: Vec<u64> = exited.iter().map(|t| t.peak_mem).collect();
+                cpu.sort_by(|a, b| b.total_cmp(a));
+                mem.sort_by(|a, b| b.cmp(a));
+                let (min_cpu, min_mem) = (cpu[TOP - 1], mem[TOP - 1]);
+                self.processes.retain(|k, t| is_live(k) || t.cpu_secs >= min_cpu || t.peak_mem >= min_mem);
+            }
+
+            /// Markdown for an incident doc: peaks, charts, busiest processes
+            /// and the alerts that fired.
+            pub fn render(&self, fired: &[(DateTime<Local>, String)]) -> String {
+                let mut out = String::new();
+                let now = Local::now();
+                let host = sysinfo::System::host_name().unwrap_or_else(|| "unknown".to_string());
+                let minutes = (now - self.started).num_minutes();
+                let _ = writeln!(out, "# sysmon-tui session summary: {}\n", host);
+                let _ = writeln!(
+                    out,
+                    "{} to {} ({}h {:02}m)\n",
+                    self.started.format("%Y-%m-%d %H:%M:%S"),
+                    now.format("%Y-%m-%d %H:%M:%S"),
+                    minutes / 60,
+                    minutes % 60,
+                );
+
+                let _ = writeln!(out, "## Peaks\n");
+                let _ = writeln!(out, "| Metric | Peak | At | Mean |");
+                let _ = writeln!(out, "|---|---:|---|---:|");
+                for (name, s) in self.series.iter().filter(|(_, s)| s.count > 0) {
+                    let _ = writeln!(
+                        out,
+                        "| {} | {} | {} | {} |",
+                        name,
+                        value(name, s.peak),
+                        s.peak_at.format("%H:%M:%S"),
+                        value(name, s.sum / s.count as f64),
+                    );
+                }
+
+                let _ = writeln!(out, "\n## History\n");
+                let span = |s: &Series| (now - self.started).num_seconds() as f64 / s.points.len().max(1) as f64;
+                let _ = writeln!(out, "```");
+                let width = self.series.iter().map(|(n, _)| n.len()).max().unwrap_or(0);
+                for (name, s) in self.series.iter().filter(|(_, s)| !s.points.is_empty()) {
+                    // Percentages on a fixed 0-100 scale, everything else relative
+                    let top = value(name, 0.0).ends_with('%').then_some(100.0);
+                    let _ = writeln!(out, "{:<width$}  {}  max {}", name, s.chart(top), value(name, s.peak), width = width);
+                }
+                let _ = writeln!(out, "```");
+                if let Some((_, s)) = self.series.first() {
+                    let _ = writeln!(out, "\nEach bar is the maximum over about {:.0}s.", span(s));
+                }
+
+                let _ = writeln!(out, "\n## Busiest processes\n");
+                let _ = writeln!(out, "| PID | Name | CPU time | Avg CPU | Peak memory |");
+                let _ = writeln!(out, "|---:|---|---:|---:|---:|");
+                let mut busiest: Vec<(&(i32, String), &ProcessTotals)> = self.processes.iter().collect();
+                busiest.sort_by(|a, b| b.1.cpu_secs.total_cmp(&a.1.cpu_secs));
+                for ((pid, _), t) in busiest.iter().take(TOP) {
+                    let _ = writeln!(
+                        out,
+                        "| {} | {} | {}s | {}% | {} |",
+                        pid,
+                        t.name,
+                        decimal(t.cpu_secs, 1),
+                        decimal(t.cpu_secs / t.seen_secs.max(f64::EPSILON) * 100.0, 1),
+                        human_bytes(t.peak_mem),
+                    );
+                }
+
+                let _ = writeln!(out, "\n## Largest processes\n");
+                let _ = writeln!(out, "| PID | Name | Peak memory |");
+                let _ = writeln!(out, "|---:|---|---:|");
+                busiest.sort_by_key(|(_, t)| std::cmp::Reverse(t.peak_mem));
+                for ((pid, _), t) in busiest.iter().take(TOP) {
+                    let _ = writeln!(out, "| {} | {} | {} |", pid, t.name, human_bytes(t.peak_mem));
+                }
+
+                let _ = writeln!(out, "\n## Alerts fired\n");
+                if fired.is_empty() {
+                    let _ = writeln!(out, "None.");
+                } else {
+                    let _ = writeln!(out, "| Time | Alert |");
+                    let _ = writeln!(out, "|---|---|");
+                    for (time, name) in fired {
+                        let _ = writeln!(out, "| {} | {} |", time.format("%H:%M:%S"), name);
+                    }
+                }
+                out
+            }
+        }
+
+        /// Formats a metric value by the unit its name implies.
+        pub fn value(name: &str, v: f64) -> String {
+            if name.ends_with("_bytes_per_sec") {
+                format!("{}/s", human_bytes(v.max(0.0) as u64))
+            } else if name.starts_with("temp.") {
+                format!("{}°C", decimal(v, 1))
+            } else {
+                format!("{}%", decimal(v, 1))
+            }
+        }
+}
+
+pub mod compare {
+        use std::collections::{BTreeMap, VecDeque};
+        use std::time::{Duration, Instant};
+
+        use crate::report::{decimal, human_bytes};
+        use crate::sinks::Snapshot;
+
+        /// Time shown by the compare view's charts.
+        pub const WINDOW: Duration = Duration::from_secs(5 * 60);
+
+        /// Every snapshot metric over the last `keep` of full refreshes,
+        /// aligned by refresh so any two share a time axis.
+        pub struct MetricHistory {
+            keep: Duration,
+            times: VecDeque<Instant>,
+            /// `None` where a metric was missing (a sensor or mount that came
+            /// and went).
+            series: BTreeMap<String, VecDeque<Option<f64>>>,
+        }
+
+        impl MetricHistory {
+            /// Keeps at least `WINDOW`, so the charts always fill.
+            pub fn new(keep: Duration) -> Self {
+                Self { keep: keep.max(WINDOW), times: VecDeque::new(), series: BTreeMap::new() }
+            }
+
+            pub fn keep(&self) -> Duration {
+                self.keep
+            }
+
+            pub fn record(&mut self, snapshot: &Snapshot) {
+                let now = Instant::now();
+                while self.times.front().is_some_and(|t| now.duration_since(*t) > self.keep) {
+                    self.times.pop_front();
+                    for values in self.series.values_mut() {
+                        values.pop_front();
+                    }
+                }
+                self.times.push_back(now);
+                let len = self.times.len();
+                for (name, value) in &snapshot.samples {
+                    let values = self.series.entry(name.clone()).or_default();
+                    values.resize(len - 1, None);
+                    values.push_back(Some(*value));
+                }
+                for values in self.series.values_mut() {
+                    values.resize(len, None);
+                }
+                self.series.retain(|_, values| values.iter().any(Option::is_some));
+            }
+
+            pub fn names(&self) -> Vec<String> {
+                self.series.keys().cloned().collect()
+            }
+
+            pub fn contains(&self, name: &str) -> bool {
+                self.series.contains_key(name)
+            }
+
+            /// Seconds from the oldest refresh kept to the latest.
+            pub fn span(&self) -> f64 {
+                match (self.times.front(), self.times.back()) {
+                    (Some(first), Some(last)) => last.duration_since(*first).as_secs_f64(),
+                    _ => 0.0,
+                }
+            }
+
+            /// `(seconds relative to the window's end, value)` for the `WINDOW`
+            /// ending `offset` before the latest refresh, oldest first. With an
+            /// offset the points land on the same axis as the current window.
+            pub fn points(&self, name: &str, offset: Duration) -> Vec<(f64, f64)> {
+                let (Some(values), Some(last)) = (self.series.get(name), self.times.back()) else {
+                    return Vec::new();
+                };
+                let end = offset.as_secs_f64();
+                self.times
+                    .iter()
+                    .zip(values)
+                    .filter_map(|(t, v)| Some((end - last.duration_since(*t).as_secs_f64(), (*v)?)))
+                    .filter(|(x, _)| (-WINDOW.as_secs_f64()..=0.0).contains(x))
+                    .collect()
+            }
+        }
+
+        /// How a metric reads, from its snapshot name. The compare view shows
+        /// every snapshot metric, including the absolute byte counts the
+        /// session summary leaves out, so it has its own mapping.
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        pub enum Unit {
+            /// Drawn on a fixed 0-100 scale.
+            Percent,
+            Bytes,
+            BytesPerSec,
+            Celsius,
+        }
+
+        impl Unit {
+            pub fn of(name: &str) -> Self {
+                if name.ends_with("_bytes_per_sec") {
+                    Unit::BytesPerSec
+                } else if name.ends_with("_bytes") {
+                    Unit::Bytes
+                } else if name.starts_with("temp.") {
+                    Unit::Celsius
+                } else {
+                    Unit::Percent
+                }
+            }
+
+            pub fn format(self, v: f64) -> String {
+                match self {
+                    Unit::Percent => format!("{}%", decimal(v, 1)),
+                    Unit::Bytes => human_bytes(v.max(0.0) as u64),
+                    Unit::BytesPerSec => format!("{}/s", human_bytes(v.max(0.0) as u64)),
+                    Unit::Celsius => format!("{}°C", decimal(v, 1)),
+                }
+            }
+        }
+
+        /// Parses `90s`, `30m` or `1h`.
+        pub fn parse_offset(text: &str) -> Option<Duration> {
+            let unit = match text.chars().last()? {
+                's' => 1,
+                'm' => 60,
+                'h' => 3600,
+                _ => return None,
+            };
+            let n: u64 = text[..text.len() - 1].parse().ok()?;
+            n.checked_mul(unit).filter(|&secs| secs > 0).map(Duration::from_secs)
+        }
+
+        /// The shortest of `1h`, `30m` or `90s` that is exact.
+        pub fn label(offset: Duration) -> String {
+            match offset.as_secs() {
+                s if s % 3600 == 0 => format!("{}h", s / 3600),
+                s if s % 60 == 0 => format!("{}m", s / 60),
+                s => format!("{}s", s),
+            }
+        }
+}
+
+pub mod recorder {
+        use std::collections::VecDeque;
+        use std::fs::File;
+        use std::io::{self, BufWriter, Write};
+
+        use chrono::{DateTime, Local};
+
+        use crate::config::RecorderConfig;
+        use crate::logfile::RecordingInfo;
+        use crate::sinks::{metric_name, Snapshot};
+        use crate::types::ProcessInfo;
+
+        /// Everything captured on one full refresh.
+        struct Frame {
+            time: DateTime<Local>,
+            snapshot: Snapshot,
+            /// `(pid, name, cpu_percent, mem_bytes)`
+            processes: Vec<(i32, String, f32, u64)>,
+        }
+
+        /// In-memory ring buffer of recent full refreshes, kept whether or not
+        /// logging is on so the lead-up to an event can be saved afterwards.
+        pub struct FlightRecorder {
+            frames: VecDeque<Frame>,
+            window: chrono::Duration,
+        }
+
+        impl FlightRecorder {
+            pub fn new(config: &RecorderConfig) -> Self {
+                Self {
+                    frames: VecDeque::new(),
+                    window: chrono::Duration::minutes(config.minutes.min(24 * 60) as i64),
+                }
+            }
+
+            pub fn is_enabled(&self) -> bool {
+                self.window > chrono::Duration::zero()
+            }
+
+            pub fn record(&mut self, snapshot: &Snapshot, processes: &[ProcessInfo]) {
+                if !self.is_enabled() {
+                    return;
+                }
+                let time = Local::now();
+                while self.frames.front().is_some_and(|f| time - f.time > self.window) {
+                    self.frames.pop_front();
+                }
+                self.frames.push_back(Frame {
+                    time,
+                    snapshot: snapshot.clone(),
+                    processes: processes.iter().map(|p| (p.pid, p.name.clone(), p.cpu_percent, p.mem_bytes)).collect(),
+                });
+            }
+
+            /// Writes the buffer as `flight-<time>.csv` (the process log format)
+            /// and `flight-<time>-metrics.csv` (`timestamp,metric,value`).
+            /// Returns the process file path and the number of refreshes.
+            pub fn dump(&self, dir: &str, info: Option<&RecordingInfo>) -> io::Result<(String, usize)> {
+                std::fs::create_dir_all(dir)?;
+                let stamp = Local::now().format("%Y-%m-%d_%H-%M-%S");
+                let path = format!("{}/flight-{}.csv", dir, stamp);
+
+                let mut procs = BufWriter::new(File::create(&path)?);
+                let mut metrics = BufWriter::new(File::create(format!("{}/flight-{}-metrics.csv", dir, stamp))?);
+                if let Some(info) = info {
+                    info.write(&mut procs)?;
+                    info.write(&mut metrics)?;
+                }
+                writeln!(procs, "timestamp,pid,name,cpu_percent,mem_bytes")?;
+                writeln!(metrics, "timestamp,metric,value")?;
+                for frame in &self.frames {
+                    let ts = frame.time.format("%Y-%m-%dT%H:%M:%S%.3f");
+                    for (pid, name, cpu, mem) in &frame.processes {
+                        writeln!(procs, "{},{},{},{:.1},{}", ts, pid, name, cpu, mem)?;
+                    }
+                    for (name, value) in &frame.snapshot.samples {
+                        writeln!(metrics, "{},{},{:.2}", ts, name, value)?;
+                    }
+                    for alert in &frame.snapshot.alerts {
+                        writeln!(metrics, "{},alert.{},1", ts, metric_name(alert))?;
+                    }
+                }
+                procs.flush()?;
+                metrics.flush()?;
+                Ok((path, self.frames.len()))
+            }
+        }
+}
+
+pub mod clipboard {
+        use std::io::Write;
+
+        const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+        fn base64(data: &[u8]) -> String {
+            let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+            for chunk in data.chunks(3) {
+                let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+                let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+                for i in 0..4 {
+                    if i <= chunk.len() {
+                        out.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+                    } else {
+                        out.push('=');
+                    }
+                }
+            }
+            out
+        }
+
+        /// Sets the system clipboard through the terminal with an OSC 52 escape,
+        /// which also works over SSH when the local terminal supports it.
+        pub fn copy(text: &str) -> std::io::Result<()> {
+            let mut stdout = std::io::stdout();
+            write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+            stdout.flush()
+        }
+}
+
+pub mod report {
+        use std::cell::Cell;
+        use std::collections::HashMap;
+        use std::ffi::{CStr, CString};
+        use std::fmt::Write;
+        use std::sync::OnceLock;
+
+        use crate::alerts::ActiveAlert;
+        use crate::config::{FormatConfig, NetworkConfig};
+        use crate::errors::SysmonError;
+        use crate::types::{ProcessInfo, SortOrder, SystemMetrics, ThermalInfo};
+
+        static FORMAT: OnceLock<FormatConfig> = OnceLock::new();
+        static SEPARATORS: OnceLock<Separators> = OnceLock::new();
+
+        thread_local! {
+            static EXPORTING: Cell<bool> = const { Cell::new(false) };
+        }
+
+        /// Thousands and decimal separators for displayed numbers.
+        struct Separators {
+            thousands: String,
+            decimal: String,
+        }
+
+        impl Separators {
+            fn plain() -> Self {
+                Self { thousands: String::new(), decimal: ".".to_string() }
+            }
+
+            fn from_config(format: &FormatConfig) -> Result<Self, String> {
+                let mut separators = match &format.locale {
+                    Some(name) => Self::from_locale(if name == "system" { "" } else { name })
+                        .ok_or_else(|| format!("locale {:?} is not available", name))?,
+                    None => Self::plain(),
+                };
+                if let Some(thousands) = &format.thousands_separator {
+                    separators.thousands = thousands.clone();
+                }
+                if let Some(decimal) = &format.decimal_separator {
+                    separators.decimal = decimal.clone();
+                }
+                Ok(separators)
+            }
+
+            /// Reads `localeconv()` for `name` ("" for the environment's), then
+            /// puts `LC_NUMERIC` back to "C".
+            fn from_locale(name: &str) -> Option<Self> {
+                let name = CString::new(name).ok()?;
+                // SAFETY: called once at startup before other threads exist;
+                // the lconv strings are copied before the locale is reset.
+                unsafe {
+                    if libc::setlocale(libc::LC_NUMERIC, name.as_ptr()).is_null() {
+                        return None;
+                    }
+                    let conv = &*libc::localeconv();
+                    let separators = Self {
+                        thousands: CStr::from_ptr(conv.thousands_sep).to_string_lossy().into_owned(),
+                        decimal: CStr::from_ptr(conv.decimal_point).to_string_lossy().into_owned(),
+                    };
+                    libc::setlocale(libc::LC_NUMERIC, c"C".as_ptr());
+                    Some(separators)
+                }
+            }
+        }
+
+        /// Sets the precision and separators used for every byte count and
+        /// rate; only the first call (at startup) takes effect.
+        pub fn set_format(format: FormatConfig) -> Result<(), SysmonError> {
+            let separators = Separators::from_config(&format).map_err(SysmonError::Config)?;
+            let _ = SEPARATORS.set(separators);
+            let _ = FORMAT.set(format);
+            Ok(())
+        }
+
+        pub fn format() -> &'static FormatConfig {
+            FORMAT.get_or_init(FormatConfig::default)
+        }
+
+        /// Runs `f` with numbers formatted for a file rather than the screen:
+        /// plain unless `locale_exports` is set.
+        pub fn for_export<T>(f: impl FnOnce() -> T) -> T {
+            EXPORTING.with(|e| e.set(true));
+            let result = f();
+            EXPORTING.with(|e| e.set(false));
+            result
+        }
+
+        /// `value` with `places` decimals and the configured separators.
+        pub fn decimal(value: f64, places: usize) -> String {
+            let text = format!("{:.*}", places, value);
+            if EXPORTING.with(Cell::get) && !format().locale_exports {
+                return text;
+            }
+            let separators = SEPARATORS.get_or_init(Separators::plain);
+            let (int, frac) = text.split_once('.').unwrap_or((&text, ""));
+            let (sign, digits) = int.strip_prefix('-').map_or(("", int), |d| ("-", d));
+            let mut out = sign.to_string();
+            for (i, digit) in digits.chars().enumerate() {
+                if i > 0 && (digits.len() - i) % 3 == 0 {
+                    out.push_str(&separators.thousands);
+                }
+                out.push(digit);
+            }
+            if !frac.is_empty() {
+                out.push_str(&separators.decimal);
+                out.push_str(frac);
+            }
+            out
+        }
+
+        /// Scales `bytes` to a binary unit: (value, unit index from 0 = bytes
+        /// to 4 = TiB, decimal places to show).
+        pub fn scale_bytes(bytes: u64) -> (f64, usize, usize) {
+            let format = format();
+            let threshold = format.unit_threshold.max(1.0);
+            let mut value = bytes as f64;
+            let mut unit = 0;
+            while value >= threshold && unit < 4 {
+                value /= 1024.0;
+                unit += 1;
+            }
+            (value, unit, if unit >= 3 { format.gib_decimals } else { format.decimals })
+        }
+
+        pub fn human_bytes(bytes: u64) -> String {
+            const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+            match scale_bytes(bytes) {
+                (_, 0, _) => format!("{} B", decimal(bytes as f64, 0)),
+                (value, unit, decimals) => format!("{} {}", decimal(value, decimals), UNITS[unit]),
+            }
+        }
+
+        /// Everything currently on screen, borrowed for a plain-text dump.
+        pub struct ViewReport<'a> {
+            pub metrics: &'a SystemMetrics,
+            /// Processes in the order the table shows them.
+            pub processes: Vec<&'a ProcessInfo>,
+            pub sort_order: SortOrder,
+            pub filter: Option<&'a str>,
+            pub extra_columns: Vec<String>,
+            pub extra_values: &'a HashMap<i32, Vec<String>>,
+            pub alerts: &'a [ActiveAlert],
+            /// `(title, text)` of each custom panel.
+            pub panels: Vec<(String, String)>,
+            /// Latest network sample in bytes per second.
+            pub net_rate: (u64, u64),
+            /// Interface aliases to report alongside the kernel's names.
+            pub network: &'a NetworkConfig,
+        }
+
+        impl ViewReport<'_> {
+            /// Renders the report without any terminal styling.
+            pub fn render(&self) -> String {
+                let mut out = String::new();
+                let m = self.metrics;
+                let _ = writeln!(
+                    out,
+                    "sysmon-tui report - {} on {}",
+                    chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+                    sysinfo::System::host_name().unwrap_or_else(|| "unknown".to_string()),
+                );
+
+                let _ = writeln!(out, "\n== CPU ==");
+                if !m.cpu.is_empty() {
+                    let avg = m.cpu.iter().map(|c| c.usage_percent).sum::<f32>() / m.cpu.len() as f32;
+                    let _ = writeln!(out, "avg {:.0}% over {} cores", avg, m.cpu.len());
+                    for chunk in m.cpu.chunks(8) {
+                        let line: Vec<String> = chunk
+                            .iter()
+                            .map(|c| format!("{:>3}:{:>4.0}%", c.core_id, c.usage_percent))
+                            .collect();
+                        let _ = writeln!(out, "{}", line.join("  "));
+                    }
+                }
+
+                let _ = writeln!(out, "\n== Memory ==");
+                let _ = writeln!(out, "RAM  {} / {}", human_bytes(m.ram.used), human_bytes(m.ram.total));
+                let _ = writeln!(out, "Swap {} / {}", human_bytes(m.swap.used), human_bytes(m.swap.total));
+
+                let _ = writeln!(out, "\n== I/O ==");
+                let _ = writeln!(
+                    out,
+                    "Network RX {}/s  TX {}/s",
+                    human_bytes(self.net_rate.0),
+                    human_bytes(self.net_rate.1),
+                );
+                for i in &m.network.interfaces {
+                    let name = self.network.alias_and_name(&i.name);
+                    // Interface counters are per 250ms tick
+                    let _ = writeln!(
+                        out,
+                        "  {:<22} RX {}/s  TX {}/s  {}",
+                        name,
+                        human_bytes(i.received_bytes * 4),
+                        human_bytes(i.transmitted_bytes * 4),
+                        i.link.operstate,
+                    );
+                }
+                let _ = writeln!(
+                    out,
+                    "Disk    read {}  write {} (last scan)",
+                    human_bytes(m.disk_io.read_bytes),
+                    human_bytes(m.disk_io.write_bytes),
+                );
+
+                let _ = writeln!(out, "\n== Thermals ==");
+                if m.thermals.is_empty() {
+                    let _ = writeln!(out, "No sensors found");
+                }
+                for t in &m.thermals {
+                    let _ = writeln!(out, "{}", thermal_line(t));
+                }
+
+                if !self.alerts.is_empty() {
+                    let _ = writeln!(out, "\n== Active alerts ==");
+                    for a in self.alerts {
+                        let _ = writeln!(out, "{} since {} {}", a.name, a.since.format("%H:%M:%S"), a.detail);
+                    }
+                }
+
+                let _ = write!(out, "\n== Processes (by {}", self.sort_order.label());
+                if let Some(f) = self.filter {
+                    let _ = write!(out, ", filter: {}", f);
+                }
+                let _ = writeln!(out, ") ==");
+                let mut header = format!("{:>8}  {:<32} {:>7} {:>10}", "PID", "NAME", "CPU%", "MEM");
+                for name in &self.extra_columns {
+                    let _ = write!(header, " {:>10}", name);
+                }
+                let _ = writeln!(out, "{}", header);
+                for p in &self.processes {
+                    let _ = write!(
+                        out,
+                        "{:>8}  {:<32} {:>7} {:>10}",
+                        p.pid,
+                        p.name,
+                        decimal(p.cpu_percent as f64, 1),
+                        human_bytes(p.mem_bytes),
+                    );
+                    if let Some(values) = self.extra_values.get(&p.pid) {
+                        for v in values {
+                            let _ = write!(out, " {:>10}", v);
+                        }
+                    }
+                    out.push('\n');
+                }
+
+                for (title, text) in &self.panels {
+                    let _ = writeln!(out, "\n== {} ==", title);
+                    let _ = writeln!(out, "{}", text.trim_end());
+                }
+                out
+            }
+        }
+
+        fn thermal_line(t: &ThermalInfo) -> String {
+            match t.critical_celsius {
+                Some(c) => format!("{:<24} {:>6}°C (crit {:.0}°C)", t.label, decimal(t.temp_celsius as f64, 1), c),
+                None => format!("{:<24} {:>6}°C", t.label, decimal(t.temp_celsius as f64, 1)),
+            }
+        }
+}
+
+pub mod collector {
+        use std::cmp::Ordering;
+        use std::collections::{HashMap, HashSet};
+        use std::fs::File;
+        use std::os::unix::fs::FileExt;
+        use std::hash::{Hash, Hasher};
+        use std::os::unix::io::AsRawFd;
+        use std::path::PathBuf;
+        use std::sync::{Arc, Mutex};
+        use std::time::{Duration, Instant};
+        use rayon::prelude::*;
+        use sysinfo::{System, Networks, Components, Disks, ProcessRefreshKind, UpdateKind, Users};
+
+        pub struct Collector {
+            sys: System,
+            networks: Networks,
+            components: Components,
+            disks: Disks,
+            tick: u32,
+            pub process_every: u32,
+            /// Whether the last `collect` refreshed processes, thermals and disk I/O.
+            pub last_was_full: bool,
+            last_disk_io: crate::types::DiskIOStats,
+            last_processes: Vec<crate::types::ProcessInfo>,
+            last_thermals: Vec<crate::types::ThermalInfo>,
+            last_filesystems: Vec<crate::types::FilesystemUsage>,
+            /// Polled every tick so mounts and unmounts refresh the filesystem
+            /// list right away instead of on the next full refresh.
+            mountinfo: Option<File>,
+            /// Filled when the last `collect` saw the mount list change.
+            pub mount_changes: Vec<crate::types::MountChange>,
+            /// Sorted; interfaces are checked every tick, disks on full refreshes.
+            /// `None` until the first read, which reports no changes.
+            known_interfaces: Option<Vec<String>>,
+            known_disks: Option<Vec<String>>,
+            /// Cumulative `/proc/diskstats` bytes per device at the last tick.
+            block_bytes: HashMap<String, (u64, u64)>,
+            /// Filled when the last `collect` saw devices come or go.
+            pub device_changes: Vec<crate::types::DeviceChange>,
+            last_memory: crate::types::MemoryBreakdown,
+            thermal_zones: Vec<ThermalZone>,
+            zones_scanned: Option<Instant>,
+            /// Link state per interface, re-read on full refreshes.
+            links: HashMap<String, crate::types::LinkInfo>,
+            throttle_source: Option<ThrottleSource>,
+            throttle_read: Option<Instant>,
+            last_throttle: Option<crate::types::PiThrottle>,
+            cpufreq_policies: Vec<PathBuf>,
+            /// hwmon inputs and the logical CPUs each one covers.
+            core_sensors: Vec<(PathBuf, Vec<usize>)>,
+            /// Per logical CPU, re-read on full refreshes.
+            last_core_temps: Vec<Option<f32>>,
+            last_clusters: Vec<crate::types::CpuCluster>,
+            /// Summed `thermal_throttle` counters at the last full refresh.
+            last_throttle_counts: Option<[u64; 4]>,
+            throttle_cpus: Vec<(usize, bool)>,
+            /// `cpuinfo_max_freq` per logical CPU, which never changes; only
+            /// read without multi-CPU cpufreq policies.
+            hw_max_khz: Vec<Option<u64>>,
+            last_freq_limits: Vec<crate::types::FreqLimit>,
+            idle_states: IdleStates,
+            last_cstates: Vec<crate::types::CStateResidency>,
+            last_runnable: Vec<u32>,
+            last_run_queue: Option<(u32, u32)>,
+            /// Summed `run_delay` and `pcount` from `/proc/schedstat`.
+            last_schedstat: Option<(u64, u64)>,
+            last_sched_wait: Option<f64>,
+            last_batteries: Vec<crate::battery::BatteryInfo>,
+            last_clock: Option<crate::types::ClockSync>,
+            users: Users,
+            users_read: Option<Instant>,
+            /// When processes were last refreshed, for per-process I/O rates.
+            processes_read: Option<Instant>,
+            /// Read each process's nice value and scheduling class (one extra
+            /// `/proc/<pid>/stat` read per process per refresh).
+            pub read_priority: bool,
+            /// Read each process's cgroup to name its container or unit.
+            pub read_cgroup: bool,
+            /// Count scheduler wakeups per process (one schedstat read per task).
+            pub read_wakeups: bool,
+            /// Interface patterns (`[network] exclude`) skipped entirely.
+            pub net_exclude: Vec<String>,
+            /// Per-process socket byte counters, while the net column is shown.
+            #[cfg(feature = "ebpf")]
+            pub socket_bytes: Option<crate::ebpf::SocketBytes>,
+            /// Timeslice counts per PID at the last process refresh.
+            last_timeslices: HashMap<i32, u64>,
+            /// Handles on the per-task `/proc` files read on full refreshes.
+            proc_files: ProcFiles,
+        }
+
+        /// Open handles on `/proc` files read on every full refresh. A kept
+        /// handle is re-read with `pread` at offset 0, which makes the kernel
+        /// regenerate the contents, so each read is one syscall instead of
+        /// open, fstat, two reads and close. Handles left unused during a
+        /// refresh belong to exited tasks and are closed by `sweep`.
+        ///
+        /// The rayon workers read these concurrently, so the map is split
+        /// into shards by path, and a hit takes one shard lock just long
+        /// enough to clone the handle; the read itself runs unlocked.
+        struct ProcFiles {
+            shards: Vec<Mutex<KeptFiles>>,
+            /// Most handles kept per shard.
+            budget: usize,
+        }
+
+        /// Path to handle, and whether it was read since the last sweep.
+        type KeptFiles = HashMap<PathBuf, (Arc<File>, bool)>;
+
+        const PROC_FILE_SHARDS: usize = 32;
+        /// Most handles kept in total, however high the descriptor limit is
+        /// (containers often set it to a million).
+        const PROC_FILES_MAX: usize = 8192;
+
+        impl ProcFiles {
+            fn new() -> Self {
+                let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+                // SAFETY: getrlimit only writes to `limit`.
+                let soft = if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } == 0 {
+                    limit.rlim_cur as usize
+                } else {
+                    1024
+                };
+                // sysinfo keeps its own /proc handles, and the rest of the app
+                // needs descriptors too
+                let budget = (soft / 4).min(PROC_FILES_MAX) / PROC_FILE_SHARDS;
+                Self { shards: (0..PROC_FILE_SHARDS).map(|_| Mutex::default()).collect(), budget }
+            }
+
+            fn shard(&self, path: &PathBuf) -> std::sync::MutexGuard<'_, KeptFiles> {
+                let mut hasher = std::collections::hash_map::DefaultHasher::new();
+                path.hash(&mut hasher);
+                let shard = &self.shards[hasher.finish() as usize % PROC_FILE_SHARDS];
+                shard.lock().unwrap_or_else(|e| e.into_inner())
+            }
+
+            /// Contents of `path`, through a kept handle when there is one. A
+            /// handle whose task has exited (or whose PID was reused) fails to
+            /// read and is replaced by a fresh open.
+            fn read(&self, path: PathBuf) -> Option<String> {
+                let kept = self.shard(&path).get_mut(&path).map(|(file, used)| {
+                    *used = true;
+                    Arc::clone(file)
+                });
+                if let Some(text) = kept.and_then(|file| read_from_start(&file)) {
+                    return Some(text);
+                }
+                let file = File::open(&path).ok()?;
+                let text = read_from_start(&file)?;
+                let mut open = self.shard(&path);
+                if open.len() < self.budget || open.contains_key(&path) {
+                    open.insert(path, (Arc::new(file), true));
+                }
+                Some(text)
+            }
+
+            fn sweep(&self) {
+                for shard in &self.shards {
+                    shard.lock().unwrap_or_else(|e| e.into_inner()).retain(|_, (_, used)| std::mem::take(used));
+                }
+            }
+        }
+
+        /// Whole contents of a `/proc` file via `pread`. procfs returns as much
+        /// as fits, so a short read means the end; empty means the task is gone.
+        fn read_from_start(file: &File) -> Option<String> {
+            let mut buf = vec![0u8; 1024];
+            let mut len = 0;
+            loop {
+                let n = file.read_at(&mut buf[len..], len as u64).ok()?;
+                len += n;
+                if n == 0 || len < buf.len() {
+                    break;
+                }
+                buf.resize(buf.len() * 2, 0);
+            }
+            if len == 0 {
+                return None;
+            }
+            buf.truncate(len);
+            String::from_utf8(buf).ok()
+        }
+
+        const USERS_REREAD: Duration = Duration::from_secs(60);
+
+        /// Nice value, real-time priority and policy: fields 19, 40 and 41 of
+        /// `/proc/<pid>/stat`, counted after the parenthesized command name.
+        fn read_sched_priority(files: &ProcFiles, pid: u32) -> Option<crate::types::Priority> {
+            let text = files.read(PathBuf::from(format!("/proc/{}/stat", pid)))?;
+            let f: Vec<&str> = text.get(text.rfind(')')? + 2..)?.split_whitespace().collect();
+            Some(crate::types::Priority {
+                nice: f.get(16)?.parse().ok()?,
+                rt_priority: f.get(37)?.parse().ok()?,
+                policy: f.get(38)?.parse().ok()?,
+            })
+        }
+
+        /// Times a task has been scheduled in (`pcount`, the third field of
+        /// `schedstat`), summed over all of a process's threads. Every wakeup
+        /// from sleep is one of these, so the rate approximates powertop's
+        /// wakeups/s without needing timer tracing.
+        fn timeslices(files: &ProcFiles, pid: i32, thread: bool) -> Option<u64> {
+            let count = |path: PathBuf| -> Option<u64> {
+                files.read(path)?.split_whitespace().nth(2)?.parse().ok()
+            };
+            if thread {
+                return count(PathBuf::from(format!("/proc/{}/schedstat", pid)));
+            }
+            let tasks = std::fs::read_dir(format!("/proc/{}/task", pid)).ok()?;
+            Some(tasks.flatten().filter_map(|t| count(t.path().join("schedstat"))).sum())
+        }
+
+        /// Totals of the per-CPU `run_delay` (ns) and `pcount` columns.
+        fn read_schedstat() -> Option<(u64, u64)> {
+            let text = std::fs::read_to_string("/proc/schedstat").ok()?;
+            let mut totals = (0u64, 0u64);
+            for line in text.lines().filter(|l| l.starts_with("cpu")) {
+                let f: Vec<u64> = line.split_whitespace().skip(1).filter_map(|v| v.parse().ok()).collect();
+                totals.0 += f.get(7)?;
+                totals.1 += f.get(8)?;
+            }
+            Some(totals)
+        }
+
+        /// Counts runnable threads per CPU from `/proc/*/task/*/stat`, using the
+        /// state and `processor` (last CPU) fields.
+        fn count_runnable(files: &ProcFiles, cpu_count: usize) -> Vec<u32> {
+            let Ok(procs) = std::fs::read_dir("/proc") else {
+                return Vec::new();
+            };
+            let pids: Vec<PathBuf> = procs
+                .flatten()
+                .filter(|e| e.file_name().to_str().is_some_and(|n| n.bytes().all(|b| b.is_ascii_digit())))
+                .map(|e| e.path().join("task"))
+                .collect();
+            // One count vector per worker rather than per process, which adds
+            // up with hundreds of CPUs
+            pids.par_iter()
+                .fold(
+                    || vec![0u32; cpu_count],
+                    |mut counts, tasks| {
+                        for task in std::fs::read_dir(tasks).into_iter().flatten().flatten() {
+                            let Some(stat) = files.read(task.path().join("stat")) else {
+                                continue;
+                            };
+                            // Fields after the parenthesised comm start at field 3 (state);
+                            // processor is field 39.
+                            let Some((_, rest)) = stat.rsplit_once(')') else {
+                                continue;
+                            };
+                            let mut fields = rest.split_whitespace();
+                            if fields.next() != Some("R") {
+                                continue;
+                            }
+                            if let Some(cpu) = fields.nth(35).and_then(|f| f.parse::<usize>().ok()) {
+                                if let Some(c) = counts.get_mut(cpu) {
+                                    *c += 1;
+                                }
+                            }
+                        }
+                        counts
+                    },
+                )
+                .reduce(
+                    || vec![0u32; cpu_count],
+                    |mut a, b| {
+                        for (x, y) in a.iter_mut().zip(b) {
+                            *x += y;
+                        }
+                        a
+                    },
+                )
+        }
+
+        /// A `/proc/meminfo` field, converted from kB to bytes.
+        fn meminfo_bytes(meminfo: &str, key: &str) -> Option<u64> {
+            meminfo.lines().find_map(|line| {
+                let (name, rest) = line.split_once(':')?;
+                if name != key {
+                    return None;
+                }
+                let kb: u64 = rest.split_whitespace().next()?.parse().ok()?;
+                Some(kb * 1024)
+            })
+        }
+
+        /// Replaces `known` with `names` (both sorted) and returns what came and
+        /// went; nothing on the first call, when `known` is still `None`.
+        fn diff_devices(kind: &'static str, known: &mut Option<Vec<String>>, names: Vec<String>) -> Vec<crate::types::DeviceChange> {
+            let Some(before) = known.replace(names) else { return Vec::new() };
+            let after = known.as_deref().unwrap_or_default();
+            let change = |name: &String, added| crate::types::DeviceChange { kind, name: name.clone(), added };
+            let added = after.iter().filter(|n| before.binary_search(n).is_err()).map(|n| change(n, true));
+            let removed = before.iter().filter(|n| after.binary_search(n).is_err()).map(|n| change(n, false));
+            added.chain(removed).collect()
+        }
+
+        /// Reads the clock state without changing it (`modes` 0 needs no privileges).
+        fn read_clock_sync() -> Option<crate::types::ClockSync> {
+            // SAFETY: timex is plain data; with `modes` 0 adjtimex only fills it in.
+            let (state, tx) = unsafe {
+                let mut tx: libc::timex = std::mem::zeroed();
+                (libc::adjtimex(&mut tx), tx)
+            };
+            if state < 0 {
+                return None;
+            }
+            let offset_unit = if tx.status & libc::STA_NANO != 0 { 1e6 } else { 1e3 };
+            Some(crate::types::ClockSync {
+                synchronized: state != libc::TIME_ERROR && tx.status & libc::STA_UNSYNC == 0,
+                offset_ms: tx.offset as f64 / offset_unit,
+                max_error_ms: tx.maxerror as f64 / 1e3,
+                // Scaled by 2^16
+                freq_ppm: tx.freq as f64 / 65536.0,
+            })
+        }
+
+        /// Whole block devices with media, sorted; loop and card reader slots
+        /// with nothing attached have a size of 0.
+        fn read_block_devices() -> Vec<String> {
+            let Ok(entries) = std::fs::read_dir("/sys/block") else { return Vec::new() };
+            let mut names: Vec<String> = entries
+                .flatten()
+                .filter(|e| {
+                    std::fs::read_to_string(e.path().join("size")).is_ok_and(|s| s.trim().parse::<u64>().is_ok_and(|n| n > 0))
+                })
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .collect();
+            names.sort();
+            names
+        }
+
+        /// Every tmpfs in `/proc/mounts` with its `statvfs` figures. Mount
+        /// points listed twice (bind or overmounts) are reported once.
+        fn read_tmpfs_mounts() -> Vec<crate::types::FilesystemUsage> {
+            let text = std::fs::read_to_string("/proc/mounts").unwrap_or_default();
+            let mut mounts: Vec<crate::types::FilesystemUsage> = text
+                .lines()
+                .filter_map(|line| {
+                    let mut fields = line.split_whitespace();
+                    let device = fields.next()?;
+                    // Spaces and tabs in mount points are octal-escaped
+                    let mount = fields.next()?.replace("\\040", " ").replace("\\011", "\t");
+                    if fields.next()? != "tmpfs" {
+                        return None;
+                    }
+                    let (total, available, read_only) = statvfs(&mount)?;
+                    Some(crate::types::FilesystemUsage {
+                        mount,
+                        device: device.to_string(),
+                        fs_type: "tmpfs".to_string(),
+                        total,
+                        available,
+                        read_only,
+                    })
+                })
+                .collect();
+            mounts.sort_by(|a, b| a.mount.cmp(&b.mount));
+            mounts.dedup_by(|a, b| a.mount == b.mount);
+            mounts
+        }
+
+        /// KSM counters, when KSM is running or still holds merged pages.
+        fn read_ksm() -> Option<crate::types::KsmStats> {
+            let read = |name: &str| -> Option<u64> {
+                std::fs::read_to_string(format!("/sys/kernel/mm/ksm/{}", name)).ok()?.trim().parse().ok()
+            };
+            let running = read("run")? == 1;
+            let pages_shared = read("pages_shared").unwrap_or(0);
+            if !running && pages_shared == 0 {
+                return None;
+            }
+            // SAFETY: sysconf has no preconditions.
+            let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+            Some(crate::types::KsmStats {
+                running,
+                pages_shared,
+                pages_sharing: read("pages_sharing").unwrap_or(0),
+                page_size: if page_size > 0 { page_size as u64 } else { 4096 },
+            })
+        }
+
+        /// THP mode from sysfs plus `AnonHugePages` and khugepaged counters.
+        fn read_thp(meminfo: &str) -> Option<crate::types::ThpStats> {
+            const DIR: &str = "/sys/kernel/mm/transparent_hugepage";
+            let enabled = std::fs::read_to_string(format!("{}/enabled", DIR)).ok()?;
+            let mode = enabled.split('[').nth(1)?.split(']').next()?.to_string();
+            let counter = |name: &str| -> u64 {
+                std::fs::read_to_string(format!("{}/khugepaged/{}", DIR, name))
+                    .ok()
+                    .and_then(|t| t.trim().parse().ok())
+                    .unwrap_or(0)
+            };
+            Some(crate::types::ThpStats {
+                mode,
+                anon_bytes: meminfo_bytes(meminfo, "AnonHugePages").unwrap_or(0),
+                collapsed: counter("pages_collapsed"),
+                full_scans: counter("full_scans"),
+            })
+        }
+
+        /// Total and available bytes of the filesystem mounted at `path`, and
+        /// whether it is mounted read-only.
+        fn statvfs(path: &str) -> Option<(u64, u64, bool)> {
+            let path = std::ffi::CString::new(path).ok()?;
+            let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+            // SAFETY: `path` is NUL-terminated and `stat` is only read after
+            // statvfs reports success.
+            let stat = unsafe {
+                if libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) != 0 {
+                    return None;
+                }
+                stat.assume_init()
+            };
+            let block = stat.f_frsize as u64;
+            let read_only = stat.f_flag & libc::ST_RDONLY != 0;
+            Some((stat.f_blocks as u64 * block, stat.f_bavail as u64 * block, read_only))
+        }
+
+        /// The `running/total` field of `/proc/loadavg`.
+        fn read_loadavg_run_queue() -> Option<(u32, u32)> {
+            let text = std::fs::read_to_string("/proc/loadavg").ok()?;
+            let (running, total) = text.split_whitespace().nth(3)?.split_once('/')?;
+            Some((running.parse().ok()?, total.parse().ok()?))
+        }
+
+        /// cpuidle `time` files grouped by state name, plus the totals from the
+        /// previous read so residency can be computed from deltas.
+        struct IdleStates {
+            /// State name and the `time` file (µs) of that state on every CPU.
+            states: Vec<(String, Vec<PathBuf>)>,
+            cpu_count: usize,
+            previous: Option<(Instant, Vec<u64>)>,
+        }
+
+        impl IdleStates {
+            fn scan() -> Self {
+                let mut states: Vec<(String, Vec<PathBuf>)> = Vec::new();
+                let mut cpu_count = 0;
+                let cpus = std::fs::read_dir("/sys/devices/system/cpu").into_iter().flatten().flatten();
+                for cpu in cpus {
+                    let name = cpu.file_name();
+                    let name = name.to_string_lossy();
+                    if name.strip_prefix("cpu").is_none_or(|n| n.parse::<usize>().is_err()) {
+                        continue;
+                    }
+                    let Ok(entries) = std::fs::read_dir(cpu.path().join("cpuidle")) else {
+                        continue;
+                    };
+                    cpu_count += 1;
+                    for state in entries.flatten() {
+                        let dir = state.path();
+                        let Ok(label) = std::fs::read_to_string(dir.join("name")) else {
+                            continue;
+                        };
+                        let label = label.trim().to_string();
+                        match states.iter_mut().find(|(n, _)| *n == label) {
+                            Some((_, paths)) => paths.push(dir.join("time")),
+                            None => states.push((label, vec![dir.join("time")])),
+                        }
+                    }
+                }
+                // Shallowest first, as the kernel numbers them
+                states.sort_by_key(|(_, paths)| {
+                    paths
+                        .first()
+                        .and_then(|p| p.parent()?.file_name()?.to_str()?.strip_prefix("state")?.parse::<usize>().ok())
+                });
+                Self { states, cpu_count, previous: None }
+            }
+
+            fn read(&mut self) -> Vec<crate::types::CStateResidency> {
+                if self.states.is_empty() {
+                    return Vec::new();
+                }
+                let now = Instant::now();
+                let totals: Vec<u64> = self
+                    .states
+                    .iter()
+                    .map(|(_, paths)| {
+                        paths
+                            .iter()
+                            .filter_map(|p| std::fs::read_to_string(p).ok()?.trim().parse::<u64>().ok())
+                            .sum()
+                    })
+                    .collect();
+                let residency = match self.previous {
+                    Some((then, ref before)) => {
+                        let wall_us = now.duration_since(then).as_micros() as f64 * self.cpu_count as f64;
+                        self.states
+                            .iter()
+                            .zip(totals.iter().zip(before))
+                            .map(|((name, _), (t, b))| crate::types::CStateResidency {
+                                name: name.clone(),
+                                percent: (t.saturating_sub(*b) as f64 / wall_us.max(1.0) * 100.0).min(100.0) as f32,
+                            })
+                            .collect()
+                    }
+                    None => Vec::new(),
+                };
+                self.previous = Some((now, totals));
+                residency
+            }
+        }
+
+        /// cpufreq policy directories, when they describe multi-CPU clusters.
+        /// Per-CPU policies (typical on x86) are not worth a separate view.
+        fn scan_cpufreq_policies(cpu_count: usize) -> Vec<PathBuf> {
+            let Ok(entries) = std::fs::read_dir("/sys/devices/system/cpu/cpufreq") else {
+                return Vec::new();
+            };
+            let mut policies: Vec<PathBuf> = entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with("policy")))
+                .collect();
+            policies.sort_by_key(|p| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .and_then(|n| n.trim_start_matches("policy").parse::<usize>().ok())
+                    .unwrap_or(0)
+            });
+            if policies.len() < 2 || policies.len() >= cpu_count {
+                return Vec::new();
+            }
+            policies
+        }
+
+        /// `thermal_throttle` event counters (Intel), summed over all CPUs:
+        /// core and package thermal, core and package power limit. `None`
+        /// when the kernel doesn't expose them.
+        fn read_throttle_counts(cpus: &[(usize, bool)]) -> Option<[u64; 4]> {
+            const FILES: [&str; 4] = [
+                "core_throttle_count",
+                "package_throttle_count",
+                "core_power_limit_count",
+                "package_power_limit_count",
+            ];
+            let mut totals = [0u64; 4];
+            let mut found = false;
+            for &(cpu, package) in cpus {
+                let dir = PathBuf::from(format!("/sys/devices/system/cpu/cpu{}/thermal_throttle", cpu));
+                // Package counters sit at odd indices
+                for (i, file) in FILES.iter().enumerate().filter(|(i, _)| package || i % 2 == 0) {
+                    if let Some(n) = std::fs::read_to_string(dir.join(file)).ok().and_then(|t| t.trim().parse::<u64>().ok()) {
+                        totals[i] += n;
+                        found = true;
+                    }
+                }
+            }
+            found.then_some(totals)
+        }
+
+        /// Whether `[network] exclude` drops `iface`.
+        pub fn interface_excluded(patterns: &[String], iface: &str) -> bool {
+            patterns.iter().any(|p| match p.strip_suffix('*') {
+                Some(prefix) => iface.starts_with(prefix),
+                None => p == iface,
+            })
+        }
+
+        fn read_sysfs_u64(path: &str) -> Option<u64> {
+            std::fs::read_to_string(path).ok()?.trim().parse().ok()
+        }
+
+        /// CPUs whose `thermal_throttle` counters are read: the first thread of
+        /// each physical core, flagged when it is also the first of its package.
+        /// Siblings share the core counters and a package shares its package
+        /// counters, so reading every CPU would cost four files per thread.
+        fn throttle_sample_cpus(cpu_count: usize) -> Vec<(usize, bool)> {
+            let mut topology = cpu_topology();
+            if topology.is_empty() {
+                return (0..cpu_count).map(|cpu| (cpu, true)).collect();
+            }
+            topology.sort();
+            let mut cores = HashSet::new();
+            let mut packages = HashSet::new();
+            topology
+                .into_iter()
+                .filter(|&(_, package, core)| cores.insert((package, core)))
+                .map(|(cpu, package, _)| (cpu, packages.insert(package)))
+                .collect()
+        }
+
+        /// Package and core id of every logical CPU, from sysfs topology.
+        fn cpu_topology() -> Vec<(usize, u32, u32)> {
+            let Ok(entries) = std::fs::read_dir("/sys/devices/system/cpu") else {
+                return Vec::new();
+            };
+            entries
+                .flatten()
+                .filter_map(|e| {
+                    let name = e.file_name();
+                    let cpu: usize = name.to_str()?.strip_prefix("cpu")?.parse().ok()?;
+                    let read = |f: &str| -> Option<u32> {
+                        std::fs::read_to_string(e.path().join("topology").join(f)).ok()?.trim().parse().ok()
+                    };
+                    Some((cpu, read("physical_package_id")?, read("core_id")?))
+                })
+                .collect()
+        }
+
+        /// Maps coretemp and k10temp hwmon inputs to logical CPUs. coretemp has
+        /// a `Core N` sensor per physical core (matched on package and core id);
+        /// k10temp only has `TccdN` per chiplet, so each package's cores are
+        /// split evenly across its CCDs in core-id order. Package-level
+        /// sensors (`Package id`, `Tctl`) stay in the Thermals panel only.
+        fn scan_core_sensors() -> Vec<(PathBuf, Vec<usize>)> {
+            let topology = cpu_topology();
+            let Ok(entries) = std::fs::read_dir("/sys/class/hwmon") else {
+                return Vec::new();
+            };
+            let mut hwmons: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+            hwmons.sort();
+            let mut sensors = Vec::new();
+            let mut k10_package = 0;
+            for dir in hwmons {
+                let name = std::fs::read_to_string(dir.join("name")).unwrap_or_default();
+                // (input, label) pairs, in tempN order
+                let mut labels: Vec<(PathBuf, String)> = std::fs::read_dir(&dir)
+                    .into_iter()
+                    .flatten()
+                    .flatten()
+                    .filter_map(|e| {
+                        let file = e.file_name().to_str()?.to_string();
+                        let stem = file.strip_suffix("_label")?;
+                        let label = std::fs::read_to_string(e.path()).ok()?.trim().to_string();
+                        Some((dir.join(format!("{}_input", stem)), label))
+                    })
+                    .collect();
+                labels.sort_by_key(|(input, _)| {
+                    input.to_string_lossy().rsplit("temp").next().and_then(|n| n.trim_end_matches("_input").parse::<u32>().ok())
+                });
+                match name.trim() {
+                    "coretemp" => {
+                        // One hwmon per package; `Package id P` names it
+                        let package = labels
+                            .iter()
+                            .find_map(|(_, l)| l.strip_prefix("Package id ")?.parse::<u32>().ok())
+                            .unwrap_or(0);
+                        for (input, label) in &labels {
+                            let Some(core) = label.strip_prefix("Core ").and_then(|c| c.parse::<u32>().ok()) else {
+                                continue;
+                            };
+                            let cpus: Vec<usize> = topology
+                                .iter()
+                                .filter(|(_, p, c)| *p == package && *c == core)
+                                .map(|(cpu, _, _)| *cpu)
+                                .collect();
+                            if !cpus.is_empty() {
+                                sensors.push((input.clone(), cpus));
+                            }
+                        }
+                    }
+                    "k10temp" => {
+                        let package = k10_package;
+                        k10_package += 1;
+                        let ccds: Vec<&PathBuf> =
+                            labels.iter().filter(|(_, l)| l.starts_with("Tccd")).map(|(input, _)| input).collect();
+                        if ccds.is_empty() {
+                            continue;
+                        }
+                        let mut cores: Vec<u32> =
+                            topology.iter().filter(|(_, p, _)| *p == package).map(|(_, _, c)| *c).collect();
+                        cores.sort_unstable();
+                        cores.dedup();
+                        let per_ccd = cores.len().div_ceil(ccds.len()).max(1);
+                        for (input, chunk) in ccds.into_iter().zip(cores.chunks(per_ccd)) {
+                            let cpus = topology
+                                .iter()
+                                .filter(|(_, p, c)| *p == package && chunk.contains(c))
+                                .map(|(cpu, _, _)| *cpu)
+                                .collect();
+                            sensors.push((input.clone(), cpus));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            sensors
+        }
+
+        /// Per logical CPU temperature; `None` for CPUs no sensor covers.
+        fn read_core_temps(sensors: &[(PathBuf, Vec<usize>)], cpu_count: usize) -> Vec<Option<f32>> {
+            let mut temps = vec![None; cpu_count];
+            for (input, cpus) in sensors {
+                let Some(milli) = std::fs::read_to_string(input).ok().and_then(|t| t.trim().parse::<f32>().ok()) else {
+                    continue;
+                };
+                for cpu in cpus {
+                    if let Some(t) = temps.get_mut(*cpu) {
+                        *t = Some(milli / 1000.0);
+                    }
+                }
+            }
+            temps
+        }
+
+        fn read_cluster(policy: &std::path::Path) -> Option<crate::types::CpuCluster> {
+            let read = |name: &str| -> Option<u64> {
+                std::fs::read_to_string(policy.join(name)).ok()?.trim().parse().ok()
+            };
+            let cpus = std::fs::read_to_string(policy.join("related_cpus"))
+                .ok()?
+                .split_whitespace()
+                .filter_map(|c| c.parse().ok())
+                .collect();
+            Some(crate::types::CpuCluster {
+                cpus,
+                cur_khz: read("scaling_cur_freq")?,
+                max_khz: read("scaling_max_freq")?,
+                hw_max_khz: read("cpuinfo_max_freq")?,
+            })
+        }
+
+        /// How often the Pi throttling state is polled; `vcgencmd` is a
+        /// subprocess, so not every full refresh.
+        const THROTTLE_POLL: Duration = Duration::from_secs(2);
+
+        /// Where the Raspberry Pi firmware throttling word comes from.
+        enum ThrottleSource {
+            /// `get_throttled` exposed by the firmware driver on newer kernels.
+            Sysfs(PathBuf),
+            Vcgencmd,
+        }
+
+        impl ThrottleSource {
+            fn detect() -> Option<Self> {
+                let model = std::fs::read_to_string("/proc/device-tree/model").unwrap_or_default();
+                if !model.contains("Raspberry Pi") {
+                    return None;
+                }
+                let sysfs = PathBuf::from("/sys/devices/platform/soc/soc:firmware/get_throttled");
+                if sysfs.exists() {
+                    Some(Self::Sysfs(sysfs))
+                } else {
+                    Some(Self::Vcgencmd)
+                }
+            }
+
+            fn read(&self) -> Option<crate::types::PiThrottle> {
+                let raw = match self {
+                    Self::Sysfs(path) => std::fs::read_to_string(path).ok()?,
+                    Self::Vcgencmd => {
+                        let out = std::process::Command::new("vcgencmd").arg("get_throttled").output().ok()?;
+                        // "throttled=0x50005"
+                        String::from_utf8_lossy(&out.stdout).trim().strip_prefix("throttled=")?.to_string()
+                    }
+                };
+                let raw = raw.trim();
+                let bits = match raw.strip_prefix("0x") {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => u32::from_str_radix(raw, 16).ok()?,
+                };
+                Some(crate::types::PiThrottle(bits))
+            }
+        }
+
+        /// How often the sysfs thermal zone directory is listed to notice
+        /// added or removed zones.
+        const ZONE_RESCAN: Duration = Duration::from_secs(60);
+
+        /// A sysfs thermal zone discovered at scan time; only `temp` is re-read.
+        struct ThermalZone {
+            name: String,
+            label: String,
+            temp_path: PathBuf,
+            critical: Option<f32>,
+        }
+
+        /// Names of the `thermal_zone*` directories in sysfs, sorted.
+        fn thermal_zone_names() -> Vec<String> {
+            let Ok(entries) = std::fs::read_dir("/sys/devices/virtual/thermal") else {
+                return Vec::new();
+            };
+            let mut names: Vec<String> = entries
+                .flatten()
+                .filter_map(|e| e.file_name().to_str().map(str::to_string))
+                .filter(|n| n.starts_with("thermal_zone"))
+                .collect();
+            names.sort();
+            names
+        }
+
+        /// Reads the labels and trip points of the named sysfs thermal zones
+        /// (GPU, CPU, SoC) once.
+        fn scan_thermal_zones(names: Vec<String>) -> Vec<ThermalZone> {
+            names
+                .into_iter()
+                .map(|name| {
+                    let path = PathBuf::from("/sys/devices/virtual/thermal").join(&name);
+                    ThermalZone {
+                        label: std::fs::read_to_string(path.join("type"))
+                            .unwrap_or_default()
+                            .trim()
+                            .to_string(),
+                        temp_path: path.join("temp"),
+                        critical: read_critical_trip(&path),
+                        name,
+                    }
+                })
+                .collect()
+        }
+
+        /// The zone's `critical` trip point in °C, falling back to `hot`.
+        /// Trip points are static, so they are read only at scan time.
+        fn read_critical_trip(zone: &std::path::Path) -> Option<f32> {
+            let mut hot = None;
+            for i in 0.. {
+                let Ok(kind) = std::fs::read_to_string(zone.join(format!("trip_point_{}_type", i))) else {
+                    break;
+                };
+                let temp = std::fs::read_to_string(zone.join(format!("trip_point_{}_temp", i)))
+                    .ok()
+                    .and_then(|t| t.trim().parse::<f32>().ok())
+                    .filter(|t| *t > 0.0)
+                    .map(|t| t / 1000.0);
+                match kind.trim() {
+                    "critical" if temp.is_some() => return temp,
+                    "hot" => hot = hot.or(temp),
+                    _ => {}
+                }
+            }
+            hot
+        }
+
+        /// Reads the current temperature of each cached zone in parallel.
+        /// Zones that can't be read right now (some firmware zones return
+        /// `ENODATA` or `EIO`) are skipped; the flag is set if a zone has
+        /// vanished, so the caller can rescan.
+        fn read_thermal_zones(zones: &[ThermalZone]) -> (Vec<crate::types::ThermalInfo>, bool) {
+            let vanished = std::sync::atomic::AtomicBool::new(false);
+            let thermals = zones
+                .par_iter()
+                .filter_map(|zone| {
+                    let raw = std::fs::read_to_string(&zone.temp_path)
+                        .inspect_err(|e| {
+                            if e.kind() == std::io::ErrorKind::NotFound {
+                                vanished.store(true, std::sync::atomic::Ordering::Relaxed);
+                            }
+                        })
+                        .ok()?;
+                    let t = raw.trim().parse::<f32>().ok()?;
+                    Some(crate::types::ThermalInfo {
+                        id: zone.name.clone(),
+                        label: zone.label.clone(),
+                        raw_label: zone.label.clone(),
+                        temp_celsius: t / 1000.0,
+                        critical_celsius: zone.critical,
+                    })
+                })
+                .collect();
+            (thermals, vanished.into_inner())
+        }
+
+        impl Default for Collector {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl Collector {
+            pub fn new() -> Self {
+                let mut sys = System::new_all();
+                sys.refresh_all();
+                let networks = Networks::new_with_refreshed_list();
+                let components = Components::new_with_refreshed_list();
+                let disks = Disks::new_with_refreshed_list();
+                let cpufreq_policies = scan_cpufreq_policies(sys.cpus().len());
+                let throttle_cpus = throttle_sample_cpus(sys.cpus().len());
+                let hw_max_khz = if cpufreq_policies.is_empty() {
+                    (0..sys.cpus().len())
+                        .map(|cpu| read_sysfs_u64(&format!("/sys/devices/system/cpu/cpu{}/cpufreq/cpuinfo_max_freq", cpu)))
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+                Self {
+                    sys, networks, components, disks,
+                    tick: 0,
+                    process_every: 4, // default: every 4th tick = 1/s
+                    last_was_full: false,
+                    last_disk_io: crate::types::DiskIOStats { read_bytes: 0, write_bytes: 0 },
+                    last_processes: Vec::new(),
+                    last_thermals: Vec::new(),
+                    last_filesystems: Vec::new(),
+                    mountinfo: File::open("/proc/self/mountinfo").ok(),
+                    mount_changes: Vec::new(),
+                    known_interfaces: None,
+                    known_disks: None,
+                    block_bytes: HashMap::new(),
+                    device_changes: Vec::new(),
+                    last_memory: crate::types::MemoryBreakdown::default(),
+                    thermal_zones: Vec::new(),
+                    zones_scanned: None,
+                    links: HashMap::new(),
+                    throttle_source: ThrottleSource::detect(),
+                    throttle_read: None,
+                    last_throttle: None,
+                    cpufreq_policies,
+                    core_sensors: scan_core_sensors(),
+                    last_core_temps: Vec::new(),
+                    last_clusters: Vec::new(),
+                    last_throttle_counts: None,
+                    throttle_cpus,
+                    hw_max_khz,
+                    last_freq_limits: Vec::new(),
+                    idle_states: IdleStates::scan(),
+                    last_cstates: Vec::new(),
+                    last_runnable: Vec::new(),
+                    last_run_queue: None,
+                    last_schedstat: None,
+                    last_sched_wait: None,
+                    last_batteries: Vec::new(),
+                    last_clock: None,
+                    users: Users::new(),
+                    users_read: None,
+                    processes_read: None,
+                    read_priority: false,
+                    read_cgroup: false,
+                    read_wakeups: false,
+                    net_exclude: Vec::new(),
+                    #[cfg(feature = "ebpf")]
+                    socket_bytes: None,
+                    last_timeslices: HashMap::new(),
+                    proc_files: ProcFiles::new(),
+                }
+            }
+
+            /// New throttle events since the previous call, then intel_pstate
+            /// and cpufreq settings that cap the frequency. Policy caps are left
+            /// to the cluster lines when those are shown.
+            fn read_freq_limits(&mut self) -> Vec<crate::types::FreqLimit> {
+                use crate::types::FreqLimit;
+                let mut limits = Vec::new();
+                let counts = read_throttle_counts(&self.throttle_cpus);
+                if let (Some(now), Some(before)) = (counts, self.last_throttle_counts) {
+                    if now[0] > before[0] {
+                        limits.push(FreqLimit::Thermal { package: false });
+                    }
+                    if now[1] > before[1] {
+                        limits.push(FreqLimit::Thermal { package: true });
+                    }
+                    if now[2] > before[2] || now[3] > before[3] {
+                        limits.push(FreqLimit::PowerLimit);
+                    }
+                }
+                self.last_throttle_counts = counts;
+
+                if read_sysfs_u64("/sys/devices/system/cpu/intel_pstate/no_turbo") == Some(1) {
+                    limits.push(FreqLimit::TurboOff);
+                }
+                if let Some(pct) = read_sysfs_u64("/sys/devices/system/cpu/intel_pstate/max_perf_pct").filter(|p| *p < 100) {
+                    limits.push(FreqLimit::MaxPerf(pct as u32));
+                }
+                if self.cpufreq_policies.is_empty() {
+                    let cap = self
+                        .hw_max_khz
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(cpu, hw_max)| {
+                            let hw_max = (*hw_max)?;
+                            let max = read_sysfs_u64(&format!("/sys/devices/system/cpu/cpu{}/cpufreq/scaling_max_freq", cpu))?;
+                            (max < hw_max).then_some((max, hw_max))
+                        })
+                        .min();
+                    if let Some((max_khz, hw_max_khz)) = cap {
+                        limits.push(FreqLimit::PolicyCap { max_khz, hw_max_khz });
+                    }
+                }
+                limits
+            }
+
+            /// Whether the mount table changed since the last call. The kernel
+            /// flags `/proc/self/mountinfo` with POLLPRI on every mount, unmount
+            /// and remount, and polling clears the flag.
+            fn mounts_changed(&self) -> bool {
+                let Some(ref file) = self.mountinfo else { return false };
+                let mut fd = libc::pollfd { fd: file.as_raw_fd(), events: libc::POLLPRI, revents: 0 };
+                // SAFETY: one valid pollfd and a zero timeout.
+                let ready = unsafe { libc::poll(&mut fd, 1, 0) };
+                ready > 0 && fd.revents & (libc::POLLPRI | libc::POLLERR) != 0
+            }
+
+            /// Rescans the mount list and records which mount points came and went.
+            fn refresh_filesystems(&mut self) {
+                self.disks.refresh(true);
+                let mut filesystems: Vec<crate::types::FilesystemUsage> = self
+                    .disks
+                    .iter()
+                    .map(|d| crate::types::FilesystemUsage {
+                        // sysinfo only checks the flag when it first sees a
+                        // mount, which misses a later remount
+                        read_only: statvfs(&d.mount_point().to_string_lossy()).map_or(d.is_read_only(), |s| s.2),
+                        mount: d.mount_point().to_string_lossy().to_string(),
+                        device: d.name().to_string_lossy().to_string(),
+                        fs_type: d.file_system().to_string_lossy().to_string(),
+                        total: d.total_space(),
+                        available: d.available_space(),
+                    })
+                    .collect();
+                filesystems.sort_by(|a, b| a.mount.cmp(&b.mount));
+                // Nothing to compare against on the first scan
+                if !self.last_filesystems.is_empty() {
+                    let had = |list: &[crate::types::FilesystemUsage], mount: &str| list.iter().any(|fs| fs.mount == mount);
+                    for fs in &filesystems {
+                        if !had(&self.last_filesystems, &fs.mount) {
+                            self.mount_changes.push(crate::types::MountChange::Mounted(fs.mount.clone()));
+                        }
+                    }
+                    for fs in &self.last_filesystems {
+                        if !had(&filesystems, &fs.mount) {
+                            self.mount_changes.push(crate::types::MountChange::Unmounted(fs.mount.clone()));
+                        }
+                    }
+                }
+                self.last_filesystems = filesystems;
+            }
+
+            pub fn collect(&mut self) -> crate::types::SystemMetrics {
+                // Cheap — every tick (250ms)
+                self.sys.refresh_cpu_usage();
+                self.sys.refresh_memory();
+                // Drop unplugged interfaces; new ones are picked up either way
+                self.networks.refresh(true);
+
+                // Expensive — every Nth tick (configurable)
+                let full = self.tick.is_multiple_of(self.process_every);
+                let mounts_changed = self.mounts_changed();
+                self.mount_changes.clear();
+                self.device_changes.clear();
+                let included = |name: &&String| !interface_excluded(&self.net_exclude, name);
+                let interfaces_changed = match &self.known_interfaces {
+                    None => true,
+                    Some(known) => {
+                        self.networks.keys().filter(included).count() != known.len()
+                            || self.networks.keys().filter(included).any(|name| known.binary_search(name).is_err())
+                    }
+                };
+                if interfaces_changed {
+                    let mut names: Vec<String> = self.networks.keys().filter(included).cloned().collect();
+                    names.sort();
+                    self.device_changes.extend(diff_devices("interface", &mut self.known_interfaces, names));
+                    self.links.retain(|name, _| self.networks.contains_key(name));
+                }
+                if full {
+                    self.sys.refresh_processes_specifics(
+                        sysinfo::ProcessesToUpdate::All,
+                        true,
+                        ProcessRefreshKind::nothing()
+                            .with_memory()
+                            .with_cpu()
+                            .with_disk_usage()
+                            .with_cmd(UpdateKind::OnlyIfNotSet)
+                            .with_exe(UpdateKind::OnlyIfNotSet)
+                            .with_user(UpdateKind::OnlyIfNotSet)
+                            .with_tasks(),
+                    );
+                    // Re-read the user list for new UIDs, at most once a minute
+                    // so UIDs without a passwd entry don't cause a read every refresh.
+                    let unknown_uid = || {
+                        self.sys.processes().values().any(|p| p.user_id().is_some_and(|u| self.users.get_user_by_id(u).is_none()))
+                    };
+                    if self.users_read.is_none_or(|t| t.elapsed() >= USERS_REREAD) && unknown_uid() {
+                        self.users.refresh();
+                        self.users_read = Some(Instant::now());
+                    }
+                    self.components.refresh(false);
+                    self.refresh_filesystems();
+                    self.device_changes.extend(diff_devices("disk", &mut self.known_disks, read_block_devices()));
+                    // sysinfo skips tmpfs, so those come from /proc/mounts
+                    let meminfo = std::fs::read_to_string("/proc/meminfo").unwrap_or_default();
+                    self.last_memory = crate::types::MemoryBreakdown {
+                        shmem: meminfo_bytes(&meminfo, "Shmem").unwrap_or(0),
+                        tmpfs: read_tmpfs_mounts(),
+                        ksm: read_ksm(),
+                        thp: read_thp(&meminfo),
+                    };
+
+                    self.last_clusters = self.cpufreq_policies.iter().filter_map(|p| read_cluster(p)).collect();
+                    self.last_freq_limits = self.read_freq_limits();
+                    self.last_cstates = self.idle_states.read();
+                    self.last_runnable = count_runnable(&self.proc_files, self.sys.cpus().len());
+                    self.last_core_temps = read_core_temps(&self.core_sensors, self.sys.cpus().len());
+                    self.last_run_queue = read_loadavg_run_queue();
+                    let schedstat = read_schedstat();
+                    if let (Some(now), Some(before)) = (schedstat, self.last_schedstat) {
+                        let slices = now.1.saturating_sub(before.1);
+                        if slices > 0 {
+                            self.last_sched_wait = Some(now.0.saturating_sub(before.0) as f64 / slices as f64 / 1e6);
+                        }
+                    }
+                    self.last_schedstat = schedstat;
+                    self.last_batteries = crate::battery::read_all();
+                    self.last_clock = read_clock_sync();
+
+                    if let Some(ref source) = self.throttle_source {
+                        if self.throttle_read.is_none_or(|t| t.elapsed() >= THROTTLE_POLL) {
+                            self.last_throttle = source.read();
+                            self.throttle_read = Some(Instant::now());
+                        }
+                    }
+                } else if mounts_changed {
+                    self.refresh_filesystems();
+                    self.last_memory.tmpfs = read_tmpfs_mounts();
+                }
+                self.tick = self.tick.wrapping_add(1);
+                self.last_was_full = full;
+
+                // CPU
+                let cpu: Vec<crate::types::CpuCoreUsage> = self
+                    .sys
+                    .cpus()
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, cpu)| crate::types::CpuCoreUsage {
+                        core_id: idx,
+                        usage_percent: cpu.cpu_usage(),
+                        runnable: self.last_runnable.get(idx).copied().unwrap_or(0),
+                        temp_celsius: self.last_core_temps.get(idx).copied().flatten(),
+                    })
+                    .collect();
+
+                // RAM
+                let ram = crate::types::RamSwapUsage {
+                    used: self.sys.used_memory(),
+                    total: self.sys.total_memory(),
+                };
+
+                let swap = crate::types::RamSwapUsage {
+                    used: self.sys.used_swap(),
+                    total: self.sys.total_swap(),
+                };
+
+                // Read every tick so the sparkline catches short bursts
+                let meminfo = std::fs::read_to_string("/proc/meminfo").unwrap_or_default();
+                let dirty = crate::types::DirtyPages {
+                    dirty: meminfo_bytes(&meminfo, "Dirty").unwrap_or(0),
+                    writeback: meminfo_bytes(&meminfo, "Writeback").unwrap_or(0),
+                };
+
+                // Per-device disk traffic, every tick like the network counters
+                let counters = crate::blockdev::byte_counters();
+                let block_io: Vec<crate::types::DeviceTraffic> = counters
+                    .iter()
+                    .map(|(name, read, write)| {
+                        let (read_before, write_before) = self.block_bytes.get(name).copied().unwrap_or((*read, *write));
+                        crate::types::DeviceTraffic {
+                            name: name.clone(),
+                            read_bytes: read.saturating_sub(read_before),
+                            write_bytes: write.saturating_sub(write_before),
+                        }
+                    })
+                    .collect();
+                self.block_bytes = counters.into_iter().map(|(name, read, write)| (name, (read, write))).collect();
+
+                // Network
+                let mut net_recv = 0u64;
+                let mut net_trans = 0u64;
+                let mut interfaces = Vec::with_capacity(self.networks.len());
+                for (name, data) in &self.networks {
+                    if interface_excluded(&self.net_exclude, name) {
+                        continue;
+                    }
+                    net_recv += data.received();
+                    net_trans += data.transmitted();
+                    if full || !self.links.contains_key(name) {
+                        self.links.insert(name.clone(), crate::types::LinkInfo::read(name));
+                    }
+                    interfaces.push(crate::types::InterfaceStats {
+                        name: name.clone(),
+                        received_bytes: data.received(),
+                        transmitted_bytes: data.transmitted(),
+                        link: self.links[name].clone(),
+                    });
+                }
+                interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+                let network = crate::types::NetworkStats {
+                    received_bytes: net_recv,
+                    transmitted_bytes: net_trans,
+                    interfaces,
+                };
+
+                // Disk I/O, Thermals, Processes — only on full refresh.
+                // The process walk and sysfs reads are independent, so they run
+                // on the rayon pool side by side.
+                let (thermals, processes) = if full {
+                    if self.zones_scanned.is_none_or(|t| t.elapsed() >= ZONE_RESCAN) {
+                        let names = thermal_zone_names();
+                        if !names.iter().eq(self.thermal_zones.iter().map(|z| &z.name)) {
+                            self.thermal_zones = scan_thermal_zones(names);
+                        }
+                        self.zones_scanned = Some(Instant::now());
+                    }
+                    let sys = &self.sys;
+                    let zones = &self.thermal_zones;
+                    let users = &self.users;
+                    let read_priority = self.read_priority;
+                    let read_cgroup = self.read_cgroup;
+                    let files = &self.proc_files;
+                    let now = Instant::now();
+                    let secs = self.processes_read.map_or(0.0, |t| now.duration_since(t).as_secs_f64());
+                    self.processes_read = Some(now);
+                    let rate = |bytes: u64| if secs > 0.0 { (bytes as f64 / secs) as u64 } else { 0 };
+                    let ((sysfs_thermals, disk_io), processes) = rayon::join(
+                        || {
+                            rayon::join(|| read_thermal_zones(zones), || {
+                                let (read_bytes, write_bytes) = sys
+                                    .processes()
+                                    .par_iter()
+                                    .map(|(_, p)| {
+                                        let usage = p.disk_usage();
+                                        (usage.read_bytes, usage.written_bytes)
+                                    })
+                                    .reduce(|| (0, 0), |a, b| (a.0 + b.0, a.1 + b.1));
+                                crate::types::DiskIOStats { read_bytes, write_bytes }
+                            })
+                        },
+                        || {
+                            let mut processes: Vec<crate::types::ProcessInfo> = sys
+                                .processes()
+                                .par_iter()
+                                .map(|(pid, process)| crate::types::ProcessInfo {
+                                    pid: pid.as_u32() as i32,
+                                    ppid: process.parent().map(|p| p.as_u32() as i32),
+                                    name: process.name().to_string_lossy().to_string(),
+                                    cmd: process
+                                        .cmd()
+                                        .iter()
+                                        .map(|a| a.to_string_lossy())
+                                        .collect::<Vec<_>>()
+                                        .join(" "),
+                                    cpu_percent: process.cpu_usage(),
+                                    mem_bytes: process.memory(),
+                                    thread: process.thread_kind() == Some(sysinfo::ThreadKind::Userland),
+                                    user: process
+                                        .user_id()
+                                        .map(|uid| match users.get_user_by_id(uid) {
+                                            Some(u) => u.name().to_string(),
+                                            None => uid.to_string(),
+                                        })
+                                        .unwrap_or_default(),
+                                    threads: process.tasks().map_or(0, |t| t.len().max(1) as u32),
+                                    read_rate: rate(process.disk_usage().read_bytes),
+                                    write_rate: rate(process.disk_usage().written_bytes),
+                                    priority: if read_priority { read_sched_priority(files, pid.as_u32()) } else { None },
+                                    unit: if read_cgroup {
+                                        files
+                                            .read(PathBuf::from(format!("/proc/{}/cgroup", pid.as_u32())))
+                                            .map(|c| crate::types::cgroup_owner(&c))
+                                            .unwrap_or_default()
+                                    } else {
+                                        String::new()
+                                    },
+                                    wakeups: 0.0,
+                                    net_rx_rate: 0,
+                                    net_tx_rate: 0,
+                                })
+                                .collect();
+                            processes.par_sort_unstable_by(|a, b| {
+                                b.cpu_percent
+                                    .partial_cmp(&a.cpu_percent)
+                                    .unwrap_or(Ordering::Equal)
+                            });
+                            processes
+                        },
+                    );
+                    self.last_disk_io = disk_io;
+                    let mut processes = processes;
+                    if self.read_wakeups {
+                        let counts: Vec<Option<u64>> = processes.par_iter().map(|p| timeslices(&self.proc_files, p.pid, p.thread)).collect();
+                        let mut next = HashMap::with_capacity(counts.len());
+                        for (p, count) in processes.iter_mut().zip(counts) {
+                            let Some(count) = count else { continue };
+                            if let Some(before) = self.last_timeslices.get(&p.pid).filter(|_| secs > 0.0) {
+                                p.wakeups = count.saturating_sub(*before) as f64 / secs;
+                            }
+                            next.insert(p.pid, count);
+                        }
+                        self.last_timeslices = next;
+                    } else {
+                        self.last_timeslices.clear();
+                    }
+                    #[cfg(feature = "ebpf")]
+                    if let Some(ref mut counters) = self.socket_bytes {
+                        // Counted per thread group, so thread rows stay blank
+                        let pids = processes.iter().filter(|p| !p.thread).map(|p| p.pid).collect();
+                        let rates = counters.sample(&pids);
+                        for p in processes.iter_mut().filter(|p| !p.thread) {
+                            if let Some(&(rx, tx)) = rates.get(&p.pid) {
+                                (p.net_rx_rate, p.net_tx_rate) = (rx, tx);
+                            }
+                        }
+                    }
+                    self.proc_files.sweep();
+                    let (mut thermals, vanished) = sysfs_thermals;
+                    if vanished {
+                        // A zone disappeared; rediscover on the next full refresh
+                        self.zones_scanned = None;
+                    }
+
+                    // hwmon sensors via sysinfo
+                    thermals.extend(self.components.iter().filter_map(|c| {
+                        Some(crate::types::ThermalInfo {
+                            id: c.label().to_string(),
+                            label: c.label().to_string(),
+                            raw_label: c.label().to_string(),
+                            temp_celsius: c.temperature()?,
+                            critical_celsius: c.critical(),
+                        })
+                    }));
+
+                    self.last_thermals = thermals.clone();
+                    self.last_processes = processes.clone();
+                    (thermals, processes)
+                } else {
+                    (self.last_thermals.clone(), self.last_processes.clone())
+                };
+                let disk_io = self.last_disk_io.clone();
+
+                crate::types::SystemMetrics {
+                    cpu,
+                    ram,
+                    swap,
+                    network,
+                    disk_io,
+                    block_io,
+                    processes,
+                    thermals,
+                    filesystems: self.last_filesystems.clone(),
+                    memory: self.last_memory.clone(),
+                    dirty,
+                    pi_throttle: self.last_throttle,
+                    clusters: self.last_clusters.clone(),
+                    freq_limits: self.last_freq_limits.clone(),
+                    cstates: self.last_cstates.clone(),
+                    run_queue: self.last_run_queue,
+                    sched_wait_ms: self.last_sched_wait,
+                    batteries: self.last_batteries.clone(),
+                    clock: self.last_clock,
+                }
+            }
+        }
+}
+
+pub mod widgets {
+        use ratatui::{
+            buffer::Buffer,
+            layout::{Rect, Layout, Constraint, Margin},
+            style::{Style, Color, Modifier},
+            text::{Line, Span},
+            symbols,
+            widgets::{
+                Axis, Block, Borders, Chart, Clear, Dataset, Gauge, GraphType, Paragraph, Row, Table, TableState, Cell,
+                Sparkline, Scrollbar, ScrollbarOrientation, ScrollbarState, StatefulWidget, Widget,
+            },
+        };
+        use crate::compare::{MetricHistory, Unit};
+
+        use crate::types::{
+            ClockSync,
+            CpuCluster,
+            FreqLimit,
+            CpuCoreUsage,
+            CStateResidency,
+            DirtyPages,
+            FilesystemUsage,
+            InterfaceStats,
+            MemoryBreakdown,
+            Percentiles,
+            PiThrottle,
+            RamSwapUsage,
+            ProcessInfo,
+            SortOrder,
+            ThermalInfo,
+            ProcessFilter,
+            keep_order,
+            process_tree,
+            visible_processes,
+        };
+        use crate::config::{HighlightConfig, NetworkConfig, NvmeConfig, ProcessColumn, TempAlertConfig, Theme, Truncate};
+        use crate::report::{decimal, human_bytes};
+        use crate::battery::BatteryInfo;
+        use crate::guests::Guest;
+        use crate::hwhealth::{Dimm, MceMonitor, Severity};
+        use crate::rapl::DomainPower;
+        use crate::sessions::Session;
+        #[cfg(feature = "perf")]
+        use crate::perf::PerfRates;
+        #[cfg(feature = "ebpf")]
+        use crate::ebpf::{bucket_limit, DeviceLatency};
+        use crate::config::CpuView;
+        use crate::sysdetails::SystemDetails;
+        use crate::units::FailedUnit;
+        use crate::connections::{Connection, Resolver, Services};
+        use crate::diag::Diagnostics;
+        use crate::interrupts::{InterruptStats, IrqRate};
+        use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+        use crate::blockdev::DeviceIo;
+        use crate::nvme::NvmeHealth;
+        use crate::latency::{Reachability, TargetStats};
+        use crate::panels::PanelOutput;
+        use crate::journal::JournalOutput;
+        use crate::procinfo::{DetailTab, ProcessDetail};
+        use crate::ups::UpsStatus;
+        use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+        /// Recolours a drawn frame for `theme`. Widgets pick colours for a
+        /// dark background; on a light one, white and light grey text vanish
+        /// and yellow, cyan and green wash out.
+        pub fn apply_theme(theme: Theme, buf: &mut Buffer) {
+            if theme == Theme::Dark {
+                return;
+            }
+            let fg = |color: Color| match color {
+                Color::White => Color::Black,
+                Color::Gray => Color::DarkGray,
+                Color::Yellow | Color::LightYellow => Color::Rgb(0xaf, 0x87, 0x00),
+                Color::Cyan | Color::LightCyan => Color::Rgb(0x00, 0x87, 0x87),
+                Color::Green | Color::LightGreen => Color::Rgb(0x00, 0x87, 0x00),
+                Color::LightRed => Color::Red,
+                Color::LightBlue => Color::Blue,
+                Color::LightMagenta => Color::Magenta,
+                other => other,
+            };
+            for cell in buf.content.iter_mut() {
+                cell.fg = fg(cell.fg);
+                // Highlight bars: a light grey still sets the row apart
+                if cell.bg == Color::DarkGray {
+                    cell.bg = Color::Gray;
+                }
+            }
+        }
+
+        /// Compact CPU widget: single average gauge + per-core summary text.
+        pub struct CpuWidget<'a> {
+            pub data: &'a [CpuCoreUsage],
+            pub clusters: &'a [CpuCluster],
+            pub cstates: &'a [CStateResidency],
+            /// System-wide `(runnable, total)`; also enables per-core counts.
+            pub run_queue: Option<(u32, u32)>,
+            pub sched_wait_ms: Option<f64>,
+            /// Hypervisor or container, shown at the right of the title.
+            pub virt: Option<String>,
+            pub freq_limits: &'a [FreqLimit],
+            pub view: CpuView,
+            /// Page of cores to show when they don't all fit; `None` condenses
+            /// runs of cores into one entry instead.
+            pub page: Option<usize>,
+        }
+
+        /// One core, or a run of consecutive cores condensed into one entry.
+        struct CoreCell {
+            label: String,
+            cores: u32,
+            /// Mean over the run.
+            usage_percent: f32,
+            /// Total over the run.
+            runnable: u32,
+            /// Hottest in the run.
+            temp_celsius: Option<f32>,
+        }
+
+        impl CoreCell {
+            fn single(core: &CpuCoreUsage) -> Self {
+                Self {
+                    label: core.core_id.to_string(),
+                    cores: 1,
+                    usage_percent: core.usage_percent,
+                    runnable: core.runnable,
+                    temp_celsius: core.temp_celsius,
+                }
+            }
+
+            /// `first-last` with the run's figures.
+            fn group(cores: &[CpuCoreUsage]) -> Self {
+                let (first, last) = (&cores[0], &cores[cores.len() - 1]);
+                if cores.len() == 1 {
+                    return Self::single(first);
+                }
+                Self {
+                    label: format!("{}-{}", first.core_id, last.core_id),
+                    cores: cores.len() as u32,
+                    usage_percent: cores.iter().map(|c| c.usage_percent).sum::<f32>() / cores.len() as f32,
+                    runnable: cores.iter().map(|c| c.runnable).sum(),
+                    temp_celsius: cores.iter().filter_map(|c| c.temp_celsius).max_by(f32::total_cmp),
+                }
+            }
+        }
+
+        /// How the CPU panel fits its cores.
+        enum CoreFit {
+            All,
+            /// Too many cores for one entry each: `per_page` of them fit a
+            /// page, or every core fits in runs of `group`.
+            Condensed { per_page: usize, group: usize },
+        }
+
+        impl<'a> CpuWidget<'a> {
+            pub fn new(data: &'a [CpuCoreUsage]) -> Self {
+                Self {
+                    data,
+                    clusters: &[],
+                    cstates: &[],
+                    run_queue: None,
+                    sched_wait_ms: None,
+                    virt: None,
+                    freq_limits: &[],
+                    view: CpuView::Text,
+                    page: None,
+                }
+            }
+
+            pub fn with_page(mut self, page: Option<usize>) -> Self {
+                self.page = page;
+                self
+            }
+
+            pub fn with_view(mut self, view: CpuView) -> Self {
+                self.view = view;
+                self
+            }
+
+            pub fn with_freq_limits(mut self, limits: &'a [FreqLimit]) -> Self {
+                self.freq_limits = limits;
+                self
+            }
+
+            pub fn with_virt(mut self, label: Option<String>) -> Self {
+                self.virt = label;
+                self
+            }
+
+            pub fn with_sched_wait(mut self, sched_wait_ms: Option<f64>) -> Self {
+                self.sched_wait_ms = sched_wait_ms;
+                self
+            }
+
+            pub fn with_run_queue(mut self, run_queue: Option<(u32, u32)>) -> Self {
+                self.run_queue = run_queue;
+                self
+            }
+
+            pub fn with_cstates(mut self, cstates: &'a [CStateResidency]) -> Self {
+                self.cstates = cstates;
+                self
+            }
+
+            pub fn with_clusters(mut self, clusters: &'a [CpuCluster]) -> Self {
+                self.clusters = clusters;
+                self
+            }
+
+            fn usage_color(percent: f32) -> Color {
+                if percent > 80.0 {
+                    Color::Red
+                } else if percent > 40.0 {
+                    Color::Yellow
+                } else {
+                    Color::Green
+                }
+            }
+
+            /// Runnable count and temperature after a cell's usage, when shown.
+            fn cell_extras(&self, cell: &CoreCell, temps: bool) -> Vec<Span<'static>> {
+                let mut spans = Vec::new();
+                // More than one runnable thread means work is queueing
+                if self.run_queue.is_some() {
+                    let queue_color = if cell.runnable > cell.cores { Color::Red } else { Color::DarkGray };
+                    spans.push(Span::styled(format!(" {}r", cell.runnable), Style::default().fg(queue_color)));
+                }
+                if temps {
+                    let (text, color) = match cell.temp_celsius {
+                        Some(t) if t >= 85.0 => (format!(" {:>2.0}°", t), Color::Red),
+                        Some(t) if t >= 70.0 => (format!(" {:>2.0}°", t), Color::Yellow),
+                        Some(t) => (format!(" {:>2.0}°", t), Color::DarkGray),
+                        None => ("    ".to_string(), Color::DarkGray),
+                    };
+                    spans.push(Span::styled(text, Style::default().fg(color)));
+                }
+                spans
+            }
+
+            /// Cells per line: up to four text figures, or as many bars as fit
+            /// at 14 columns plus the label.
+            fn per_row(&self, width: usize, label_width: usize, temps: bool) -> usize {
+                let extras = if self.run_queue.is_some() { 3 } else { 0 } + if temps { 4 } else { 0 };
+                match self.view {
+                    // `label: 12%`, the extras, then ` | `
+                    CpuView::Text => ((width + 3) / (label_width + 5 + extras + 3)).clamp(1, 4),
+                    CpuView::Bars => ((width + 1) / (label_width + 14 + extras + 1)).max(1),
+                }
+            }
+
+            /// Whether every core gets its own entry in `area`, and if not how
+            /// many fit per page and how many to condense into one entry.
+            fn fit(&self, area: Rect) -> CoreFit {
+                let temps = self.data.iter().any(|c| c.temp_celsius.is_some());
+                let width = area.width.saturating_sub(2) as usize;
+                // Below the gauge and inside the border
+                let height = area.height.saturating_sub(5) as usize;
+                let fixed = self.clusters.len() + usize::from(!self.freq_limits.is_empty()) + usize::from(!self.cstates.is_empty());
+                let rows = height.saturating_sub(fixed).max(1);
+                let cores = self.data.len();
+                let label_width = |cells: &[CoreCell]| cells.iter().map(|c| c.label.len()).max().unwrap_or(0).max(2);
+                let singles: Vec<CoreCell> = self.data.iter().map(CoreCell::single).collect();
+                let per_row = self.per_row(width, label_width(&singles), temps);
+                if cores.div_ceil(per_row) <= rows {
+                    return CoreFit::All;
+                }
+                // One row goes to the spread line
+                let rows = rows.saturating_sub(1).max(1);
+                let per_page = rows * per_row;
+                let mut group = cores.div_ceil(per_page).next_power_of_two();
+                while group < cores {
+                    let groups: Vec<CoreCell> = self.data.chunks(group).map(CoreCell::group).collect();
+                    if groups.len().div_ceil(self.per_row(width, label_width(&groups), temps)) <= rows {
+                        break;
+                    }
+                    group *= 2;
+                }
+                CoreFit::Condensed { per_page, group }
+            }
+
+            /// Pages of cores `area` shows one at a time; 1 when they all fit.
+            pub fn pages(&self, area: Rect) -> usize {
+                match self.fit(area) {
+                    CoreFit::All => 1,
+                    CoreFit::Condensed { per_page, .. } => self.data.len().div_ceil(per_page),
+                }
+            }
+
+            /// `cores p50 12%  p90 80%  max 100%`, then what the entries below are.
+            fn spread_line(&self, note: String) -> Line<'static> {
+                let mut usage: Vec<f32> = self.data.iter().map(|c| c.usage_percent).collect();
+                usage.sort_by(f32::total_cmp);
+                // Nearest rank
+                let rank = |p: f32| usage[((p * usage.len() as f32).ceil() as usize).clamp(1, usage.len()) - 1];
+                let figure = |p: f32| {
+                    let value = rank(p);
+                    Span::styled(format!("{:.0}%  ", value), Style::default().fg(Self::usage_color(value)))
+                };
+                Line::from(vec![
+                    Span::styled("cores ", Style::default().fg(Color::Cyan)),
+                    Span::raw("p50 "),
+                    figure(0.50),
+                    Span::raw("p90 "),
+                    figure(0.90),
+                    Span::raw("max "),
+                    figure(1.0),
+                    Span::styled(note, Style::default().fg(Color::DarkGray)),
+                ])
+            }
+
+            /// ` 0: 12% |  1: 40%`, up to four cells a line.
+            fn text_lines(&self, cells: &[CoreCell], width: usize, temps: bool) -> Vec<Line<'static>> {
+                let mut lines = Vec::new();
+                let mut spans: Vec<Span> = Vec::new();
+                let label_width = cells.iter().map(|c| c.label.len()).max().unwrap_or(0).max(2);
+                let per_line = self.per_row(width, label_width, temps);
+                for (i, cell) in cells.iter().enumerate() {
+                    if !spans.is_empty() {
+                        spans.push(Span::raw(" | "));
+                    }
+                    spans.push(Span::styled(
+                        format!("{:>label_width$}:{:>3.0}%", cell.label, cell.usage_percent),
+                        Style::default().fg(Self::usage_color(cell.usage_percent)),
+                    ));
+                    spans.extend(self.cell_extras(cell, temps));
+                    if (i + 1) % per_line == 0 {
+                        lines.push(Line::from(std::mem::take(&mut spans)));
+                    }
+                }
+                if !spans.is_empty() {
+                    lines.push(Line::from(spans));
+                }
+                lines
+            }
+
+            /// htop-style ` 0[|||||     12%]` bars, as many columns as fit,
+            /// numbered down each column.
+            fn bar_lines(&self, cells: &[CoreCell], width: usize, temps: bool) -> Vec<Line<'static>> {
+                if cells.is_empty() {
+                    return Vec::new();
+                }
+                let extras = if self.run_queue.is_some() { 3 } else { 0 } + if temps { 4 } else { 0 };
+                let label_width = cells.iter().map(|c| c.label.len()).max().unwrap_or(0).max(2);
+                let columns = self.per_row(width, label_width, temps).clamp(1, cells.len());
+                let rows = cells.len().div_ceil(columns);
+                let cell_width = (width + 1) / columns - 1;
+                // Inside the brackets, with the percentage at the right end
+                let inside = cell_width.saturating_sub(label_width + 2 + extras).max(4);
+                let mut lines = Vec::with_capacity(rows);
+                for row in 0..rows {
+                    let mut spans: Vec<Span> = Vec::new();
+                    for cell in (0..columns).filter_map(|col| cells.get(col * rows + row)) {
+                        if !spans.is_empty() {
+                            spans.push(Span::raw(" "));
+                        }
+                        let percent = format!("{:.0}%", cell.usage_percent);
+                        let room = inside.saturating_sub(percent.len());
+                        let filled =
+                            ((cell.usage_percent.clamp(0.0, 100.0) / 100.0 * inside as f32).round() as usize).min(room);
+                        spans.push(Span::styled(
+                            format!("{:>label_width$}[", cell.label),
+                            Style::default().fg(Color::Cyan),
+                        ));
+                        spans.push(Span::styled(
+                            "|".repeat(filled),
+                            Style::default().fg(Self::usage_color(cell.usage_percent)),
+                        ));
+                        spans.push(Span::raw(format!("{:>w$}]", percent, w = inside - filled)));
+                        spans.extend(self.cell_extras(cell, temps));
+                    }
+                    lines.push(Line::from(spans));
+                }
+                lines
+            }
+
+            /// `cpu 4-7  1.20/2.40 GHz`, red when the governor ceiling sits
+            /// below the hardware maximum.
+            fn cluster_line(c: &CpuCluster) -> Line<'static> {
+                let ghz = |khz: u64| khz as f64 / 1_000_000.0;
+                let capped = c.max_khz < c.hw_max_khz;
+                let mut spans = vec![
+                    Span::styled(format!("cpu {:<6}", c.cpu_list()), Style::default().fg(Color::Cyan)),
+                    Span::raw(format!("{:.2}/{:.2} GHz", ghz(c.cur_khz), ghz(c.hw_max_khz))),
+                ];
+                if capped {
+                    spans.push(Span::styled(
+                        format!(" capped {:.2}", ghz(c.max_khz)),
+                        Style::default().fg(Color::Red),
+                    ));
+                }
+                Line::from(spans)
+            }
+        }
+
+        impl Widget for CpuWidget<'_> {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                if self.data.is_empty() {
+                    return;
+                }
+
+                let avg = self.data.iter().map(|c| c.usage_percent).sum::<f32>()
+                    / self.data.len() as f32;
+
+                let chunks = Layout::vertical([
+                    Constraint::Length(3), // gauge
+                    Constraint::Min(1),    // per-core text
+                ])
+                .split(area);
+
+                let run_queue = self
+                    .run_queue
+                    .map(|(running, total)| format!(" run {}/{}", running, total))
+                    .unwrap_or_default();
+                let wait = self.sched_wait_ms.map(|ms| format!(" wait {:.2}ms", ms)).unwrap_or_default();
+                let mut block = Block::default().borders(Borders::ALL).title(
+                    format!("CPU ({} cores) avg {:.0}%{}{}", self.data.len(), avg, run_queue, wait),
+                );
+                if let Some(ref virt) = self.virt {
+                    block = block.title(Line::styled(virt.as_str(), Style::default().fg(Color::DarkGray)).right_aligned());
+                }
+                let gauge = Gauge::default()
+                    .block(block)
+                    .gauge_style(Style::default().fg(Color::Green))
+                    .percent(avg.min(100.0) as u16);
+                gauge.render(chunks[0], buf);
+
+                let mut lines: Vec<Line> = self.clusters.iter().map(Self::cluster_line).collect();
+                if !self.freq_limits.is_empty() {
+                    // Red while the hardware is throttling, yellow for settings
+                    let mut limited = vec![Span::styled("limited ", Style::default().fg(Color::Cyan))];
+                    for (i, limit) in self.freq_limits.iter().enumerate() {
+                        if i > 0 {
+                            limited.push(Span::raw(", "));
+                        }
+                        let color = if limit.is_event() { Color::Red } else { Color::Yellow };
+                        limited.push(Span::styled(limit.label(), Style::default().fg(color)));
+                    }
+                    lines.push(Line::from(limited));
+                }
+                let temps = self.data.iter().any(|c| c.temp_celsius.is_some());
+                let inner_width = chunks[1].width.saturating_sub(2) as usize;
+                let cells: Vec<CoreCell> = match self.fit(area) {
+                    CoreFit::All => self.data.iter().map(CoreCell::single).collect(),
+                    CoreFit::Condensed { per_page, group } => match self.page {
+                        Some(page) => {
+                            let pages = self.data.len().div_ceil(per_page);
+                            let page = page.min(pages - 1);
+                            lines.push(self.spread_line(format!("page {}/{}", page + 1, pages)));
+                            self.data.iter().skip(page * per_page).take(per_page).map(CoreCell::single).collect()
+                        }
+                        None => {
+                            lines.push(self.spread_line(format!("{} cores per entry", group)));
+                            self.data.chunks(group).map(CoreCell::group).collect()
+                        }
+                    },
+                };
+                match self.view {
+                    CpuView::Text => lines.extend(self.text_lines(&cells, inner_width, temps)),
+                    CpuView::Bars => lines.extend(self.bar_lines(&cells, inner_width, temps)),
+                }
+                if !self.cstates.is_empty() {
+                    let mut idle = vec![Span::styled("idle ", Style::default().fg(Color::Cyan))];
+                    for c in self.cstates {
+                        idle.push(Span::raw(format!("{} {:.0}%  ", c.name, c.percent)));
+                    }
+                    lines.push(Line::from(idle));
+                }
+
+                let para = Paragraph::new(lines)
+                    .block(Block::default().borders(Borders::ALL));
+                para.render(chunks[1], buf);
+            }
+        }
+
+        /// RAM usage gauge, with shared memory and tmpfs usage listed under
+        /// it when the panel is tall enough.
+        pub struct RamGaugeWidget<'a> {
+            pub data: &'a RamSwapUsage,
+            pub breakdown: Option<&'a MemoryBreakdown>,
+        }
+
+        impl<'a> RamGaugeWidget<'a> {
+            pub fn new(data: &'a RamSwapUsage) -> Self {
+                Self { data, breakdown: None }
+            }
+
+            pub fn with_breakdown(mut self, breakdown: &'a MemoryBreakdown) -> Self {
+                self.breakdown = Some(breakdown);
+                self
+            }
+
+            /// Lines under the gauge: hugepage and KSM figures, the Shmem total,
+            /// then tmpfs mounts by usage. Empty mounts are left out except
+            /// /dev/shm and /tmp.
+            fn breakdown_lines(&self, width: usize) -> Vec<Line<'static>> {
+                let Some(breakdown) = self.breakdown else {
+                    return Vec::new();
+                };
+                let row = |label: &str, value: String, style: Style| {
+                    let room = width.saturating_sub(value.len() + 1).max(1);
+                    let label: String = if label.chars().count() > room {
+                        let tail: String = label.chars().rev().take(room.saturating_sub(1)).collect();
+                        format!("…{}", tail.chars().rev().collect::<String>())
+                    } else {
+                        label.to_string()
+                    };
+                    Line::from(vec![
+                        Span::styled(format!("{:<room$} ", label), style),
+                        Span::raw(value),
+                    ])
+                };
+                let mut mounts: Vec<&FilesystemUsage> = breakdown
+                    .tmpfs
+                    .iter()
+                    .filter(|m| m.used() > 0 || m.mount == "/dev/shm" || m.mount == "/tmp")
+                    .collect();
+                mounts.sort_by(|a, b| b.used().cmp(&a.used()).then_with(|| a.mount.cmp(&b.mount)));
+                let tmpfs: u64 = breakdown.tmpfs.iter().map(FilesystemUsage::used).sum();
+                let dim = Style::default().fg(Color::DarkGray);
+                let mut lines = Vec::new();
+                if let Some(ref thp) = breakdown.thp {
+                    lines.push(row("THP anon", crate::report::human_bytes(thp.anon_bytes), Style::default()));
+                    lines.push(row(
+                        " collapsed/scans",
+                        format!("{}/{}", thp.collapsed, thp.full_scans),
+                        dim,
+                    ));
+                }
+                if let Some(ksm) = breakdown.ksm {
+                    let label = if ksm.running { "KSM saved" } else { "KSM saved (stopped)" };
+                    lines.push(row(label, crate::report::human_bytes(ksm.saved_bytes()), Style::default()));
+                    lines.push(row(
+                        " shared/sharing",
+                        format!("{}/{}", ksm.pages_shared, ksm.pages_sharing),
+                        dim,
+                    ));
+                }
+                lines.push(row("Shared", crate::report::human_bytes(breakdown.shmem), Style::default()));
+                lines.push(row("tmpfs", crate::report::human_bytes(tmpfs), Style::default()));
+                lines.extend(
+                    mounts
+                        .into_iter()
+                        .map(|m| row(&format!(" {}", m.mount), crate::report::human_bytes(m.used()), dim)),
+                );
+                lines
+            }
+        }
+
+        impl Widget for RamGaugeWidget<'_> {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                let percent = if self.data.total > 0 {
+                    (self.data.used as f64 / self.data.total as f64 * 100.0) as u16
+                } else {
+                    0
+                };
+                let used_gib = self.data.used as f64 / (1024.0 * 1024.0 * 1024.0);
+                let total_gib = self.data.total as f64 / (1024.0 * 1024.0 * 1024.0);
+                let decimals = crate::report::format().gib_decimals;
+                let mut block = Block::default().borders(Borders::ALL).title(
+                    format!("RAM {:.*}/{:.*} GiB", decimals, used_gib, decimals, total_gib),
+                );
+                if let Some(thp) = self.breakdown.and_then(|b| b.thp.as_ref()) {
+                    // `always` is the setting databases warn about
+                    let color = if thp.mode == "always" { Color::Yellow } else { Color::DarkGray };
+                    block = block.title(
+                        Line::styled(format!("THP {}", thp.mode), Style::default().fg(color)).right_aligned(),
+                    );
+                }
+                let inner = block.inner(area);
+                block.render(area, buf);
+                let mut lines = self.breakdown_lines(inner.width as usize);
+                // Keep at least three rows of gauge
+                lines.truncate(inner.height.saturating_sub(3) as usize);
+                let chunks = Layout::default()
+                    .direction(ratatui::layout::Direction::Vertical)
+                    .constraints([Constraint::Min(1), Constraint::Length(lines.len() as u16)])
+                    .split(inner);
+                let gauge = Gauge::default()
+                    .gauge_style(Style::default().fg(Color::Cyan))
+                    .percent(percent);
+                gauge.render(chunks[0], buf);
+                if !lines.is_empty() {
+                    Paragraph::new(lines).render(chunks[1], buf);
+                }
+            }
+        }
+
+        /// Thermal sensors table with color-coded temperatures.
+        pub struct ThermalWidget<'a> {
+            pub data: &'a [ThermalInfo],
+            /// Per-sensor limits; matching sensors are colored against their
+            /// own limit instead of the global 65/85°C bands.
+            pub rules: &'a [TempAlertConfig],
+            pub pi_throttle: Option<PiThrottle>,
+            /// Explains an empty panel under virtualization.
+            pub virt_note: Option<&'static str>,
+        }
+
+        impl<'a> ThermalWidget<'a> {
+            pub fn new(data: &'a [ThermalInfo]) -> Self {
+                Self { data, rules: &[], pi_throttle: None, virt_note: None }
+            }
+
+            pub fn with_virt_note(mut self, note: Option<&'static str>) -> Self {
+                self.virt_note = note;
+                self
+            }
+
+            pub fn with_pi_throttle(mut self, pi_throttle: Option<PiThrottle>) -> Self {
+                self.pi_throttle = pi_throttle;
+                self
+            }
+
+            /// One row per throttling state: red for active, dim for past.
+            fn throttle_rows(&self) -> Vec<Row<'static>> {
+                let Some(t) = self.pi_throttle else {
+                    return Vec::new();
+                };
+                let (current, past) = (t.current(), t.past());
+                if current.is_empty() && past.is_empty() {
+                    return vec![Row::new(vec![Cell::from("Pi power"), Cell::from("OK").style(Style::default().fg(Color::Green))])];
+                }
+                let mut rows = Vec::new();
+                if !current.is_empty() {
+                    rows.push(Row::new(vec![
+                        Cell::from("Pi now"),
+                        Cell::from(current.join(", ")).style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+                    ]));
+                }
+                if !past.is_empty() {
+                    rows.push(Row::new(vec![
+                        Cell::from("Pi since boot"),
+                        Cell::from(past.join(", ")).style(Style::default().fg(Color::Yellow)),
+                    ]));
+                }
+                rows
+            }
+
+            pub fn with_rules(mut self, rules: &'a [TempAlertConfig]) -> Self {
+                self.rules = rules;
+                self
+            }
+        }
+
+        impl Widget for ThermalWidget<'_> {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                if self.data.is_empty() && self.pi_throttle.is_none() {
+                    let block = Block::default().borders(Borders::ALL).title("Thermals");
+                    let mut lines = vec![Line::from("No sensors found")];
+                    if let Some(note) = self.virt_note {
+                        lines.push(Line::styled(note, Style::default().fg(Color::DarkGray)));
+                    }
+                    let para = Paragraph::new(lines)
+                        .wrap(ratatui::widgets::Wrap { trim: true })
+                        .block(block);
+                    para.render(area, buf);
+                    return;
+                }
+
+                let mut rows: Vec<Row> = self.throttle_rows();
+                let value_width = self
+                    .pi_throttle
+                    .map(|t| t.current().join(", ").len().max(t.past().join(", ").len()))
+                    .unwrap_or(0)
+                    .max(16) as u16;
+                rows.extend(self.data.iter().map(|t| {
+                    let (hot, warm) = TempAlertConfig::levels(self.rules, &[&t.raw_label, &t.label]);
+                    let color = if t.temp_celsius > hot {
+                        Color::Red
+                    } else if t.temp_celsius > warm {
+                        Color::Yellow
+                    } else {
+                        Color::Green
+                    };
+                    let crit_str = match t.critical_celsius {
+                        Some(c) => format!("/{:.0}°C", c),
+                        None => String::new(),
+                    };
+                    Row::new(vec![
+                        Cell::from(t.label.clone()),
+                        Cell::from(format!("{:.1}°C{}", t.temp_celsius, crit_str))
+                            .style(Style::default().fg(color)),
+                    ])
+                }));
+
+                let widths = [Constraint::Min(12), Constraint::Length(value_width)];
+                let table = Table::new(rows, widths)
+                    .block(Block::default().borders(Borders::ALL).title("Thermals"));
+
+                Widget::render(table, area, buf);
+            }
+        }
+
+        /// Network sparkline with RX/TX history and per-interface link state.
+        pub struct NetworkSparklineWidget<'a> {
+            pub rx: &'a VecDeque<u64>,
+            pub tx: &'a VecDeque<u64>,
+            /// Interface shown in the titles; `None` means all interfaces.
+            pub iface: Option<&'a str>,
+            pub interfaces: &'a [InterfaceStats],
+            /// Aliases shown in place of the kernel's names.
+            pub network: Option<&'a NetworkConfig>,
+            /// RX/TX bytes per interface since startup.
+            pub totals: Option<&'a BTreeMap<String, (u64, u64)>>,
+            /// Quota `(used, allowed)` bytes for the current period.
+            pub quota: Option<(u64, u64)>,
+            pub quota_warn_percent: f64,
+            pub reachability: Option<Reachability>,
+        }
+
+        impl<'a> NetworkSparklineWidget<'a> {
+            pub fn new(
+                (rx, tx): (&'a VecDeque<u64>, &'a VecDeque<u64>),
+                iface: Option<&'a str>,
+                interfaces: &'a [InterfaceStats],
+            ) -> Self {
+                Self {
+                    rx,
+                    tx,
+                    iface,
+                    interfaces,
+                    network: None,
+                    totals: None,
+                    quota: None,
+                    quota_warn_percent: 80.0,
+                    reachability: None,
+                }
+            }
+
+            pub fn with_reachability(mut self, reachability: Option<Reachability>) -> Self {
+                self.reachability = reachability;
+                self
+            }
+
+            pub fn with_aliases(mut self, network: &'a NetworkConfig) -> Self {
+                self.network = Some(network);
+                self
+            }
+
+            fn alias(&self, iface: &'a str) -> &'a str {
+                self.network.map_or(iface, |n| n.alias(iface))
+            }
+
+            pub fn with_totals(
+                mut self,
+                totals: &'a BTreeMap<String, (u64, u64)>,
+                quota: Option<(u64, u64)>,
+                quota_warn_percent: f64,
+            ) -> Self {
+                self.totals = Some(totals);
+                self.quota = quota;
+                self.quota_warn_percent = quota_warn_percent;
+                self
+            }
+        }
+
+        /// Compact byte count for narrow panels: `512`, `3.4K`, `1.2G`.
+        fn short_bytes(bytes: u64) -> String {
+            const UNITS: [&str; 5] = ["", "K", "M", "G", "T"];
+            match crate::report::scale_bytes(bytes) {
+                (_, 0, _) => format!("{}", bytes),
+                (value, unit, decimals) => format!("{:.*}{}", decimals, value, UNITS[unit]),
+            }
+        }
+
+        fn link_line(i: &InterfaceStats, name: &str, total: Option<&(u64, u64)>) -> Line<'static> {
+            let (mark, color) = match i.link.operstate.as_str() {
+                "up" => ("▲", Color::Green),
+                "down" | "lowerlayerdown" | "notpresent" => ("▼", Color::Red),
+                _ => ("●", Color::DarkGray),
+            };
+            let speed = match i.link.speed_mbps {
+                Some(s) if s >= 1000 && s % 1000 == 0 => format!("{}G", s / 1000),
+                Some(s) if s >= 1000 => format!("{:.1}G", s as f32 / 1000.0),
+                Some(s) => format!("{}M", s),
+                None => String::new(),
+            };
+            Line::from(vec![
+                Span::styled(format!("{} ", mark), Style::default().fg(color)),
+                Span::raw(format!("{} ", name)),
+                Span::styled(speed, Style::default().fg(Color::DarkGray)),
+                Span::styled(
+                    total.map(|(rx, tx)| format!(" ↓{} ↑{}", short_bytes(*rx), short_bytes(*tx))).unwrap_or_default(),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ])
+        }
+
+        impl Widget for NetworkSparklineWidget<'_> {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                let links_height = if self.interfaces.is_empty() {
+                    0
+                } else {
+                    (self.interfaces.len() as u16 + 2).min(area.height / 3)
+                };
+                let chunks = Layout::vertical([
+                    Constraint::Fill(1),
+                    Constraint::Fill(1),
+                    Constraint::Length(links_height),
+                ]).split(area);
+
+                let iface = self.iface.map_or("all", |i| self.alias(i));
+                // History holds bytes per 250ms tick
+                let rate = |series: &VecDeque<u64>| human_bytes(series.back().copied().unwrap_or(0) * 4);
+                let mut rx_block = Block::default().borders(Borders::ALL).title(format!("RX ({}) {}/s", iface, rate(self.rx)));
+                if let Some(ref r) = self.reachability {
+                    let mark = |label: &str, up: bool| {
+                        let (text, color) = if up { ("up", Color::Green) } else { ("DOWN", Color::Red) };
+                        [
+                            Span::styled(format!(" {} ", label), Style::default().fg(Color::DarkGray)),
+                            Span::styled(text, Style::default().fg(color).add_modifier(Modifier::BOLD)),
+                        ]
+                    };
+                    let mut spans = Vec::new();
+                    if let Some(up) = r.gateway_up {
+                        spans.extend(mark("gw", up));
+                    }
+                    if let Some(up) = r.target_up {
+                        spans.extend(mark("net", up));
+                    }
+                    rx_block = rx_block.title(Line::from(spans).right_aligned());
+                }
+                let rx_spark = Sparkline::default()
+                    .block(rx_block)
+                    .data(self.rx)
+                    .style(Style::default().fg(Color::Green));
+                rx_spark.render(chunks[0], buf);
+
+                let tx_spark = Sparkline::default()
+                    .block(Block::default().borders(Borders::ALL).title(format!("TX ({}) {}/s", iface, rate(self.tx))))
+                    .data(self.tx)
+                    .style(Style::default().fg(Color::Yellow));
+                tx_spark.render(chunks[1], buf);
+
+                if links_height > 0 {
+                    let lines: Vec<Line> = self
+                        .interfaces
+                        .iter()
+                        .map(|i| link_line(i, self.alias(&i.name), self.totals.and_then(|t| t.get(&i.name))))
+                        .collect();
+                    let mut block = Block::default().borders(Borders::ALL).title("Links");
+                    if let Some((used, quota)) = self.quota {
+                        let pct = used as f64 / quota.max(1) as f64 * 100.0;
+                        let color = if pct >= 100.0 { Color::Red } else if pct >= self.quota_warn_percent { Color::Yellow } else { Color::DarkGray };
+                        block = block.title(
+                            Line::from(Span::styled(
+                                format!("{}/{} {:.0}%", short_bytes(used), short_bytes(quota), pct),
+                                Style::default().fg(color),
+                            ))
+                            .right_aligned(),
+                        );
+                    }
+                    let para = Paragraph::new(lines).block(block);
+                    para.render(chunks[2], buf);
+                }
+            }
+        }
+
+        /// Disk I/O sparklines with history.
+        pub struct DiskIOSparkWidget<'a> {
+            pub read: &'a VecDeque<u64>,
+            pub write: &'a VecDeque<u64>,
+            /// Block device shown in the titles; `None` means all devices.
+            pub device: Option<&'a str>,
+            /// Dirty plus writeback history and the latest split, drawn as a
+            /// third sparkline.
+            pub dirty: Option<(&'a VecDeque<u64>, DirtyPages)>,
+        }
+
+        impl<'a> DiskIOSparkWidget<'a> {
+            pub fn new((read, write): (&'a VecDeque<u64>, &'a VecDeque<u64>), device: Option<&'a str>) -> Self {
+                Self { read, write, device, dirty: None }
+            }
+
+            pub fn with_dirty(mut self, history: &'a VecDeque<u64>, current: DirtyPages) -> Self {
+                self.dirty = Some((history, current));
+                self
+            }
+        }
+
+        impl Widget for DiskIOSparkWidget<'_> {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                let chunks = if self.dirty.is_some() {
+                    Layout::vertical([Constraint::Ratio(1, 3); 3]).split(area)
+                } else {
+                    Layout::vertical([
+                        Constraint::Percentage(50),
+                        Constraint::Percentage(50),
+                    ]).split(area)
+                };
+
+                // History holds bytes per 250ms tick
+                let rate = |series: &VecDeque<u64>| human_bytes(series.back().copied().unwrap_or(0) * 4);
+                let device = self.device.unwrap_or("all");
+                let read_spark = Sparkline::default()
+                    .block(Block::default().borders(Borders::ALL).title(format!("Read ({}) {}/s", device, rate(self.read))))
+                    .data(self.read)
+                    .style(Style::default().fg(Color::Blue));
+                read_spark.render(chunks[0], buf);
+
+                let write_spark = Sparkline::default()
+                    .block(Block::default().borders(Borders::ALL).title(format!("Write ({}) {}/s", device, rate(self.write))))
+                    .data(self.write)
+                    .style(Style::default().fg(Color::Magenta));
+                write_spark.render(chunks[1], buf);
+
+                if let Some((history, current)) = self.dirty {
+                    let dirty_spark = Sparkline::default()
+                        .block(Block::default().borders(Borders::ALL).title(format!(
+                            "Dirty {} WB {}",
+                            human_bytes(current.dirty),
+                            human_bytes(current.writeback),
+                        )))
+                        .data(history)
+                        .style(Style::default().fg(Color::Yellow));
+                    dirty_spark.render(chunks[2], buf);
+                }
+            }
+        }
+
+        /// Selection and scroll position of the process table, kept between
+        /// frames so the rows don't jump while the selection moves.
+        #[derive(Default)]
+        pub struct ProcessTableState {
+            /// Selected row, as an index into the visible (filtered, sorted) list.
+            pub table: TableState,
+            /// Scrollable columns (everything after Name) skipped on the left.
+            pub hscroll: usize,
+        }
+
+        impl ProcessTableState {
+            pub fn selected(&self) -> Option<usize> {
+                self.table.selected()
+            }
+
+            pub fn select(&mut self, row: Option<usize>) {
+                self.table.select(row);
+            }
+        }
+
+        /// Process table.
+        pub struct ProcessTableWidget<'a> {
+            pub data: &'a [ProcessInfo],
+            pub sort_order: SortOrder,
+            /// Case-insensitive substring match on the process name.
+            pub filter: Option<&'a ProcessFilter>,
+            /// Why the last `:filter` pattern was rejected.
+            pub filter_error: Option<&'a str>,
+            /// Names of scripted columns appended after MEM.
+            pub extra_columns: &'a [String],
+            /// Scripted column values keyed by PID.
+            pub extra_values: Option<&'a HashMap<i32, Vec<String>>>,
+            /// PID drawn in a distinct color (sysmon-tui itself).
+            pub own_pid: Option<i32>,
+            /// Collapsed PIDs when shown as a tree; `None` for a flat list.
+            pub tree: Option<&'a HashSet<i32>>,
+            /// Where names too long for the column are cut.
+            pub truncate: Truncate,
+            /// Optional built-in columns, placed before scripted ones.
+            pub columns: &'a [ProcessColumn],
+            /// Row order (PIDs) to hold instead of sorting.
+            pub frozen: Option<&'a [i32]>,
+            /// CPU/MEM coloring bands; `None` leaves cells uncolored.
+            pub highlight: Option<&'a HighlightConfig>,
+            /// Total RAM, for the memory bands.
+            pub ram_total: u64,
+        }
+
+        impl<'a> ProcessTableWidget<'a> {
+            pub fn new(data: &'a [ProcessInfo], sort_order: SortOrder, filter: Option<&'a ProcessFilter>) -> Self {
+                Self {
+                    data,
+                    sort_order,
+                    filter,
+                    filter_error: None,
+                    extra_columns: &[],
+                    extra_values: None,
+                    own_pid: None,
+                    tree: None,
+                    truncate: Truncate::End,
+                    columns: &[],
+                    frozen: None,
+                    highlight: None,
+                    ram_total: 0,
+                }
+            }
+
+            pub fn with_highlight(mut self, highlight: &'a HighlightConfig, ram_total: u64) -> Self {
+                self.highlight = highlight.enabled.then_some(highlight);
+                self.ram_total = ram_total;
+                self
+            }
+
+            /// Band color for a process's CPU and memory use.
+            fn colors(&self, cpu: f32, mem: u64) -> (Option<Color>, Option<Color>) {
+                let Some(h) = self.highlight else {
+                    return (None, None);
+                };
+                let band = |value: f64, warn: f64, critical: f64| {
+                    if value > critical {
+                        Some(Color::Red)
+                    } else if value > warn {
+                        Some(Color::Yellow)
+                    } else {
+                        None
+                    }
+                };
+                let mem_percent = mem as f64 / self.ram_total.max(1) as f64 * 100.0;
+                (
+                    band(cpu as f64, h.cpu_warn as f64, h.cpu_critical as f64),
+                    band(mem_percent, h.mem_warn_percent, h.mem_critical_percent),
+                )
+            }
+
+            pub fn with_frozen(mut self, order: Option<&'a [i32]>) -> Self {
+                self.frozen = order;
+                self
+            }
+
+            pub fn with_columns(mut self, columns: &'a [ProcessColumn]) -> Self {
+                self.columns = columns;
+                self
+            }
+
+            pub fn with_truncate(mut self, truncate: Truncate) -> Self {
+                self.truncate = truncate;
+                self
+            }
+
+            pub fn with_filter_error(mut self, error: Option<&'a str>) -> Self {
+                self.filter_error = error;
+                self
+            }
+
+            pub fn with_tree(mut self, collapsed: Option<&'a HashSet<i32>>) -> Self {
+                self.tree = collapsed;
+                self
+            }
+
+            pub fn with_own_pid(mut self, pid: Option<i32>) -> Self {
+                self.own_pid = pid;
+                self
+            }
+
+            pub fn with_extra_columns(mut self, names: &'a [String], values: &'a HashMap<i32, Vec<String>>) -> Self {
+                self.extra_columns = names;
+                self.extra_values = Some(values);
+                self
+            }
+        }
+
+        /// Shortens `text` to at most `width` terminal cells, marking the cut
+        /// with `…` at the end or, for `Truncate::Middle`, between the kept head
+        /// and tail (so both ends of a path stay readable).
+        pub fn truncate(text: &str, width: usize, mode: Truncate) -> String {
+            if text.width() <= width {
+                return text.to_string();
+            }
+            if width == 0 {
+                return String::new();
+            }
+            // Characters from `chars` while they fit in `cells`; a wide
+            // character that would straddle the limit is left out.
+            fn fit(chars: impl Iterator<Item = char>, cells: usize) -> Vec<char> {
+                let mut used = 0;
+                chars
+                    .take_while(|c| {
+                        used += c.width().unwrap_or(0);
+                        used <= cells
+                    })
+                    .collect()
+            }
+            let keep = width - 1;
+            match mode {
+                Truncate::End => fit(text.chars(), keep).into_iter().chain(std::iter::once('…')).collect(),
+                Truncate::Middle => {
+                    let tail = keep / 2;
+                    let mut out: String = fit(text.chars(), keep - tail).into_iter().collect();
+                    out.push('…');
+                    out.extend(fit(text.chars().rev(), tail).into_iter().rev());
+                    out
+                }
+            }
+        }
+
+        /// A process row before its name is fitted to the column.
+        struct TableLine<'a> {
+            process: &'a ProcessInfo,
+            /// Tree indentation and fold marker.
+            prefix: String,
+            /// Count of folded descendants.
+            suffix: String,
+            /// Subtree totals, for tree parents.
+            totals: Option<(f32, u64)>,
+        }
+
+        /// Narrowest the Name column gets before other columns are pushed off
+        /// to the right (reachable with horizontal scrolling).
+        const MIN_NAME_WIDTH: u16 = 16;
+
+        /// Width of the PID column.
+        const PID_WIDTH: u16 = 8;
+
+        impl StatefulWidget for ProcessTableWidget<'_> {
+            type State = ProcessTableState;
+
+            fn render(self, area: Rect, buf: &mut Buffer, state: &mut ProcessTableState) {
+                let lines: Vec<TableLine> = match self.tree {
+                    Some(collapsed) => process_tree(self.data, self.sort_order, self.filter, collapsed)
+                        .into_iter()
+                        .map(|r| {
+                            let marker = match (r.has_children, r.hidden > 0) {
+                                (true, true) => "▸ ",
+                                (true, false) => "▾ ",
+                                _ => "  ",
+                            };
+                            TableLine {
+                                process: r.process,
+                                prefix: format!("{}{}", "  ".repeat(r.depth), marker),
+                                suffix: if r.hidden > 0 { format!(" (+{})", r.hidden) } else { String::new() },
+                                totals: r.has_children.then_some((r.total_cpu, r.total_mem)),
+                            }
+                        })
+                        .collect(),
+                    None => {
+                        let mut list = visible_processes(self.data, self.sort_order, self.filter);
+                        if let Some(order) = self.frozen {
+                            keep_order(&mut list, order);
+                        }
+                        list
+                    }
+                    .into_iter()
+                        .map(|p| TableLine { process: p, prefix: String::new(), suffix: String::new(), totals: None })
+                        .collect(),
+                };
+
+                // Sorting by wakeups brings their column along.
+                let mut optional = self.columns.to_vec();
+                if self.sort_order == SortOrder::Wakeups && !optional.contains(&ProcessColumn::Wakeups) {
+                    optional.insert(0, ProcessColumn::Wakeups);
+                }
+                let mut columns: Vec<(String, u16)> = vec![("CPU%".into(), 8), ("MEM".into(), 11)];
+                if self.tree.is_some() {
+                    columns.extend([("ΣCPU%".into(), 8), ("ΣMEM".into(), 11)]);
+                }
+                let fixed = columns.len();
+                columns.extend(optional.iter().map(|c| (c.header().to_string(), c.width())));
+                // All sorts are descending; the tree orders siblings by subtree
+                // total for CPU and memory.
+                let tree_offset = if self.tree.is_some() { 2 } else { 0 };
+                let sorted = match self.sort_order {
+                    SortOrder::Cpu => tree_offset,
+                    SortOrder::Mem => 1 + tree_offset,
+                    SortOrder::Wakeups => fixed + optional.iter().position(|c| *c == ProcessColumn::Wakeups).unwrap_or(0),
+                };
+                columns[sorted].0.push_str(" ▼");
+                columns.extend(self.extra_columns.iter().map(|n| (n.clone(), u16::try_from(n.width()).unwrap_or(u16::MAX).max(8))));
+
+                // Take columns from the scroll offset while Name keeps its
+                // minimum; Name then gets whatever the taken columns, their
+                // one-cell gaps and the borders leave.
+                let start = state.hscroll.min(columns.len().saturating_sub(1));
+                let room = area.width.saturating_sub(PID_WIDTH + MIN_NAME_WIDTH + 3);
+                let mut used: u16 = 0;
+                let mut end = start;
+                while end < columns.len() && used.saturating_add(columns[end].1) < room {
+                    used += columns[end].1 + 1;
+                    end += 1;
+                }
+                let name_width = area.width.saturating_sub(PID_WIDTH.saturating_add(used).saturating_add(3)).max(MIN_NAME_WIDTH);
+
+                let mut widths = vec![Constraint::Length(PID_WIDTH), Constraint::Length(name_width)];
+                widths.extend(columns[start..end].iter().map(|(_, w)| Constraint::Length(*w)));
+                let mut header = vec!["PID", "Name"];
+                header.extend(columns[start..end].iter().map(|(h, _)| h.as_str()));
+
+                let rows: Vec<Row> = lines
+                    .into_iter()
+                    .map(|TableLine { process: p, prefix, suffix, totals }| {
+                        let budget = (name_width as usize).saturating_sub(prefix.width() + suffix.width());
+                        let name = format!("{}{}{}", prefix, truncate(&p.name, budget, self.truncate), suffix);
+                        let (cpu_color, mem_color) = self.colors(p.cpu_percent, p.mem_bytes);
+                        let mut values = vec![
+                            (format!("{}%", decimal(p.cpu_percent as f64, 1)), cpu_color),
+                            (human_bytes(p.mem_bytes), mem_color),
+                        ];
+                        if self.tree.is_some() {
+                            match totals {
+                                Some((cpu, mem)) => values.extend([(format!("{}%", decimal(cpu as f64, 1)), None), (human_bytes(mem), None)]),
+                                None => values.extend([(String::new(), None), (String::new(), None)]),
+                            }
+                        }
+                        values.extend(optional.iter().map(|c| match c {
+                            ProcessColumn::User => (truncate(&p.user, c.width() as usize, Truncate::End), None),
+                            ProcessColumn::Threads if p.thread => (String::new(), None),
+                            ProcessColumn::Threads => (p.threads.to_string(), None),
+                            ProcessColumn::Io => (format!("{}/{}", short_bytes(p.read_rate), short_bytes(p.write_rate)), None),
+                            ProcessColumn::Cmd => (truncate(&p.cmd, c.width() as usize, self.truncate), None),
+                            // High priority stands out; niced-down work fades.
+                            ProcessColumn::Nice => match p.priority {
+                                Some(pr) if pr.is_realtime() => (format!("{} {}", pr.class(), pr.rt_priority), Some(Color::Magenta)),
+                                Some(pr) if pr.nice < 0 => (format!("{} {}", pr.nice, pr.class()), Some(Color::Magenta)),
+                                Some(pr) if pr.nice > 0 => (format!("{} {}", pr.nice, pr.class()), Some(Color::DarkGray)),
+                                Some(pr) => (format!("{} {}", pr.nice, pr.class()), None),
+                                None => (String::new(), None),
+                            },
+                            ProcessColumn::Unit => (truncate(&p.unit, c.width() as usize, Truncate::Middle), None),
+                            ProcessColumn::Wakeups => (format!("{:.0}", p.wakeups), None),
+                            ProcessColumn::Net if p.thread => (String::new(), None),
+                            ProcessColumn::Net => (format!("{}/{}", short_bytes(p.net_rx_rate), short_bytes(p.net_tx_rate)), None),
+                        }));
+                        if !self.extra_columns.is_empty() {
+                            let extra = self.extra_values.and_then(|v| v.get(&p.pid));
+                            values.extend(
+                                (0..self.extra_columns.len())
+                                    .map(|i| (extra.and_then(|v| v.get(i)).cloned().unwrap_or_default(), None)),
+                            );
+                        }
+                        let mut cells = vec![Cell::from(p.pid.to_string()), Cell::from(name)];
+                        cells.extend(values.drain(start..end).map(|(v, color)| match color {
+                            Some(c) => Cell::from(v).style(Style::default().fg(c)),
+                            None => Cell::from(v),
+                        }));
+                        let row = Row::new(cells);
+                        let row_color = cpu_color.into_iter().chain(mem_color).max_by_key(|c| *c == Color::Red);
+                        match (self.own_pid == Some(p.pid), row_color) {
+                            (true, _) => row.style(Style::default().fg(Color::Cyan)),
+                            (false, Some(c)) if self.highlight.is_some_and(|h| h.rows) => row.style(Style::default().fg(c)),
+                            _ => row,
+                        }
+                    })
+                    .collect();
+                let row_count = rows.len();
+
+                let mut title = vec![Span::raw(match self.filter {
+                    Some(f) => format!("Processes (filter: {})", f.text),
+                    None => "Processes".to_string(),
+                })];
+                if self.tree.is_some() {
+                    title.push(Span::raw(" [tree]"));
+                }
+                if self.frozen.is_some() {
+                    title.push(Span::raw(" [frozen]"));
+                }
+                // Hidden column counts either side of the visible window.
+                let more = Style::default().fg(Color::Cyan);
+                if start > 0 {
+                    title.push(Span::styled(format!(" ◀{}", start), more));
+                }
+                if end < columns.len() {
+                    title.push(Span::styled(format!(" {}▶", columns.len() - end), more));
+                }
+                if let Some(e) = self.filter_error {
+                    title.push(Span::styled(format!(" invalid filter {} ", e), Style::default().fg(Color::White).bg(Color::Red)));
+                }
+                let table = Table::new(rows, widths)
+                    .header(
+                        Row::new(header)
+                            .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                    )
+                    .block(Block::default().borders(Borders::ALL).title(Line::from(title)))
+                    .row_highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD));
+
+                StatefulWidget::render(table, area, buf, &mut state.table);
+                table_scrollbar(buf, area, row_count, state.table.offset());
+            }
+        }
+
+        /// Draws a scrollbar on the right border of a bordered table with a
+        /// header row, beside the body rows only, when `rows` don't all fit.
+        /// `offset` is the first row on screen.
+        fn table_scrollbar(buf: &mut Buffer, area: Rect, rows: usize, offset: usize) {
+            let visible = area.height.saturating_sub(3) as usize;
+            if rows <= visible {
+                return;
+            }
+            let track = area.inner(Margin { vertical: 1, horizontal: 0 });
+            let track = Rect { y: track.y + 1, height: track.height.saturating_sub(1), ..track };
+            let mut state = ScrollbarState::new(rows - visible).viewport_content_length(visible).position(offset);
+            StatefulWidget::render(
+                Scrollbar::new(ScrollbarOrientation::VerticalRight).begin_symbol(None).end_symbol(None),
+                track,
+                buf,
+                &mut state,
+            );
+        }
+
+        /// RTT sparkline and packet loss per latency target.
+        pub struct LatencyWidget {
+            pub targets: Vec<TargetStats>,
+        }
+
+        impl LatencyWidget {
+            pub fn new(targets: Vec<TargetStats>) -> Self {
+                Self { targets }
+            }
+        }
+
+        impl Widget for LatencyWidget {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                let block = Block::default().borders(Borders::ALL).title("Latency");
+                let inner = block.inner(area);
+                block.render(area, buf);
+                if self.targets.is_empty() {
+                    return;
+                }
+                let rows = Layout::vertical(vec![Constraint::Ratio(1, self.targets.len() as u32); self.targets.len()])
+                    .split(inner);
+                for (t, row) in self.targets.iter().zip(rows.iter()) {
+                    let chunks = Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).split(*row);
+                    let loss = t.loss_percent();
+                    let (rtt, color) = match (t.last_rtt(), &t.error) {
+                        (Some(d), _) => (format!("{:.1} ms", d.as_secs_f64() * 1000.0), Color::Green),
+                        (None, Some(e)) => (e.clone(), Color::Red),
+                        (None, None) => ("...".to_string(), Color::DarkGray),
+                    };
+                    let loss_color = if loss > 5.0 { Color::Red } else if loss > 0.0 { Color::Yellow } else { Color::DarkGray };
+                    Widget::render(
+                        Paragraph::new(Line::from(vec![
+                            Span::raw(format!("{} ", t.target)),
+                            Span::styled(rtt, Style::default().fg(color)),
+                            Span::styled(format!("  loss {:.0}%", loss), Style::default().fg(loss_color)),
+                        ])),
+                        chunks[0],
+                        buf,
+                    );
+                    // Lost probes plot as zero
+                    let data: Vec<u64> = t
+                        .samples
+                        .iter()
+                        .map(|s| s.map_or(0, |d| d.as_micros() as u64))
+                        .collect();
+                    let start = data.len().saturating_sub(chunks[1].width as usize);
+                    Widget::render(
+                        Sparkline::default().data(&data[start..]).style(Style::default().fg(Color::Cyan)),
+                        chunks[1],
+                        buf,
+                    );
+                }
+            }
+        }
+
+        /// Charge gauge per battery with health, power draw and time estimate.
+        pub struct BatteryWidget<'a> {
+            pub batteries: &'a [BatteryInfo],
+            /// Averaged draw and the estimate derived from it.
+            pub average_w: Option<f64>,
+            pub remaining: Option<std::time::Duration>,
+        }
+
+        impl Widget for BatteryWidget<'_> {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                let time = match self.remaining {
+                    Some(d) => {
+                        let mins = d.as_secs() / 60;
+                        let what = if self.batteries.iter().any(|b| b.discharging()) { "left" } else { "to full" };
+                        format!(" {}:{:02} {}", mins / 60, mins % 60, what)
+                    }
+                    None => String::new(),
+                };
+                let avg = self.average_w.map(|w| format!(" avg {:.1} W", w)).unwrap_or_default();
+                let block = Block::default().borders(Borders::ALL).title(format!("Battery{}{}", avg, time));
+                let inner = block.inner(area);
+                block.render(area, buf);
+                let rows = Layout::vertical(vec![Constraint::Length(2); self.batteries.len()]).split(inner);
+                for (b, row) in self.batteries.iter().zip(rows.iter()) {
+                    let chunks = Layout::vertical([Constraint::Length(1), Constraint::Length(1)]).split(*row);
+                    let color = if b.capacity_percent < 15.0 {
+                        Color::Red
+                    } else if b.capacity_percent < 30.0 {
+                        Color::Yellow
+                    } else {
+                        Color::Green
+                    };
+                    Widget::render(
+                        Gauge::default()
+                            .gauge_style(Style::default().fg(color))
+                            .label(format!("{} {:.0}%", b.name, b.capacity_percent))
+                            .percent(b.capacity_percent.clamp(0.0, 100.0) as u16),
+                        chunks[0],
+                        buf,
+                    );
+                    let health = b
+                        .health_percent()
+                        .map(|h| format!("  health {:.0}% ({:.1}/{:.1} Wh)", h, b.energy_full_wh, b.energy_design_wh))
+                        .unwrap_or_default();
+                    Widget::render(
+                        Paragraph::new(Line::from(vec![
+                            Span::raw(format!("{} {:.1} W", b.status, b.power_w)),
+                            Span::styled(health, Style::default().fg(Color::DarkGray)),
+                        ])),
+                        chunks[1],
+                        buf,
+                    );
+                }
+            }
+        }
+
+        /// RAPL power per domain: the reading and a one-row sparkline each.
+        pub struct RaplWidget<'a> {
+            pub domains: &'a [DomainPower],
+        }
+
+        impl Widget for RaplWidget<'_> {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                // The platform domain covers everything. Otherwise packages plus
+                // DRAM: cores and uncore are part of their package's figure,
+                // DRAM is not.
+                let total: f64 = match self.domains.iter().find(|d| d.label == "platform") {
+                    Some(platform) => platform.watts,
+                    None => self
+                        .domains
+                        .iter()
+                        .filter(|d| !d.label.contains(' ') || d.label.ends_with(" dram"))
+                        .map(|d| d.watts)
+                        .sum(),
+                };
+                let block = Block::default().borders(Borders::ALL).title(format!("Power (RAPL) {:.1} W", total));
+                let inner = block.inner(area);
+                block.render(area, buf);
+                if self.domains.is_empty() {
+                    Paragraph::new(Line::styled("sampling...", Style::default().fg(Color::DarkGray))).render(inner, buf);
+                    return;
+                }
+                let rows = Layout::vertical(vec![Constraint::Length(1); self.domains.len()]).split(inner);
+                for (d, row) in self.domains.iter().zip(rows.iter()) {
+                    let chunks = Layout::horizontal([Constraint::Length(23), Constraint::Min(1)]).split(*row);
+                    Paragraph::new(format!("{:<13}{:>7.1} W", d.label, d.watts)).render(chunks[0], buf);
+                    let width = chunks[1].width as usize;
+                    let tail = &d.history[d.history.len().saturating_sub(width)..];
+                    Sparkline::default().data(tail).style(Style::default().fg(Color::Yellow)).render(chunks[1], buf);
+                }
+            }
+        }
+
+        /// Hardware health: ECC error counts per DIMM, machine check counters
+        /// and recent hardware errors from the kernel log.
+        pub struct HardwareWidget<'a> {
+            pub dimms: &'a [Dimm],
+            pub mce: &'a MceMonitor,
+        }
+
+        impl Widget for HardwareWidget<'_> {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                let block = Block::default().borders(Borders::ALL).title("Hardware health");
+                let inner = block.inner(area);
+                block.render(area, buf);
+                let table_height = if self.dimms.is_empty() { 0 } else { self.dimms.len() as u16 + 1 };
+                let chunks = Layout::vertical([Constraint::Length(table_height), Constraint::Min(0)]).split(inner);
+                let count = |total: u64, new: u64, color: Color| {
+                    let text = if new > 0 { format!("{} (+{})", total, new) } else { total.to_string() };
+                    let style = if total > 0 { Style::default().fg(color) } else { Style::default().fg(Color::DarkGray) };
+                    Cell::from(text).style(style)
+                };
+                let rows: Vec<Row> = self
+                    .dimms
+                    .iter()
+                    .map(|d| {
+                        Row::new(vec![
+                            Cell::from(d.label.clone()),
+                            count(d.correctable, d.new_correctable, Color::Yellow),
+                            count(d.uncorrectable, d.new_uncorrectable, Color::Red),
+                        ])
+                    })
+                    .collect();
+                let widths = [Constraint::Fill(1), Constraint::Length(14), Constraint::Length(14)];
+                let table = Table::new(rows, widths).header(
+                    Row::new(vec!["DIMM", "Corrected", "Uncorrected"])
+                        .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                );
+                Widget::render(table, chunks[0], buf);
+
+                let mut lines = Vec::new();
+                if let Some(c) = self.mce.counts.filter(|c| c.total() > 0) {
+                    let new = self.mce.new_counts();
+                    let color = if new > 0 { Color::Red } else { Color::Yellow };
+                    lines.push(Line::from(vec![
+                        Span::styled("Machine checks ", Style::default().fg(Color::Cyan)),
+                        Span::styled(
+                            format!(
+                                "{} exceptions, {} threshold, {} deferred{}",
+                                c.exceptions,
+                                c.threshold,
+                                c.deferred,
+                                if new > 0 { format!(" (+{} since start)", new) } else { String::new() },
+                            ),
+                            Style::default().fg(color),
+                        ),
+                    ]));
+                }
+                // Newest first
+                for e in self.mce.events.iter().rev() {
+                    let color = match e.severity {
+                        Severity::Fatal => Color::Red,
+                        Severity::Error => Color::Yellow,
+                        Severity::Corrected => Color::DarkGray,
+                    };
+                    let time = chrono::DateTime::<chrono::Local>::from(e.time).format("%m-%d %H:%M:%S");
+                    lines.push(Line::from(vec![
+                        Span::styled(format!("{} ", time), Style::default().fg(Color::DarkGray)),
+                        Span::styled(e.message.clone(), Style::default().fg(color)),
+                    ]));
+                }
+                Paragraph::new(lines).render(chunks[1], buf);
+            }
+        }
+
+        /// Running KVM guests: vCPU load and memory per QEMU process.
+        pub struct GuestsWidget<'a> {
+            pub guests: &'a [Guest],
+        }
+
+        impl Widget for GuestsWidget<'_> {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                let rows: Vec<Row> = self
+                    .guests
+                    .iter()
+                    .map(|g| {
+                        let per_vcpu = g.vcpu_percent / g.vcpus.max(1) as f64;
+                        let color = if per_vcpu > 80.0 {
+                            Color::Red
+                        } else if per_vcpu > 40.0 {
+                            Color::Yellow
+                        } else {
+                            Color::Green
+                        };
+                        let memory = match g.assigned {
+                            Some(assigned) => format!("{} / {}", human_bytes(g.resident), human_bytes(assigned)),
+                            None => human_bytes(g.resident),
+                        };
+                        Row::new(vec![
+                            Cell::from(g.name.clone()),
+                            Cell::from(g.pid.to_string()),
+                            Cell::from(g.vcpus.to_string()),
+                            Cell::from(format!("{:.0}%", g.vcpu_percent)).style(Style::default().fg(color)),
+                            Cell::from(memory),
+                        ])
+                    })
+                    .collect();
+                let widths = [
+                    Constraint::Fill(1),
+                    Constraint::Length(8),
+                    Constraint::Length(5),
+                    Constraint::Length(6),
+                    Constraint::Length(23),
+                ];
+                let table = Table::new(rows, widths)
+                    .header(
+                        Row::new(vec!["Guest", "PID", "vCPU", "CPU%", "Resident / assigned"])
+                            .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                    )
+                    .block(Block::default().borders(Borders::ALL).title(format!("KVM guests ({})", self.guests.len())));
+                Widget::render(table, area, buf);
+            }
+        }
+
+        /// Logged-in users, as `w` lists them.
+        pub struct SessionsWidget<'a> {
+            pub sessions: &'a [Session],
+        }
+
+        impl Widget for SessionsWidget<'_> {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                let rows: Vec<Row> = self
+                    .sessions
+                    .iter()
+                    .map(|s| {
+                        let login = s
+                            .login
+                            .map(|t| chrono::DateTime::<chrono::Local>::from(t).format("%m-%d %H:%M").to_string())
+                            .unwrap_or_default();
+                        // Like `w`: blank under a minute, then minutes, hours, days
+                        let idle = match s.idle.map(|d| d.as_secs()) {
+                            Some(secs) if secs >= 86400 => format!("{}d", secs / 86400),
+                            Some(secs) if secs >= 3600 => format!("{}h{:02}m", secs / 3600, secs / 60 % 60),
+                            Some(secs) if secs >= 60 => format!("{}m", secs / 60),
+                            _ => String::new(),
+                        };
+                        Row::new(vec![
+                            Cell::from(s.user.clone()),
+                            Cell::from(s.line.clone()),
+                            Cell::from(if s.host.is_empty() { "local".to_string() } else { s.host.clone() }),
+                            Cell::from(login),
+                            Cell::from(idle),
+                        ])
+                    })
+                    .collect();
+                let widths = [
+                    Constraint::Length(12),
+                    Constraint::Length(8),
+                    Constraint::Fill(1),
+                    Constraint::Length(11),
+                    Constraint::Length(6),
+                ];
+                let users: HashSet<&str> = self.sessions.iter().map(|s| s.user.as_str()).collect();
+                let title = format!("Users ({} logged in, {} sessions)", users.len(), self.sessions.len());
+                let table = Table::new(rows, widths)
+                    .header(
+                        Row::new(vec!["User", "TTY", "From", "Login", "Idle"])
+                            .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                    )
+                    .block(Block::default().borders(Borders::ALL).title(title));
+                Widget::render(table, area, buf);
+            }
+        }
+
+        /// System-wide IPC and cache-miss rate from hardware counters.
+        #[cfg(feature = "perf")]
+        pub struct PerfWidget<'a> {
+            pub rates: Option<PerfRates>,
+            /// Why the counters couldn't be opened.
+            pub error: Option<&'a str>,
+        }
+
+        #[cfg(feature = "perf")]
+        impl Widget for PerfWidget<'_> {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                // 3.2G, 850.0M: event counts rather than bytes, so powers of 1000
+                let si = |v: f64| {
+                    const UNITS: [(f64, &str); 3] = [(1e9, "G"), (1e6, "M"), (1e3, "K")];
+                    match UNITS.iter().find(|(scale, _)| v >= *scale) {
+                        Some((scale, unit)) => format!("{:.1}{}", v / scale, unit),
+                        None => format!("{:.0}", v),
+                    }
+                };
+                let dim = Style::default().fg(Color::DarkGray);
+                let lines = match (self.error, self.rates) {
+                    (Some(e), _) => vec![Line::styled(e.to_string(), dim)],
+                    (None, None) => vec![Line::styled("sampling...", dim)],
+                    (None, Some(r)) => {
+                        let ipc = r.ipc().unwrap_or(0.0);
+                        let miss = r.cache_miss_percent();
+                        let mut lines = vec![
+                            Line::from(vec![Span::raw("IPC         "), Span::styled(format!("{:.2}", ipc), Style::default().add_modifier(Modifier::BOLD))]),
+                            Line::raw(format!("Instr/s     {}", si(r.instructions))),
+                            Line::raw(format!("Cycles/s    {}", si(r.cycles))),
+                        ];
+                        match (miss, r.cache_misses) {
+                            (Some(pct), Some(misses)) => {
+                                let color = if pct >= 10.0 { Color::Yellow } else { Color::Green };
+                                lines.push(Line::from(vec![
+                                    Span::raw("Cache miss  "),
+                                    Span::styled(format!("{:.1}%", pct), Style::default().fg(color)),
+                                    Span::raw(format!(" ({}/s)", si(misses))),
+                                ]));
+                            }
+                            _ => lines.push(Line::styled("Cache miss  not counted", dim)),
+                        }
+                        // Stalled on memory more than executing
+                        if ipc < 1.0 && miss.is_some_and(|p| p >= 10.0) {
+                            lines.push(Line::styled("low IPC, high miss rate: memory-bound", Style::default().fg(Color::Yellow)));
+                        }
+                        lines
+                    }
+                };
+                let para = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Perf counters"));
+                para.render(area, buf);
+            }
+        }
+
+        /// Block I/O latency histograms per device, so tail latency shows
+        /// next to the median.
+        #[cfg(feature = "ebpf")]
+        pub struct BioLatencyWidget<'a> {
+            pub devices: &'a [DeviceLatency],
+            /// No second sample yet.
+            pub sampling: bool,
+        }
+
+        #[cfg(feature = "ebpf")]
+        impl Widget for BioLatencyWidget<'_> {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+                // Bucket bounds are powers of two, so these are approximate
+                let latency = |us: u64| match us {
+                    0..=999 => format!("{}µs", us),
+                    1_000..=999_999 => format!("{}ms", us / 1_000),
+                    _ => format!("{}s", us / 1_000_000),
+                };
+                let dim = Style::default().fg(Color::DarkGray);
+                let lines = match self.devices {
+                    _ if self.sampling => vec![Line::styled("sampling...", dim)],
+                    [] => vec![Line::styled("no block I/O", dim)],
+                    devices => devices
+                        .iter()
+                        .flat_map(|d| {
+                            let p50 = d.percentile(50.0).unwrap_or(0);
+                            let p99 = d.percentile(99.0).unwrap_or(0);
+                            let color = match p99 {
+                                100_000.. => Color::Red,
+                                10_000.. => Color::Yellow,
+                                _ => Color::Green,
+                            };
+                            let summary = Line::from(vec![
+                                Span::styled(format!("{:<8} ", d.name), Style::default().add_modifier(Modifier::BOLD)),
+                                Span::raw(format!("{:>6.0} IO/s  p50 <{:<6} p99 ", d.per_sec, latency(p50))),
+                                Span::styled(format!("<{}", latency(p99)), Style::default().fg(color)),
+                            ]);
+                            // One cell per bucket from the fastest to the slowest seen
+                            let first = d.buckets.iter().position(|&n| n > 0).unwrap_or(0);
+                            let last = d.buckets.iter().rposition(|&n| n > 0).unwrap_or(0);
+                            let peak = d.buckets[first..=last].iter().copied().max().unwrap_or(1).max(1);
+                            let bars: String = d.buckets[first..=last]
+                                .iter()
+                                .map(|&n| match n {
+                                    0 => ' ',
+                                    _ => BARS[(n * 7 / peak) as usize],
+                                })
+                                .collect();
+                            let floor = if first == 0 { "0".to_string() } else { latency(bucket_limit(first - 1)) };
+                            let histogram = Line::from(vec![
+                                Span::styled(format!("{:>8} ", floor), dim),
+                                Span::styled(bars, Style::default().fg(Color::Cyan)),
+                                Span::styled(format!(" {}", latency(bucket_limit(last))), dim),
+                            ]);
+                            [summary, histogram]
+                        })
+                        .collect(),
+                };
+                let para = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Block I/O latency (10s)"));
+                para.render(area, buf);
+            }
+        }
+
+        /// UPS charge, load and runtime from NUT.
+        pub struct UpsWidget {
+            pub status: UpsStatus,
+        }
+
+        impl Widget for UpsWidget {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                let s = &self.status;
+                let block = Block::default().borders(Borders::ALL).title(format!("UPS {}", s.name));
+                let inner = block.inner(area);
+                block.render(area, buf);
+                let chunks = Layout::vertical([Constraint::Length(1), Constraint::Length(1), Constraint::Min(0)]).split(inner);
+
+                let mut status: Vec<Span> = Vec::new();
+                if let Some(ref e) = s.error {
+                    status.push(Span::styled(e.clone(), Style::default().fg(Color::Red)));
+                }
+                for flag in s.flags() {
+                    let (text, color) = match flag {
+                        "OL" => ("on line", Color::Green),
+                        "OB" => ("ON BATTERY", Color::Red),
+                        "LB" => ("LOW BATTERY", Color::Red),
+                        "CHRG" => ("charging", Color::Cyan),
+                        "DISCHRG" => ("discharging", Color::Yellow),
+                        "RB" => ("replace battery", Color::Yellow),
+                        "OVER" => ("overload", Color::Red),
+                        other => (other, Color::DarkGray),
+                    };
+                    status.push(Span::styled(format!("{} ", text), Style::default().fg(color)));
+                }
+                let details = [
+                    s.load_percent().map(|l| format!("load {:.0}%", l)),
+                    s.runtime().map(|r| format!("runtime {}:{:02}", r.as_secs() / 60, r.as_secs() % 60)),
+                    s.vars.get("input.voltage").map(|v| format!("input {} V", v)),
+                ];
+                status.push(Span::styled(
+                    details.into_iter().flatten().collect::<Vec<_>>().join("  "),
+                    Style::default().fg(Color::DarkGray),
+                ));
+                Paragraph::new(Line::from(status)).render(chunks[0], buf);
+
+                if let Some(charge) = s.charge_percent() {
+                    let color = if charge < 30.0 { Color::Red } else if charge < 60.0 { Color::Yellow } else { Color::Green };
+                    Widget::render(
+                        Gauge::default()
+                            .gauge_style(Style::default().fg(color))
+                            .label(format!("charge {:.0}%", charge))
+                            .percent(charge.clamp(0.0, 100.0) as u16),
+                        chunks[1],
+                        buf,
+                    );
+                }
+            }
+        }
+
+        /// Socket table with optional hostname and service-name resolution.
+        pub struct ConnectionsWidget<'a> {
+            pub data: &'a [Connection],
+            pub processes: &'a [ProcessInfo],
+            /// `None` shows raw addresses and port numbers.
+            pub names: Option<(&'a Resolver, &'a Services)>,
+        }
+
+        impl ConnectionsWidget<'_> {
+            fn endpoint(&self, addr: &std::net::SocketAddr, proto: &str, remote: bool) -> String {
+                if remote && addr.ip().is_unspecified() && addr.port() == 0 {
+                    return "*".to_string();
+                }
+                let Some((resolver, services)) = self.names else {
+                    return addr.to_string();
+                };
+                let host = if remote {
+                    resolver.lookup(addr.ip()).unwrap_or_else(|| addr.ip().to_string())
+                } else {
+                    addr.ip().to_string()
+                };
+                let port = services
+                    .name(addr.port(), proto)
+                    .map(str::to_string)
+                    .unwrap_or_else(|| addr.port().to_string());
+                format!("{}:{}", host, port)
+            }
+        }
+
+        /// The state holds the selected row and scroll offset.
+        impl StatefulWidget for ConnectionsWidget<'_> {
+            type State = TableState;
+
+            fn render(self, area: Rect, buf: &mut Buffer, state: &mut TableState) {
+                let rows: Vec<Row> = self
+                    .data
+                    .iter()
+                    .map(|c| {
+                        let owner = c
+                            .pid
+                            .map(|pid| {
+                                let name = self.processes.iter().find(|p| p.pid == pid).map_or("", |p| p.name.as_str());
+                                format!("{} {}", pid, name)
+                            })
+                            .unwrap_or_default();
+                        let state_color = match c.state {
+                            "LISTEN" => Color::Cyan,
+                            "ESTABLISHED" => Color::Green,
+                            "TIME_WAIT" | "CLOSE_WAIT" | "FIN_WAIT1" | "FIN_WAIT2" => Color::Yellow,
+                            _ => Color::DarkGray,
+                        };
+                        Row::new(vec![
+                            Cell::from(c.proto),
+                            Cell::from(self.endpoint(&c.local, c.proto, false)),
+                            Cell::from(self.endpoint(&c.remote, c.proto, true)),
+                            Cell::from(c.state).style(Style::default().fg(state_color)),
+                            Cell::from(owner),
+                        ])
+                    })
+                    .collect();
+                let widths = [
+                    Constraint::Length(5),
+                    Constraint::Percentage(30),
+                    Constraint::Percentage(35),
+                    Constraint::Length(11),
+                    Constraint::Min(10),
+                ];
+                let title = format!(
+                    "Connections ({}){}",
+                    self.data.len(),
+                    if self.names.is_none() { " raw" } else { "" },
+                );
+                let table = Table::new(rows, widths)
+                    .header(
+                        Row::new(vec!["Proto", "Local", "Remote", "State", "Process"])
+                            .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                    )
+                    .block(Block::default().borders(Borders::ALL).title(title))
+                    .row_highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD));
+                StatefulWidget::render(table, area, buf, state);
+                table_scrollbar(buf, area, self.data.len(), state.offset());
+            }
+        }
+
+        /// `df`-style table of mounted filesystems above the block device
+        /// queue and NVMe health tables.
+        pub struct StorageWidget<'a> {
+            pub data: &'a [FilesystemUsage],
+            pub devices: &'a [DeviceIo],
+            pub nvme: &'a [NvmeHealth],
+            pub nvme_config: &'a NvmeConfig,
+        }
+
+        /// The state holds the selected row and scroll offset.
+        impl StatefulWidget for StorageWidget<'_> {
+            type State = TableState;
+
+            fn render(self, area: Rect, buf: &mut Buffer, state: &mut TableState) {
+                // Each lower table gets at most a third of the view, so the
+                // filesystems stay visible on machines with many drives
+                let table_height = |rows: usize| if rows == 0 { 0 } else { (rows as u16 + 3).min(area.height / 3) };
+                let devices: Vec<DeviceIo> =
+                    self.devices.iter().filter(|d| !crate::blockdev::pseudo_device(&d.name)).cloned().collect();
+                let chunks = Layout::vertical([
+                    Constraint::Min(4),
+                    Constraint::Length(table_height(devices.len())),
+                    Constraint::Length(table_height(self.nvme.len())),
+                ])
+                .split(area);
+                if !devices.is_empty() {
+                    DeviceQueueWidget { data: &devices }.render(chunks[1], buf);
+                }
+                if !self.nvme.is_empty() {
+                    NvmeWidget { data: self.nvme, config: self.nvme_config }.render(chunks[2], buf);
+                }
+                let area = chunks[0];
+                const BAR: usize = 20;
+                let rows: Vec<Row> = self
+                    .data
+                    .iter()
+                    .map(|fs| {
+                        let pct = fs.used_percent();
+                        let color = if pct >= 95.0 {
+                            Color::Red
+                        } else if pct >= 85.0 {
+                            Color::Yellow
+                        } else {
+                            Color::Green
+                        };
+                        let filled = ((pct / 100.0) * BAR as f64).round() as usize;
+                        let bar = format!("{}{}", "█".repeat(filled.min(BAR)), "·".repeat(BAR - filled.min(BAR)));
+                        Row::new(vec![
+                            Cell::from(fs.mount.clone()),
+                            Cell::from(fs.device.clone()),
+                            if fs.read_only {
+                                Cell::from(format!("{} ro", fs.fs_type)).style(Style::default().fg(Color::Magenta))
+                            } else {
+                                Cell::from(fs.fs_type.clone())
+                            },
+                            Cell::from(crate::report::human_bytes(fs.total - fs.available.min(fs.total))),
+                            Cell::from(crate::report::human_bytes(fs.total)),
+                            Cell::from(format!("{:.0}%", pct)).style(Style::default().fg(color)),
+                            Cell::from(bar).style(Style::default().fg(color)),
+                        ])
+                    })
+                    .collect();
+                let widths = [
+                    Constraint::Percentage(25),
+                    Constraint::Percentage(25),
+                    Constraint::Length(11),
+                    Constraint::Length(10),
+                    Constraint::Length(10),
+                    Constraint::Length(5),
+                    Constraint::Length(BAR as u16),
+                ];
+                let table = Table::new(rows, widths)
+                    .header(
+                        Row::new(vec!["Mount", "Device", "Type", "Used", "Size", "Use%", ""])
+                            .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                    )
+                    .block(Block::default().borders(Borders::ALL).title(format!("Storage ({})", self.data.len())))
+                    .row_highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD));
+                StatefulWidget::render(table, area, buf, state);
+            }
+        }
+
+        /// In-flight requests, queue depth, utilization and latency per block
+        /// device.
+        pub struct DeviceQueueWidget<'a> {
+            pub data: &'a [DeviceIo],
+        }
+
+        impl Widget for DeviceQueueWidget<'_> {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                let rows: Vec<Row> = self
+                    .data
+                    .iter()
+                    .map(|d| {
+                        // Busy all the time with requests queueing: saturated
+                        let color = if d.util_percent >= 90.0 && d.avg_queue >= 1.0 {
+                            Color::Red
+                        } else if d.util_percent >= 60.0 {
+                            Color::Yellow
+                        } else {
+                            Color::Green
+                        };
+                        Row::new(vec![
+                            Cell::from(d.name.clone()),
+                            Cell::from(d.in_flight.to_string()),
+                            Cell::from(d.queue_size.map_or("-".to_string(), |q| q.to_string())),
+                            Cell::from(format!("{:.2}", d.avg_queue)),
+                            Cell::from(format!("{:.0}%", d.util_percent)).style(Style::default().fg(color)),
+                            Cell::from(d.await_ms.map_or("-".to_string(), |ms| format!("{:.1} ms", ms))),
+                            Cell::from(format!("{:.0}", d.iops)),
+                        ])
+                    })
+                    .collect();
+                let widths = [
+                    Constraint::Length(12),
+                    Constraint::Length(9),
+                    Constraint::Length(6),
+                    Constraint::Length(10),
+                    Constraint::Length(6),
+                    Constraint::Length(10),
+                    Constraint::Length(8),
+                ];
+                let table = Table::new(rows, widths)
+                    .header(
+                        Row::new(vec!["Device", "In-flight", "Queue", "Avg queue", "Util", "Await", "IOPS"])
+                            .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                    )
+                    .block(Block::default().borders(Borders::ALL).title("Block devices"));
+                Widget::render(table, area, buf);
+            }
+        }
+
+        /// NVMe temperature, wear and spare capacity per controller.
+        pub struct NvmeWidget<'a> {
+            pub data: &'a [NvmeHealth],
+            pub config: &'a NvmeConfig,
+        }
+
+        impl Widget for NvmeWidget<'_> {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                let unknown = || Cell::from("-").style(Style::default().fg(Color::DarkGray));
+                let rows: Vec<Row> = self
+                    .data
+                    .iter()
+                    .map(|d| {
+                        let temp = match d.temp_celsius {
+                            Some(t) => {
+                                let color = if t >= self.config.temp_warn { Color::Red } else { Color::Green };
+                                Cell::from(format!("{:.0}°C", t)).style(Style::default().fg(color))
+                            }
+                            None => unknown(),
+                        };
+                        let wear = match d.percent_used {
+                            Some(used) => {
+                                let color = if used >= self.config.wear_critical_percent {
+                                    Color::Red
+                                } else if used >= self.config.wear_warn_percent {
+                                    Color::Yellow
+                                } else {
+                                    Color::Green
+                                };
+                                Cell::from(format!("{}%", used)).style(Style::default().fg(color))
+                            }
+                            None => unknown(),
+                        };
+                        let spare = match (d.available_spare, d.spare_threshold) {
+                            (Some(spare), Some(threshold)) => {
+                                let color = if spare <= threshold { Color::Red } else { Color::Green };
+                                Cell::from(format!("{}% (min {}%)", spare, threshold)).style(Style::default().fg(color))
+                            }
+                            _ => unknown(),
+                        };
+                        let warnings = d.warnings().join(", ");
+                        Row::new(vec![
+                            Cell::from(d.name.clone()),
+                            Cell::from(d.model.clone()),
+                            temp,
+                            wear,
+                            spare,
+                            Cell::from(warnings).style(Style::default().fg(Color::Red)),
+                        ])
+                    })
+                    .collect();
+                let widths = [
+                    Constraint::Length(8),
+                    Constraint::Length(28),
+                    Constraint::Length(6),
+                    Constraint::Length(6),
+                    Constraint::Length(15),
+                    Constraint::Min(10),
+                ];
+                // Wear and spare come from the SMART log, which needs root
+                let title = if self.data.iter().all(|d| d.percent_used.is_none()) {
+                    "NVMe health (wear needs root)"
+                } else {
+                    "NVMe health"
+                };
+                let table = Table::new(rows, widths)
+                    .header(
+                        Row::new(vec!["Ctrl", "Model", "Temp", "Wear", "Spare", "Warnings"])
+                            .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                    )
+                    .block(Block::default().borders(Borders::ALL).title(title));
+                Widget::render(table, area, buf);
+            }
+        }
+
+        /// Metric list beside two charts sharing a time axis.
+        pub struct CompareWidget<'a> {
+            pub history: &'a MetricHistory,
+            pub names: &'a [String],
+            /// Top and bottom chart.
+            pub series: [Option<&'a str>; 2],
+            /// How far back the ghosted comparison window ends.
+            pub overlay: Option<std::time::Duration>,
+        }
+
+        /// The state holds the selected row and scroll offset.
+        impl StatefulWidget for CompareWidget<'_> {
+            type State = TableState;
+
+            fn render(self, area: Rect, buf: &mut Buffer, state: &mut TableState) {
+                let list_width = self.names.iter().map(|n| n.len() as u16).max().unwrap_or(0).clamp(12, 32) + 4;
+                let chunks = Layout::horizontal([Constraint::Length(list_width + 2), Constraint::Min(20)]).split(area);
+
+                let rows: Vec<Row> = self
+                    .names
+                    .iter()
+                    .map(|name| {
+                        let mark = match self.series.iter().position(|s| *s == Some(name.as_str())) {
+                            Some(0) => Span::styled("A ", Style::default().fg(Color::Cyan)),
+                            Some(_) => Span::styled("B ", Style::default().fg(Color::Magenta)),
+                            None => Span::raw("  "),
+                        };
+                        Row::new(vec![Cell::from(Line::from(vec![mark, Span::raw(name.clone())]))])
+                    })
+                    .collect();
+                let table = Table::new(rows, [Constraint::Min(10)])
+                    .block(Block::default().borders(Borders::ALL).title("Metrics (a/b)"))
+                    .row_highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD));
+                StatefulWidget::render(table, chunks[0], buf, state);
+
+                let span = self.history.span().clamp(1.0, crate::compare::WINDOW.as_secs_f64());
+                let charts = Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)]).split(chunks[1]);
+                for ((slot, name), (area, color)) in
+                    ["A", "B"].iter().zip(self.series).zip(charts.iter().zip([Color::Cyan, Color::Magenta]))
+                {
+                    let Some(name) = name else {
+                        let hint = format!("{}: select a metric and press {}", slot, slot.to_lowercase());
+                        Paragraph::new(hint).block(Block::default().borders(Borders::ALL)).render(*area, buf);
+                        continue;
+                    };
+                    let points = self.history.points(name, std::time::Duration::ZERO);
+                    let ghost = self.overlay.map(|offset| self.history.points(name, offset)).unwrap_or_default();
+                    let unit = Unit::of(name);
+                    let value = |v: f64| unit.format(v);
+                    let (lo, hi) = points
+                        .iter()
+                        .chain(&ghost)
+                        .fold((f64::MAX, f64::MIN), |(lo, hi), (_, v)| (lo.min(*v), hi.max(*v)));
+                    // Percentages on a fixed 0-100 scale, everything else fitted
+                    let (lo, hi) = if unit == Unit::Percent {
+                        (0.0, 100.0)
+                    } else if points.is_empty() && ghost.is_empty() {
+                        (0.0, 1.0)
+                    } else {
+                        let pad = ((hi - lo) * 0.1).max(hi.abs() * 0.01);
+                        if pad > 0.0 { ((lo - pad).max(0.0), hi + pad) } else { (lo, lo + 1.0) }
+                    };
+                    let mut title = match points.last() {
+                        Some((_, now)) => format!("{} {}  {}", slot, name, value(*now)),
+                        None => format!("{} {}", slot, name),
+                    };
+                    let mut datasets = Vec::new();
+                    if let Some(offset) = self.overlay {
+                        title.push_str(&format!("  (grey: {} ago)", crate::compare::label(offset)));
+                        // Drawn first so the current series stays on top
+                        datasets.push(
+                            Dataset::default()
+                                .marker(symbols::Marker::Braille)
+                                .graph_type(GraphType::Line)
+                                .style(Style::default().fg(Color::DarkGray))
+                                .data(&ghost),
+                        );
+                    }
+                    datasets.push(
+                        Dataset::default()
+                            .marker(symbols::Marker::Braille)
+                            .graph_type(GraphType::Line)
+                            .style(Style::default().fg(color))
+                            .data(&points),
+                    );
+                    let chart = Chart::new(datasets)
+                        .block(Block::default().borders(Borders::ALL).title(title))
+                        .x_axis(
+                            Axis::default()
+                                .bounds([-span, 0.0])
+                                .labels([format!("-{:.0}s", span), "now".to_string()])
+                                .style(Style::default().fg(Color::DarkGray)),
+                        )
+                        .y_axis(
+                            Axis::default()
+                                .bounds([lo, hi])
+                                .labels([value(lo), value(hi)])
+                                .style(Style::default().fg(Color::DarkGray)),
+                        );
+                    chart.render(*area, buf);
+                }
+            }
+        }
+
+        /// Interrupt rates: one bar per CPU above a source-by-CPU table.
+        pub struct InterruptsWidget<'a> {
+            pub data: &'a InterruptStats,
+        }
+
+        /// The state holds the selected row and scroll offset.
+        impl StatefulWidget for InterruptsWidget<'_> {
+            type State = TableState;
+
+            fn render(self, area: Rect, buf: &mut Buffer, state: &mut TableState) {
+                let cpu_rows = self.data.cpus.len().div_ceil(2) as u16;
+                let chunks = Layout::vertical([Constraint::Length(cpu_rows + 2), Constraint::Min(3)]).split(area);
+                let lower = Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).split(chunks[1]);
+                let side = Layout::vertical([
+                    Constraint::Min(4),
+                    Constraint::Length(self.data.softirqs.len().max(1) as u16 + 3),
+                ])
+                .split(lower[1]);
+                TopIrqWidget { data: self.data }.render(side[0], buf);
+                SoftirqWidget { data: self.data }.render(side[1], buf);
+
+                // Per-CPU totals, two CPUs per line
+                let max = self.data.per_cpu.iter().cloned().fold(1.0, f64::max);
+                let bar_width = (chunks[0].width.saturating_sub(4) / 2).saturating_sub(20) as usize;
+                let mut lines = Vec::new();
+                let mut spans = Vec::new();
+                for (i, (cpu, rate)) in self.data.cpus.iter().zip(&self.data.per_cpu).enumerate() {
+                    let filled = (rate / max * bar_width as f64).round() as usize;
+                    let share = rate / self.data.per_cpu.iter().sum::<f64>().max(1.0);
+                    let color = if share > 0.5 && self.data.cpus.len() > 1 { Color::Red } else { Color::Green };
+                    spans.push(Span::raw(format!("{:<6}{:>9.0}/s ", cpu, rate)));
+                    spans.push(Span::styled(
+                        format!("{:<width$}", "█".repeat(filled), width = bar_width),
+                        Style::default().fg(color),
+                    ));
+                    if i % 2 == 1 {
+                        lines.push(Line::from(std::mem::take(&mut spans)));
+                    } else {
+                        spans.push(Span::raw("  "));
+                    }
+                }
+                if !spans.is_empty() {
+                    lines.push(Line::from(spans));
+                }
+                let total: f64 = self.data.per_cpu.iter().sum();
+                Widget::render(
+                    Paragraph::new(lines).block(
+                        Block::default().borders(Borders::ALL).title(format!("Interrupts {:.0}/s", total)),
+                    ),
+                    chunks[0],
+                    buf,
+                );
+
+                // Sources by CPU, as many CPU columns as fit
+                let cpu_cols = (lower[0].width.saturating_sub(2 + 8 + 20) / 9).min(self.data.cpus.len() as u16) as usize;
+                let rows: Vec<Row> = self
+                    .data
+                    .sources
+                    .iter()
+                    .map(|s| {
+                        let mut cells = vec![Cell::from(s.irq.clone())];
+                        cells.extend(s.per_cpu.iter().take(cpu_cols).map(|r| Cell::from(format!("{:.0}", r))));
+                        cells.push(Cell::from(s.desc.clone()));
+                        Row::new(cells)
+                    })
+                    .collect();
+                let mut widths = vec![Constraint::Length(8)];
+                widths.extend(std::iter::repeat_n(Constraint::Length(8), cpu_cols));
+                widths.push(Constraint::Min(20));
+                let mut header = vec!["IRQ".to_string()];
+                header.extend(self.data.cpus.iter().take(cpu_cols).cloned());
+                header.push("Source".to_string());
+                let table = Table::new(rows, widths)
+                    .header(Row::new(header).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)))
+                    .block(Block::default().borders(Borders::ALL).title("Per-CPU rate (/s)"))
+                    .row_highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD));
+                StatefulWidget::render(table, lower[0], buf, state);
+            }
+        }
+
+        /// Busiest interrupt sources by total rate, as many as fit.
+        pub struct TopIrqWidget<'a> {
+            pub data: &'a InterruptStats,
+        }
+
+        impl Widget for TopIrqWidget<'_> {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                let mut top: Vec<&IrqRate> = self.data.sources.iter().collect();
+                top.sort_by(|a, b| b.total().total_cmp(&a.total()));
+                let rows: Vec<Row> = top
+                    .into_iter()
+                    .take(area.height.saturating_sub(3) as usize)
+                    .map(|s| {
+                        // Busiest CPU and its share of this source
+                        let (cpu, share) = s
+                            .per_cpu
+                            .iter()
+                            .enumerate()
+                            .max_by(|a, b| a.1.total_cmp(b.1))
+                            .map(|(i, r)| (i, r / s.total().max(f64::MIN_POSITIVE)))
+                            .unwrap_or((0, 0.0));
+                        Row::new(vec![
+                            Cell::from(format!("{:.0}", s.total())),
+                            Cell::from(s.irq.clone()),
+                            Cell::from(format!("{} {:.0}%", self.data.cpus.get(cpu).map_or("", |c| c.as_str()), share * 100.0)),
+                            Cell::from(s.device()),
+                        ])
+                    })
+                    .collect();
+                let widths = [Constraint::Length(8), Constraint::Length(6), Constraint::Length(11), Constraint::Min(10)];
+                let table = Table::new(rows, widths)
+                    .header(
+                        Row::new(vec!["/s", "IRQ", "Busiest", "Device"])
+                            .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                    )
+                    .block(Block::default().borders(Borders::ALL).title("Top sources"));
+                Widget::render(table, area, buf);
+            }
+        }
+
+        /// Softirq rates by type; network and timer load shows up here rather
+        /// than in any process's CPU time.
+        pub struct SoftirqWidget<'a> {
+            pub data: &'a InterruptStats,
+        }
+
+        impl Widget for SoftirqWidget<'_> {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                let rows: Vec<Row> = self
+                    .data
+                    .softirqs
+                    .iter()
+                    .map(|s| {
+                        let color = match s.irq.as_str() {
+                            "NET_RX" | "NET_TX" => Color::Cyan,
+                            _ if s.total() == 0.0 => Color::DarkGray,
+                            _ => Color::Reset,
+                        };
+                        let busiest = s
+                            .per_cpu
+                            .iter()
+                            .enumerate()
+                            .max_by(|a, b| a.1.total_cmp(b.1))
+                            .filter(|(_, r)| **r > 0.0)
+                            .and_then(|(i, _)| self.data.cpus.get(i).cloned())
+                            .unwrap_or_default();
+                        Row::new(vec![
+                            Cell::from(s.irq.clone()),
+                            Cell::from(format!("{:.0}", s.total())),
+                            Cell::from(busiest),
+                        ])
+                        .style(Style::default().fg(color))
+                    })
+                    .collect();
+                let widths = [Constraint::Length(9), Constraint::Length(9), Constraint::Min(6)];
+                let table = Table::new(rows, widths)
+                    .header(
+                        Row::new(vec!["Softirq", "/s", "Busiest"])
+                            .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                    )
+                    .block(Block::default().borders(Borders::ALL).title("Softirqs"));
+                Widget::render(table, area, buf);
+            }
+        }
+
+        /// Detail pane for one process, opened with Enter.
+        pub struct ProcessDetailWidget<'a> {
+            pub detail: &'a ProcessDetail,
+            pub info: Option<&'a ProcessInfo>,
+        }
+
+        impl ProcessDetailWidget<'_> {
+            fn overview(&self) -> Vec<Line<'static>> {
+                let label = Style::default().fg(Color::Yellow);
+                let mut lines = Vec::new();
+                if let Some(p) = self.info {
+                    lines.push(Line::from(vec![Span::styled("Name     ", label), Span::raw(p.name.clone())]));
+                    lines.push(Line::from(vec![Span::styled("Command  ", label), Span::raw(p.cmd.clone())]));
+                    lines.push(Line::from(vec![
+                        Span::styled("CPU      ", label),
+                        Span::raw(format!(
+                            "{}%   mem {}",
+                            crate::report::decimal(p.cpu_percent as f64, 1),
+                            crate::report::human_bytes(p.mem_bytes),
+                        )),
+                    ]));
+                }
+                for (key, value) in &self.detail.status {
+                    lines.push(Line::from(vec![Span::styled(format!("{:<9}", key), label), Span::raw(value.clone())]));
+                }
+                let sched = match self.detail.sched {
+                    Some(w) => {
+                        let color = if w.wait_percent > 10.0 { Color::Red } else { Color::Reset };
+                        Span::styled(
+                            format!("{:.1}% of time waiting, {:.2} ms per slice", w.wait_percent, w.avg_wait_ms),
+                            Style::default().fg(color),
+                        )
+                    }
+                    None => Span::styled("measuring…", Style::default().fg(Color::DarkGray)),
+                };
+                lines.push(Line::from(vec![Span::styled("Sched    ", label), sched]));
+                if let Some(total) = self.detail.total_wait_ms {
+                    lines.push(Line::from(vec![
+                        Span::styled("Waited   ", label),
+                        Span::raw(format!("{:.0} ms since start", total)),
+                    ]));
+                }
+                lines
+            }
+
+            fn environment(&self) -> Vec<Line<'static>> {
+                let label = Style::default().fg(Color::Yellow);
+                let error = Style::default().fg(Color::DarkGray);
+                let mut lines = vec![match self.detail.cwd {
+                    Ok(ref cwd) => Line::from(vec![Span::styled("Cwd      ", label), Span::raw(cwd.clone())]),
+                    Err(ref e) => Line::from(vec![Span::styled("Cwd      ", label), Span::styled(e.clone(), error)]),
+                }];
+                lines.push(Line::from(""));
+                match self.detail.environ {
+                    Ok(ref vars) if vars.is_empty() => lines.push(Line::from(Span::styled("(empty environment)", error))),
+                    Ok(ref vars) => {
+                        for kv in vars {
+                            let (key, value) = kv.split_once('=').unwrap_or((kv, ""));
+                            lines.push(Line::from(vec![
+                                Span::styled(key.to_string(), Style::default().fg(Color::Cyan)),
+                                Span::raw(format!("={}", value)),
+                            ]));
+                        }
+                    }
+                    Err(ref e) => lines.push(Line::from(Span::styled(e.clone(), error))),
+                }
+                lines
+            }
+
+            fn files(&self) -> Vec<Line<'static>> {
+                let files = match self.detail.files {
+                    Ok(ref files) => files,
+                    Err(ref e) => return vec![Line::from(Span::styled(e.clone(), Style::default().fg(Color::DarkGray)))],
+                };
+                let mut counts: Vec<(&str, usize)> = Vec::new();
+                for f in files {
+                    match counts.iter_mut().find(|(k, _)| *k == f.kind) {
+                        Some((_, n)) => *n += 1,
+                        None => counts.push((f.kind, 1)),
+                    }
+                }
+                let summary: Vec<String> = counts.iter().map(|(k, n)| format!("{} {}", n, k)).collect();
+                let mut lines = vec![Line::from(Span::styled(
+                    format!("{} open: {}", files.len(), summary.join(", ")),
+                    Style::default().fg(Color::Yellow),
+                ))];
+                for f in files {
+                    let color = match f.kind {
+                        "socket" => Color::Magenta,
+                        "pipe" | "anon" => Color::DarkGray,
+                        "dev" => Color::Cyan,
+                        _ if f.target.ends_with(" (deleted)") => Color::Red,
+                        _ => Color::Reset,
+                    };
+                    lines.push(Line::from(vec![
+                        Span::styled(format!("{:>5} {:<7}", f.fd, f.kind), Style::default().fg(Color::DarkGray)),
+                        Span::styled(f.target.clone(), Style::default().fg(color)),
+                    ]));
+                }
+                lines
+            }
+
+            fn memory(&self) -> Vec<Line<'static>> {
+                let m = match self.detail.memory {
+                    Ok(m) => m,
+                    Err(ref e) => return vec![Line::from(Span::styled(e.clone(), Style::default().fg(Color::DarkGray)))],
+                };
+                let label = Style::default().fg(Color::Yellow);
+                let note = Style::default().fg(Color::DarkGray);
+                let bytes = crate::report::human_bytes;
+                let row = |key: &str, value: String, extra: Span<'static>| {
+                    Line::from(vec![Span::styled(format!("{:<9}", key), label), Span::raw(format!("{:<11}", value)), extra])
+                };
+                let hint = |text: &str| Span::styled(text.to_string(), note);
+                let swap_style = if m.swap > 0 { Style::default().fg(Color::Red) } else { Style::default() };
+                vec![
+                    row("RSS", bytes(m.rss), hint("all resident pages")),
+                    row("PSS", bytes(m.pss), hint("shared pages split")),
+                    row("USS", bytes(m.uss), hint("private only")),
+                    Line::from(""),
+                    row("", "clean".to_string(), hint("dirty")),
+                    row("Shared", bytes(m.shared_clean), Span::raw(bytes(m.shared_dirty))),
+                    row("Private", bytes(m.private_clean), Span::raw(bytes(m.private_dirty))),
+                    Line::from(""),
+                    row("Anon", bytes(m.anonymous), Span::raw("")),
+                    row("PSS file", bytes(m.pss_file), Span::raw("")),
+                    row("PSS shm", bytes(m.pss_shmem), Span::raw("")),
+                    Line::from(vec![
+                        Span::styled(format!("{:<9}", "Swap"), label),
+                        Span::styled(format!("{:<11}", bytes(m.swap)), swap_style),
+                        hint(&format!("PSS {}", bytes(m.swap_pss))),
+                    ]),
+                ]
+            }
+
+            fn limits(&self) -> Vec<Line<'static>> {
+                let limits = match self.detail.limits {
+                    Ok(ref limits) => limits,
+                    Err(ref e) => return vec![Line::from(Span::styled(e.clone(), Style::default().fg(Color::DarkGray)))],
+                };
+                let format = |value: Option<u64>, units: &str| match value {
+                    None => "unlimited".to_string(),
+                    Some(v) if units == "bytes" => crate::report::human_bytes(v),
+                    Some(v) => v.to_string(),
+                };
+                let mut lines = vec![Line::from(Span::styled(
+                    format!("{:<16}{:>9} {:>9} {:>9}", "limit", "used", "soft", "hard"),
+                    Style::default().fg(Color::Yellow),
+                ))];
+                for l in limits {
+                    let style = match l.usage() {
+                        Some(u) if u >= NEAR_LIMIT_CRITICAL => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                        Some(u) if u >= NEAR_LIMIT_WARN => Style::default().fg(Color::Yellow),
+                        _ if l.used.is_none() => Style::default().fg(Color::DarkGray),
+                        _ => Style::default(),
+                    };
+                    let used = l.used.map(|u| format(Some(u), &l.units)).unwrap_or_default();
+                    lines.push(Line::from(vec![
+                        Span::styled(format!("{:<16.16}{:>9} {:>9}", l.name, used, format(l.soft, &l.units)), style),
+                        Span::styled(format!(" {:>9}", format(l.hard, &l.units)), Style::default().fg(Color::DarkGray)),
+                    ]));
+                }
+                lines
+            }
+        }
+
+        impl Widget for ProcessDetailWidget<'_> {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                let mut lines = match self.detail.tab {
+                    DetailTab::Overview => self.overview(),
+                    DetailTab::Environment => self.environment(),
+                    DetailTab::Files => self.files(),
+                    DetailTab::Memory => self.memory(),
+                    DetailTab::Limits => self.limits(),
+                };
+                if self.detail.exited {
+                    lines.push(Line::from(Span::styled("process exited", Style::default().fg(Color::Red))));
+                }
+                let mut title = vec![Span::raw(format!("PID {} ", self.detail.pid))];
+                for tab in DetailTab::ALL {
+                    let style = if tab == self.detail.tab {
+                        Style::default().fg(Color::Black).bg(Color::Cyan)
+                    } else {
+                        Style::default().fg(Color::DarkGray)
+                    };
+                    title.push(Span::styled(format!(" {} ", tab.label()), style));
+                }
+                title.push(Span::styled(" ←/→ Esc", Style::default().fg(Color::DarkGray)));
+                let mut para = Paragraph::new(lines)
+                    .scroll((self.detail.scroll, 0))
+                    .block(Block::default().borders(Borders::ALL).title(Line::from(title)));
+                // The limits table reads better clipped than wrapped.
+                if self.detail.tab != DetailTab::Limits {
+                    para = para.wrap(ratatui::widgets::Wrap { trim: false });
+                }
+                para.render(area, buf);
+            }
+        }
+
+        /// Share of a soft limit at which the limits tab turns yellow, then red.
+        const NEAR_LIMIT_WARN: f64 = 0.8;
+        const NEAR_LIMIT_CRITICAL: f64 = 0.95;
+
+        /// Plain-text output of a custom command panel.
+        pub struct CommandPanelWidget<'a> {
+            pub title: &'a str,
+            pub output: PanelOutput,
+        }
+
+        impl<'a> CommandPanelWidget<'a> {
+            pub fn new(title: &'a str, output: PanelOutput) -> Self {
+                Self { title, output }
+            }
+        }
+
+        impl Widget for CommandPanelWidget<'_> {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                let style = if self.output.failed {
+                    Style::default().fg(Color::Red)
+                } else {
+                    Style::default()
+                };
+                let para = Paragraph::new(self.output.text.as_str())
+                    .style(style)
+                    .block(Block::default().borders(Borders::ALL).title(self.title));
+                para.render(area, buf);
+            }
+        }
+
+        /// Newest journal lines that fit, above any error from `journalctl`.
+        pub struct JournalWidget<'a> {
+            pub title: &'a str,
+            pub output: JournalOutput,
+        }
+
+        impl Widget for JournalWidget<'_> {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                let rows = area.height.saturating_sub(2) as usize;
+                let mut lines: Vec<Line> = Vec::new();
+                if let Some(ref error) = self.output.error {
+                    lines.push(Line::styled(error.clone(), Style::default().fg(Color::Red)));
+                }
+                let skip = (self.output.lines.len() + lines.len()).saturating_sub(rows);
+                for line in self.output.lines.iter().skip(skip) {
+                    // `2026-10-16T10:26:00+0000 host unit[pid]: message`; drop the host
+                    let mut parts = line.splitn(3, ' ');
+                    let (Some(time), Some(_host), Some(rest)) = (parts.next(), parts.next(), parts.next()) else {
+                        lines.push(Line::raw(line.clone()));
+                        continue;
+                    };
+                    let time = time.get(5..19).unwrap_or(time).replace('T', " ");
+                    let (source, message) = rest.split_once(": ").unwrap_or(("", rest));
+                    lines.push(Line::from(vec![
+                        Span::styled(format!("{} ", time), Style::default().fg(Color::DarkGray)),
+                        Span::styled(format!("{}: ", source), Style::default().fg(Color::Cyan)),
+                        Span::styled(message.to_string(), Style::default().fg(Color::Yellow)),
+                    ]));
+                }
+                if lines.is_empty() {
+                    lines.push(Line::styled("no matching entries", Style::default().fg(Color::DarkGray)));
+                }
+                let para = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(self.title));
+                para.render(area, buf);
+            }
+        }
+
+        /// F12 overlay with the monitor's own collection/render timings.
+        pub struct DiagnosticsWidget<'a> {
+            pub diag: &'a Diagnostics,
+        }
+
+        impl<'a> DiagnosticsWidget<'a> {
+            pub fn new(diag: &'a Diagnostics) -> Self {
+                Self { diag }
+            }
+        }
+
+        impl Widget for DiagnosticsWidget<'_> {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                let w = 46.min(area.width);
+                let h = 7.min(area.height);
+                let popup = Rect::new(area.x + (area.width - w) / 2, area.y + (area.height - h) / 2, w, h);
+                let d = self.diag;
+                let ms = |t: std::time::Duration| format!("{:.2} ms", t.as_secs_f64() * 1000.0);
+                let lines = vec![
+                    Line::from(format!(
+                        "collect  {:>10} (max {}){}",
+                        ms(d.collect),
+                        ms(d.collect_max),
+                        if d.collect_full { " full" } else { "" },
+                    )),
+                    Line::from(format!("render   {:>10} (max {})", ms(d.render), ms(d.render_max))),
+                    Line::from(format!("backlog  {:>10} events", d.backlog)),
+                    Line::from(format!(
+                        "allocs   {:>10} / tick ({})",
+                        d.collect_allocs.0,
+                        crate::report::human_bytes(d.collect_allocs.1),
+                    )),
+                    Line::from(format!(
+                        "allocs   {:>10} / frame ({})",
+                        d.render_allocs.0,
+                        crate::report::human_bytes(d.render_allocs.1),
+                    )),
+                ];
+                let para = Paragraph::new(lines).block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title("Diagnostics (F12)")
+                        .border_style(Style::default().fg(Color::Cyan)),
+                );
+                Clear.render(popup, buf);
+                para.render(popup, buf);
+            }
+        }
+
+        /// Overlay listing failed systemd units (`u`).
+        pub struct FailedUnitsWidget<'a> {
+            pub units: &'a [FailedUnit],
+        }
+
+        impl Widget for FailedUnitsWidget<'_> {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                let w = (area.width * 3 / 5).max(40.min(area.width));
+                let h = (self.units.len() as u16 + 3).clamp(5.min(area.height), area.height);
+                let popup = Rect::new(area.x + (area.width - w) / 2, area.y + (area.height - h) / 2, w, h);
+                let rows: Vec<Row> = self
+                    .units
+                    .iter()
+                    .map(|u| {
+                        Row::new(vec![
+                            Cell::from(u.name.clone()).style(Style::default().fg(Color::Red)),
+                            Cell::from(if u.load == "loaded" { String::new() } else { u.load.clone() })
+                                .style(Style::default().fg(Color::DarkGray)),
+                            Cell::from(u.description.clone()),
+                        ])
+                    })
+                    .collect();
+                let widths = [Constraint::Percentage(35), Constraint::Length(10), Constraint::Fill(1)];
+                let mut block = Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("Failed units ({}) (u)", self.units.len()))
+                    .border_style(Style::default().fg(Color::Cyan));
+                if self.units.is_empty() {
+                    block = block.title_bottom(Line::styled(" none ", Style::default().fg(Color::Green)));
+                }
+                let table = Table::new(rows, widths)
+                    .header(
+                        Row::new(vec!["Unit", "Load", "Description"])
+                            .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                    )
+                    .block(block);
+                Clear.render(popup, buf);
+                Widget::render(table, popup, buf);
+            }
+        }
+
+        /// Overlay with boot time, clock sync, kernel command line, microcode
+        /// and CPU vulnerability mitigations (`s`).
+        pub struct SystemDetailsWidget<'a> {
+            pub details: &'a SystemDetails,
+            pub virt: Option<String>,
+            /// Current, unlike `details`, which is read when the overlay opens.
+            pub clock: Option<ClockSync>,
+        }
+
+        impl Widget for SystemDetailsWidget<'_> {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                let w = (area.width * 4 / 5).max(40.min(area.width));
+                let h = (area.height * 4 / 5).max(10.min(area.height));
+                let popup = Rect::new(area.x + (area.width - w) / 2, area.y + (area.height - h) / 2, w, h);
+                let d = self.details;
+                let label = |text: &'static str| Span::styled(format!("{:<16}", text), Style::default().fg(Color::DarkGray));
+                let booted = match d.boot_time {
+                    Some(t) => {
+                        let up = (chrono::Local::now() - t).num_minutes().max(0);
+                        format!("{} (up {}d {}h {}m)", t.format("%Y-%m-%d %H:%M:%S"), up / 1440, up / 60 % 24, up % 60)
+                    }
+                    None => "unknown".to_string(),
+                };
+                let mut lines = vec![
+                    Line::from(vec![label("Kernel"), Span::raw(d.kernel.clone())]),
+                    Line::from(vec![label("Booted"), Span::raw(booted)]),
+                    Line::from(vec![
+                        label("Clock"),
+                        match self.clock {
+                            Some(c) if c.synchronized => Span::styled(
+                                format!(
+                                    "synchronized, offset {:+.3} ms, max error {:.1} ms, freq {:+.2} ppm",
+                                    c.offset_ms, c.max_error_ms, c.freq_ppm
+                                ),
+                                Style::default().fg(Color::Green),
+                            ),
+                            Some(c) => Span::styled(
+                                format!("not synchronized (max error {:.1} s)", c.max_error_ms / 1000.0),
+                                Style::default().fg(Color::Yellow),
+                            ),
+                            None => Span::raw("unknown"),
+                        },
+                    ]),
+                    Line::from(vec![
+                        label("Microcode"),
+                        Span::raw(if d.microcode.is_empty() { "unknown".to_string() } else { d.microcode.join(", ") }),
+                    ]),
+                ];
+                if let Some(ref virt) = self.virt {
+                    lines.push(Line::from(vec![label("Virtualization"), Span::raw(virt.clone())]));
+                }
+                lines.push(Line::from(vec![label("Command line"), Span::raw(d.cmdline.clone())]));
+                lines.push(Line::from(""));
+                lines.push(Line::styled("CPU vulnerabilities", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
+                for (name, status) in &d.vulnerabilities {
+                    let color = if status.starts_with("Vulnerable") {
+                        Color::Red
+                    } else if status.contains("Vulnerable") {
+                        // Mitigated with a known gap (e.g. spectre_v2 BHI)
+                        Color::Yellow
+                    } else if status.starts_with("Mitigation") {
+                        Color::Green
+                    } else {
+                        Color::DarkGray
+                    };
+                    lines.push(Line::from(vec![
+                        Span::raw(format!("  {:<28}", name)),
+                        Span::styled(status.clone(), Style::default().fg(color)),
+                    ]));
+                }
+                if d.vulnerabilities.is_empty() {
+                    lines.push(Line::styled("  not reported by this kernel", Style::default().fg(Color::DarkGray)));
+                }
+                let para = Paragraph::new(lines).wrap(ratatui::widgets::Wrap { trim: false }).block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title("System details (s)")
+                        .border_style(Style::default().fg(Color::Cyan)),
+                );
+                Clear.render(popup, buf);
+                para.render(popup, buf);
+            }
+        }
+
+        /// Blank idle screen: the time, load averages, CPU usage and active
+        /// alert count. Moves to a new spot every minute against burn-in.
+        pub struct IdleClockWidget {
+            pub now: chrono::DateTime<chrono::Local>,
+            pub load: (f64, f64, f64),
+            pub cpu_percent: f32,
+            pub alerts: usize,
+        }
+
+        impl Widget for IdleClockWidget {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                use chrono::Timelike;
+                Clear.render(area, buf);
+                let mut lines = vec![
+                    Line::styled(self.now.format("%H:%M").to_string(), Style::default().add_modifier(Modifier::BOLD)),
+                    Line::styled(
+                        format!("load {:.2} {:.2} {:.2}  cpu {:.0}%", self.load.0, self.load.1, self.load.2, self.cpu_percent),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ];
+                if self.alerts > 0 {
+                    let noun = if self.alerts == 1 { "alert" } else { "alerts" };
+                    lines.push(Line::styled(format!("{} {}", self.alerts, noun), Style::default().fg(Color::Red)));
+                }
+                let width = lines.iter().map(Line::width).max().unwrap_or(0) as u16;
+                let height = lines.len() as u16;
+                // Steps apart in both directions so consecutive spots don't overlap
+                let step = self.now.hour() * 60 + self.now.minute();
+                let x = (step * 7) as u16 % (area.width.saturating_sub(width) + 1);
+                let y = (step * 5) as u16 % (area.height.saturating_sub(height) + 1);
+                let spot = Rect::new(area.x + x, area.y + y, width.min(area.width), height.min(area.height));
+                Paragraph::new(lines).render(spot, buf);
+            }
+        }
+
+        /// Status bar showing scan rate, log status, and key hints.
+        /// While the `:` prompt is open it shows the command being typed instead.
+        pub struct StatusBarWidget {
+            pub process_every: u32,
+            pub tick_ms: u32,
+            pub snap_path: Option<String>,
+            pub log_path: Option<String>,
+            pub command: Option<String>,
+            pub message: Option<String>,
+            /// Text for the first active alert plus how many others are active.
+            pub alert: Option<(String, usize)>,
+            /// sysmon-tui's own CPU% and resident memory.
+            pub self_usage: Option<(f32, u64)>,
+            /// Failed systemd units; the badge only shows when non-zero.
+            pub failed_units: usize,
+            /// Rolling percentiles and the window they cover, in minutes.
+            pub percentiles: Option<(Percentiles, u64)>,
+            /// `(hot, warm)` for the temperature percentiles, from the hottest
+            /// sensor's `[[temp_alert]]` rule.
+            pub temp_levels: (f32, f32),
+            /// Active config profile.
+            pub profile: Option<String>,
+            /// Read-only session, flagged next to the profile.
+            pub locked: bool,
+            /// The key reference at the end; off when embedded in another app.
+            pub key_hints: bool,
+        }
+
+        impl StatusBarWidget {
+            pub fn new(
+                process_every: u32,
+                tick_ms: u32,
+                snap_path: Option<String>,
+                log_path: Option<String>,
+                command: Option<String>,
+                message: Option<String>,
+                alert: Option<(String, usize)>,
+            ) -> Self {
+                Self { process_every, tick_ms, snap_path, log_path, command, message, alert, self_usage: None, failed_units: 0, percentiles: None, temp_levels: (85.0, 65.0), profile: None, locked: false, key_hints: true }
+            }
+
+            pub fn with_self_usage(mut self, usage: Option<(f32, u64)>) -> Self {
+                self.self_usage = usage;
+                self
+            }
+
+            pub fn with_key_hints(mut self, show: bool) -> Self {
+                self.key_hints = show;
+                self
+            }
+
+            pub fn with_failed_units(mut self, count: usize) -> Self {
+                self.failed_units = count;
+                self
+            }
+
+            pub fn with_percentiles(mut self, percentiles: Option<Percentiles>, minutes: u64) -> Self {
+                self.percentiles = percentiles.map(|p| (p, minutes));
+                self
+            }
+
+            pub fn with_temp_levels(mut self, levels: (f32, f32)) -> Self {
+                self.temp_levels = levels;
+                self
+            }
+
+            pub fn with_profile(mut self, profile: Option<String>) -> Self {
+                self.profile = profile;
+                self
+            }
+
+            pub fn with_locked(mut self, locked: bool) -> Self {
+                self.locked = locked;
+                self
+            }
+        }
+
+        impl Widget for StatusBarWidget {
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                if let Some(ref cmd) = self.command {
+                    let para = Paragraph::new(Line::from(vec![
+                        Span::styled(":", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                        Span::raw(cmd.clone()),
+                        Span::styled("_", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+                    ]));
+                    para.render(area, buf);
+                    return;
+                }
+
+                let scan_ms = self.process_every * self.tick_ms;
+                let scan_str = if scan_ms >= 1000 {
+                    format!("{:.1}s", scan_ms as f32 / 1000.0)
+                } else {
+                    format!("{}ms", scan_ms)
+                };
+
+                let mut spans = vec![
+                    Span::styled(" Proc scan: ", Style::default().fg(Color::DarkGray)),
+                    Span::styled(scan_str, Style::default().fg(Color::White).add_modifier(Modifier::BOLD)),
+                ];
+
+                if let Some(ref profile) = self.profile {
+                    spans.push(Span::styled(" | profile: ", Style::default().fg(Color::DarkGray)));
+                    spans.push(Span::styled(profile.clone(), Style::default().fg(Color::Magenta)));
+                }
+                if self.locked {
+                    spans.push(Span::styled(" | ", Style::default().fg(Color::DarkGray)));
+                    spans.push(Span::styled("read-only", Style::default().fg(Color::Yellow)));
+                }
+
+                if let Some((cpu, mem)) = self.self_usage {
+                    spans.push(Span::styled(" | self: ", Style::default().fg(Color::DarkGray)));
+                    spans.push(Span::styled(
+                        format!("{}% {}", crate::report::decimal(cpu as f64, 1), crate::report::human_bytes(mem)),
+                        Style::default().fg(Color::Cyan),
+                    ));
+                }
+
+                if let Some((p, minutes)) = self.percentiles {
+                    let level = |v: f32, warn: f32, crit: f32| {
+                        let color = if v >= crit { Color::Red } else if v >= warn { Color::Yellow } else { Color::Gray };
+                        Style::default().fg(color)
+                    };
+                    spans.push(Span::styled(format!(" | {}m CPU p50 ", minutes), Style::default().fg(Color::DarkGray)));
+                    spans.push(Span::styled(format!("{:.0}%", p.cpu.0), level(p.cpu.0, 70.0, 90.0)));
+                    spans.push(Span::styled(" p95 ", Style::default().fg(Color::DarkGray)));
+                    spans.push(Span::styled(format!("{:.0}%", p.cpu.1), level(p.cpu.1, 70.0, 90.0)));
+                    if let Some((p50, p95)) = p.temp {
+                        let (hot, warm) = self.temp_levels;
+                        spans.push(Span::styled("  temp p50 ", Style::default().fg(Color::DarkGray)));
+                        spans.push(Span::styled(format!("{:.0}°", p50), level(p50, warm, hot)));
+                        spans.push(Span::styled(" p95 ", Style::default().fg(Color::DarkGray)));
+                        spans.push(Span::styled(format!("{:.0}°", p95), level(p95, warm, hot)));
+                    }
+                }
+
+                if self.failed_units > 0 {
+                    spans.push(Span::styled(" | ", Style::default().fg(Color::DarkGray)));
+                    spans.push(Span::styled(
+                        format!("{} FAILED UNIT{} (u)", self.failed_units, if self.failed_units == 1 { "" } else { "S" }),
+                        Style::default().fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD),
+                    ));
+                }
+
+                if let Some((ref text, others)) = self.alert {
+                    let text = if others > 0 { format!("ALERT: {} (+{})", text, others) } else { format!("ALERT: {}", text) };
+                    spans.push(Span::styled(" | ", Style::default().fg(Color::DarkGray)));
+                    spans.push(Span::styled(text, Style::default().fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD)));
+                }
+
+                if let Some(ref p) = self.log_path {
+                    spans.push(Span::styled(" | ", Style::default().fg(Color::DarkGray)));
+                    spans.push(Span::styled(format!("REC: {}", p), Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)));
+                }
+
+                if let Some(ref p) = self.snap_path {
+                    spans.push(Span::styled(" | ", Style::default().fg(Color::DarkGray)));
+                    spans.push(Span::styled(format!("SNAP: {}", p), Style::default().fg(Color::Green)));
+                }
+
+                if let Some(ref m) = self.message {
+                    spans.push(Span::styled(" | ", Style::default().fg(Color::DarkGray)));
+                    spans.push(Span::styled(m.clone(), Style::default().fg(Color::Yellow)));
+                }
+
+                if self.key_hints {
+                    spans.push(Span::styled(" | ", Style::default().fg(Color::DarkGray)));
+                    spans.push(Span::styled("[/] scan rate  l:snap  e:export  d:dump  y:copy  i:iface  D:disk  B:bars  {/}:cores  Tab:view  Alt+l:log  c/m/w:sort  ::cmd  q:quit", Style::default().fg(Color::DarkGray)));
+                }
+
+                let para = Paragraph::new(Line::from(spans));
+                para.render(area, buf);
+            }
+        }
+}
+
+pub mod layout {
+        use ratatui::layout::{Constraint, Direction, Layout, Rect};
+
+        use crate::config::LayoutPreset;
+
+        pub struct LayoutManager {
+            pub cpu_area: Rect,
+            pub ram_area: Rect,
+            pub thermal_area: Rect,
+            pub net_area: Rect,
+            pub disk_area: Rect,
+            pub proc_area: Rect,
+            /// One area per strip panel (latency, custom panels); empty when none are configured.
+            pub panel_areas: Vec<Rect>,
+            pub status_area: Rect,
+        }
+
+        impl LayoutManager {
+            /// Layout (overview preset):
+            /// Top 35%:    [CPU 40% | RAM 25% | Thermals 35%]
+            /// Middle 64%: [Network 20% | Disk 20% | Processes 60%]
+            /// Panels:     [custom panel strip, only when panels are configured]
+            /// Bottom 1:   [Status bar]
+            ///
+            /// The other presets change the percentages; a 0% pane has an empty
+            /// area and is not drawn.
+            pub fn new(size: Rect, panels: usize, panel_height: u16, preset: LayoutPreset) -> Self {
+                let (top, [cpu, ram, thermal], [net, disk, proc]) = match preset {
+                    LayoutPreset::Overview => (35, [40, 25, 35], [20, 20, 60]),
+                    LayoutPreset::Processes => (20, [50, 25, 25], [0, 0, 100]),
+                    LayoutPreset::Network => (25, [40, 25, 35], [55, 25, 20]),
+                    LayoutPreset::Thermals => (60, [30, 15, 55], [20, 20, 60]),
+                };
+                let panel_height = if panels > 0 { panel_height } else { 0 };
+                let main_chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([
+                        Constraint::Percentage(top),
+                        Constraint::Min(1),
+                        Constraint::Length(panel_height),
+                        Constraint::Length(1),
+                    ])
+                    .split(size);
+
+                let top_chunks = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([
+                        Constraint::Percentage(cpu),
+                        Constraint::Percentage(ram),
+                        Constraint::Percentage(thermal),
+                    ])
+                    .split(main_chunks[0]);
+
+                let bottom_chunks = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([
+                        Constraint::Percentage(net),
+                        Constraint::Percentage(disk),
+                        Constraint::Percentage(proc),
+                    ])
+                    .split(main_chunks[1]);
+
+                let panel_areas = if panels > 0 {
+                    Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints(vec![Constraint::Ratio(1, panels as u32); panels])
+                        .split(main_chunks[2])
+                        .to_vec()
+                } else {
+                    Vec::new()
+                };
+
+                Self {
+                    cpu_area: top_chunks[0],
+                    ram_area: top_chunks[1],
+                    thermal_area: top_chunks[2],
+                    net_area: bottom_chunks[0],
+                    disk_area: bottom_chunks[1],
+                    proc_area: bottom_chunks[2],
+                    panel_areas,
+                    status_area: main_chunks[3],
+                }
+            }
+        }
+}
+
+pub mod embed {
+        //! A system monitor pane for other ratatui applications: the collector,
+        //! the sparkline history and the main widgets, without sysmon-tui's own
+        //! event loop, config file, logging or alerts.
+        //!
+        //! ```no_run
+        //! use sysmon_tui::embed::{MonitorPane, TICK};
+        //!
+        //! let mut pane = MonitorPane::new();
+        //! # let mut terminal = ratatui::init();
+        //! loop {
+        //!     pane.update();
+        //!     terminal.draw(|f| f.render_widget(&mut pane, f.area()))?;
+        //!     std::thread::sleep(TICK);
+        //! }
+        //! # Ok::<(), std::io::Error>(())
+        //! ```
+        use std::time::Duration;
+
+        use ratatui::{buffer::Buffer, layout::Rect, widgets::{StatefulWidget, Widget}};
+
+        use crate::collector::Collector;
+        use crate::config::{CpuView, LayoutPreset};
+        use crate::layout::LayoutManager;
+        use crate::types::{ProcessFilter, SortOrder, SparklineHistory, SystemMetrics};
+        use crate::widgets::{
+            CpuWidget, DiskIOSparkWidget, NetworkSparklineWidget, ProcessTableState, ProcessTableWidget, RamGaugeWidget,
+            StatusBarWidget, ThermalWidget,
+        };
+
+        /// How often `MonitorPane::update` expects to be called. Rates and the
+        /// process scan interval are counted in ticks of this length.
+        pub const TICK: Duration = Duration::from_millis(250);
+
+        pub struct MonitorPane {
+            collector: Collector,
+            metrics: SystemMetrics,
+            history: SparklineHistory,
+            pub preset: LayoutPreset,
+            pub cpu_view: CpuView,
+            pub sort_order: SortOrder,
+            pub filter: Option<ProcessFilter>,
+            /// Selected process and scroll position; `select` a row to highlight it.
+            pub table: ProcessTableState,
+        }
+
+        impl Default for MonitorPane {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl MonitorPane {
+            /// Takes a first sample, so the pane has something to draw before
+            /// the first `update`.
+            pub fn new() -> Self {
+                let mut collector = Collector::new();
+                let metrics = collector.collect();
+                Self {
+                    collector,
+                    metrics,
+                    history: SparklineHistory::new(120),
+                    preset: LayoutPreset::Overview,
+                    cpu_view: CpuView::Text,
+                    sort_order: SortOrder::Cpu,
+                    filter: None,
+                    table: ProcessTableState::default(),
+                }
+            }
+
+            /// Rescans processes, sensors and filesystems every `every` ticks
+            /// instead of every 4th (once a second).
+            pub fn with_process_every(mut self, every: u32) -> Self {
+                self.collector.process_every = every.max(1);
+                self
+            }
+
+            pub fn with_preset(mut self, preset: LayoutPreset) -> Self {
+                self.preset = preset;
+                self
+            }
+
+            /// Samples the system. Call once per `TICK`.
+            pub fn update(&mut self) {
+                self.metrics = self.collector.collect();
+                self.history.push(&self.metrics.network, &self.metrics.disk_io, &self.metrics.block_io, self.metrics.dirty);
+            }
+
+            /// The latest sample, for hosts that draw their own widgets.
+            pub fn metrics(&self) -> &SystemMetrics {
+                &self.metrics
+            }
+
+            pub fn history(&self) -> &SparklineHistory {
+                &self.history
+            }
+        }
+
+        impl Widget for &mut MonitorPane {
+            /// Same arrangement as sysmon-tui's own screen for `preset`, with
+            /// the process scan interval in the bottom row.
+            fn render(self, area: Rect, buf: &mut Buffer) {
+                let layout = LayoutManager::new(area, 0, 0, self.preset);
+                let metrics = &self.metrics;
+                CpuWidget::new(&metrics.cpu)
+                    .with_run_queue(metrics.run_queue)
+                    .with_view(self.cpu_view)
+                    .render(layout.cpu_area, buf);
+                RamGaugeWidget::new(&metrics.ram)
+                    .with_breakdown(&metrics.memory)
+                    .render(layout.ram_area, buf);
+                ThermalWidget::new(&metrics.thermals).render(layout.thermal_area, buf);
+                if !layout.net_area.is_empty() {
+                    NetworkSparklineWidget::new(self.history.net_series(None), None, &metrics.network.interfaces)
+                        .render(layout.net_area, buf);
+                }
+                if !layout.disk_area.is_empty() {
+                    DiskIOSparkWidget::new(self.history.disk_series(None), None)
+                        .with_dirty(&self.history.dirty, metrics.dirty)
+                        .render(layout.disk_area, buf);
+                }
+                ProcessTableWidget::new(&metrics.processes, self.sort_order, self.filter.as_ref())
+                    .render(layout.proc_area, buf, &mut self.table);
+                StatusBarWidget::new(self.collector.process_every, TICK.as_millis() as u32, None, None, None, None, None)
+                    .with_key_hints(false)
+                    .render(layout.status_area, buf);
+            }
+        }
+}
+
+pub mod app {
+        use std::io::Write;
+        use crate::types::{
+            DiskIOStats, NetworkStats, RamSwapUsage, RollingStats, SortOrder,
+            SparklineHistory, SystemMetrics,
+        };
+        use crate::collector::Collector;
+        use crate::command::{self, Command, LogAction};
+        use crate::alerts::AlertTracker;
+        use crate::bandwidth::BandwidthTracker;
+        use crate::battery::PowerHistory;
+        use crate::rapl::{DomainPower, RaplSampler};
+        use crate::hwhealth::{Dimm, EdacMonitor, MceMonitor};
+        use crate::guests::{Guest, GuestSampler};
+        use crate::sysdetails::SystemDetails;
+        use crate::units::FailedUnits;
+        use crate::virt::Virtualization;
+        use crate::sessions::{self, Session};
+        #[cfg(feature = "perf")]
+        use crate::perf::{PerfCounters, PerfRates};
+        #[cfg(feature = "ebpf")]
+        use crate::ebpf::{BioLatency, DeviceLatency};
+        use crate::config::{Config, CpuView, IdleMode, LayoutPreset, ProcessColumn, TempAlertConfig, Theme};
+        use crate::diag::{self, Diagnostics};
+        use crate::errors::SysmonError;
+        use crate::connections::{self, Connection, Resolver, Services};
+        use crate::interrupts::{InterruptSampler, InterruptStats};
+        use crate::blockdev::{DeviceIo, DeviceSampler};
+        use crate::nvme::{self, NvmeHealth};
+        use crate::latency::{LatencyMonitor, ReachabilityMonitor};
+        use crate::logfile::{LogWriter, RecordingInfo};
+        use crate::notify::Notifier;
+        use crate::panels::CommandPanel;
+        use crate::journal::JournalTail;
+        use crate::procinfo::ProcessDetail;
+        use crate::state::UiState;
+        use crate::recorder::FlightRecorder;
+        use crate::watchdog::Watchdog;
+        use crate::offenders::OffenderTracker;
+        use crate::summary::SessionSummary;
+        use crate::compare::MetricHistory;
+        use crate::report::ViewReport;
+        use crate::ups::UpsMonitor;
+        use crate::script::ScriptEngine;
+        use crate::sinks::{self, GraphiteSink, MqttSink, SinkWorker, StatsdSink};
+        use crate::types::{keep_order, process_tree, visible_processes, MountChange, ProcessFilter, ProcessInfo, Tab};
+        use std::collections::{HashMap, HashSet};
+        use crate::layout::LayoutManager;
+        use crate::widgets::{
+            apply_theme, CommandPanelWidget, CompareWidget, ConnectionsWidget, CpuWidget, BatteryWidget, HardwareWidget, RaplWidget, DiagnosticsWidget, DiskIOSparkWidget, GuestsWidget, IdleClockWidget, InterruptsWidget, SessionsWidget, SystemDetailsWidget, FailedUnitsWidget, LatencyWidget, ProcessDetailWidget, NetworkSparklineWidget,
+            ProcessTableState, ProcessTableWidget, RamGaugeWidget, StorageWidget, ThermalWidget, StatusBarWidget, UpsWidget, JournalWidget,
+        };
+        #[cfg(feature = "perf")]
+        use crate::widgets::PerfWidget;
+        #[cfg(feature = "ebpf")]
+        use crate::widgets::BioLatencyWidget;
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
+        use ratatui::layout::{Constraint, Layout};
+        use ratatui::style::{Color, Modifier, Style};
+        use ratatui::{widgets::TableState, Frame};
+
+        pub struct AppState {
+            pub metrics: SystemMetrics,
+            pub sort_order: SortOrder,
+            config: Config,
+            collector: Collector,
+            history: SparklineHistory,
+            /// CPU and temperature samples behind the status bar percentiles.
+            rolling: RollingStats,
+            log_dir: String,
+            /// Briefly shows the last snapshot path, cleared after a few ticks.
+            snap_path: Option<String>,
+            snap_ttl: u32,
+            /// Continuous logging (Alt+L toggle)
+            log_writer: Option<LogWriter>,
+            log_path: Option<String>,
+            /// Set while logging was started by an alert's `record_minutes`;
+            /// logging stops at this time.
+            log_until: Option<std::time::Instant>,
+            /// Last flush of the log; rows are buffered in between.
+            log_flushed: std::time::Instant,
+            /// Process filter set via `:filter`.
+            filter: Option<ProcessFilter>,
+            /// Set when the last `:filter` pattern did not compile; the
+            /// previous filter stays active.
+            filter_error: Option<String>,
+            /// Text typed at the `:` prompt; `Some` while the prompt is open.
+            command: Option<String>,
+            /// Feedback from the last command, cleared after a few ticks.
+            message: Option<String>,
+            message_ttl: u32,
+            /// Interface feeding the network sparklines; `None` for all.
+            net_iface: Option<String>,
+            /// Block device shown in the disk sparklines; `None` = all devices.
+            disk_device: Option<String>,
+            bandwidth: BandwidthTracker,
+            /// Full refreshes since the bandwidth totals were last saved.
+            bandwidth_unsaved: u32,
+            /// Content of the large right-hand pane.
+            tab: Tab,
+            /// Selected row in the visible process list, scroll position and
+            /// columns scrolled off to the left.
+            process_table: ProcessTableState,
+            /// Row order (PIDs) held while a selection is active, with
+            /// `process.freeze_on_select`.
+            frozen: Option<Vec<i32>>,
+            /// Show processes nested under their parents.
+            tree: bool,
+            /// Tree branches folded with Left/Space.
+            collapsed: HashSet<i32>,
+            /// Sockets from the last full refresh; only read while their tab is shown.
+            connections: Vec<Connection>,
+            conn_table: TableState,
+            /// Selected row in the storage view.
+            fs_table: TableState,
+            irq_sampler: InterruptSampler,
+            interrupts: InterruptStats,
+            block_sampler: DeviceSampler,
+            block_devices: Vec<DeviceIo>,
+            /// NVMe health, re-read every `nvme::REREAD` for the Storage tab
+            /// and `[nvme]` alerts.
+            nvme: Vec<NvmeHealth>,
+            nvme_read: Option<std::time::Instant>,
+            irq_table: TableState,
+            /// Process detail pane, open while `Some`.
+            detail: Option<ProcessDetail>,
+            power_history: PowerHistory,
+            /// Scanned once at startup; the panel shows when any domain is readable.
+            rapl: RaplSampler,
+            rapl_power: Vec<DomainPower>,
+            /// Mounts seen read-write; one of these going read-only is a
+            /// remount after an I/O error rather than an intentional `ro` mount.
+            writable_mounts: HashSet<String>,
+            edac: EdacMonitor,
+            /// ECC counts from the last full refresh; the panel shows when
+            /// EDAC reports any memory controller.
+            dimms: Vec<Dimm>,
+            mce: MceMonitor,
+            guest_sampler: GuestSampler,
+            /// Detected once at startup.
+            virt: Option<Virtualization>,
+            /// Logins from utmp, re-read on full refreshes while shown.
+            sessions: Vec<Session>,
+            /// Hardware counters, or why they couldn't be opened.
+            #[cfg(feature = "perf")]
+            perf: Result<PerfCounters, String>,
+            #[cfg(feature = "perf")]
+            perf_rates: Option<PerfRates>,
+            /// Block I/O latency programs, while `[layout] biolatency` is on
+            /// and they could be loaded.
+            #[cfg(feature = "ebpf")]
+            biolatency: Option<BioLatency>,
+            /// Per-device histograms from the last full refresh; `None`
+            /// before the second.
+            #[cfg(feature = "ebpf")]
+            bio_devices: Option<Vec<DeviceLatency>>,
+            /// KVM guests from the last full refresh; the panel shows while any run.
+            guests: Vec<Guest>,
+            ups: Option<UpsMonitor>,
+            journal: Option<JournalTail>,
+            /// Metric exporters (MQTT, ...), fed on every full refresh.
+            sinks: Vec<SinkWorker>,
+            recorder: FlightRecorder,
+            watchdog: Watchdog,
+            offenders: OffenderTracker,
+            summary: SessionSummary,
+            resolver: Resolver,
+            services: Services,
+            /// Show raw IPs and port numbers instead of resolved names.
+            raw_addresses: bool,
+            own_pid: i32,
+            /// sysmon-tui's own CPU% and RSS from the last process refresh.
+            self_usage: Option<(f32, u64)>,
+            panels: Vec<CommandPanel>,
+            latency: LatencyMonitor,
+            /// `None` when both connectivity checks are turned off.
+            reachability: Option<ReachabilityMonitor>,
+            script: ScriptEngine,
+            /// Scripted column values from the last full refresh, keyed by PID.
+            column_values: HashMap<i32, Vec<String>>,
+            pub alerts: AlertTracker,
+            notifier: Notifier,
+            pub diagnostics: Diagnostics,
+            show_diagnostics: bool,
+            /// Pane proportions, switched with F1–F4.
+            layout_preset: LayoutPreset,
+            theme: Theme,
+            cpu_view: CpuView,
+            /// Page of cores in the CPU panel; `None` condenses them when
+            /// there are too many to show one by one.
+            core_page: Option<usize>,
+            /// Pages the CPU panel had at the last draw.
+            core_pages: usize,
+            /// Every snapshot metric over recent full refreshes, for the compare view.
+            metric_history: MetricHistory,
+            compare_table: TableState,
+            /// Metrics in the compare view's top and bottom charts.
+            compare: [Option<String>; 2],
+            /// Offset of the ghosted earlier window, and the one `g` turns back on.
+            overlay: Option<std::time::Duration>,
+            last_overlay: std::time::Duration,
+            /// System details overlay, read when opened.
+            system_details: Option<SystemDetails>,
+            failed_units: FailedUnits,
+            show_failed_units: bool,
+            /// Last key press or paste, for `[idle]`.
+            last_input: std::time::Instant,
+            /// Refuses the keys and commands that write files or switch
+            /// profiles; `:lock` / `:unlock` toggle it.
+            locked: bool,
+            /// Started with `--read-only`, so `:unlock` is refused too.
+            read_only: bool,
+            /// Event loop tick, recorded in file metadata and the status bar.
+            tick_ms: u32,
+            pub should_quit: bool,
+        }
+
+        /// Scan rate presets: ticks between process refreshes.
+        /// With 250ms tick: 1=4/s, 2=2/s, 4=1/s, 8=0.5/s, 20=once per 5s
+        const SCAN_PRESETS: &[u32] = &[1, 2, 4, 8, 20];
+
+        /// How often a log being written is flushed. Flushing a compressed
+        /// stream ends the current block, so doing it every row costs ratio.
+        const LOG_FLUSH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+        /// The scan preset closest to `ms`.
+        fn scan_preset(ms: u32) -> u32 {
+            SCAN_PRESETS.iter().copied().min_by_key(|p| (p * 250).abs_diff(ms)).unwrap_or(4)
+        }
+
+        impl Default for AppState {
+            fn default() -> Self {
+                Self::new(Config::default()).expect("default config has no scripts")
+            }
+        }
+
+        impl AppState {
+            pub fn new(config: Config) -> Result<Self, SysmonError> {
+                let log_dir = std::env::var("SYSMON_LOG_DIR")
+                    .unwrap_or_else(|_| "/tmp/sysmon-tui".to_string());
+                let panels = config.panels.iter().map(CommandPanel::spawn).collect();
+                let latency = LatencyMonitor::spawn(&config.latency);
+                let reachability = ReachabilityMonitor::spawn(&config.network);
+                let script = ScriptEngine::new(&config)?;
+                let mut collector = Collector::new();
+                collector.read_priority = config.process.columns.contains(&ProcessColumn::Nice);
+                collector.read_cgroup = config.process.columns.contains(&ProcessColumn::Unit);
+                collector.read_wakeups = config.process.columns.contains(&ProcessColumn::Wakeups);
+                collector.net_exclude = config.network.exclude.clone();
+                if let Some(ms) = config.process.scan_ms {
+                    collector.process_every = scan_preset(ms);
+                }
+                let notifier = Notifier::new(&config.notify);
+                let ups = config.ups.as_ref().map(UpsMonitor::spawn);
+                let journal = config.journal.as_ref().map(JournalTail::spawn);
+                let recorder = FlightRecorder::new(&config.recorder);
+                let watchdog = Watchdog::new(&config.watches);
+                let offenders = OffenderTracker::new(&config.offenders);
+                let mut sinks = Vec::new();
+                if let Some(ref mqtt) = config.mqtt {
+                    let interval = std::time::Duration::from_secs(mqtt.interval_secs.max(1));
+                    sinks.push(SinkWorker::spawn(Box::new(MqttSink::new(mqtt)), interval));
+                }
+                if let Some(ref statsd) = config.statsd {
+                    let interval = std::time::Duration::from_secs(statsd.interval_secs.max(1));
+                    sinks.push(SinkWorker::spawn(Box::new(StatsdSink::new(statsd)), interval));
+                }
+                if let Some(ref graphite) = config.graphite {
+                    let interval = std::time::Duration::from_secs(graphite.interval_secs.max(1));
+                    sinks.push(SinkWorker::spawn(Box::new(GraphiteSink::new(graphite)), interval));
+                }
+
+                let mut app = Self {
+                    metrics: SystemMetrics {
+                        cpu: Vec::new(),
+                        ram: RamSwapUsage { used: 0, total: 0 },
+                        swap: RamSwapUsage { used: 0, total: 0 },
+                        memory: Default::default(),
+                        dirty: Default::default(),
+                        network: NetworkStats {
+                            received_bytes: 0,
+                            transmitted_bytes: 0,
+                            interfaces: Vec::new(),
+                        },
+                        disk_io: DiskIOStats {
+                            read_bytes: 0,
+                            write_bytes: 0,
+                        },
+                        block_io: Vec::new(),
+                        processes: Vec::new(),
+                        thermals: Vec::new(),
+                        filesystems: Vec::new(),
+                        pi_throttle: None,
+                        clusters: Vec::new(),
+                        freq_limits: Vec::new(),
+                        cstates: Vec::new(),
+                        run_queue: None,
+                        sched_wait_ms: None,
+                        batteries: Vec::new(),
+                        clock: None,
+                    },
+                    sort_order: SortOrder::Cpu,
+                    rolling: RollingStats::new(std::time::Duration::from_secs(config.layout.percentile_minutes.saturating_mul(60))),
+                    layout_preset: config.layout.preset,
+                    theme: config.layout.theme,
+                    cpu_view: config.layout.cpu_view,
+                    core_page: None,
+                    core_pages: 1,
+                    metric_history: MetricHistory::new(std::time::Duration::from_secs(config.compare.keep_minutes.saturating_mul(60))),
+                    config,
+                    collector,
+                    history: SparklineHistory::new(120),
+                    log_dir,
+                    snap_path: None,
+                    snap_ttl: 0,
+                    log_writer: None,
+                    log_path: None,
+                    log_until: None,
+                    log_flushed: std::time::Instant::now(),
+                    filter: None,
+                    filter_error: None,
+                    command: None,
+                    message: None,
+                    message_ttl: 0,
+                    net_iface: None,
+                    disk_device: None,
+                    bandwidth: BandwidthTracker::load(),
+                    bandwidth_unsaved: 0,
+                    tab: Tab::Processes,
+                    process_table: ProcessTableState::default(),
+                    frozen: None,
+                    tree: false,
+                    collapsed: HashSet::new(),
+                    connections: Vec::new(),
+                    conn_table: TableState::default(),
+                    fs_table: TableState::default(),
+                    irq_sampler: InterruptSampler::default(),
+                    interrupts: InterruptStats::default(),
+                    block_sampler: DeviceSampler::default(),
+                    block_devices: Vec::new(),
+                    nvme: Vec::new(),
+                    nvme_read: None,
+                    irq_table: TableState::default(),
+                    detail: None,
+                    power_history: PowerHistory::default(),
+                    rapl: RaplSampler::scan(),
+                    rapl_power: Vec::new(),
+                    writable_mounts: HashSet::new(),
+                    edac: EdacMonitor::default(),
+                    dimms: Vec::new(),
+                    mce: MceMonitor::new(),
+                    guest_sampler: GuestSampler::default(),
+                    virt: Virtualization::detect(),
+                    sessions: Vec::new(),
+                    #[cfg(feature = "perf")]
+                    perf: PerfCounters::open(),
+                    #[cfg(feature = "perf")]
+                    perf_rates: None,
+                    #[cfg(feature = "ebpf")]
+                    biolatency: None,
+                    #[cfg(feature = "ebpf")]
+                    bio_devices: None,
+                    guests: Vec::new(),
+                    ups,
+                    journal,
+                    sinks,
+                    recorder,
+                    watchdog,
+                    offenders,
+                    summary: SessionSummary::new(),
+                    resolver: Resolver::new(),
+                    services: Services::load(),
+                    raw_addresses: false,
+                    own_pid: std::process::id() as i32,
+                    self_usage: None,
+                    panels,
+                    latency,
+                    reachability,
+                    script,
+                    column_values: HashMap::new(),
+                    alerts: AlertTracker::default(),
+                    notifier,
+                    diagnostics: Diagnostics::default(),
+                    show_diagnostics: false,
+                    compare_table: TableState::default(),
+                    compare: [None, None],
+                    overlay: None,
+                    last_overlay: std::time::Duration::from_secs(3600),
+                    system_details: None,
+                    failed_units: FailedUnits::spawn(),
+                    show_failed_units: false,
+                    last_input: std::time::Instant::now(),
+                    locked: false,
+                    read_only: false,
+                    tick_ms: crate::embed::TICK.as_millis() as u32,
+                    should_quit: false,
+                };
+                app.sync_socket_bytes();
+                app.sync_biolatency();
+                Ok(app)
+            }
+
+            pub fn update_metrics(&mut self) {
+                // The table order is built once before the refresh and once after
+                let visible: Vec<i32> = self.visible().iter().map(|p| p.pid).collect();
+                let anchor = self.process_table.selected().and_then(|i| visible.get(i).copied());
+                if self.config.process.freeze_on_select && anchor.is_some() && !self.tree {
+                    if self.frozen.is_none() {
+                        self.frozen = Some(visible);
+                    }
+                } else {
+                    self.frozen = None;
+                }
+                let (metrics, took, allocs) = diag::measure(|| self.collector.collect());
+                self.metrics = metrics;
+                self.diagnostics.record_collect(took, self.collector.last_was_full, allocs);
+                let mut changes: Vec<String> = self
+                    .collector
+                    .mount_changes
+                    .iter()
+                    .map(|c| match c {
+                        MountChange::Mounted(mount) => format!("mounted {}", mount),
+                        MountChange::Unmounted(mount) => format!("unmounted {}", mount),
+                    })
+                    .collect();
+                for c in &self.collector.device_changes {
+                    let name = if c.kind == "interface" { self.config.network.alias_and_name(&c.name) } else { c.name.clone() };
+                    changes.push(format!("{} {} {}", c.kind, name, if c.added { "added" } else { "removed" }));
+                    if c.kind == "disk" {
+                        // Pick up a new NVMe drive without waiting out the reread interval
+                        self.nvme_read = None;
+                        if !c.added && self.disk_device.as_ref() == Some(&c.name) {
+                            self.disk_device = None;
+                        }
+                    } else if !c.added && self.net_iface.as_ref() == Some(&c.name) {
+                        self.net_iface = None;
+                    }
+                }
+                if !changes.is_empty() {
+                    self.set_message(changes.join(", "));
+                }
+                if let Some(own) = self.metrics.processes.iter().find(|p| p.pid == self.own_pid) {
+                    self.self_usage = Some((own.cpu_percent, own.mem_bytes));
+                }
+                if self.config.process.hide_self {
+                    self.metrics.processes.retain(|p| p.pid != self.own_pid);
+                }
+                if !self.reselect(anchor) {
+                    if let Some(pid) = anchor {
+                        self.set_message(format!("process {} exited", pid));
+                    }
+                }
+                let sensors = &self.config.sensors;
+                if !sensors.hide.is_empty() || !sensors.rename.is_empty() {
+                    self.metrics.thermals.retain(|t| !sensors.hide.iter().any(|h| *h == t.id || *h == t.label));
+                    for t in &mut self.metrics.thermals {
+                        if let Some(name) = sensors.rename.get(&t.id).or_else(|| sensors.rename.get(&t.label)) {
+                            t.label = name.clone();
+                        }
+                    }
+                }
+                self.history.push(&self.metrics.network, &self.metrics.disk_io, &self.metrics.block_io, self.metrics.dirty);
+                // A device restored from ui-state.tsv that isn't there any more
+                if !self.history.disks.is_empty()
+                    && self.disk_device.as_ref().is_some_and(|d| !self.history.disks.contains_key(d))
+                {
+                    self.disk_device = None;
+                }
+                if self.collector.last_was_full && self.config.layout.percentile_minutes > 0 {
+                    self.rolling.push(&self.metrics.cpu, &self.metrics.thermals);
+                }
+                self.write_log();
+                if self.tab == Tab::Connections && self.collector.last_was_full {
+                    self.connections = connections::read_all();
+                }
+                if self.tab == Tab::Interrupts && self.collector.last_was_full {
+                    self.interrupts = self.irq_sampler.sample();
+                }
+                if self.tab == Tab::Storage && self.collector.last_was_full {
+                    self.block_devices = self.block_sampler.sample();
+                }
+                if self.collector.last_was_full && self.nvme_read.is_none_or(|t| t.elapsed() >= nvme::REREAD) {
+                    self.nvme = nvme::read_all();
+                    self.nvme_read = Some(std::time::Instant::now());
+                }
+                if self.collector.last_was_full && !self.metrics.batteries.is_empty() {
+                    self.power_history.push(&self.metrics.batteries);
+                }
+                if self.collector.last_was_full && !self.rapl.is_empty() {
+                    self.rapl_power = self.rapl.sample();
+                }
+                if self.collector.last_was_full {
+                    let writable = self.metrics.filesystems.iter().filter(|fs| !fs.read_only);
+                    self.writable_mounts.extend(writable.map(|fs| fs.mount.clone()));
+                }
+                if self.collector.last_was_full {
+                    self.dimms = self.edac.read();
+                    self.mce.poll();
+                }
+                if self.collector.last_was_full {
+                    self.guests = self.guest_sampler.sample(&self.metrics.processes);
+                }
+                if self.collector.last_was_full && self.config.layout.sessions {
+                    self.sessions = sessions::read();
+                }
+                #[cfg(feature = "perf")]
+                if let (true, Ok(counters)) = (self.collector.last_was_full, self.perf.as_mut()) {
+                    self.perf_rates = counters.sample();
+                }
+                #[cfg(feature = "ebpf")]
+                if let (true, Some(biolatency)) = (self.collector.last_was_full, self.biolatency.as_mut()) {
+                    let devices = biolatency.sample();
+                    self.bio_devices = biolatency.primed().then_some(devices);
+                }
+                if let Some(ref mut detail) = self.detail {
+                    if self.collector.last_was_full && !detail.exited {
+                        detail.refresh();
+                    }
+                }
+                self.bandwidth.record(&self.metrics.network);
+                if self.collector.last_was_full {
+                    self.column_values = self.script.eval_columns(&self.metrics);
+                    let mut firing = self.script.eval_alerts(&self.metrics);
+                    if let Some((used, quota)) = self.bandwidth.quota_usage(&self.config.network) {
+                        let pct = used as f64 / quota.max(1) as f64 * 100.0;
+                        if pct >= self.config.network.quota_warn_percent {
+                            firing.push(("bandwidth quota".to_string(), format!("{:.0}% used", pct)));
+                        }
+                    }
+                    if let Some(r) = self.reachability.as_ref().map(ReachabilityMonitor::status) {
+                        if let (Some(gateway), Some(false)) = (r.gateway, r.gateway_up) {
+                            firing.push(("gateway unreachable".to_string(), format!("{} not answering", gateway)));
+                        }
+                        if let (Some(target), Some(false)) = (r.target, r.target_up) {
+                            firing.push(("network unreachable".to_string(), format!("{} not answering", target)));
+                        }
+                    }
+                    firing.extend(self.disk_alerts());
+                    firing.extend(self.temp_alerts());
+                    firing.extend(self.nvme_alerts());
+                    firing.extend(self.ecc_alerts());
+                    firing.extend(self.clock_alerts());
+                    firing.extend(self.watchdog.check(&self.metrics.processes));
+                    self.offenders.record(&self.metrics.processes, self.metrics.ram.total);
+                    if let Some(status) = self.ups.as_ref().map(UpsMonitor::status).filter(|s| s.on_battery()) {
+                        let charge = status.charge_percent().map(|c| format!("charge {:.0}%", c)).unwrap_or_default();
+                        firing.push(("ups on battery".to_string(), charge));
+                    }
+                    let newly = self.alerts.update(firing);
+                    for name in &newly {
+                        if let Some(a) = self.alerts.active.iter().find(|a| a.name == *name) {
+                            self.notifier.send(&a.name, &a.detail);
+                        }
+                    }
+
+                    let snapshot = sinks::snapshot(
+                        &self.metrics,
+                        (
+                            self.history.net_rx.back().copied().unwrap_or(0) * 4,
+                            self.history.net_tx.back().copied().unwrap_or(0) * 4,
+                        ),
+                        self.collector.process_every as f64 * 0.25,
+                        self.alerts.active.iter().map(|a| a.name.clone()).collect(),
+                    );
+                    self.summary.record(&snapshot, &self.metrics.processes);
+                    self.metric_history.record(&snapshot);
+                    if self.compare == [None, None] {
+                        // CPU against the first sensor, or memory without one
+                        let names = self.metric_history.names();
+                        let second = names.iter().find(|n| n.starts_with("temp.")).map_or("ram.percent", |n| n.as_str());
+                        self.compare = [Some("cpu.avg".to_string()), Some(second.to_string())];
+                    }
+                    self.recorder.record(&snapshot, &self.metrics.processes);
+                    for sink in &self.sinks {
+                        sink.update(snapshot.clone());
+                    }
+                    if let Some(e) = self.sinks.iter().find_map(SinkWorker::take_error) {
+                        self.set_message(format!("export failed: {}", e));
+                    }
+                    // After recording, so a dump includes the refresh that fired.
+                    for name in &newly {
+                        self.run_alert_actions(name);
+                    }
+
+                    self.bandwidth_unsaved += 1;
+                    if self.bandwidth_unsaved >= 60 {
+                        self.bandwidth.save();
+                        self.bandwidth_unsaved = 0;
+                    }
+                }
+                if self.log_until.is_some_and(|t| std::time::Instant::now() >= t) {
+                    self.toggle_log();
+                }
+                // Fade out snapshot notification
+                if self.snap_ttl > 0 {
+                    self.snap_ttl -= 1;
+                    if self.snap_ttl == 0 {
+                        self.snap_path = None;
+                    }
+                }
+                if self.message_ttl > 0 {
+                    self.message_ttl -= 1;
+                    if self.message_ttl == 0 {
+                        self.message = None;
+                    }
+                }
+            }
+
+            /// Filesystems past a `[[disk_alert]]` threshold, or remounted read-only
+            /// since startup. The alert name carries the level, so crossing from
+            /// warning to critical notifies again.
+            fn disk_alerts(&self) -> Vec<(String, String)> {
+                let mut firing = Vec::new();
+                for fs in &self.metrics.filesystems {
+                    // Not tied to a rule: the kernel remounts read-only on
+                    // errors, and everything else keeps looking fine
+                    if fs.read_only && self.writable_mounts.contains(&fs.mount) {
+                        firing.push((
+                            format!("disk {} read-only", fs.mount),
+                            format!("{} remounted read-only, writes will fail", fs.device),
+                        ));
+                    }
+                    let pct = fs.used_percent();
+                    let rule = self
+                        .config
+                        .disk_alerts
+                        .iter()
+                        .find(|r| r.mount.as_deref() == Some(fs.mount.as_str()))
+                        .or_else(|| self.config.disk_alerts.iter().find(|r| r.mount.is_none()));
+                    let Some(rule) = rule else {
+                        continue;
+                    };
+                    let level = if pct >= rule.critical_percent {
+                        "critical"
+                    } else if pct >= rule.warn_percent {
+                        "warning"
+                    } else {
+                        continue;
+                    };
+                    firing.push((
+                        format!("disk {} {}", fs.mount, level),
+                        format!("{:.0}% used, {} free", pct, crate::report::human_bytes(fs.available)),
+                    ));
+                }
+                firing
+            }
+
+            /// Sensors above the limit of their `[[temp_alert]]` rule.
+            fn temp_alerts(&self) -> Vec<(String, String)> {
+                self.metrics
+                    .thermals
+                    .iter()
+                    .filter_map(|t| {
+                        let rule = TempAlertConfig::find(&self.config.temp_alerts, &[&t.raw_label, &t.label])?;
+                        (t.temp_celsius > rule.above).then(|| {
+                            (format!("temp {}", t.label), format!("{:.1}°C > {:.0}°C", t.temp_celsius, rule.above))
+                        })
+                    })
+                    .collect()
+            }
+
+            /// NVMe drives past an `[nvme]` wear or temperature threshold, or
+            /// reporting a SMART critical warning.
+            fn nvme_alerts(&self) -> Vec<(String, String)> {
+                let config = &self.config.nvme;
+                let mut firing = Vec::new();
+                for drive in &self.nvme {
+                    if let Some(used) = drive.percent_used {
+                        let level = if used >= config.wear_critical_percent {
+                            Some("critical")
+                        } else if used >= config.wear_warn_percent {
+                            Some("warning")
+                        } else {
+                            None
+                        };
+                        if let Some(level) = level {
+                            firing.push((format!("nvme {} wear {}", drive.name, level), format!("{}% of endurance used", used)));
+                        }
+                    }
+                    if let Some(temp) = drive.temp_celsius.filter(|t| *t >= config.temp_warn) {
+                        firing.push((
+                            format!("nvme {} temp", drive.name),
+                            format!("{:.0}°C >= {:.0}°C", temp, config.temp_warn),
+                        ));
+                    }
+                    let warnings = drive.warnings();
+                    if !warnings.is_empty() {
+                        firing.push((format!("nvme {} critical warning", drive.name), warnings.join(", ")));
+                    }
+                }
+                firing
+            }
+
+            /// A clock nothing is steering, or one still far off; metrics recorded
+            /// with either carry bad timestamps.
+            fn clock_alerts(&self) -> Vec<(String, String)> {
+                let config = &self.config.clock;
+                let mut firing = Vec::new();
+                if let Some(c) = self.metrics.clock {
+                    if !c.synchronized && config.warn_unsynchronized {
+                        firing.push(("clock unsynchronized".to_string(), "no NTP sync, timestamps may drift".to_string()));
+                    }
+                    if c.offset_ms.abs() >= config.offset_warn_ms {
+                        firing.push((
+                            "clock offset".to_string(),
+                            format!("{:+.0} ms >= {:.0} ms", c.offset_ms, config.offset_warn_ms),
+                        ));
+                    }
+                }
+                firing
+            }
+
+            /// DIMMs that logged ECC errors, and machine checks or kernel-logged
+            /// hardware errors, since startup. Any new error counts, and the
+            /// alert stays up for the rest of the session.
+            fn ecc_alerts(&self) -> Vec<(String, String)> {
+                let mut firing = Vec::new();
+                let new_mce = self.mce.new_counts();
+                if new_mce > 0 {
+                    firing.push(("machine check".to_string(), format!("+{} since start", new_mce)));
+                }
+                if self.mce.new_events > 0 {
+                    let last = self.mce.events.back().map(|e| e.message.clone()).unwrap_or_default();
+                    firing.push(("hardware error".to_string(), format!("{} since start, last: {}", self.mce.new_events, last)));
+                }
+                for d in &self.dimms {
+                    if d.new_uncorrectable > 0 {
+                        firing.push((
+                            format!("ecc {} uncorrectable", d.label),
+                            format!("+{} since start, {} total", d.new_uncorrectable, d.uncorrectable),
+                        ));
+                    }
+                    if d.new_correctable > 0 {
+                        firing.push((
+                            format!("ecc {} correctable", d.label),
+                            format!("+{} since start, {} total", d.new_correctable, d.correctable),
+                        ));
+                    }
+                }
+                firing
+            }
+
+            /// Flushes persistent state; call once before exiting. Returns a
+            /// note to print once the terminal is restored. A `--read-only`
+            /// session leaves the UI state and summary unwritten; bandwidth
+            /// totals are saved regardless, as they are during the run.
+            pub fn shutdown(&mut self) -> Option<String> {
+                self.bandwidth.save();
+                if self.config.state.restore && !self.read_only {
+                    let _ = self.ui_state().save();
+                }
+                if let Some(writer) = self.log_writer.take() {
+                    let _ = writer.finish();
+                }
+                (self.config.summary.on_exit && !self.read_only).then(|| self.write_summary())
+            }
+
+            /// Scan rate, layout and CPU view are only saved when changed from
+            /// what the config sets, so that editing the config takes effect.
+            fn ui_state(&self) -> UiState {
+                let every = self.collector.process_every;
+                UiState {
+                    tab: Some(self.tab),
+                    sort: Some(self.sort_order),
+                    process_every: (self.config.process.scan_ms.map(scan_preset) != Some(every)).then_some(every),
+                    filter: self.filter.as_ref().map(|f| f.text.clone()),
+                    tree: self.tree,
+                    net_iface: self.net_iface.clone(),
+                    disk_device: self.disk_device.clone(),
+                    raw_addresses: self.raw_addresses,
+                    profile: self.config.profile.clone(),
+                    layout: (self.layout_preset != self.config.layout.preset).then_some(self.layout_preset),
+                    cpu_view: (self.cpu_view != self.config.layout.cpu_view).then_some(self.cpu_view),
+                }
+            }
+
+            /// Puts back the UI state saved by the last run.
+            pub fn restore(&mut self, state: UiState) {
+                if let Some(tab) = state.tab {
+                    self.set_tab(tab);
+                }
+                if let Some(order) = state.sort {
+                    self.set_sort(order);
+                }
+                if let Some(every) = state.process_every.filter(|e| SCAN_PRESETS.contains(e)) {
+                    self.collector.process_every = every;
+                }
+                self.filter = state.filter.and_then(|text| ProcessFilter::parse(&text).ok());
+                self.tree = state.tree;
+                self.net_iface = state.net_iface;
+                self.disk_device = state.disk_device;
+                self.raw_addresses = state.raw_addresses;
+                if let Some(preset) = state.layout {
+                    self.layout_preset = preset;
+                }
+                if let Some(view) = state.cpu_view {
+                    self.cpu_view = view;
+                }
+            }
+
+            /// Metadata for a new recording; `None` unless `[log] metadata` is on.
+            fn recording_info(&self) -> Option<RecordingInfo> {
+                self.config.log.metadata.then_some(RecordingInfo {
+                    cores: self.metrics.cpu.len(),
+                    ram_bytes: self.metrics.ram.total,
+                    tick_ms: self.tick_ms,
+                    process_every: self.collector.process_every,
+                })
+            }
+
+            fn set_message(&mut self, msg: String) {
+                self.message = Some(msg);
+                self.message_ttl = 16; // ~4 seconds at 250ms tick
+            }
+
+            fn snapshot(&mut self) {
+                let _ = std::fs::create_dir_all(&self.log_dir);
+                let now = chrono::Local::now();
+                let filename = format!("snap-{}.csv", now.format("%Y-%m-%d_%H-%M-%S"));
+                let path = format!("{}/{}", self.log_dir, filename);
+                if let Ok(file) = std::fs::File::create(&path) {
+                    let mut w = std::io::BufWriter::new(file);
+                    if let Some(info) = self.recording_info() {
+                        let _ = info.write(&mut w);
+                    }
+                    let _ = writeln!(w, "timestamp,pid,name,cpu_percent,mem_bytes");
+                    let ts = now.format("%Y-%m-%dT%H:%M:%S%.3f");
+                    for p in &self.metrics.processes {
+                        let _ = writeln!(w, "{},{},{},{:.1},{}", ts, p.pid, p.name, p.cpu_percent, p.mem_bytes);
+                    }
+                    let _ = w.flush();
+                    self.snap_path = Some(path);
+                    self.snap_ttl = 12; // ~3 seconds at 250ms tick
+                }
+            }
+
+            /// Writes a plain-text rendering of the current view to the log dir.
+            fn export_report(&mut self) {
+                let report = ViewReport {
+                    metrics: &self.metrics,
+                    processes: self.visible(),
+                    sort_order: self.sort_order,
+                    filter: self.filter.as_ref().map(|f| f.text.as_str()),
+                    extra_columns: self.script.column_names(),
+                    extra_values: &self.column_values,
+                    alerts: &self.alerts.active,
+                    panels: self.panels.iter().map(|p| (p.title.clone(), p.output().text)).collect(),
+                    // History holds bytes per 250ms tick
+                    net_rate: (
+                        self.history.net_rx.back().copied().unwrap_or(0) * 4,
+                        self.history.net_tx.back().copied().unwrap_or(0) * 4,
+                    ),
+                    network: &self.config.network,
+                };
+                let text = crate::report::for_export(|| report.render());
+                let _ = std::fs::create_dir_all(&self.log_dir);
+                let now = chrono::Local::now();
+                let path = format!("{}/report-{}.txt", self.log_dir, now.format("%Y-%m-%d_%H-%M-%S"));
+                match std::fs::write(&path, text) {
+                    Ok(()) => self.set_message(format!("report: {}", path)),
+                    Err(e) => self.set_message(format!("report failed: {}", e)),
+                }
+            }
+
+            /// Writes the ranking of processes longest over the `[offenders]`
+            /// thresholds this session to the log dir.
+            fn offender_report(&mut self) {
+                let _ = std::fs::create_dir_all(&self.log_dir);
+                let now = chrono::Local::now();
+                let path = format!("{}/offenders-{}.txt", self.log_dir, now.format("%Y-%m-%d_%H-%M-%S"));
+                match std::fs::write(&path, crate::report::for_export(|| self.offenders.report())) {
+                    Ok(()) => self.set_message(format!("offenders: {}", path)),
+                    Err(e) => self.set_message(format!("offender report failed: {}", e)),
+                }
+            }
+
+            /// Writes the session summary to the log dir; returns what to tell the user.
+            fn write_summary(&self) -> String {
+                let _ = std::fs::create_dir_all(&self.log_dir);
+                let now = chrono::Local::now();
+                let path = format!("{}/summary-{}.md", self.log_dir, now.format("%Y-%m-%d_%H-%M-%S"));
+                match std::fs::write(&path, crate::report::for_export(|| self.summary.render(&self.alerts.fired))) {
+                    Ok(()) => format!("summary: {}", path),
+                    Err(e) => format!("summary failed: {}", e),
+                }
+            }
+
+            /// Processes in the order the table shows them.
+            fn visible(&self) -> Vec<&ProcessInfo> {
+                if self.tree {
+                    process_tree(&self.metrics.processes, self.sort_order, self.filter.as_ref(), &self.collapsed)
+                        .into_iter()
+                        .map(|r| r.process)
+                        .collect()
+                } else {
+                    let mut list = visible_processes(&self.metrics.processes, self.sort_order, self.filter.as_ref());
+                    if let Some(ref order) = self.frozen {
+                        keep_order(&mut list, order);
+                    }
+                    list
+                }
+            }
+
+            fn selected_pid(&self) -> Option<i32> {
+                self.process_table.selected().and_then(|i| self.visible().get(i).map(|p| p.pid))
+            }
+
+            /// Moves the selection to wherever `pid` now is in the table, so it
+            /// follows the process rather than the row when the list re-sorts.
+            /// When `pid` is gone the selection is cleared instead of landing
+            /// on whatever took its row; returns `false` then.
+            fn reselect(&mut self, pid: Option<i32>) -> bool {
+                let Some(pid) = pid else {
+                    return true;
+                };
+                let row = self.visible().iter().position(|p| p.pid == pid);
+                self.process_table.select(row);
+                row.is_some()
+            }
+
+            /// Changes the sort key, keeping the selected process selected. An
+            /// explicit re-sort also releases a frozen order.
+            fn set_sort(&mut self, order: SortOrder) {
+                let anchor = self.selected_pid();
+                self.sort_order = order;
+                self.sync_wakeups();
+                self.frozen = None;
+                self.reselect(anchor);
+            }
+
+            /// Reads `/proc/<pid>/schedstat` only while wakeups are shown or
+            /// sorted by.
+            fn sync_wakeups(&mut self) {
+                self.collector.read_wakeups =
+                    self.sort_order == SortOrder::Wakeups || self.config.process.columns.contains(&ProcessColumn::Wakeups);
+            }
+
+            /// Attaches the socket byte counters while the net column is
+            /// configured, and detaches them when it no longer is.
+            fn sync_socket_bytes(&mut self) {
+                let wanted = self.config.process.columns.contains(&ProcessColumn::Net);
+                #[cfg(feature = "ebpf")]
+                match (wanted, self.collector.socket_bytes.is_some()) {
+                    (true, false) => match crate::ebpf::SocketBytes::open() {
+                        Ok(counters) => self.collector.socket_bytes = Some(counters),
+                        Err(e) => self.set_message(format!("net column: {}", e)),
+                    },
+                    (false, true) => self.collector.socket_bytes = None,
+                    _ => {}
+                }
+                #[cfg(not(feature = "ebpf"))]
+                if wanted {
+                    self.set_message("net column: built without the ebpf feature".to_string());
+                }
+            }
+
+            /// Loads the block I/O latency programs while `[layout] biolatency`
+            /// is on, and detaches them when it no longer is. A failure is
+            /// reported once and leaves the panel out.
+            fn sync_biolatency(&mut self) {
+                let wanted = self.config.layout.biolatency;
+                #[cfg(feature = "ebpf")]
+                match (wanted, self.biolatency.is_some()) {
+                    (true, false) => match BioLatency::open() {
+                        Ok(biolatency) => self.biolatency = Some(biolatency),
+                        Err(e) => self.set_message(format!("latency panel: {}", e)),
+                    },
+                    (false, true) => {
+                        self.biolatency = None;
+                        self.bio_devices = None;
+                    }
+                    _ => {}
+                }
+                #[cfg(not(feature = "ebpf"))]
+                if wanted {
+                    self.set_message("latency panel: built without the ebpf feature".to_string());
+                }
+            }
+
+            /// Shifts the process table's columns after Name by `delta`,
+            /// stopping with the last column still on screen.
+            fn scroll_columns(&mut self, delta: isize) {
+                let columns = &self.config.process.columns;
+                let count = 2
+                    + if self.tree { 2 } else { 0 }
+                    + columns.len()
+                    + usize::from(self.sort_order == SortOrder::Wakeups && !columns.contains(&ProcessColumn::Wakeups))
+                    + self.script.column_names().len();
+                self.process_table.hscroll = self.process_table.hscroll.saturating_add_signed(delta).min(count - 1);
+            }
+
+            /// Folds (`Some(true)`), unfolds (`Some(false)`) or toggles the
+            /// selected tree branch. Folding a leaf or an already folded
+            /// branch moves the selection to its parent instead.
+            fn fold_selected(&mut self, fold: Option<bool>) {
+                let rows = process_tree(&self.metrics.processes, self.sort_order, self.filter.as_ref(), &self.collapsed);
+                let Some(row) = self.process_table.selected().and_then(|i| rows.get(i)) else {
+                    return;
+                };
+                let pid = row.process.pid;
+                let folded = row.hidden > 0;
+                match fold.unwrap_or(!folded) {
+                    true if row.has_children && !folded => {
+                        self.collapsed.insert(pid);
+                    }
+                    true => {
+                        let parent = row.process.ppid;
+                        if let Some(i) = rows.iter().position(|r| Some(r.process.pid) == parent) {
+                            self.process_table.select(Some(i));
+                        }
+                    }
+                    false => {
+                        self.collapsed.remove(&pid);
+                    }
+                }
+            }
+
+            /// Selection of the active tab's table and that table's length.
+            fn selection(&mut self) -> (&mut Option<usize>, usize) {
+                match self.tab {
+                    Tab::Processes => {
+                        let len = self.visible().len();
+                        (self.process_table.table.selected_mut(), len)
+                    }
+                    Tab::Connections => (self.conn_table.selected_mut(), self.connections.len()),
+                    Tab::Storage => (self.fs_table.selected_mut(), self.metrics.filesystems.len()),
+                    Tab::Interrupts => (self.irq_table.selected_mut(), self.interrupts.sources.len()),
+                    Tab::Compare => (self.compare_table.selected_mut(), self.metric_history.names().len()),
+                }
+            }
+
+            /// Moves the active tab's selection by `delta` rows, clamped to the list.
+            fn move_selection(&mut self, delta: isize) {
+                let (selected, len) = self.selection();
+                if len == 0 {
+                    *selected = None;
+                    return;
+                }
+                let next = match *selected {
+                    Some(i) => i.saturating_add_signed(delta),
+                    None if delta < 0 => len - 1,
+                    None => 0,
+                };
+                *selected = Some(next.min(len - 1));
+            }
+
+            fn set_tab(&mut self, tab: Tab) {
+                self.tab = tab;
+                if tab == Tab::Connections && self.connections.is_empty() {
+                    self.connections = connections::read_all();
+                }
+                if tab == Tab::Interrupts {
+                    // Restart the baseline so the first rates aren't averaged
+                    // over the time the view was hidden
+                    self.irq_sampler = InterruptSampler::default();
+                    self.irq_sampler.sample();
+                }
+                if tab == Tab::Storage {
+                    self.block_sampler = DeviceSampler::default();
+                    self.block_devices = self.block_sampler.sample();
+                }
+            }
+
+            /// Opens the detail pane for the selected process.
+            fn open_detail(&mut self) {
+                if self.tab != Tab::Processes {
+                    return;
+                }
+                let visible = self.visible();
+                if let Some(p) = self.process_table.selected().and_then(|i| visible.get(i)) {
+                    self.detail = Some(ProcessDetail::new(p.pid));
+                }
+            }
+
+            fn copy_selected(&mut self) {
+                let visible = self.visible();
+                let Some(p) = self.process_table.selected().and_then(|i| visible.get(i)) else {
+                    self.set_message("no process selected".to_string());
+                    return;
+                };
+                let text = format!(
+                    "PID: {}\nName: {}\nCommand: {}\nCPU: {:.1}%\nMemory: {} bytes",
+                    p.pid, p.name, p.cmd, p.cpu_percent, p.mem_bytes,
+                );
+                let msg = match crate::clipboard::copy(&text) {
+                    Ok(()) => format!("copied {} ({})", p.name, p.pid),
+                    Err(e) => format!("copy failed: {}", e),
+                };
+                self.set_message(msg);
+            }
+
+            /// Steps the network sparkline source: all -> each interface -> all.
+            fn cycle_interface(&mut self) {
+                let names: Vec<&String> = self.history.ifaces.keys().collect();
+                let next = match self.net_iface {
+                    None => names.first(),
+                    Some(ref cur) => names.iter().position(|n| *n == cur).and_then(|i| names.get(i + 1)),
+                };
+                self.net_iface = next.map(|n| n.to_string());
+            }
+
+            /// Steps through the pages of cores and back to the condensed view.
+            fn page_cores(&mut self, forward: bool) {
+                if self.core_pages <= 1 {
+                    self.core_page = None;
+                    self.set_message("all cores fit the CPU panel".to_string());
+                    return;
+                }
+                let last = self.core_pages - 1;
+                self.core_page = match (self.core_page, forward) {
+                    (None, true) => Some(0),
+                    (None, false) => Some(last),
+                    (Some(p), true) if p < last => Some(p + 1),
+                    (Some(p), false) if p > 0 => Some(p - 1),
+                    _ => None,
+                };
+            }
+
+            fn cycle_disk_device(&mut self) {
+                let names: Vec<&String> =
+                    self.history.disks.keys().filter(|name| !crate::blockdev::pseudo_device(name)).collect();
+                let next = match self.disk_device {
+                    None => names.first(),
+                    Some(ref cur) => names.iter().position(|n| *n == cur).and_then(|i| names.get(i + 1)),
+                };
+                self.disk_device = next.map(|n| n.to_string());
+            }
+
+            /// Captures data for a newly fired `[[alert]]` as its config asks.
+            fn run_alert_actions(&mut self, name: &str) {
+                let Some(rule) = self.config.alerts.iter().find(|a| a.name == name) else {
+                    return;
+                };
+                let (snapshot, dump, record) = (rule.snapshot, rule.dump, rule.record_minutes);
+                if snapshot {
+                    self.snapshot();
+                }
+                if dump {
+                    self.dump_recorder();
+                }
+                if let Some(minutes) = record {
+                    // `None` when too far ahead to represent: record until stopped
+                    let until = std::time::Instant::now().checked_add(std::time::Duration::from_secs(minutes.saturating_mul(60)));
+                    if self.log_writer.is_none() {
+                        self.toggle_log();
+                        self.log_until = self.log_writer.is_some().then_some(until).flatten();
+                    } else if let Some(current) = self.log_until {
+                        // Already recording for an alert: extend, never shorten.
+                        self.log_until = until.map(|until| current.max(until));
+                    }
+                }
+            }
+
+            fn dump_recorder(&mut self) {
+                if !self.recorder.is_enabled() {
+                    self.set_message("flight recorder is disabled ([recorder] minutes = 0)".to_string());
+                    return;
+                }
+                match self.recorder.dump(&self.log_dir, self.recording_info().as_ref()) {
+                    Ok((path, frames)) => self.set_message(format!("dumped {} refreshes -> {}", frames, path)),
+                    Err(e) => self.set_message(format!("dump failed: {}", e)),
+                }
+            }
+
+            fn toggle_log(&mut self) {
+                if let Some(writer) = self.log_writer.take() {
+                    self.log_path = None;
+                    self.log_until = None;
+                    if let Err(e) = writer.finish() {
+                        self.set_message(format!("log: {}", e));
+                    }
+                } else {
+                    let _ = std::fs::create_dir_all(&self.log_dir);
+                    let now = chrono::Local::now();
+                    let ext = self.config.log.compression.extension();
+                    let filename = format!("sysmon-{}.{}", now.format("%Y-%m-%d_%H-%M-%S"), ext);
+                    let path = format!("{}/{}", self.log_dir, filename);
+                    if let Ok(mut w) = LogWriter::create(path.as_ref(), self.config.log.compression) {
+                        if let Some(info) = self.recording_info() {
+                            let _ = info.write(&mut w);
+                        }
+                        let _ = writeln!(w, "timestamp,pid,name,cpu_percent,mem_bytes");
+                        self.log_writer = Some(w);
+                        self.log_path = Some(path);
+                    }
+                }
+            }
+
+            fn write_log(&mut self) {
+                if let Some(ref mut writer) = self.log_writer {
+                    let now = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f");
+                    for p in &self.metrics.processes {
+                        let _ = writeln!(writer, "{},{},{},{:.1},{}", now, p.pid, p.name, p.cpu_percent, p.mem_bytes);
+                    }
+                    if self.log_flushed.elapsed() >= LOG_FLUSH_INTERVAL {
+                        let _ = writer.flush();
+                        self.log_flushed = std::time::Instant::now();
+                    }
+                }
+            }
+
+            /// Steps through the base config and each profile in turn.
+            fn cycle_profile(&mut self) {
+                if self.config.profiles.is_empty() {
+                    self.set_message("no [profile.<name>] tables in the config".to_string());
+                    return;
+                }
+                let next = match &self.config.profile {
+                    None => 0,
+                    Some(current) => self.config.profiles.iter().position(|p| p == current).map_or(0, |i| i + 1),
+                };
+                self.switch_profile(self.config.profiles.get(next).cloned());
+            }
+
+            /// Re-reads the config with `name` merged in and applies what can
+            /// change at runtime: layout, columns, thresholds, scan rate, panels,
+            /// latency and connectivity checks, journal, scripts and watches.
+            /// Export sinks, UPS, the flight recorder, logging, timezone and
+            /// number format keep their startup settings.
+            fn switch_profile(&mut self, name: Option<String>) {
+                let config = match Config::load(name.as_deref()) {
+                    Ok(config) => config,
+                    Err(e) => return self.set_message(e.to_string()),
+                };
+                let script = match ScriptEngine::new(&config) {
+                    Ok(script) => script,
+                    Err(e) => return self.set_message(e.to_string()),
+                };
+                self.script = script;
+                self.column_values.clear();
+                self.panels = config.panels.iter().map(CommandPanel::spawn).collect();
+                self.latency = LatencyMonitor::spawn(&config.latency);
+                self.reachability = ReachabilityMonitor::spawn(&config.network);
+                self.journal = config.journal.as_ref().map(JournalTail::spawn);
+                self.notifier = Notifier::new(&config.notify);
+                self.watchdog = Watchdog::new(&config.watches);
+                self.offenders.set_config(&config.offenders);
+                self.collector.read_priority = config.process.columns.contains(&ProcessColumn::Nice);
+                self.collector.read_cgroup = config.process.columns.contains(&ProcessColumn::Unit);
+                self.collector.net_exclude = config.network.exclude.clone();
+                if let Some(ms) = config.process.scan_ms {
+                    self.collector.process_every = scan_preset(ms);
+                }
+                if config.layout.percentile_minutes != self.config.layout.percentile_minutes {
+                    self.rolling = RollingStats::new(std::time::Duration::from_secs(config.layout.percentile_minutes.saturating_mul(60)));
+                }
+                self.process_table.hscroll = 0;
+                self.layout_preset = config.layout.preset;
+                self.theme = config.layout.theme;
+                self.cpu_view = config.layout.cpu_view;
+                self.set_message(format!("profile: {}", config.profile.as_deref().unwrap_or("default")));
+                self.config = config;
+                self.sync_wakeups();
+                self.sync_socket_bytes();
+                self.sync_biolatency();
+            }
+
+            /// Ghosts the window from `offset` ago behind the compare charts.
+            fn set_overlay(&mut self, offset: Option<std::time::Duration>) {
+                let keep = self.metric_history.keep().saturating_sub(crate::compare::WINDOW);
+                match offset {
+                    Some(offset) if offset > keep => self.set_message(format!(
+                        "overlay: only {} of history is kept ([compare] keep_minutes)",
+                        crate::compare::label(keep)
+                    )),
+                    Some(offset) => {
+                        self.overlay = Some(offset);
+                        self.last_overlay = offset;
+                        self.set_message(format!("overlay: {} ago", crate::compare::label(offset)));
+                    }
+                    None => self.overlay = None,
+                }
+            }
+
+            fn set_layout(&mut self, preset: LayoutPreset) {
+                self.layout_preset = preset;
+                self.set_message(format!("layout: {}", preset.label()));
+            }
+
+            fn scan_faster(&mut self) {
+                let cur = self.collector.process_every;
+                for &p in SCAN_PRESETS.iter().rev() {
+                    if p < cur {
+                        self.collector.process_every = p;
+                        return;
+                    }
+                }
+            }
+
+            fn scan_slower(&mut self) {
+                let cur = self.collector.process_every;
+                for &p in SCAN_PRESETS.iter() {
+                    if p > cur {
+                        self.collector.process_every = p;
+                        return;
+                    }
+                }
+            }
+
+            /// The event loop's tick, `embed::TICK` unless set.
+            pub fn set_tick_rate(&mut self, tick: std::time::Duration) {
+                self.tick_ms = u32::try_from(tick.as_millis()).unwrap_or(u32::MAX);
+            }
+
+            /// Locks the session for good, for `--read-only`.
+            pub fn set_read_only(&mut self) {
+                self.locked = true;
+                self.read_only = true;
+            }
+
+            fn set_locked(&mut self, locked: bool) {
+                if !locked && self.read_only {
+                    return self.set_message("started with --read-only; cannot unlock".to_string());
+                }
+                self.locked = locked;
+                self.set_message(if locked { "locked: read-only" } else { "unlocked" }.to_string());
+            }
+
+            fn run_command(&mut self, line: &str) {
+                match command::parse(line) {
+                    Ok(command) if self.locked && command.needs_unlock() => {
+                        self.set_message(format!("locked: :{} is disabled", line.trim()));
+                    }
+                    Ok(Command::Sort(order)) => self.set_sort(order),
+                    Ok(Command::Filter(None)) => {
+                        self.filter = None;
+                        self.filter_error = None;
+                    }
+                    Ok(Command::Filter(Some(text))) => match ProcessFilter::parse(&text) {
+                        Ok(filter) => {
+                            self.filter = Some(filter);
+                            self.filter_error = None;
+                        }
+                        Err(e) => self.filter_error = Some(format!("{}: {}", text, e)),
+                    },
+                    Ok(Command::Log(action)) => {
+                        let active = self.log_writer.is_some();
+                        match action {
+                            LogAction::Start if active => {}
+                            LogAction::Stop if !active => {}
+                            _ => self.toggle_log(),
+                        }
+                    }
+                    Ok(Command::Snapshot) => self.snapshot(),
+                    Ok(Command::Tree) => self.tree = !self.tree,
+                    Ok(Command::Dump) => self.dump_recorder(),
+                    Ok(Command::Export) => self.export_report(),
+                    Ok(Command::Offenders) => self.offender_report(),
+                    Ok(Command::Profile(name)) => self.switch_profile(name),
+                    Ok(Command::Layout(preset)) => self.set_layout(preset),
+                    Ok(Command::Theme(theme)) => {
+                        self.theme = theme;
+                        self.set_message(format!("theme: {}", theme.label()));
+                    }
+                    Ok(Command::Overlay(offset)) => {
+                        self.set_overlay(offset);
+                        self.set_tab(Tab::Compare);
+                    }
+                    Ok(Command::Compare(top, bottom)) => {
+                        let unknown = [&top, &bottom].into_iter().find(|n| !self.metric_history.contains(n)).cloned();
+                        if let Some(unknown) = unknown {
+                            self.set_message(format!("compare: no metric named {}", unknown));
+                        } else {
+                            self.compare = [Some(top), Some(bottom)];
+                            self.set_tab(Tab::Compare);
+                        }
+                    }
+                    Ok(Command::Summary) => {
+                        let message = self.write_summary();
+                        self.set_message(message);
+                    }
+                    Ok(Command::View(tab)) => self.set_tab(tab),
+                    Ok(Command::ScanFaster) => self.scan_faster(),
+                    Ok(Command::ScanSlower) => self.scan_slower(),
+                    Ok(Command::Lock(locked)) => self.set_locked(locked),
+                    Ok(Command::Quit) => self.should_quit = true,
+                    Err(e) => self.set_message(e),
+                }
+            }
+
+            fn handle_prompt_input(&mut self, key: KeyEvent) {
+                let Some(ref mut buf) = self.command else {
+                    return;
+                };
+                match key.code {
+                    KeyCode::Esc => self.command = None,
+                    KeyCode::Enter => {
+                        let line = std::mem::take(buf);
+                        self.command = None;
+                        self.run_command(&line);
+                    }
+                    KeyCode::Backspace if buf.pop().is_none() => self.command = None,
+                    KeyCode::Char(c) => buf.push(c),
+                    _ => {}
+                }
+            }
+
+            /// The `[idle]` mode once the configured time has passed without input.
+            fn idle_mode(&self) -> Option<IdleMode> {
+                let after = self.config.idle.after_minutes;
+                (after > 0 && self.last_input.elapsed().as_secs() >= after.saturating_mul(60)).then_some(self.config.idle.mode)
+            }
+
+            /// Records input; true when it only woke the screen from idle.
+            fn wake(&mut self) -> bool {
+                let was_idle = self.idle_mode().is_some();
+                self.last_input = std::time::Instant::now();
+                was_idle
+            }
+
+            /// With `[layout] mouse` on, the wheel moves the selection three
+            /// rows at a time; other mouse events are ignored.
+            pub fn handle_mouse(&mut self, mouse: MouseEvent) {
+                let delta = match mouse.kind {
+                    MouseEventKind::ScrollUp => -3,
+                    MouseEventKind::ScrollDown => 3,
+                    _ => return,
+                };
+                if !self.wake() && self.command.is_none() {
+                    self.move_selection(delta);
+                }
+            }
+
+            /// Pasted text goes into the command prompt when it is open, as
+            /// one line; elsewhere it is dropped rather than run as keys.
+            pub fn handle_paste(&mut self, text: &str) {
+                if self.wake() {
+                    return;
+                }
+                if let Some(ref mut buf) = self.command {
+                    buf.extend(text.chars().map(|c| if c.is_control() { ' ' } else { c }));
+                }
+            }
+
+            pub fn handle_input(&mut self, key: KeyEvent) {
+                // The key that wakes the screen does nothing else
+                if self.wake() {
+                    return;
+                }
+                if self.command.is_some() {
+                    self.handle_prompt_input(key);
+                    return;
+                }
+                match key.code {
+                    KeyCode::Char('q') => self.should_quit = true,
+                    _ if self.locked && Command::for_key(&key).is_some_and(|c| c.needs_unlock()) => {
+                        let hint = if self.read_only { "" } else { " (:unlock)" };
+                        self.set_message(format!("locked: read-only{}", hint));
+                    }
+                    KeyCode::Char(':') => self.command = Some(String::new()),
+                    KeyCode::Char('/') => self.command = Some("filter ".to_string()),
+                    KeyCode::Char('c') | KeyCode::Char('C') => self.set_sort(SortOrder::Cpu),
+                    KeyCode::Char('m') | KeyCode::Char('M') => self.set_sort(SortOrder::Mem),
+                    KeyCode::Char('w') => self.set_sort(SortOrder::Wakeups),
+                    KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::ALT) => self.toggle_log(),
+                    KeyCode::Char('l') | KeyCode::Char('L') => self.snapshot(),
+                    KeyCode::Char('e') => self.export_report(),
+                    KeyCode::Char('o') => self.offender_report(),
+                    KeyCode::Char('P') => self.cycle_profile(),
+                    KeyCode::Char('S') => {
+                        let message = self.write_summary();
+                        self.set_message(message);
+                    }
+                    KeyCode::Char('d') => self.dump_recorder(),
+                    KeyCode::Char('i') => self.cycle_interface(),
+                    KeyCode::Char('D') => self.cycle_disk_device(),
+                    KeyCode::Char('B') => self.cpu_view = self.cpu_view.toggled(),
+                    KeyCode::Char('}') => self.page_cores(true),
+                    KeyCode::Char('{') => self.page_cores(false),
+                    KeyCode::Char('y') => self.copy_selected(),
+                    KeyCode::Up => self.move_selection(-1),
+                    KeyCode::Down => self.move_selection(1),
+                    KeyCode::PageUp | KeyCode::PageDown if self.detail.is_some() && self.tab == Tab::Processes => {
+                        if let Some(ref mut d) = self.detail {
+                            d.scroll_by(if key.code == KeyCode::PageUp { -10 } else { 10 });
+                        }
+                    }
+                    KeyCode::PageUp => self.move_selection(-10),
+                    KeyCode::PageDown => self.move_selection(10),
+                    KeyCode::Home => {
+                        let (selected, len) = self.selection();
+                        *selected = (len > 0).then_some(0);
+                    }
+                    KeyCode::End => self.move_selection(isize::MAX),
+                    KeyCode::Enter => self.open_detail(),
+                    KeyCode::Esc if self.system_details.is_some() => self.system_details = None,
+                    KeyCode::Esc if self.show_failed_units => self.show_failed_units = false,
+                    KeyCode::Esc if self.detail.is_some() => self.detail = None,
+                    KeyCode::Left | KeyCode::Right if self.detail.is_some() && self.tab == Tab::Processes => {
+                        if let Some(ref mut d) = self.detail {
+                            d.set_tab(if key.code == KeyCode::Left { d.tab.prev() } else { d.tab.next() });
+                        }
+                    }
+                    KeyCode::Left if self.tree && self.tab == Tab::Processes => self.fold_selected(Some(true)),
+                    KeyCode::Right if self.tree && self.tab == Tab::Processes => self.fold_selected(Some(false)),
+                    KeyCode::Char(' ') if self.tree && self.tab == Tab::Processes => self.fold_selected(None),
+                    KeyCode::Left | KeyCode::Char('<') if self.tab == Tab::Processes => self.scroll_columns(-1),
+                    KeyCode::Right | KeyCode::Char('>') if self.tab == Tab::Processes => self.scroll_columns(1),
+                    KeyCode::Char('t') => self.tree = !self.tree,
+                    KeyCode::Char('g') if self.tab == Tab::Compare => {
+                        self.set_overlay(if self.overlay.is_some() { None } else { Some(self.last_overlay) })
+                    }
+                    KeyCode::Char(c @ ('a' | 'b')) if self.tab == Tab::Compare => {
+                        let names = self.metric_history.names();
+                        if let Some(name) = self.compare_table.selected().and_then(|i| names.get(i)) {
+                            self.compare[usize::from(c == 'b')] = Some(name.clone());
+                        }
+                    }
+                    KeyCode::Esc => *self.selection().0 = None,
+                    KeyCode::Tab => self.set_tab(self.tab.next()),
+                    KeyCode::Char('n') => self.raw_addresses = !self.raw_addresses,
+                    KeyCode::F(12) => self.show_diagnostics = !self.show_diagnostics,
+                    KeyCode::F(n @ 1..=4) => self.set_layout(LayoutPreset::ALL[n as usize - 1]),
+                    // Nothing to list without systemd
+                    KeyCode::Char('u') if self.failed_units.get().is_some() => self.show_failed_units = !self.show_failed_units,
+                    KeyCode::Char('s') => {
+                        self.system_details = match self.system_details {
+                            Some(_) => None,
+                            None => Some(SystemDetails::read()),
+                        }
+                    }
+                    KeyCode::Char('[') => self.scan_faster(),
+                    KeyCode::Char(']') => self.scan_slower(),
+                    _ => {}
+                }
+            }
+
+            pub fn render(&mut self, f: &mut Frame) {
+                self.draw(f);
+                apply_theme(self.theme, f.buffer_mut());
+            }
+
+            fn draw(&mut self, f: &mut Frame) {
+                let size = f.area();
+                let idle = self.idle_mode();
+                if idle == Some(IdleMode::Blank) {
+                    let load = sysinfo::System::load_average();
+                    let cpu = &self.metrics.cpu;
+                    f.render_widget(
+                        IdleClockWidget {
+                            now: chrono::Local::now(),
+                            load: (load.one, load.five, load.fifteen),
+                            cpu_percent: cpu.iter().map(|c| c.usage_percent).sum::<f32>() / cpu.len().max(1) as f32,
+                            alerts: self.alerts.active.len(),
+                        },
+                        size,
+                    );
+                    return;
+                }
+                let strip = self.panels.len()
+                    + usize::from(!self.latency.is_empty())
+                    + usize::from(!self.metrics.batteries.is_empty())
+                    + usize::from(!self.rapl.is_empty())
+                    + usize::from(!self.dimms.is_empty() || !self.mce.is_empty())
+                    + usize::from(!self.guests.is_empty())
+                    + usize::from(self.config.layout.sessions)
+                    + usize::from(cfg!(feature = "perf"))
+                    + usize::from(self.ups.is_some())
+                    + usize::from(self.journal.is_some());
+                #[cfg(feature = "ebpf")]
+                let strip = strip + usize::from(self.biolatency.is_some());
+                let layout = LayoutManager::new(size, strip, self.config.layout.panel_height, self.layout_preset);
+                let metrics = &self.metrics;
+
+                let cpu = CpuWidget::new(&metrics.cpu)
+                    .with_clusters(&metrics.clusters)
+                    .with_freq_limits(&metrics.freq_limits)
+                    .with_cstates(&metrics.cstates)
+                    .with_run_queue(metrics.run_queue)
+                    .with_sched_wait(metrics.sched_wait_ms)
+                    .with_virt(self.virt.as_ref().map(Virtualization::label))
+                    .with_view(self.cpu_view)
+                    .with_page(self.core_page);
+                self.core_pages = cpu.pages(layout.cpu_area);
+                f.render_widget(cpu, layout.cpu_area);
+
+                f.render_widget(RamGaugeWidget::new(&metrics.ram).with_breakdown(&metrics.memory), layout.ram_area);
+
+                f.render_widget(
+                    ThermalWidget::new(&metrics.thermals)
+                        .with_rules(&self.config.temp_alerts)
+                        .with_pi_throttle(metrics.pi_throttle)
+                        .with_virt_note(self.virt.as_ref().map(Virtualization::note)),
+                    layout.thermal_area,
+                );
+
+                if !layout.net_area.is_empty() {
+                    let iface = self.net_iface.as_deref();
+                    f.render_widget(
+                        NetworkSparklineWidget::new(self.history.net_series(iface), iface, &metrics.network.interfaces)
+                            .with_totals(
+                                &self.bandwidth.since_start,
+                                self.bandwidth.quota_usage(&self.config.network),
+                                self.config.network.quota_warn_percent,
+                            )
+                            .with_reachability(self.reachability.as_ref().map(ReachabilityMonitor::status))
+                            .with_aliases(&self.config.network),
+                        layout.net_area,
+                    );
+                }
+
+                if !layout.disk_area.is_empty() {
+                    let device = self.disk_device.as_deref();
+                    f.render_widget(
+                        DiskIOSparkWidget::new(self.history.disk_series(device), device)
+                            .with_dirty(&self.history.dirty, metrics.dirty),
+                        layout.disk_area,
+                    );
+                }
+
+                match self.tab {
+                    Tab::Processes => {
+                        let mut table_area = layout.proc_area;
+                        if let Some(ref detail) = self.detail {
+                            let split = Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)])
+                                .split(layout.proc_area);
+                            table_area = split[0];
+                            f.render_widget(
+                                ProcessDetailWidget {
+                                    detail,
+                                    info: metrics.processes.iter().find(|p| p.pid == detail.pid),
+                                },
+                                split[1],
+                            );
+                        }
+                        let extra_columns = self.script.column_names();
+                        f.render_stateful_widget(
+                            ProcessTableWidget::new(&metrics.processes, self.sort_order, self.filter.as_ref())
+                                .with_filter_error(self.filter_error.as_deref())
+                                .with_truncate(self.config.process.truncate)
+                                .with_columns(&self.config.process.columns)
+                                .with_frozen(self.frozen.as_deref())
+                                .with_highlight(&self.config.process.highlight, metrics.ram.total)
+                                .with_extra_columns(&extra_columns, &self.column_values)
+                                .with_own_pid(Some(self.own_pid))
+                                .with_tree(self.tree.then_some(&self.collapsed)),
+                            table_area,
+                            &mut self.process_table,
+                        );
+                    }
+                    Tab::Connections => {
+                        f.render_stateful_widget(
+                            ConnectionsWidget {
+                                data: &self.connections,
+                                processes: &metrics.processes,
+                                names: (!self.raw_addresses).then_some((&self.resolver, &self.services)),
+                            },
+                            layout.proc_area,
+                            &mut self.conn_table,
+                        );
+                    }
+                    Tab::Storage => {
+                        f.render_stateful_widget(
+                            StorageWidget {
+                                data: &metrics.filesystems,
+                                devices: &self.block_devices,
+                                nvme: &self.nvme,
+                                nvme_config: &self.config.nvme,
+                            },
+                            layout.proc_area,
+                            &mut self.fs_table,
+                        );
+                    }
+                    Tab::Interrupts => {
+                        f.render_stateful_widget(InterruptsWidget { data: &self.interrupts }, layout.proc_area, &mut self.irq_table);
+                    }
+                    Tab::Compare => {
+                        f.render_stateful_widget(
+                            CompareWidget {
+                                history: &self.metric_history,
+                                names: &self.metric_history.names(),
+                                series: [self.compare[0].as_deref(), self.compare[1].as_deref()],
+                                overlay: self.overlay,
+                            },
+                            layout.proc_area,
+                            &mut self.compare_table,
+                        );
+                    }
+                }
+
+                let mut strip_areas = layout.panel_areas.iter();
+                if let Some(ref ups) = self.ups {
+                    if let Some(area) = strip_areas.next() {
+                        f.render_widget(UpsWidget { status: ups.status() }, *area);
+                    }
+                }
+                if !metrics.batteries.is_empty() {
+                    if let Some(area) = strip_areas.next() {
+                        f.render_widget(
+                            BatteryWidget {
+                                batteries: &metrics.batteries,
+                                average_w: self.power_history.average_w(),
+                                remaining: self.power_history.time_remaining(&metrics.batteries),
+                            },
+                            *area,
+                        );
+                    }
+                }
+                if !self.rapl.is_empty() {
+                    if let Some(area) = strip_areas.next() {
+                        f.render_widget(RaplWidget { domains: &self.rapl_power }, *area);
+                    }
+                }
+                if !self.dimms.is_empty() || !self.mce.is_empty() {
+                    if let Some(area) = strip_areas.next() {
+                        f.render_widget(HardwareWidget { dimms: &self.dimms, mce: &self.mce }, *area);
+                    }
+                }
+                if !self.guests.is_empty() {
+                    if let Some(area) = strip_areas.next() {
+                        f.render_widget(GuestsWidget { guests: &self.guests }, *area);
+                    }
+                }
+                if self.config.layout.sessions {
+                    if let Some(area) = strip_areas.next() {
+                        f.render_widget(SessionsWidget { sessions: &self.sessions }, *area);
+                    }
+                }
+                #[cfg(feature = "perf")]
+                if let Some(area) = strip_areas.next() {
+                    f.render_widget(
+                        PerfWidget { rates: self.perf_rates, error: self.perf.as_ref().err().map(String::as_str) },
+                        *area,
+                    );
+                }
+                #[cfg(feature = "ebpf")]
+                if self.biolatency.is_some() {
+                    if let Some(area) = strip_areas.next() {
+                        f.render_widget(
+                            BioLatencyWidget {
+                                devices: self.bio_devices.as_deref().unwrap_or_default(),
+                                sampling: self.bio_devices.is_none(),
+                            },
+                            *area,
+                        );
+                    }
+                }
+                if !self.latency.is_empty() {
+                    if let Some(area) = strip_areas.next() {
+                        f.render_widget(LatencyWidget::new(self.latency.stats()), *area);
+                    }
+                }
+                if let Some(ref journal) = self.journal {
+                    if let Some(area) = strip_areas.next() {
+                        f.render_widget(JournalWidget { title: &journal.title, output: journal.output() }, *area);
+                    }
+                }
+                for (panel, area) in self.panels.iter().zip(strip_areas) {
+                    f.render_widget(CommandPanelWidget::new(&panel.title, panel.output()), *area);
+                }
+
+                let failed_units = self.failed_units.get();
+                f.render_widget(
+                    StatusBarWidget::new(
+                        self.collector.process_every,
+                        self.tick_ms,
+                        self.snap_path.clone(),
+                        self.log_path.clone(),
+                        self.command.clone(),
+                        self.message.clone(),
+                        self.alerts.active.first().map(|a| {
+                            let text = if a.detail.is_empty() { a.name.clone() } else { format!("{}: {}", a.name, a.detail) };
+                            (text, self.alerts.active.len() - 1)
+                        }),
+                    )
+                    .with_self_usage(self.self_usage)
+                    .with_failed_units(failed_units.as_ref().map_or(0, Vec::len))
+                    .with_percentiles(self.rolling.percentiles(), self.config.layout.percentile_minutes)
+                    .with_temp_levels(
+                        self.metrics
+                            .thermals
+                            .iter()
+                            .max_by(|a, b| a.temp_celsius.total_cmp(&b.temp_celsius))
+                            .map_or((85.0, 65.0), |t| {
+                                TempAlertConfig::levels(&self.config.temp_alerts, &[&t.raw_label, &t.label])
+                            }),
+                    )
+                    .with_profile(self.config.profile.clone())
+                    .with_locked(self.locked),
+                    layout.status_area,
+                );
+
+                if let (true, Some(units)) = (self.show_failed_units, &failed_units) {
+                    f.render_widget(FailedUnitsWidget { units }, size);
+                }
+                if let Some(ref details) = self.system_details {
+                    f.render_widget(
+                        SystemDetailsWidget { details, virt: self.virt.as_ref().map(Virtualization::label), clock: metrics.clock },
+                        size,
+                    );
+                }
+                if self.show_diagnostics {
+                    f.render_widget(DiagnosticsWidget::new(&self.diagnostics), size);
+                }
+                if idle == Some(IdleMode::Dim) {
+                    let dim = Style::default().fg(Color::DarkGray).bg(Color::Reset).remove_modifier(Modifier::BOLD);
+                    f.buffer_mut().set_style(size, dim);
+                }
+            }
+        }
+}